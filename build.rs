@@ -1,49 +1,30 @@
 use std::path::PathBuf;
 
-fn main() {
-    let dir: PathBuf = ["tree-sitter-python", "src"].iter().collect();
-    cc::Build::new()
-        .include(&dir)
-        .file(dir.join("parser.c"))
-        .file(dir.join("scanner.c"))
-        .compile("tree-sitter-python");
-    println!("cargo:rerun-if-changed=tree-sitter-python/src/parser.c");
-
-    let dir: PathBuf = ["tree-sitter-javascript", "src"].iter().collect();
-    cc::Build::new()
-        .include(&dir)
-        .file(dir.join("parser.c"))
-        .file(dir.join("scanner.c"))
-        .compile("tree-sitter-javascript");
-    println!("cargo:rerun-if-changed=tree-sitter-javascript/src/parser.c");
-
-    let dir: PathBuf = ["tree-sitter-go", "src"].iter().collect();
-    cc::Build::new()
-        .include(&dir)
-        .file(dir.join("parser.c"))
-        .compile("tree-sitter-go");
-    println!("cargo:rerun-if-changed=tree-sitter-go/src/parser.c");
+// Each grammar is only compiled when its lang-* feature is enabled
+fn compile_grammar(name: &str, has_scanner: bool) {
+    let dir: PathBuf = [format!("tree-sitter-{}", name).as_str(), "src"].iter().collect();
+    let mut build = cc::Build::new();
+    build.include(&dir).file(dir.join("parser.c"));
+    if has_scanner {
+        build.file(dir.join("scanner.c"));
+    }
+    build.compile(&format!("tree-sitter-{}", name));
+    println!("cargo:rerun-if-changed=tree-sitter-{}/src/parser.c", name);
+}
 
-    let dir: PathBuf = ["tree-sitter-cpp", "src"].iter().collect();
-    cc::Build::new()
-        .include(&dir)
-        .file(dir.join("parser.c"))
-        .file(dir.join("scanner.c"))
-        .compile("tree-sitter-cpp");
-    println!("cargo:rerun-if-changed=tree-sitter-cpp/src/parser.c");
-
-    let dir: PathBuf = ["tree-sitter-java", "src"].iter().collect();
-    cc::Build::new()
-        .include(&dir)
-        .file(dir.join("parser.c"))
-        .compile("tree-sitter-java");
-    println!("cargo:rerun-if-changed=tree-sitter-java/src/parser.c");
+fn main() {
+    let grammars = [
+        ("python", "CARGO_FEATURE_LANG_PYTHON", true),
+        ("javascript", "CARGO_FEATURE_LANG_JAVASCRIPT", true),
+        ("go", "CARGO_FEATURE_LANG_GO", false),
+        ("cpp", "CARGO_FEATURE_LANG_CPP", true),
+        ("java", "CARGO_FEATURE_LANG_JAVA", false),
+        ("php", "CARGO_FEATURE_LANG_PHP", true),
+    ];
 
-    let dir: PathBuf = ["tree-sitter-php", "src"].iter().collect();
-    cc::Build::new()
-        .include(&dir)
-        .file(dir.join("parser.c"))
-        .file(dir.join("scanner.c"))
-        .compile("tree-sitter-php");
-    println!("cargo:rerun-if-changed=tree-sitter-php/src/parser.c");
-}
\ No newline at end of file
+    for (name, feature, has_scanner) in grammars {
+        if std::env::var_os(feature).is_some() {
+            compile_grammar(name, has_scanner);
+        }
+    }
+}
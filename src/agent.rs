@@ -0,0 +1,188 @@
+use crate::config::Config;
+use crate::error::{Result, RiftError};
+use crate::executor::{ExecOptions, ExecutionOutput};
+use crate::value::Value;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const TOKEN_VAR: &str = "RIFT_AGENT_TOKEN";
+const FACTS_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Wire format between `@fuse ... on "name"` and `rift agent`. Output bytes are
+// base64 so binary artifacts survive the trip.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExecRequest {
+    pub lang: String,
+    pub code: String,
+    pub exports: Vec<(String, serde_json::Value)>,
+    #[serde(default)]
+    pub env: Vec<(String, String)>,
+    pub timeout_secs: u64,
+    pub source_label: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ExecResponse {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub deps_installed: usize,
+    pub error: Option<String>,
+}
+
+// Agents are listed in rift.toml as `[agents] gpu-box = "http://10.0.0.5:7878"`
+// and both ends share the token in $RIFT_AGENT_TOKEN
+pub async fn execute_remote(agent: &str, config: &Config, lang: &str, code: &str, options: &ExecOptions) -> Result<ExecutionOutput> {
+    let url = config.agents.get(agent).ok_or_else(|| RiftError::ConfigError(format!(
+        "Unknown agent '{}'; add it under [agents] in rift.toml", agent
+    )))?;
+    let token = agent_token()?;
+    let request = ExecRequest {
+        lang: lang.to_string(),
+        code: code.to_string(),
+        exports: options.exports.iter().map(|(name, value)| (name.clone(), value.to_json())).collect(),
+        env: options.env.clone(),
+        timeout_secs: options.timeout.as_secs(),
+        source_label: format!("{} on {}", options.source_label, agent),
+    };
+
+    let response: ExecResponse = reqwest::Client::new()
+        .post(format!("{}/execute", url.trim_end_matches('/')))
+        .bearer_auth(token)
+        // Leave the agent room to report its own timeout first
+        .timeout(options.timeout + Duration::from_secs(30))
+        .json(&request)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    if let Some(message) = response.error {
+        return Err(RiftError::ExecutionError { language: lang.to_string(), message: format!("agent {}: {}", agent, message) });
+    }
+    let decode = |data: &str| BASE64.decode(data).map_err(|e| RiftError::ExecutionError {
+        language: lang.to_string(),
+        message: format!("agent {} sent malformed output: {}", agent, e),
+    });
+    let stdout = decode(&response.stdout)?;
+    Ok(ExecutionOutput {
+        stdout_len: stdout.len() as u64,
+        stdout,
+        stderr: decode(&response.stderr)?,
+        exit_code: response.exit_code,
+        duration: Duration::from_millis(response.duration_ms),
+        stdout_file: None,
+        stderr_file: None,
+        deps_installed: response.deps_installed,
+    })
+}
+
+// The agent machine's `host` facts, like its gpu; agents from before
+// `/facts` existed answer with an error
+pub async fn facts(agent: &str, config: &Config) -> Result<serde_json::Value> {
+    let url = config.agents.get(agent).ok_or_else(|| RiftError::ConfigError(format!(
+        "Unknown agent '{}'; add it under [agents] in rift.toml", agent
+    )))?;
+    Ok(reqwest::Client::new()
+        .get(format!("{}/facts", url.trim_end_matches('/')))
+        .bearer_auth(agent_token()?)
+        .timeout(FACTS_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+pub(crate) fn agent_token() -> Result<String> {
+    std::env::var(TOKEN_VAR)
+        .ok()
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| RiftError::ConfigError(format!("{} must be set to talk to or run an agent or session", TOKEN_VAR)))
+}
+
+// Spilled output is read back so the caller always gets the full bytes
+#[cfg(feature = "server")]
+fn run_request(request: ExecRequest) -> ExecResponse {
+    let options = ExecOptions {
+        timeout: Duration::from_secs(request.timeout_secs),
+        exports: request.exports.into_iter().map(|(name, json)| (name, Value::from_json(json))).collect(),
+        env: request.env,
+        source_label: request.source_label,
+        // The agent machine decides where its dependencies come from
+        provider: Config::load().map(|config| config.executor.provider).unwrap_or_default(),
+        ..ExecOptions::default()
+    };
+    match crate::executor::execute(&request.lang, &request.code, &options) {
+        Ok(output) => {
+            let stdout = match &output.stdout_file {
+                Some(path) => std::fs::read(path).unwrap_or(output.stdout),
+                None => output.stdout,
+            };
+            ExecResponse {
+                stdout: BASE64.encode(stdout),
+                stderr: BASE64.encode(output.stderr),
+                exit_code: output.exit_code,
+                duration_ms: output.duration.as_millis() as u64,
+                deps_installed: output.deps_installed,
+                error: None,
+            }
+        }
+        Err(e) => ExecResponse { error: Some(e.to_string()), ..ExecResponse::default() },
+    }
+}
+
+#[cfg(feature = "server")]
+pub async fn serve(listen: &str) -> Result<()> {
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+
+    let token = agent_token()?;
+    let bearer = format!("Bearer {}", token);
+    let authorized = move |headers: &HeaderMap| {
+        headers.get("authorization").and_then(|value| value.to_str().ok()).is_some_and(|value| value == bearer)
+    };
+    let facts_authorized = authorized.clone();
+    let facts = move |headers: HeaderMap| async move {
+        if !facts_authorized(&headers) {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        tokio::task::spawn_blocking(|| crate::host::facts().to_json())
+            .await
+            .map(Json)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    };
+    let execute = move |headers: HeaderMap, Json(request): Json<ExecRequest>| {
+        let authorized = authorized.clone();
+        async move {
+            if !authorized(&headers) {
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+            tracing::info!(lang = %request.lang, label = %request.source_label, "executing");
+            tokio::task::spawn_blocking(move || run_request(request))
+                .await
+                .map(Json)
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    };
+    let app = Router::new()
+        .route("/health", get(|| async { "ok" }))
+        .route("/facts", get(facts))
+        .route("/execute", post(execute));
+
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    println!("rift agent listening on {}", listener.local_addr()?);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+#[cfg(not(feature = "server"))]
+pub async fn serve(_listen: &str) -> Result<()> {
+    Err(RiftError::ConfigError("rift was built without the 'server' feature".to_string()))
+}
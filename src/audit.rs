@@ -0,0 +1,147 @@
+use crate::error::Result;
+use crate::interpreter::Environment;
+use crate::sbom::{self, Component};
+use crate::AST;
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+
+const OSV_API: &str = "https://api.osv.dev/v1";
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Unknown,
+    Low,
+    Moderate,
+    High,
+    Critical,
+}
+
+impl Severity {
+    fn parse(text: &str) -> Self {
+        match text.to_ascii_lowercase().as_str() {
+            "low" => Severity::Low,
+            "moderate" | "medium" => Severity::Moderate,
+            "high" => Severity::High,
+            "critical" => Severity::Critical,
+            _ => Severity::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Unknown => "unknown",
+            Severity::Low => "low",
+            Severity::Moderate => "moderate",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub component: Component,
+    pub id: String,
+    pub summary: String,
+    pub severity: Severity,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchResponse {
+    results: Vec<BatchResult>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct BatchResult {
+    #[serde(default)]
+    vulns: Vec<VulnId>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VulnId {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Vuln {
+    id: String,
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    withdrawn: Option<String>,
+    #[serde(default)]
+    database_specific: serde_json::Value,
+}
+
+// OSV's names for the ecosystems in `sbom::Component`
+fn osv_ecosystem(ecosystem: &str) -> &str {
+    match ecosystem {
+        "pypi" => "PyPI",
+        "maven" => "Maven",
+        "cargo" => "crates.io",
+        "golang" => "Go",
+        "composer" => "Packagist",
+        other => other,
+    }
+}
+
+// Known advisories for each component from the OSV database. Rift does not
+// pin versions, so a package matches every advisory ever published for it
+// that has not been withdrawn; severity is the one OSV's source assigned.
+pub async fn scan(components: &[Component]) -> Result<Vec<Finding>> {
+    if components.is_empty() {
+        return Ok(Vec::new());
+    }
+    let client = reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?;
+    let queries: Vec<_> = components.iter().map(|c| serde_json::json!({
+        "package": { "name": c.name, "ecosystem": osv_ecosystem(c.ecosystem) },
+    })).collect();
+    let batch: BatchResponse = client.post(format!("{}/querybatch", OSV_API))
+        .json(&serde_json::json!({ "queries": queries }))
+        .send().await?
+        .error_for_status()?
+        .json().await?;
+
+    let mut findings = Vec::new();
+    for (component, result) in components.iter().zip(batch.results) {
+        for VulnId { id } in result.vulns {
+            let vuln: Vuln = client.get(format!("{}/vulns/{}", OSV_API, id)).send().await?.error_for_status()?.json().await?;
+            if vuln.withdrawn.is_some() {
+                continue;
+            }
+            let severity = vuln.database_specific.get("severity").and_then(|s| s.as_str()).map_or(Severity::Unknown, Severity::parse);
+            findings.push(Finding { component: component.clone(), id: vuln.id, summary: vuln.summary, severity });
+        }
+    }
+    findings.sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| a.component.cmp(&b.component)));
+    Ok(findings)
+}
+
+// With [policy] block_severity set, refuses a deploy while any dependency
+// of the rifts being packaged has an advisory at or above it
+pub async fn check_deploy(env: &Environment) -> std::result::Result<(), String> {
+    let Some(threshold) = env.config.policy.block_severity else {
+        return Ok(());
+    };
+    let program = AST::Program(env.rifts.iter().map(|(name, body)| AST::Rift(name.clone(), body.clone())).collect());
+    let components = sbom::components(&program, None).map_err(|e| e.to_string())?;
+    let blocking: Vec<_> = scan(&components).await
+        .map_err(|e| format!("dependency audit failed, refusing to deploy: {}", e))?
+        .into_iter()
+        .filter(|finding| finding.severity >= threshold)
+        .map(|finding| format!("{} {} ({}, {})", finding.component.purl(), finding.id, finding.severity, finding.summary))
+        .collect();
+    if blocking.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Policy blocks deploys with {} or worse advisories (see [policy] in rift.toml):\n  {}",
+        threshold, blocking.join("\n  ")
+    ))
+}
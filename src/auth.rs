@@ -0,0 +1,359 @@
+// `rift auth check`: tries each credential a deploy would use before a long
+// pipeline starts, instead of letting it fail at the last step
+use crate::config::Config;
+use crate::deployer;
+use crate::error::{Result, RiftError};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// One credential and what was found: Ok with who or what it identifies, or
+// Err saying why it is missing, rejected or expired
+pub struct Finding {
+    pub target: String,
+    pub credential: &'static str,
+    pub result: std::result::Result<String, String>,
+}
+
+// The IAM action behind each rusoto request the AWS backends build.
+// `rift auth policy aws` is made from this table; a test keeps it in step
+// with the requests in deployer.rs and upload.rs.
+pub const AWS_ACTIONS: &[(&str, &str)] = &[
+    ("PutObjectRequest", "s3:PutObject"),
+    ("CreateMultipartUploadRequest", "s3:PutObject"),
+    ("UploadPartRequest", "s3:PutObject"),
+    ("CompleteMultipartUploadRequest", "s3:PutObject"),
+    ("ListPartsRequest", "s3:ListMultipartUploadParts"),
+    ("CreateFunctionRequest", "lambda:CreateFunction"),
+    ("CreateFunctionRequest", "iam:PassRole"),
+    ("UpdateFunctionCodeRequest", "lambda:UpdateFunctionCode"),
+    ("GetAliasRequest", "lambda:GetAlias"),
+    ("CreateAliasRequest", "lambda:CreateAlias"),
+    ("UpdateAliasRequest", "lambda:UpdateAlias"),
+    ("InvocationRequest", "lambda:InvokeFunction"),
+    ("CreateInvalidationRequest", "cloudfront:CreateInvalidation"),
+];
+
+// A profile's settings with its secrets filled in, or none when no profile
+// is given
+fn profile_settings(profile: Option<&str>, config: &Config) -> Result<HashMap<String, String>> {
+    match profile {
+        Some(name) => {
+            let settings = config.profiles.get(name)
+                .ok_or_else(|| RiftError::ConfigError(format!("unknown profile '{}'", name)))?;
+            crate::secrets::resolve(settings).map_err(RiftError::SecretError)
+        }
+        None => Ok(HashMap::new()),
+    }
+}
+
+// Checks the credentials of `target` (or of the profile's target) with the
+// profile's settings
+pub async fn check(target: Option<&str>, profile: Option<&str>, config: &Config) -> Result<Vec<Finding>> {
+    let settings = profile_settings(profile, config)?;
+    let target = target.map(str::to_string).or_else(|| settings.get("target").cloned())
+        .ok_or_else(|| RiftError::ConfigError("name a target, or a profile that sets one".to_string()))?;
+    let targets = deployer::select_targets(&target);
+    if targets.is_empty() {
+        return Err(RiftError::ConfigError(format!("unsupported deploy target '{}'", target)));
+    }
+
+    let http = reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?;
+    let mut findings = Vec::new();
+    for name in targets {
+        let mut found = |credential, result| findings.push(Finding { target: name.to_string(), credential, result });
+        match name {
+            "aws" => found("AWS credentials", aws_identity(name, &settings).await),
+            "static" => match settings.get("provider").map(String::as_str).unwrap_or("s3") {
+                "s3" => found("AWS credentials", aws_identity(name, &settings).await),
+                "gcs" => found("GCS token", gcs_token(&http, &settings).await),
+                "azure" => found("Azure SAS token", sas_token(&settings)),
+                _ => {}
+            },
+            "ethereum" => {
+                found("Ethereum RPC access", ethereum_rpc(&http, &settings).await);
+                if let Some(key) = settings.get("etherscan_api_key") {
+                    found("Etherscan API key", etherscan_key(&http, key).await);
+                }
+            }
+            "solana" => {
+                found("Solana RPC access", solana_rpc(&http, &settings).await);
+                if let Some(path) = settings.get("keypair") {
+                    found("Solana keypair", solana_keypair(path));
+                }
+            }
+            "ipfs" => {
+                if let Some(token) = settings.get("pinning_token") {
+                    found("IPFS pinning token", pinning_token(&http, token, &settings).await);
+                }
+            }
+            "kubernetes" => {
+                found("Kubernetes access", kubectl_access(&settings));
+                if let Some(image) = settings.get("image") {
+                    found("Registry login", registry_login(image));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(findings)
+}
+
+// An IAM policy allowing exactly the calls in AWS_ACTIONS, scoped to the
+// profile's bucket, function, role and distribution where it names them. A
+// profile for the aws target leaves out CloudFront; one for static leaves
+// out Lambda.
+pub fn aws_policy(profile: Option<&str>, config: &Config) -> Result<serde_json::Value> {
+    let settings = profile_settings(profile, config)?;
+    let get = |key: &str| settings.get(key).map(String::as_str).unwrap_or("*");
+    let wanted = |service: &str| match settings.get("target").map(String::as_str) {
+        Some("aws") => service != "cloudfront",
+        Some("static") => matches!(service, "s3" | "cloudfront"),
+        _ => true,
+    };
+
+    let mut services: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for &(_, action) in AWS_ACTIONS {
+        let service = action.split(':').next().unwrap_or(action);
+        if wanted(service) {
+            services.entry(service).or_default().insert(action);
+        }
+    }
+    let statements: Vec<_> = services.into_iter().map(|(service, actions)| {
+        let function = format!("arn:aws:lambda:{}:*:function:{}", get("region"), get("function"));
+        let resources = match service {
+            "s3" => vec![format!("arn:aws:s3:::{}/*", get("bucket"))],
+            // Aliases and versions are `function:name:qualifier`
+            "lambda" => vec![function.clone(), format!("{}:*", function)],
+            "iam" => vec![settings.get("role").cloned().unwrap_or_else(|| "arn:aws:iam::*:role/*".to_string())],
+            "cloudfront" => vec![format!("arn:aws:cloudfront::*:distribution/{}", get("distribution"))],
+            _ => vec!["*".to_string()],
+        };
+        let mut statement = serde_json::json!({
+            "Sid": format!("Rift{}", service.to_uppercase()),
+            "Effect": "Allow",
+            "Action": actions,
+            "Resource": resources,
+        });
+        if service == "iam" {
+            statement["Condition"] = serde_json::json!({ "StringEquals": { "iam:PassedToService": "lambda.amazonaws.com" } });
+        }
+        statement
+    }).collect();
+    Ok(serde_json::json!({ "Version": "2012-10-17", "Statement": statements }))
+}
+
+fn setting<'a>(settings: &'a HashMap<String, String>, key: &str) -> std::result::Result<&'a String, String> {
+    settings.get(key).ok_or_else(|| format!("{} is not set", key))
+}
+
+#[cfg(feature = "deploy-aws")]
+async fn aws_identity(target: &str, settings: &HashMap<String, String>) -> std::result::Result<String, String> {
+    use rusoto_core::credential::{ChainProvider, ProvideAwsCredentials};
+    use rusoto_sts::{GetCallerIdentityRequest, Sts, StsClient};
+
+    let credentials = ChainProvider::new().credentials().await.map_err(|e| format!("none found: {}", e))?;
+    if let Some(expiry) = credentials.expires_at() {
+        if *expiry <= chrono::Utc::now() {
+            return Err(format!("expired at {}", expiry));
+        }
+    }
+    // STS answers in any region; the configured one keeps LocalStack working
+    let mut settings = settings.clone();
+    settings.entry("region".to_string()).or_insert_with(|| "us-east-1".to_string());
+    let region = deployer::aws_region(target, &settings).await?;
+    let identity = StsClient::new(region).get_caller_identity(GetCallerIdentityRequest {}).await
+        .map_err(|e| format!("rejected: {}", e))?;
+    Ok(identity.arn.unwrap_or_default())
+}
+
+#[cfg(not(feature = "deploy-aws"))]
+async fn aws_identity(_target: &str, _settings: &HashMap<String, String>) -> std::result::Result<String, String> {
+    Err("rift was built without the 'deploy-aws' feature".to_string())
+}
+
+// A JSON-RPC call that any node or gateway answers once it accepts the key
+async fn json_rpc(http: &reqwest::Client, url: &str, method: &str) -> std::result::Result<serde_json::Value, String> {
+    let response = http.post(url)
+        .json(&serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": [] }))
+        .send().await
+        .map_err(|e| format!("cannot reach {}: {}", url, e))?;
+    match response.status().as_u16() {
+        401 | 403 => return Err(format!("rejected (HTTP {})", response.status())),
+        status if status >= 400 => return Err(format!("HTTP {} from {}", response.status(), url)),
+        _ => {}
+    }
+    let body: serde_json::Value = response.json().await.map_err(|e| format!("invalid response: {}", e))?;
+    match body.get("error") {
+        Some(error) => Err(format!("rejected: {}", error["message"].as_str().unwrap_or("unknown error"))),
+        None => Ok(body["result"].clone()),
+    }
+}
+
+async fn ethereum_rpc(http: &reqwest::Client, settings: &HashMap<String, String>) -> std::result::Result<String, String> {
+    let url = match deployer::endpoint("ethereum", settings).await? {
+        Some(url) => url,
+        None => format!("https://mainnet.infura.io/v3/{}", setting(settings, "api_key")?),
+    };
+    let chain = json_rpc(http, &url, "eth_chainId").await?;
+    Ok(format!("chain {}", chain.as_str().unwrap_or("?")))
+}
+
+async fn etherscan_key(http: &reqwest::Client, key: &str) -> std::result::Result<String, String> {
+    let body: serde_json::Value = http.get("https://api.etherscan.io/api")
+        .query(&[("module", "stats"), ("action", "ethsupply"), ("apikey", key)])
+        .send().await.map_err(|e| format!("cannot reach Etherscan: {}", e))?
+        .json().await.map_err(|e| format!("invalid response: {}", e))?;
+    match body["status"].as_str() {
+        Some("1") => Ok("accepted".to_string()),
+        _ => Err(format!("rejected: {}", body["result"].as_str().unwrap_or("unknown error"))),
+    }
+}
+
+async fn solana_rpc(http: &reqwest::Client, settings: &HashMap<String, String>) -> std::result::Result<String, String> {
+    let url = deployer::endpoint("solana", settings).await?.ok_or("rpc_url is not set")?;
+    json_rpc(http, &url, "getHealth").await?;
+    Ok(url)
+}
+
+// The file solana-keygen writes: a JSON array of 64 bytes
+fn solana_keypair(path: &str) -> std::result::Result<String, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    match serde_json::from_str::<Vec<u8>>(&text) {
+        Ok(bytes) if bytes.len() == 64 => Ok(path.to_string()),
+        _ => Err(format!("{} is not a keypair file", path)),
+    }
+}
+
+async fn gcs_token(http: &reqwest::Client, settings: &HashMap<String, String>) -> std::result::Result<String, String> {
+    let token = setting(settings, "gcs_token")?;
+    let bucket = setting(settings, "bucket")?;
+    let response = http.get(format!("https://storage.googleapis.com/storage/v1/b/{}", bucket))
+        .bearer_auth(token)
+        .send().await
+        .map_err(|e| format!("cannot reach GCS: {}", e))?;
+    match response.status().as_u16() {
+        401 => Err("rejected (HTTP 401); the token has probably expired".to_string()),
+        403 => Err(format!("no access to bucket {}", bucket)),
+        status if status >= 400 => Err(format!("HTTP {}", response.status())),
+        _ => Ok(format!("bucket {}", bucket)),
+    }
+}
+
+// SAS tokens carry their own expiry as `se=`
+fn sas_token(settings: &HashMap<String, String>) -> std::result::Result<String, String> {
+    let sas = setting(settings, "sas_token")?;
+    let expiry = sas.trim_start_matches('?').split('&')
+        .find_map(|pair| pair.strip_prefix("se="))
+        .ok_or("the token has no expiry (se=)")?
+        .replace("%3A", ":");
+    let expiry = chrono::DateTime::parse_from_rfc3339(&expiry).map_err(|_| format!("unreadable expiry '{}'", expiry))?;
+    if expiry.with_timezone(&chrono::Utc) <= chrono::Utc::now() {
+        return Err(format!("expired at {}", expiry));
+    }
+    Ok(format!("valid until {}", expiry))
+}
+
+async fn pinning_token(http: &reqwest::Client, token: &str, settings: &HashMap<String, String>) -> std::result::Result<String, String> {
+    if settings.contains_key("pinning_url") {
+        return Ok("set; a custom pinning_url cannot be checked".to_string());
+    }
+    let response = http.get("https://api.pinata.cloud/data/testAuthentication")
+        .bearer_auth(token)
+        .send().await
+        .map_err(|e| format!("cannot reach Pinata: {}", e))?;
+    match response.status().is_success() {
+        true => Ok("accepted".to_string()),
+        false => Err(format!("rejected (HTTP {})", response.status())),
+    }
+}
+
+fn kubectl_access(settings: &HashMap<String, String>) -> std::result::Result<String, String> {
+    let namespace = settings.get("namespace").map(String::as_str).unwrap_or("default");
+    let output = std::process::Command::new("kubectl")
+        .args(["auth", "can-i", "patch", "deployments", "-n", namespace])
+        .output()
+        .map_err(|e| format!("cannot run kubectl: {}", e))?;
+    let answer = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    match answer.as_str() {
+        "yes" => Ok(format!("can patch deployments in {}", namespace)),
+        "no" => Err(format!("not allowed to patch deployments in {}", namespace)),
+        _ => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
+
+// Looks for a `docker login` (or credential helper) for the image's registry
+fn registry_login(image: &str) -> std::result::Result<String, String> {
+    let registry = registry_of(image);
+    let dir = std::env::var_os("DOCKER_CONFIG").map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".docker")))
+        .ok_or("cannot find the docker config directory")?;
+    let path = dir.join("config.json");
+    let text = std::fs::read_to_string(&path).map_err(|_| format!("no docker login for {} ({} not found)", registry, path.display()))?;
+    let config: serde_json::Value = serde_json::from_str(&text).map_err(|e| format!("invalid {}: {}", path.display(), e))?;
+    let (hub, own) = (["https://index.docker.io/v1/", "docker.io"], [registry]);
+    let keys: &[&str] = if registry == "docker.io" { &hub } else { &own };
+    let logged_in = keys.iter().any(|key| config["auths"].get(key).is_some() || config["credHelpers"].get(key).is_some())
+        || config.get("credsStore").is_some();
+    match logged_in {
+        true => Ok(registry.to_string()),
+        false => Err(format!("no docker login for {}", registry)),
+    }
+}
+
+// `ghcr.io/org/app:1` is on ghcr.io; `org/app` and `app` are on Docker Hub
+fn registry_of(image: &str) -> &str {
+    match image.split_once('/') {
+        Some((host, _)) if host.contains('.') || host.contains(':') || host == "localhost" => host,
+        _ => "docker.io",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_of() {
+        assert_eq!(registry_of("ghcr.io/org/app:1"), "ghcr.io");
+        assert_eq!(registry_of("localhost:5000/app"), "localhost:5000");
+        assert_eq!(registry_of("org/app"), "docker.io");
+        assert_eq!(registry_of("nginx"), "docker.io");
+    }
+
+    #[test]
+    fn test_aws_actions_cover_the_backends() {
+        let requests = regex::Regex::new(r"\b([A-Z]\w*Request)\s*\{").unwrap();
+        for source in [include_str!("deployer.rs"), include_str!("upload.rs")] {
+            for request in requests.captures_iter(source).map(|c| c[1].to_string()) {
+                // web3's eth_call, not an AWS request
+                if request == "CallRequest" {
+                    continue;
+                }
+                assert!(AWS_ACTIONS.iter().any(|(name, _)| *name == request), "{} has no IAM action in AWS_ACTIONS", request);
+            }
+        }
+    }
+
+    #[test]
+    fn test_aws_policy() {
+        let mut config = Config::default();
+        config.profiles.insert("site".to_string(), HashMap::from([
+            ("target".to_string(), "static".to_string()),
+            ("bucket".to_string(), "www".to_string()),
+        ]));
+        let policy = aws_policy(Some("site"), &config).unwrap();
+        let statements = policy["Statement"].as_array().unwrap();
+        assert_eq!(statements.len(), 2, "static deploys need only S3 and CloudFront");
+        assert!(statements.iter().any(|s| s["Resource"][0] == "arn:aws:s3:::www/*"));
+        assert_eq!(aws_policy(None, &config).unwrap()["Statement"].as_array().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_sas_token() {
+        let settings = |sas: &str| HashMap::from([("sas_token".to_string(), sas.to_string())]);
+        assert!(sas_token(&settings("?sv=2022&se=2001-01-01T00%3A00%3A00Z&sig=x")).unwrap_err().contains("expired"));
+        assert!(sas_token(&settings("sv=2022&se=2999-01-01T00:00:00Z")).is_ok());
+        assert!(sas_token(&settings("sv=2022")).is_err());
+    }
+}
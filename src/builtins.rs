@@ -0,0 +1,513 @@
+use crate::deployer::parse_duration;
+use crate::interpreter::Environment;
+use crate::policy;
+use crate::store;
+use crate::tables;
+use crate::value::Value;
+use rand::Rng;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Functions callable as `name(args)` from rift source. Returns None when
+// `name` is not a builtin so the caller can fall back to rifts and tasks.
+pub async fn call(name: &str, args: &[Value], env: &mut Environment) -> Option<Result<Value, String>> {
+    let result = match name {
+        "print" => print(args),
+        "input" => input(args),
+        "http_get" => http_get(args).await,
+        "http_post" => http_post(args).await,
+        "read_file" => read_file(args, env),
+        "write_file" => write_file(args, env),
+        "exists" => exists(args, env),
+        "glob" => glob(args, env),
+        "json_parse" => json_parse(args),
+        "json_string" => json_string(args),
+        "now" => now(args),
+        "format_time" => format_time(args),
+        "sleep" => sleep(args).await,
+        "uuid" => uuid(args),
+        "random_int" => random_int(args),
+        "regex_match" => regex_match(args),
+        "regex_replace" => regex_replace(args),
+        "regex_captures" => regex_captures(args),
+        "env" => env_var(args, env),
+        "set_env" => set_env(args, env),
+        "log.debug" | "log.info" | "log.warn" | "log.error" => log(name, args, env),
+        "store_put" => store_put(args),
+        "store_get" => store_get(args),
+        "table" => table(args),
+        "table_preview" => table_preview(args),
+        "len" => len(args),
+        "push" => push(args),
+        "split" => split(args),
+        "target" => target(args, env),
+        _ => return None,
+    };
+    Some(result)
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    matches!(
+        name,
+        "print" | "input" | "http_get" | "http_post" | "read_file" | "write_file" | "exists" | "glob"
+            | "json_parse" | "json_string" | "now" | "format_time" | "sleep" | "uuid" | "random_int"
+            | "regex_match" | "regex_replace" | "regex_captures" | "env" | "set_env"
+            | "log.debug" | "log.info" | "log.warn" | "log.error" | "store_put" | "store_get"
+            | "table" | "table_preview" | "len" | "push" | "split" | "target"
+    )
+}
+
+fn print(args: &[Value]) -> Result<Value, String> {
+    let line: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    println!("{}", line.join(" "));
+    Ok(Value::Null)
+}
+
+fn len(args: &[Value]) -> Result<Value, String> {
+    let [value] = args else {
+        return Err(format!("len() takes 1 argument, got {}", args.len()));
+    };
+    let len = match value {
+        Value::List(items) => items.len(),
+        Value::Map(entries) => entries.len(),
+        other => match other.as_str() {
+            Some(text) => text.chars().count(),
+            None => return Err(format!("len() needs a list, map or string, got {}", other.type_name())),
+        },
+    };
+    Ok(Value::Int(len as i64))
+}
+
+// Values are copies, so this returns the longer list: `xs = push(xs, v);`
+fn push(args: &[Value]) -> Result<Value, String> {
+    let [list, item] = args else {
+        return Err(format!("push() takes 2 arguments, got {}", args.len()));
+    };
+    let Value::List(items) = list else {
+        return Err(format!("push() needs a list, got {} '{}'", list.type_name(), list));
+    };
+    let mut items = items.clone();
+    items.push(item.clone());
+    Ok(Value::List(items))
+}
+
+// `split(text)` splits on runs of whitespace, `split(text, sep)` on each sep
+fn split(args: &[Value]) -> Result<Value, String> {
+    let parts: Vec<&str> = match args {
+        [text] => string_arg("split", text)?.split_whitespace().collect(),
+        [text, sep] => match string_arg("split", sep)? {
+            "" => return Err("split() needs a non-empty separator".to_string()),
+            sep => string_arg("split", text)?.split(sep).collect(),
+        },
+        _ => return Err(format!("split() takes 1 or 2 arguments, got {}", args.len())),
+    };
+    Ok(Value::List(parts.into_iter().map(|part| Value::Str(part.to_string())).collect()))
+}
+
+fn input(args: &[Value]) -> Result<Value, String> {
+    if args.len() > 1 {
+        return Err(format!("input() takes at most 1 argument, got {}", args.len()));
+    }
+    if let Some(prompt) = args.first() {
+        print!("{}", prompt);
+        io::stdout().flush().map_err(|e| e.to_string())?;
+    }
+    let mut line = String::new();
+    let read = io::stdin().lock().read_line(&mut line).map_err(|e| e.to_string())?;
+    if read == 0 {
+        return Err("input(): end of input".to_string());
+    }
+    Ok(Value::Str(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+async fn http_get(args: &[Value]) -> Result<Value, String> {
+    let [url] = args else {
+        return Err(format!("http_get() takes 1 argument, got {}", args.len()));
+    };
+    let url = string_arg("http_get", url)?;
+    http_response(http_client()?.get(url).send().await).await
+}
+
+async fn http_post(args: &[Value]) -> Result<Value, String> {
+    let [url, body] = args else {
+        return Err(format!("http_post() takes 2 arguments, got {}", args.len()));
+    };
+    let url = string_arg("http_post", url)?;
+    let request = match body {
+        // Collections go out as JSON; everything else is sent as text
+        Value::Map(_) | Value::List(_) => http_client()?.post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_json().to_string()),
+        other => http_client()?.post(url).body(other.to_string()),
+    };
+    http_response(request.send().await).await
+}
+
+fn read_file(args: &[Value], env: &Environment) -> Result<Value, String> {
+    let [path] = args else {
+        return Err(format!("read_file() takes 1 argument, got {}", args.len()));
+    };
+    let path = policy::check_read(&env.config.policy, string_arg("read_file", path)?)?;
+    fs::read_to_string(&path)
+        .map(Value::Str)
+        .map_err(|e| format!("read_file(): {}: {}", path.display(), e))
+}
+
+fn write_file(args: &[Value], env: &Environment) -> Result<Value, String> {
+    let [path, content] = args else {
+        return Err(format!("write_file() takes 2 arguments, got {}", args.len()));
+    };
+    let path = policy::check_write(&env.config.policy, string_arg("write_file", path)?)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("write_file(): {}: {}", parent.display(), e))?;
+    }
+    fs::write(&path, content.to_string()).map_err(|e| format!("write_file(): {}: {}", path.display(), e))?;
+    Ok(Value::Null)
+}
+
+fn exists(args: &[Value], env: &Environment) -> Result<Value, String> {
+    let [path] = args else {
+        return Err(format!("exists() takes 1 argument, got {}", args.len()));
+    };
+    let path = policy::check_read(&env.config.policy, string_arg("exists", path)?)?;
+    Ok(Value::Bool(path.exists()))
+}
+
+// Matches outside the read roots are dropped rather than failing the call
+fn glob(args: &[Value], env: &Environment) -> Result<Value, String> {
+    let [pattern] = args else {
+        return Err(format!("glob() takes 1 argument, got {}", args.len()));
+    };
+    let pattern = string_arg("glob", pattern)?;
+    let paths = glob::glob(pattern).map_err(|e| format!("glob(): invalid pattern '{}': {}", pattern, e))?;
+    let mut matches = Vec::new();
+    for path in paths.flatten() {
+        let path = path.to_string_lossy().into_owned();
+        if policy::check_read(&env.config.policy, &path).is_ok() {
+            matches.push(Value::Str(path));
+        }
+    }
+    Ok(Value::List(matches))
+}
+
+fn json_parse(args: &[Value]) -> Result<Value, String> {
+    let [text] = args else {
+        return Err(format!("json_parse() takes 1 argument, got {}", args.len()));
+    };
+    let json: serde_json::Value = serde_json::from_str(string_arg("json_parse", text)?)
+        .map_err(|e| format!("json_parse(): {}", e))?;
+    Ok(Value::from_json(json))
+}
+
+fn json_string(args: &[Value]) -> Result<Value, String> {
+    let [value] = args else {
+        return Err(format!("json_string() takes 1 argument, got {}", args.len()));
+    };
+    Ok(Value::Str(value.to_json().to_string()))
+}
+
+// Unix seconds, so timestamps compare and subtract as plain ints
+fn now(args: &[Value]) -> Result<Value, String> {
+    if !args.is_empty() {
+        return Err(format!("now() takes no arguments, got {}", args.len()));
+    }
+    Ok(Value::Int(chrono::Utc::now().timestamp()))
+}
+
+fn format_time(args: &[Value]) -> Result<Value, String> {
+    let (timestamp, format) = match args {
+        [timestamp] => (timestamp, "%Y-%m-%dT%H:%M:%SZ"),
+        [timestamp, format] => (timestamp, string_arg("format_time", format)?),
+        _ => return Err(format!("format_time() takes 1 or 2 arguments, got {}", args.len())),
+    };
+    let seconds = timestamp.as_int()
+        .ok_or_else(|| format!("format_time() expects a timestamp, got {}", timestamp.type_name()))?;
+    let time = chrono::DateTime::from_timestamp(seconds, 0)
+        .ok_or_else(|| format!("format_time(): timestamp {} is out of range", seconds))?;
+    Ok(Value::Str(time.format(format).to_string()))
+}
+
+// Accepts plain seconds (0.5 included) or a duration string such as "500ms",
+// "30s" or "2m"
+async fn sleep(args: &[Value]) -> Result<Value, String> {
+    let [duration] = args else {
+        return Err(format!("sleep() takes 1 argument, got {}", args.len()));
+    };
+    let duration = match duration {
+        Value::Int(seconds) if *seconds >= 0 => Duration::from_secs(*seconds as u64),
+        Value::Float(seconds) if seconds.is_finite() && *seconds >= 0.0 => Duration::from_secs_f64(*seconds),
+        Value::Str(text) => parse_duration(text)?,
+        other => return Err(format!("sleep() expects seconds or a duration string, got {}", other)),
+    };
+    tokio::time::sleep(duration).await;
+    Ok(Value::Null)
+}
+
+fn uuid(args: &[Value]) -> Result<Value, String> {
+    if !args.is_empty() {
+        return Err(format!("uuid() takes no arguments, got {}", args.len()));
+    }
+    Ok(Value::Str(uuid::Uuid::new_v4().to_string()))
+}
+
+// Inclusive on both ends, so random_int(1, 6) behaves like a die
+fn random_int(args: &[Value]) -> Result<Value, String> {
+    let [low, high] = args else {
+        return Err(format!("random_int() takes 2 arguments, got {}", args.len()));
+    };
+    let (Some(low), Some(high)) = (low.as_int(), high.as_int()) else {
+        return Err("random_int() expects two integers".to_string());
+    };
+    if low > high {
+        return Err(format!("random_int(): lower bound {} is greater than upper bound {}", low, high));
+    }
+    Ok(Value::Int(rand::thread_rng().gen_range(low..=high)))
+}
+
+fn regex_match(args: &[Value]) -> Result<Value, String> {
+    let [pattern, text] = args else {
+        return Err(format!("regex_match() takes 2 arguments, got {}", args.len()));
+    };
+    let regex = compile_regex("regex_match", pattern)?;
+    Ok(Value::Bool(regex.is_match(string_arg("regex_match", text)?)))
+}
+
+fn regex_replace(args: &[Value]) -> Result<Value, String> {
+    let [pattern, text, replacement] = args else {
+        return Err(format!("regex_replace() takes 3 arguments, got {}", args.len()));
+    };
+    let regex = compile_regex("regex_replace", pattern)?;
+    let text = string_arg("regex_replace", text)?;
+    let replacement = string_arg("regex_replace", replacement)?;
+    Ok(Value::Str(regex.replace_all(text, replacement).into_owned()))
+}
+
+// First match only: index 0 is the whole match, then each group in order
+// (null where a group did not participate). Null when nothing matches.
+fn regex_captures(args: &[Value]) -> Result<Value, String> {
+    let [pattern, text] = args else {
+        return Err(format!("regex_captures() takes 2 arguments, got {}", args.len()));
+    };
+    let regex = compile_regex("regex_captures", pattern)?;
+    let Some(captures) = regex.captures(string_arg("regex_captures", text)?) else {
+        return Ok(Value::Null);
+    };
+    Ok(Value::List(captures.iter()
+        .map(|group| group.map_or(Value::Null, |m| Value::from(m.as_str())))
+        .collect()))
+}
+
+fn compile_regex(function: &str, pattern: &Value) -> Result<Regex, String> {
+    let pattern = string_arg(function, pattern)?;
+    Regex::new(pattern).map_err(|e| format!("{}(): invalid pattern: {}", function, e))
+}
+
+// env("NAME", default) falls back to the default, otherwise null, when unset
+fn env_var(args: &[Value], env: &Environment) -> Result<Value, String> {
+    let (name, default) = match args {
+        [name] => (name, Value::Null),
+        [name, default] => (name, default.clone()),
+        _ => return Err(format!("env() takes 1 or 2 arguments, got {}", args.len())),
+    };
+    let name = string_arg("env", name)?;
+    policy::check_env_read(&env.config.policy, name)?;
+    match env.env_vars.get(name) {
+        Some(value) => Ok(Value::Str(value.clone())),
+        None => Ok(std::env::var(name).map(Value::Str).unwrap_or(default)),
+    }
+}
+
+// The language set by `@target`, or null before any
+fn target(args: &[Value], env: &Environment) -> Result<Value, String> {
+    if !args.is_empty() {
+        return Err(format!("target() takes no arguments, got {}", args.len()));
+    }
+    Ok(env.target_lang.clone().map_or(Value::Null, Value::Str))
+}
+
+// Set on the rift process itself, so later fuse blocks inherit it
+fn set_env(args: &[Value], env: &mut Environment) -> Result<Value, String> {
+    let [name, value] = args else {
+        return Err(format!("set_env() takes 2 arguments, got {}", args.len()));
+    };
+    let name = string_arg("set_env", name)?;
+    if name.is_empty() || name.contains(['=', '\0']) {
+        return Err(format!("set_env(): invalid variable name {:?}", name));
+    }
+    policy::check_env_write(&env.config.policy, name)?;
+    env.env_vars.insert(name.to_string(), value.to_string());
+    Ok(Value::Null)
+}
+
+// Script logs go through tracing on stderr, tagged with the calling rift or
+// task, so they stay separate from fuse program output on stdout
+fn log(name: &str, args: &[Value], env: &Environment) -> Result<Value, String> {
+    let message: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    let message = message.join(" ");
+    let scope = env.call_stack.last().map(String::as_str).unwrap_or("main");
+    match name {
+        "log.debug" => tracing::debug!(target: "rift::script", rift = scope, "{}", message),
+        "log.info" => tracing::info!(target: "rift::script", rift = scope, "{}", message),
+        "log.warn" => tracing::warn!(target: "rift::script", rift = scope, "{}", message),
+        _ => tracing::error!(target: "rift::script", rift = scope, "{}", message),
+    }
+    Ok(Value::Null)
+}
+
+fn store_put(args: &[Value]) -> Result<Value, String> {
+    let [key, value] = args else {
+        return Err(format!("store_put() takes 2 arguments, got {}", args.len()));
+    };
+    store::put(string_arg("store_put", key)?, value)?;
+    Ok(Value::Null)
+}
+
+// Missing keys give the default (null unless one is passed)
+fn store_get(args: &[Value]) -> Result<Value, String> {
+    let (key, default) = match args {
+        [key] => (key, Value::Null),
+        [key, default] => (key, default.clone()),
+        _ => return Err(format!("store_get() takes 1 or 2 arguments, got {}", args.len())),
+    };
+    Ok(store::get(string_arg("store_get", key)?)?.unwrap_or(default))
+}
+
+fn table(args: &[Value]) -> Result<Value, String> {
+    let [name] = args else {
+        return Err(format!("table() takes 1 argument, got {}", args.len()));
+    };
+    tables::table(string_arg("table", name)?)
+}
+
+// Takes a table artifact or a table name, plus an optional row count
+fn table_preview(args: &[Value]) -> Result<Value, String> {
+    let (table, rows) = match args {
+        [table] => (table, 10),
+        [table, Value::Int(rows)] if *rows > 0 => (table, *rows as usize),
+        _ => return Err("table_preview() takes a table and an optional positive row count".to_string()),
+    };
+    let table = match table {
+        Value::Str(name) => tables::table(name)?,
+        other => other.clone(),
+    };
+    let Value::Artifact { path, .. } = &table else {
+        return Err(format!("table_preview() expects a table, got {}", table.type_name()));
+    };
+    println!("{}", tables::preview(path, rows)?);
+    Ok(Value::Null)
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder().timeout(HTTP_TIMEOUT).build().map_err(|e| e.to_string())
+}
+
+// Non-2xx statuses are returned, not raised, so scripts can branch on them
+async fn http_response(response: reqwest::Result<reqwest::Response>) -> Result<Value, String> {
+    let response = response.map_err(|e| format!("HTTP request failed: {}", e))?;
+    let status = response.status();
+    let headers: BTreeMap<String, Value> = response.headers().iter()
+        .filter_map(|(name, value)| Some((name.to_string(), Value::from(value.to_str().ok()?))))
+        .collect();
+    let body = response.text().await.map_err(|e| format!("Failed to read HTTP response: {}", e))?;
+
+    let mut result = BTreeMap::new();
+    result.insert("status".to_string(), Value::Int(status.as_u16() as i64));
+    result.insert("ok".to_string(), Value::Bool(status.is_success()));
+    result.insert("headers".to_string(), Value::Map(headers));
+    result.insert("body".to_string(), Value::Str(body));
+    Ok(Value::Map(result))
+}
+
+fn string_arg<'a>(function: &str, value: &'a Value) -> Result<&'a str, String> {
+    value.as_str().ok_or_else(|| format!("{}() expects a string, got {}", function, value.type_name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strs(items: &[&str]) -> Value {
+        Value::List(items.iter().map(|item| Value::from(*item)).collect())
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split(&["  a \t b\n\nc ".into()]).unwrap(), strs(&["a", "b", "c"]));
+        assert_eq!(split(&["".into()]).unwrap(), strs(&[]));
+        assert_eq!(split(&["a,,b,".into(), ",".into()]).unwrap(), strs(&["a", "", "b", ""]));
+        assert_eq!(split(&["k=>v".into(), "=>".into()]).unwrap(), strs(&["k", "v"]));
+        assert!(split(&["a b".into(), "".into()]).unwrap_err().contains("non-empty separator"));
+        assert!(split(&[Value::Int(1)]).is_err());
+        assert!(split(&[]).is_err());
+    }
+
+    #[test]
+    fn test_set_env() {
+        let mut env = Environment::new();
+        env.config.policy.env_read = vec!["RIFT_SET_ENV_TEST".to_string()];
+        env.config.policy.env_write = vec!["RIFT_SET_ENV_TEST".to_string()];
+        set_env(&["RIFT_SET_ENV_TEST".into(), Value::Int(1)], &mut env).unwrap();
+        assert_eq!(env_var(&["RIFT_SET_ENV_TEST".into()], &env).unwrap(), Value::from("1"));
+        // Kept out of rift's own environment
+        assert!(std::env::var("RIFT_SET_ENV_TEST").is_err());
+    }
+
+    #[test]
+    fn test_len_and_push() {
+        assert_eq!(len(&[strs(&["a", "b"])]).unwrap(), Value::Int(2));
+        assert_eq!(len(&["héllo".into()]).unwrap(), Value::Int(5));
+        assert_eq!(len(&[Value::Map(BTreeMap::from([("k".to_string(), Value::Null)]))]).unwrap(), Value::Int(1));
+        assert!(len(&[Value::Int(3)]).is_err());
+
+        let list = strs(&["a"]);
+        assert_eq!(push(&[list.clone(), "b".into()]).unwrap(), strs(&["a", "b"]));
+        assert_eq!(list, strs(&["a"]));
+        assert!(push(&["a".into(), "b".into()]).is_err());
+    }
+
+    #[test]
+    fn test_json() {
+        let value = json_parse(&[r#"{"name": "rift", "tags": [1, 2.5, true, null]}"#.into()]).unwrap();
+        let Value::Map(entries) = &value else { panic!("expected a map, got {:?}", value) };
+        assert_eq!(entries["name"], Value::from("rift"));
+        assert_eq!(entries["tags"], Value::List(vec![Value::Int(1), Value::Float(2.5), Value::Bool(true), Value::Null]));
+
+        let text = json_string(&[value.clone()]).unwrap();
+        assert_eq!(json_parse(&[text]).unwrap(), value);
+        assert!(json_parse(&["{".into()]).unwrap_err().starts_with("json_parse():"));
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(&[Value::Int(0)]).unwrap(), Value::from("1970-01-01T00:00:00Z"));
+        assert_eq!(format_time(&[Value::Int(86_400), "%Y-%m-%d".into()]).unwrap(), Value::from("1970-01-02"));
+        assert!(format_time(&["soon".into()]).is_err());
+        assert!(format_time(&[Value::Int(i64::MAX)]).is_err());
+    }
+
+    #[test]
+    fn test_regex() {
+        assert_eq!(regex_match(&[r"^\d+$".into(), "42".into()]).unwrap(), Value::Bool(true));
+        assert_eq!(regex_match(&[r"^\d+$".into(), "4x2".into()]).unwrap(), Value::Bool(false));
+        assert_eq!(regex_replace(&[r"(\w+)@".into(), "ann@x bob@y".into(), "$1 at ".into()]).unwrap(), Value::from("ann at x bob at y"));
+        assert_eq!(regex_captures(&[r"(\d+)-(\d+)?".into(), "v 10-".into()]).unwrap(), Value::List(vec!["10-".into(), "10".into(), Value::Null]));
+        assert_eq!(regex_captures(&[r"\d".into(), "none".into()]).unwrap(), Value::Null);
+        assert!(regex_match(&["(".into(), "x".into()]).is_err());
+    }
+
+    #[test]
+    fn test_random_int() {
+        for _ in 0..100 {
+            let Value::Int(n) = random_int(&[Value::Int(-2), Value::Int(2)]).unwrap() else { panic!() };
+            assert!((-2..=2).contains(&n));
+        }
+        assert_eq!(random_int(&[Value::Int(7), Value::Int(7)]).unwrap(), Value::Int(7));
+        assert!(random_int(&[Value::Int(3), Value::Int(1)]).is_err());
+        assert!(random_int(&[Value::Int(1)]).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::error::{Result, RiftError};
+use crate::state;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// A bundle is a copy of the rift executable with this appended:
+// JSON payload, its length as a little-endian u64, then MAGIC
+const MAGIC: &[u8; 8] = b"RIFTBNDL";
+const CONFIG_FILE: &str = "rift.toml";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Bundle {
+    pub script_name: String,
+    pub source: String,
+    // rift.toml from where the bundle was built, so hooks and profiles apply
+    pub config: Option<String>,
+    // Files under the state directory as (relative path, base64 contents)
+    #[serde(default)]
+    pub files: Vec<(String, String)>,
+}
+
+// `rift bundle`: writes `output`, an executable that runs `script` with no
+// rift installation. With `with_artifacts`, stored artifacts and cached
+// blobs from the state directory go along too.
+pub fn create(script: &Path, output: &Path, with_artifacts: bool) -> Result<u64> {
+    let source = fs::read_to_string(script)?;
+    crate::parser::parse(&crate::lexer::tokenize(&source)?)?;
+    let config_path = std::env::var("RIFT_CONFIG").map(PathBuf::from).unwrap_or_else(|_| PathBuf::from(CONFIG_FILE));
+    let mut bundle = Bundle {
+        script_name: script.display().to_string(),
+        source,
+        config: fs::read_to_string(config_path).ok(),
+        files: Vec::new(),
+    };
+    if with_artifacts {
+        let root = state::state_dir();
+        for dir in ["artifacts", "cache"] {
+            collect(&root, &root.join(dir), &mut bundle.files)?;
+        }
+    }
+
+    let payload = serde_json::to_vec(&bundle).map_err(|e| RiftError::CacheError(e.to_string()))?;
+    let mut executable = fs::read(std::env::current_exe()?)?;
+    // Bundling from a bundle replaces its payload rather than stacking another
+    if let Some(len) = payload_len(&executable) {
+        executable.truncate(executable.len() - len as usize - 16);
+    }
+    executable.extend_from_slice(&payload);
+    executable.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    executable.extend_from_slice(MAGIC);
+    fs::write(output, &executable)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(output, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(executable.len() as u64)
+}
+
+fn collect(root: &Path, dir: &Path, files: &mut Vec<(String, String)>) -> Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let mut entries: Vec<_> = fs::read_dir(dir)?.filter_map(|entry| entry.ok().map(|e| e.path())).collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect(root, &path, files)?;
+        } else {
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+            files.push((relative, base64::engine::general_purpose::STANDARD.encode(fs::read(&path)?)));
+        }
+    }
+    Ok(())
+}
+
+fn payload_len(executable: &[u8]) -> Option<u64> {
+    let trailer = executable.len().checked_sub(16).map(|start| &executable[start..])?;
+    if &trailer[8..] != MAGIC {
+        return None;
+    }
+    let len = u64::from_le_bytes(trailer[..8].try_into().ok()?);
+    (len + 16 <= executable.len() as u64).then_some(len)
+}
+
+// The bundle appended to the running executable, if it is one. Only the
+// trailer is read for a plain `rift`, so startup stays cheap.
+pub fn embedded() -> Result<Option<Bundle>> {
+    let mut file = fs::File::open(std::env::current_exe()?)?;
+    let size = file.metadata()?.len();
+    if size < 16 {
+        return Ok(None);
+    }
+    let mut trailer = [0u8; 16];
+    file.seek(SeekFrom::End(-16))?;
+    file.read_exact(&mut trailer)?;
+    if &trailer[8..] != MAGIC {
+        return Ok(None);
+    }
+    let len = u64::from_le_bytes(trailer[..8].try_into().unwrap_or_default());
+    if len + 16 > size {
+        return Err(RiftError::CacheError("bundle payload is truncated".to_string()));
+    }
+    let mut payload = vec![0u8; len as usize];
+    file.seek(SeekFrom::Start(size - 16 - len))?;
+    file.read_exact(&mut payload)?;
+    serde_json::from_slice(&payload).map(Some).map_err(|e| RiftError::CacheError(format!("invalid bundle: {}", e)))
+}
+
+impl Bundle {
+    // Writes the embedded config and state files to a directory of their
+    // own, keyed by content, and points RIFT_CONFIG and RIFT_STATE_DIR at
+    // it. Deployments the script records land there too.
+    pub fn unpack(&self) -> Result<()> {
+        if self.config.is_none() && self.files.is_empty() {
+            return Ok(());
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(self.source.as_bytes());
+        for (path, contents) in &self.files {
+            hasher.update(path.as_bytes());
+            hasher.update(contents.as_bytes());
+        }
+        let hash = format!("{:x}", hasher.finalize());
+        let dir = std::env::temp_dir().join(format!("rift-bundle-{}", &hash[..12]));
+        let state_dir = dir.join(".rift");
+        for (path, contents) in &self.files {
+            if path.split('/').any(|part| part == "..") {
+                return Err(RiftError::CacheError(format!("bundle file {} escapes the state directory", path)));
+            }
+            let target = state_dir.join(path);
+            if target.exists() {
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let bytes = base64::engine::general_purpose::STANDARD.decode(contents)
+                .map_err(|e| RiftError::CacheError(format!("bundle file {}: {}", path, e)))?;
+            fs::write(target, bytes)?;
+        }
+        fs::create_dir_all(&state_dir)?;
+        std::env::set_var("RIFT_STATE_DIR", &state_dir);
+        if let Some(config) = &self.config {
+            let path = dir.join(CONFIG_FILE);
+            fs::write(&path, config)?;
+            std::env::set_var("RIFT_CONFIG", path);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_len() {
+        let mut executable = b"\x7fELF...".to_vec();
+        assert_eq!(payload_len(&executable), None);
+        executable.extend_from_slice(b"{}");
+        executable.extend_from_slice(&2u64.to_le_bytes());
+        executable.extend_from_slice(MAGIC);
+        assert_eq!(payload_len(&executable), Some(2));
+    }
+}
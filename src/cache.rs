@@ -0,0 +1,137 @@
+use crate::i18n;
+use crate::interpreter::Environment;
+use crate::state;
+use crate::value::Value;
+
+// REPL `cache ...` commands over the session's fuse output cache. Entries
+// are keyed by fuse hash and can be named by any unambiguous prefix of it.
+pub fn command(args: &[&str], env: &mut Environment) -> Result<(), String> {
+    match args {
+        ["list"] => {
+            list(env);
+            Ok(())
+        }
+        ["stats"] => {
+            stats(env);
+            Ok(())
+        }
+        ["info", hash] => info(&resolve(hash, env)?, env),
+        ["pin", hash] => {
+            let hash = resolve(hash, env)?;
+            println!("{}", i18n::message("cache.pinned", &[&&hash[..12]]));
+            env.pinned.insert(hash);
+            Ok(())
+        }
+        ["unpin", hash] => {
+            let hash = resolve(hash, env)?;
+            env.pinned.remove(&hash);
+            println!("{}", i18n::message("cache.unpinned", &[&&hash[..12]]));
+            Ok(())
+        }
+        ["rm", hash] => remove(&resolve(hash, env)?, false, env),
+        ["rm", hash, "--force"] => remove(&resolve(hash, env)?, true, env),
+        _ => Err("Usage: cache list | cache stats | cache info <hash> | cache pin <hash> | cache unpin <hash> | cache rm <hash> [--force]".to_string()),
+    }
+}
+
+fn list(env: &Environment) {
+    if env.artifact_cache.is_empty() {
+        println!("{}", i18n::text("cache.empty"));
+        return;
+    }
+    let mut entries: Vec<_> = env.artifact_cache.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    for (hash, value) in entries {
+        let pin = if env.pinned.contains(hash) { " (pinned)" } else { "" };
+        println!("  {}  {:<10} {:>10} bytes{}", &hash[..12], describe(value), size(value), pin);
+    }
+}
+
+// Blobs are stored once however many entries refer to them; the savings
+// are what storing each entry's output separately would have cost on top
+fn stats(env: &Environment) {
+    let mut blobs = std::collections::BTreeMap::new();
+    let (mut inline, mut referenced) = (0u64, 0u64);
+    for value in env.artifact_cache.values() {
+        match value {
+            Value::Artifact { hash, size, .. } => {
+                referenced += size;
+                blobs.entry(hash).or_insert((0, *size)).0 += 1;
+            }
+            other => inline += size(other),
+        }
+    }
+    let stored: u64 = blobs.values().map(|(_, size)| size).sum();
+    let shared = blobs.values().filter(|(uses, _)| *uses > 1).count();
+    println!("{}", i18n::message("cache.entries", &[&env.artifact_cache.len(), &env.pinned.len()]));
+    println!("{}", i18n::message("cache.inline", &[&inline]));
+    println!("{}", i18n::message("cache.blobs", &[&blobs.len(), &stored, &shared]));
+    println!("{}", i18n::message("cache.saved", &[&(referenced - stored)]));
+}
+
+fn info(hash: &str, env: &Environment) -> Result<(), String> {
+    let value = &env.artifact_cache[hash];
+    println!("Hash:   {}", hash);
+    println!("Kind:   {}", describe(value));
+    println!("Size:   {} bytes", size(value));
+    println!("Pinned: {}", if env.pinned.contains(hash) { "yes" } else { "no" });
+    if let Some(age) = env.cached_at.get(hash).and_then(|stored| stored.elapsed().ok()) {
+        println!("Age:    {}s", age.as_secs());
+    }
+    match value {
+        Value::Artifact { path, hash: blob, .. } => {
+            println!("Blob:   {} ({})", path.display(), &blob[..12.min(blob.len())]);
+        }
+        other => {
+            let text = other.to_string();
+            let preview: Vec<_> = text.lines().take(5).collect();
+            println!("Output:\n  {}", preview.join("\n  "));
+            if text.lines().count() > preview.len() {
+                println!("  ...");
+            }
+        }
+    }
+    Ok(())
+}
+
+// Pinned entries (say, a long Rust build) only go with --force. A blob on
+// disk is deleted once no other entry refers to it.
+fn remove(hash: &str, force: bool, env: &mut Environment) -> Result<(), String> {
+    if env.pinned.contains(hash) && !force {
+        return Err(format!("{} is pinned; use 'cache rm {} --force' or unpin it first", &hash[..12], &hash[..12]));
+    }
+    env.pinned.remove(hash);
+    env.cached_at.remove(hash);
+    if let Some(Value::Artifact { path, .. }) = env.artifact_cache.remove(hash) {
+        let shared = env.artifact_cache.values().any(|v| matches!(v, Value::Artifact { path: p, .. } if *p == path));
+        if !shared && path.starts_with(state::state_dir()) {
+            std::fs::remove_file(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+    }
+    println!("{}", i18n::message("cache.removed", &[&&hash[..12]]));
+    Ok(())
+}
+
+fn resolve(prefix: &str, env: &Environment) -> Result<String, String> {
+    let matches: Vec<_> = env.artifact_cache.keys().filter(|hash| hash.starts_with(prefix)).collect();
+    match matches.as_slice() {
+        [hash] => Ok((*hash).clone()),
+        [] => Err(format!("No cache entry with hash {}", prefix)),
+        _ => Err(format!("Cache hash {} is ambiguous", prefix)),
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Output { lang, .. } => lang.clone(),
+        other => other.type_name().to_string(),
+    }
+}
+
+fn size(value: &Value) -> u64 {
+    match value {
+        Value::Output { stdout, .. } => stdout.len() as u64,
+        Value::Artifact { size, .. } => *size,
+        other => other.to_string().len() as u64,
+    }
+}
@@ -0,0 +1,128 @@
+use crate::error::Result;
+use crate::lexer::{tokenize, Token, TokenKind};
+use crate::parser::parse;
+use crate::{git, validate, AST};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+// `rift run --changed-since <rev>`: the rifts and tasks in `script` that
+// could behave differently than at `rev`. One is affected when its body
+// changed, when a file it reads with `@fuse ... from "path"` changed, or
+// when it calls (directly or not) something affected.
+pub fn affected(script: &Path, source: &str, rev: &str) -> Result<BTreeSet<String>> {
+    let changed: BTreeSet<PathBuf> = git::changed_files(rev)?.into_iter().map(|p| normalize(&p)).collect();
+    let tokens = tokenize(source)?;
+    let bodies = definitions(&parse(&tokens)?);
+
+    let mut affected = BTreeSet::new();
+    for (name, files) in source_files(&tokens) {
+        if files.iter().any(|file| changed.contains(&normalize(file))) {
+            affected.insert(name);
+        }
+    }
+    if changed.contains(&normalize(script)) {
+        // A script that did not exist or parse at `rev` has nothing to compare
+        let old = git::show(rev, script).ok()
+            .and_then(|old| tokenize(&old).and_then(|tokens| parse(&tokens)).ok())
+            .map(|program| definitions(&program))
+            .unwrap_or_default();
+        for (name, body) in &bodies {
+            if old.get(name) != Some(body) {
+                affected.insert(name.clone());
+            }
+        }
+    }
+
+    // Callers of anything affected, until nothing new turns up
+    let calls: BTreeMap<&String, BTreeSet<&str>> = bodies.iter().map(|(name, body)| (name, called(body))).collect();
+    loop {
+        let before = affected.len();
+        for (name, callees) in &calls {
+            if callees.iter().any(|callee| affected.contains(*callee)) {
+                affected.insert((*name).clone());
+            }
+        }
+        if affected.len() == before {
+            return Ok(affected);
+        }
+    }
+}
+
+// Each rift, task and fn as the Debug text of its body, which is enough to
+// tell whether it changed; rifts win over tasks and tasks over functions of
+// the same name, as in calls
+fn definitions(program: &AST) -> BTreeMap<String, String> {
+    let mut found = BTreeMap::new();
+    if let AST::Program(statements) = program {
+        for statement in statements.iter().rev() {
+            if let AST::Function(name, _, body) = statement {
+                found.insert(name.clone(), format!("{:?}", body));
+            }
+        }
+        for statement in statements.iter().rev() {
+            if let AST::Task(name, body) = statement {
+                found.insert(name.clone(), format!("{:?}", body));
+            }
+        }
+        for statement in statements.iter().rev() {
+            if let AST::Rift(name, body) = statement {
+                found.insert(name.clone(), format!("{:?}", body));
+            }
+        }
+    }
+    found
+}
+
+fn called(body: &str) -> BTreeSet<&str> {
+    // Bodies are kept as Debug text, so find calls in that form
+    body.match_indices("Call(\"")
+        .filter_map(|(i, prefix)| body[i + prefix.len()..].split('"').next())
+        .collect()
+}
+
+// Working-tree files each rift or task reads with `from "path"`. Files named
+// with git_file(...) are pinned to a revision and never change.
+fn source_files(tokens: &[Token]) -> BTreeMap<String, Vec<String>> {
+    let mut files: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut current: Option<(String, usize)> = None;
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        let is = |kind: TokenKind, value: &str| token.kind == kind && token.value == value;
+        if (is(TokenKind::Keyword, "@rift") || is(TokenKind::Keyword, "@task")) && current.is_none() {
+            if let Some(name) = tokens.get(i + 1) {
+                current = Some((name.value.clone(), depth));
+            }
+        } else if is(TokenKind::Symbol, "{") {
+            depth += 1;
+        } else if is(TokenKind::Symbol, "}") {
+            depth -= 1;
+            if current.as_ref().is_some_and(|(_, start)| *start == depth) {
+                current = None;
+            }
+        } else if is(TokenKind::Identifier, "from") {
+            if let (Some((name, _)), Some(path)) = (&current, tokens.get(i + 1).filter(|t| t.kind == TokenKind::String)) {
+                files.entry(name.clone()).or_default().push(path.value.clone());
+            }
+        }
+    }
+    files
+}
+
+fn normalize(path: impl AsRef<Path>) -> PathBuf {
+    path.as_ref().components().filter(|c| !matches!(c, std::path::Component::CurDir)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calls_and_sources() {
+        let source = "@task helper { @fuse \"python\" { \"1\" } } @rift app { call helper; } @rift other { @fuse \"python\" { \"2\" } }";
+        let tokens = tokenize(source).unwrap();
+        let bodies = definitions(&parse(&tokens).unwrap());
+        assert_eq!(called(&bodies["app"]), BTreeSet::from(["helper"]));
+        assert!(called(&bodies["other"]).is_empty());
+        assert!(source_files(&tokens).is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+use crate::error::Result;
+use crate::state::DeploymentRecord;
+use crate::stats::ExecStats;
+use regex::Regex;
+use std::fmt::Write;
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::path::Path;
+
+// Set by --ci: where results are reported besides the terminal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CiMode {
+    Github,
+}
+
+impl CiMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "github" => Some(CiMode::Github),
+            _ => None,
+        }
+    }
+}
+
+// A GitHub Actions workflow command that annotates `file` in the PR.
+// Messages carry positions as "line N" when they have one.
+pub fn annotation(level: &str, file: &Path, message: &str) -> String {
+    let line = Regex::new(r"line (\d+)").ok()
+        .and_then(|re| re.captures(message))
+        .map(|captures| format!(",line={}", &captures[1]))
+        .unwrap_or_default();
+    format!("::{} file={}{}::{}", level, escape_property(&file.display().to_string()), line, escape_data(message))
+}
+
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+// Markdown for the job summary page: fuse executions and the deployments
+// the script recorded
+pub fn job_summary(script: &Path, status: i32, stats: &ExecStats, deployments: &[DeploymentRecord]) -> String {
+    let mut summary = String::new();
+    let result = if status == 0 { "passed" } else { "failed" };
+    writeln!(summary, "### rift: `{}` {}\n", script.display(), result).ok();
+    if stats.is_empty() {
+        writeln!(summary, "No fuse blocks ran.\n").ok();
+    } else {
+        summary.push_str(&stats.markdown());
+        summary.push('\n');
+    }
+    if !deployments.is_empty() {
+        writeln!(summary, "| Deployment | Target | Address | Artifact |\n|---|---|---|---|").ok();
+        for record in deployments {
+            writeln!(summary, "| {} | {} | {} | `{}` |", record.id, record.target, record.address, &record.artifact_hash[..12.min(record.artifact_hash.len())]).ok();
+        }
+        summary.push('\n');
+    }
+    summary
+}
+
+// Appends to $GITHUB_STEP_SUMMARY; outside Actions there is nowhere to put it
+pub fn write_summary(markdown: &str) -> Result<()> {
+    let Ok(path) = std::env::var("GITHUB_STEP_SUMMARY") else {
+        return Ok(());
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(crate::redact::text(markdown).as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotation() {
+        assert_eq!(
+            annotation("error", Path::new("deploy.rift"), "Parse error: Unexpected token: '}' at line 4, column 2"),
+            "::error file=deploy.rift,line=4::Parse error: Unexpected token: '}' at line 4, column 2",
+        );
+        assert_eq!(annotation("warning", Path::new("a,b.rift"), "50% done\nnext"), "::warning file=a%2Cb.rift::50%25 done%0Anext");
+    }
+}
@@ -0,0 +1,285 @@
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(name = "rift", version, about = "Multi-Language Code Fusion and Deployment Platform")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(flatten)]
+    pub context: ContextArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Script to run; shorthand for `rift run <script>`
+    pub script: Option<PathBuf>,
+}
+
+// Flags every mode that builds an Environment understands
+#[derive(Debug, Clone, Default, Args)]
+pub struct ContextArgs {
+    /// Redeploy even when the artifact is unchanged
+    #[arg(long, global = true)]
+    pub force: bool,
+
+    /// Simulate every deploy target: configs are checked and deployments
+    /// recorded, but nothing is contacted
+    #[arg(long, global = true)]
+    pub mock_deploys: bool,
+
+    /// Skip loading the standard library
+    #[arg(long, global = true)]
+    pub no_stdlib: bool,
+
+    /// Configuration file to use instead of ./rift.toml
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Reject (or with =warn, report) fuse blocks that use the network,
+    /// the clock or randomness
+    #[arg(long, global = true, value_name = "MODE", num_args = 0..=1, default_missing_value = "deny",
+          value_parser = ["warn", "deny"])]
+    pub deterministic: Option<String>,
+
+    /// Plain linear output for screen readers and dumb terminals: no color,
+    /// symbols or progress redraws, also in the tools fuse code runs
+    #[arg(long, global = true)]
+    pub plain: bool,
+
+    /// Also report diagnostics and results in a CI system's format
+    #[arg(long, global = true, value_name = "PROVIDER", value_parser = ["github"])]
+    pub ci: Option<String>,
+
+    /// Set a string variable before the script runs; may be repeated
+    #[arg(short = 'D', long = "define", global = true, value_name = "NAME=VALUE", value_parser = parse_define)]
+    pub define: Vec<(String, String)>,
+
+    /// Set the variables in a JSON object before the script runs; -D wins
+    /// over entries of the same name
+    #[arg(long, global = true, value_name = "PATH")]
+    pub var_file: Option<PathBuf>,
+}
+
+fn parse_define(arg: &str) -> Result<(String, String), String> {
+    let (name, value) = arg.split_once('=').ok_or_else(|| format!("expected NAME=VALUE, got '{}'", arg))?;
+    let valid = name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("'{}' is not a variable name", name));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start the interactive REPL (the default)
+    Repl,
+    /// Run a script and exit with its status
+    Run {
+        script: PathBuf,
+        /// Read the script, and `@fuse ... from` files, as committed at this revision
+        #[arg(long, value_name = "REV")]
+        git_rev: Option<String>,
+        /// Only call the rifts and tasks affected by changes since this revision
+        #[arg(long, value_name = "REV", conflicts_with = "git_rev")]
+        changed_since: Option<String>,
+    },
+    /// Reformat scripts in place
+    Fmt {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Report unformatted files instead of rewriting them
+        #[arg(long)]
+        check: bool,
+    },
+    /// Rewrite syntax from earlier rift versions, showing the changes first
+    Migrate {
+        file: PathBuf,
+        /// Save the migrated script instead of only previewing it
+        #[arg(long)]
+        write: bool,
+    },
+    /// Parse and validate scripts without running anything
+    Check {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Translate a script into a standalone program
+    Compile {
+        file: PathBuf,
+        /// What to generate
+        #[arg(long, value_parser = ["rust-project"])]
+        emit: String,
+        /// Directory to write the project to (default: the script's name)
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Package a script and the rift runtime as one executable
+    Bundle {
+        file: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Include stored artifacts and cached blobs from .rift
+        #[arg(long)]
+        with_artifacts: bool,
+    },
+    /// Write a software bill of materials for a script's fuse dependencies
+    Sbom {
+        file: PathBuf,
+        /// Only the blocks in this rift
+        #[arg(long)]
+        rift: Option<String>,
+        #[arg(long, default_value = "cyclonedx", value_parser = ["cyclonedx", "spdx"])]
+        format: String,
+        /// Write here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// List the licenses of a script's fuse dependencies
+    Licenses {
+        file: PathBuf,
+        /// Only the blocks in this rift
+        #[arg(long)]
+        rift: Option<String>,
+    },
+    /// Fetch a rift library from the package registry so scripts can `import` it
+    Install {
+        /// Package name, optionally with a version: name@1.2.0
+        package: String,
+        /// Registry index (HTTP(S) URL, git+URL or path); overrides RIFT_REGISTRY and rift.toml
+        #[arg(long)]
+        registry: Option<String>,
+    },
+    /// Check a package against its rift-package.toml and add it to the registry
+    Publish {
+        /// Directory holding rift-package.toml and the package's .rift files
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+        /// Registry to publish to; overrides RIFT_REGISTRY and rift.toml
+        #[arg(long)]
+        registry: Option<String>,
+        /// Only check and pack the package
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Verify deploy credentials before running a pipeline
+    Auth {
+        #[command(subcommand)]
+        command: AuthCommand,
+    },
+    /// Keep API keys in the OS keyring; use them as "secret:NAME" in deploy configs
+    Secret {
+        #[command(subcommand)]
+        command: SecretCommand,
+    },
+    /// Check a script for known security problems
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommand,
+    },
+    /// Turn REPL transcripts (see `record` in the REPL) into recordings
+    Demo {
+        #[command(subcommand)]
+        command: DemoCommand,
+    },
+    /// Run a script and list the deployments it recorded
+    Deploy { script: PathBuf },
+    /// Run each script in a fresh environment and report which fail
+    Test {
+        /// Scripts or directories to search for *.rift files (default: tests)
+        paths: Vec<PathBuf>,
+    },
+    /// Keep a session open for `rift attach`, optionally running a script in it
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:7879")]
+        listen: String,
+        script: Option<PathBuf>,
+    },
+    /// Serve `@fuse ... on "agent"` executions for other machines
+    Agent {
+        #[arg(long, default_value = "0.0.0.0:7878")]
+        listen: String,
+    },
+    /// Drive a served session from this terminal
+    Attach { address: String },
+    /// Inspect stored artifacts
+    Artifact {
+        #[command(subcommand)]
+        command: ArtifactCommand,
+    },
+    /// Local usage statistics, kept only with usage_stats = true in rift.toml
+    Stats {
+        #[command(subcommand)]
+        command: StatsCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AuditCommand {
+    /// Look up fuse dependencies in the OSV advisory database
+    Deps {
+        file: PathBuf,
+        /// Only the blocks in this rift
+        #[arg(long)]
+        rift: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DemoCommand {
+    /// Replay a transcript and write it as an asciinema recording
+    Export {
+        /// Where to write the .cast file
+        output: PathBuf,
+        /// Transcript written by `record <file>` in the REPL
+        #[arg(long, short)]
+        transcript: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AuthCommand {
+    /// Try each credential a deploy to the target would use
+    Check {
+        /// Deploy target, or "all" (default: the profile's target)
+        target: Option<String>,
+        /// rift.toml profile whose settings hold the credentials
+        #[arg(long)]
+        profile: Option<String>,
+    },
+    /// Print the least-privilege IAM policy the AWS backends need
+    Policy {
+        #[arg(value_parser = ["aws"])]
+        provider: String,
+        /// Scope the policy to this rift.toml profile's resources
+        #[arg(long)]
+        profile: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum StatsCommand {
+    /// Show runs and time per command and language, newest month first
+    Report {
+        #[arg(long, default_value_t = 3)]
+        months: usize,
+    },
+    /// Delete the statistics collected so far
+    Reset,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SecretCommand {
+    /// Store a secret, prompting for it (or reading the first line of stdin)
+    Set { name: String },
+    /// Print a stored secret
+    Get { name: String },
+    /// Delete a stored secret
+    Rm { name: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ArtifactCommand {
+    /// Check an artifact file or deployment against its build manifest
+    Verify { reference: String },
+}
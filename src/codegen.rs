@@ -0,0 +1,279 @@
+use crate::error::{Result, RiftError};
+use crate::i18n;
+use crate::AST;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+// Runtime the generated program is built on: fuse blocks go through
+// rift_lang's executor and deploys through its deployer, so the binary
+// spawns the same commands and makes the same calls as `rift run`.
+const RUNTIME: &str = r#"use rift_lang::{deployer, executor, state};
+use std::collections::HashMap;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+#[derive(Default)]
+struct Pipeline {
+    // Text output of each fuse block that has run, by block id
+    outputs: HashMap<usize, String>,
+    vars: HashMap<String, String>,
+}
+
+impl Pipeline {
+    async fn fuse(&mut self, id: usize, lang: &'static str, code: &'static str) -> Result<String> {
+        let output = tokio::task::spawn_blocking(move || {
+            executor::execute(lang, code, &executor::ExecOptions::default())
+        }).await??;
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        if !output.success() {
+            return Err(format!("{} block failed: {}", lang, String::from_utf8_lossy(&output.stderr)).into());
+        }
+        let (text, _) = executor::split_result(&stdout)?;
+        print!("{}", text);
+        self.outputs.insert(id, text.clone());
+        Ok(text)
+    }
+
+    fn interactive(&mut self, lang: &str, code: &str) -> Result<()> {
+        match executor::execute_interactive(lang, code, &executor::ExecOptions::default())? {
+            Some(0) => Ok(()),
+            status => Err(format!("{} block exited with {:?}", lang, status).into()),
+        }
+    }
+
+    fn var(&self, name: &str) -> Result<String> {
+        self.vars.get(name).cloned().ok_or_else(|| format!("Variable not found: {}", name).into())
+    }
+
+    async fn deploy(&mut self, target: &str, config: &[(&str, &str)]) -> Result<()> {
+        let config: HashMap<String, String> = config.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let artifact = ARTIFACT.iter()
+            .map(|(id, lang, code)| self.outputs.get(id).cloned().unwrap_or_else(|| format!("{}: {}", lang, code)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let targets = deployer::select_targets(target);
+        if targets.is_empty() {
+            return Err(format!("Unsupported target: {}", target).into());
+        }
+        for name in targets {
+            let address = deployer::deploy(name, &artifact, &config).await?;
+            let record = state::record_deployment(name, &address, &artifact, &config)?;
+            println!("{}", i18n::message("deploy.recorded", &[&record.id, &name]));
+            if let Some(var) = config.get("into") {
+                self.vars.insert(var.clone(), address);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let mut pipeline = Pipeline::default();
+    if let Err(e) = run(&mut pipeline).await {
+        eprintln!("{}", i18n::message("repl.error", &[&e]));
+        std::process::exit(1);
+    }
+}
+"#;
+
+struct Generator<'a> {
+    rifts: BTreeMap<&'a str, &'a [AST]>,
+    tasks: BTreeMap<&'a str, &'a [AST]>,
+    next_id: usize,
+}
+
+// `rift compile --emit rust-project`: a Cargo project, as (relative path,
+// contents), whose binary runs the same pipeline as the script. Covers the
+// pipeline subset of the language (rifts, tasks, fuse blocks, deploys, let,
+// print and exit); anything else is reported rather than approximated.
+pub fn rust_project(program: &AST, name: &str, source_name: &str) -> Result<Vec<(String, String)>> {
+    let AST::Program(statements) = program else {
+        return Err(RiftError::ParseError("expected a whole script".to_string()));
+    };
+    let mut generator = Generator { rifts: BTreeMap::new(), tasks: BTreeMap::new(), next_id: 0 };
+    for statement in statements {
+        match statement {
+            AST::Rift(name, body) => generator.rifts.insert(name, body),
+            AST::Task(name, body) => generator.tasks.insert(name, body),
+            _ => None,
+        };
+    }
+
+    let mut main = format!(
+        "// Generated by `rift compile --emit rust-project` from {}; regenerate\n// rather than editing by hand.\n#![allow(dead_code, unused_variables)]\n\n",
+        source_name,
+    );
+    main.push_str(RUNTIME);
+
+    // Rifts first and in name order, which is also how `rift` assembles the
+    // deploy artifact from their blocks
+    let mut artifact = Vec::new();
+    let rifts: Vec<_> = generator.rifts.iter().map(|(n, b)| (*n, *b)).collect();
+    for (rift, body) in rifts {
+        let first_id = generator.next_id;
+        let code = generator.block(body, 1)?;
+        let mut id = first_id;
+        for node in body {
+            if let Some((lang, source)) = fuse_source(node) {
+                artifact.push(format!("    ({}, {:?}, {:?}),\n", id, lang, source));
+            }
+            id += count_fuses(node);
+        }
+        write!(main, "\nasync fn rift_{}(p: &mut Pipeline) -> Result<()> {{\n{}    Ok(())\n}}\n", ident(rift), code).ok();
+    }
+    let tasks: Vec<_> = generator.tasks.iter().map(|(n, b)| (*n, *b)).collect();
+    for (task, body) in tasks {
+        let code = generator.block(body, 1)?;
+        write!(main, "\nasync fn task_{}(p: &mut Pipeline) -> Result<()> {{\n{}    Ok(())\n}}\n", ident(task), code).ok();
+    }
+    let top: Vec<_> = statements.iter().filter(|s| !matches!(s, AST::Rift(..) | AST::Task(..))).cloned().collect();
+    let code = generator.block(&top, 1)?;
+    write!(main, "\nasync fn run(p: &mut Pipeline) -> Result<()> {{\n{}    Ok(())\n}}\n", code).ok();
+    write!(main, "\nconst ARTIFACT: &[(usize, &str, &str)] = &[\n{}];\n", artifact.concat()).ok();
+
+    let manifest = format!(
+        "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\nrift-lang = \"{}\"\ntokio = {{ version = \"1\", features = [\"full\"] }}\n",
+        name,
+        env!("CARGO_PKG_VERSION"),
+    );
+    Ok(vec![("Cargo.toml".to_string(), manifest), ("src/main.rs".to_string(), main)])
+}
+
+impl Generator<'_> {
+    fn block(&mut self, body: &[AST], depth: usize) -> Result<String> {
+        let mut out = String::new();
+        for node in body {
+            let line = self.statement(node)?;
+            if !line.is_empty() {
+                writeln!(out, "{}{}", "    ".repeat(depth), line).ok();
+            }
+        }
+        Ok(out)
+    }
+
+    fn statement(&mut self, node: &AST) -> Result<String> {
+        Ok(match node {
+            AST::Fuse(lang, code) => format!("p.fuse({}, {:?}, {:?}).await?;", self.id(), lang, literal(code)?),
+            // No cache, and a GPU is up to whoever starts the program
+            AST::CachedFuse(_, fuse) | AST::GpuFuse(fuse) => self.statement(fuse)?,
+            AST::InteractiveFuse(lang, code) => format!("p.interactive({:?}, {:?})?;", lang, literal(code)?),
+            // The compiled program has no fuse cache and optimize never runs
+            AST::Cache(_) | AST::Target(_) => String::new(),
+            // Nor does it check a rift's requires
+            AST::Meta(_) => String::new(),
+            // The compiled program's variables are all global
+            AST::Let(name, value) | AST::Global(name, value) => match fuse_source(value) {
+                Some((lang, code)) => {
+                    format!("let value = p.fuse({}, {:?}, {:?}).await?; p.vars.insert({:?}.to_string(), value);", self.id(), lang, literal(code)?, name)
+                }
+                None => format!("let value = {}; p.vars.insert({:?}.to_string(), value);", expression(value)?, name),
+            },
+            AST::Assign(name, value) => format!("let value = {}; p.vars.insert({:?}.to_string(), value);", expression(value)?, name),
+            AST::Deploy(target, config) => {
+                let mut entries: Vec<_> = config.iter().collect();
+                entries.sort();
+                let config: Vec<_> = entries.iter().map(|(k, v)| format!("({:?}, {:?})", k, v)).collect();
+                format!("p.deploy({:?}, &[{}]).await?;", target, config.join(", "))
+            }
+            AST::Call(name, args) if name == "print" && args.is_empty() => "println!();".to_string(),
+            AST::Call(name, args) if name == "print" => {
+                let args = args.iter().map(expression).collect::<Result<Vec<_>>>()?;
+                format!("println!(\"{{}}\", [{}].join(\" \"));", args.join(", "))
+            }
+            AST::Call(name, args) if name == "exit" => match args.as_slice() {
+                [] => "std::process::exit(0);".to_string(),
+                [AST::Number(code)] => format!("std::process::exit({});", code),
+                _ => return Err(unsupported("exit() with a computed status")),
+            },
+            AST::Call(name, args) if args.is_empty() && self.rifts.contains_key(name.as_str()) => {
+                format!("Box::pin(rift_{}(p)).await?;", ident(name))
+            }
+            AST::Call(name, args) if args.is_empty() && self.tasks.contains_key(name.as_str()) => {
+                format!("Box::pin(task_{}(p)).await?;", ident(name))
+            }
+            AST::Call(name, _) => return Err(unsupported(&format!("call {}", name))),
+            AST::RemoteFuse(..) => return Err(unsupported("@fuse ... on an agent")),
+            AST::Invoke(..) => return Err(unsupported("@invoke")),
+            AST::If(..) | AST::While(..) | AST::For(..) | AST::Match(..) => return Err(unsupported("if, while, for and match")),
+            AST::Export(_) => return Err(unsupported("export")),
+            AST::Import(_) | AST::ImportFile(_) => return Err(unsupported("import")),
+            other => return Err(unsupported(&format!("{:?}", other))),
+        })
+    }
+
+    fn id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+}
+
+fn expression(node: &AST) -> Result<String> {
+    match node {
+        AST::String(s) => Ok(format!("{:?}.to_string()", literal(s)?)),
+        AST::Number(n) => Ok(format!("\"{}\".to_string()", n)),
+        AST::Float(x) => Ok(format!("\"{}\".to_string()", x)),
+        AST::Bool(b) => Ok(format!("\"{}\".to_string()", b)),
+        AST::Identifier(name) => Ok(format!("p.var({:?})?", name)),
+        other => Err(unsupported(&format!("the expression {:?}", other))),
+    }
+}
+
+fn fuse_source(node: &AST) -> Option<(&String, &String)> {
+    match node {
+        AST::Fuse(lang, code) => Some((lang, code)),
+        AST::CachedFuse(_, fuse) | AST::GpuFuse(fuse) => fuse_source(fuse),
+        _ => None,
+    }
+}
+
+// Ids `statement` hands out for a node, to line the artifact table up
+fn count_fuses(node: &AST) -> usize {
+    match node {
+        AST::Fuse(..) => 1,
+        AST::CachedFuse(_, fuse) | AST::GpuFuse(fuse) | AST::Let(_, fuse) | AST::Global(_, fuse) => count_fuses(fuse),
+        _ => 0,
+    }
+}
+
+fn ident(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect()
+}
+
+// Strings and fuse code as written, with `\${` unescaped; filling in `${...}`
+// takes the interpreter
+fn literal(text: &str) -> Result<String> {
+    crate::interpolate::parts(text)?.into_iter()
+        .map(|part| match part {
+            crate::interpolate::Part::Text(text) => Ok(text),
+            crate::interpolate::Part::Expr(_) => Err(unsupported("${...} interpolation")),
+        })
+        .collect()
+}
+
+fn unsupported(what: &str) -> RiftError {
+    RiftError::ParseError(format!("rift compile cannot translate {} yet; use `rift run`", what))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::tokenize, parser::parse};
+
+    #[test]
+    fn test_rust_project() {
+        let source = "@rift app { @fuse \"python\" { \"print(1)\" } } call app; @deploy \"local\" { path = \"out\" }";
+        let files = rust_project(&parse(&tokenize(source).unwrap()).unwrap(), "app", "app.rift").unwrap();
+        let main = &files[1].1;
+        assert!(main.contains("p.fuse(0, \"python\", \"print(1)\").await?;"));
+        assert!(main.contains("Box::pin(rift_app(p)).await?;"));
+        assert!(main.contains("p.deploy(\"local\", &[(\"path\", \"out\")]).await?;"));
+        assert!(main.contains("(0, \"python\", \"print(1)\"),"));
+    }
+
+    #[test]
+    fn test_rejects_unsupported() {
+        let program = parse(&tokenize("if x { call a; }").unwrap()).unwrap();
+        assert!(rust_project(&program, "app", "app.rift").is_err());
+    }
+}
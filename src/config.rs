@@ -0,0 +1,175 @@
+use crate::audit::Severity;
+use crate::error::{Result, RiftError};
+use crate::executor::{DEFAULT_MAX_OUTPUT_BYTES, DEFAULT_TIMEOUT_SECS};
+use crate::provider::Provider;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+const CONFIG_FILE: &str = "rift.toml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    // Named deploy settings, e.g. [profiles.prod] target = "aws", region = "..."
+    #[serde(default)]
+    pub profiles: HashMap<String, HashMap<String, String>>,
+    #[serde(default)]
+    pub executor: ExecutorConfig,
+    #[serde(default)]
+    pub policy: PolicyConfig,
+    // Remote execution agents by name, e.g. [agents] gpu-box = "http://10.0.0.5:7878"
+    #[serde(default)]
+    pub agents: HashMap<String, String>,
+    // Level for each `rift check` lint, e.g. [lints] unused_variable = "deny"
+    #[serde(default)]
+    pub lints: HashMap<String, LintLevel>,
+    // Deploys allowed in flight at once per target, e.g. [concurrency] aws = 2;
+    // see `deployer::DEFAULT_CONCURRENCY`
+    #[serde(default)]
+    pub concurrency: HashMap<String, usize>,
+    // Language for messages, e.g. locale = "de"; see `i18n`
+    #[serde(default)]
+    pub locale: Option<String>,
+    // Package index for `rift install`; see `registry`
+    #[serde(default)]
+    pub registry: Option<String>,
+    // Keep local usage statistics, e.g. usage_stats = true; see `usage`
+    #[serde(default)]
+    pub usage_stats: bool,
+    // What to mask in transcripts, logs and hook payloads; see `redact`
+    #[serde(default)]
+    pub redact: RedactConfig,
+}
+
+// [redact] patterns = ["acme_[0-9a-f]{32}"], env = ["DEPLOY_PASS"]
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RedactConfig {
+    // Regexes whose matches are masked
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    // Environment variables whose values are secret, besides those named
+    // like keys, tokens, secrets and passwords
+    #[serde(default)]
+    pub env: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExecutorConfig {
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub stream_output: bool,
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+    // Where dependencies come from: "system", "nix" or "conda"; see `provider`
+    #[serde(default)]
+    pub provider: Provider,
+}
+
+impl Default for ExecutorConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            stream_output: false,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            provider: Provider::default(),
+        }
+    }
+}
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+fn default_max_output_bytes() -> usize {
+    DEFAULT_MAX_OUTPUT_BYTES
+}
+
+// Roots that file builtins may touch. Relative entries are resolved against
+// the working directory, which is also the default for both. Environment
+// variable access is controlled by name patterns such as "CI_*".
+// `block_severity` refuses deploys whose fuse dependencies have advisories
+// at that severity or worse, see `rift audit deps`; `denied_licenses` are
+// SPDX patterns `rift licenses` flags.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PolicyConfig {
+    #[serde(default = "default_fs_roots")]
+    pub fs_read: Vec<String>,
+    #[serde(default = "default_fs_roots")]
+    pub fs_write: Vec<String>,
+    #[serde(default = "default_env_patterns")]
+    pub env_read: Vec<String>,
+    #[serde(default = "default_env_patterns")]
+    pub env_write: Vec<String>,
+    #[serde(default)]
+    pub block_severity: Option<Severity>,
+    #[serde(default)]
+    pub denied_licenses: Vec<String>,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            fs_read: default_fs_roots(),
+            fs_write: default_fs_roots(),
+            env_read: default_env_patterns(),
+            env_write: default_env_patterns(),
+            block_severity: None,
+            denied_licenses: Vec::new(),
+        }
+    }
+}
+
+fn default_fs_roots() -> Vec<String> {
+    vec![".".to_string()]
+}
+
+fn default_env_patterns() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+// Each hook is either an http(s) URL that receives the event as a JSON POST,
+// or a shell command that receives it on stdin.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HooksConfig {
+    pub on_deploy_success: Option<String>,
+    pub on_deploy_failure: Option<String>,
+}
+
+impl Config {
+    pub fn load() -> Result<Config> {
+        let path = config_path();
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let data = fs::read_to_string(&path)?;
+        toml::from_str(&data).map_err(|e| RiftError::ConfigError(format!("{}: {}", path.display(), e)))
+    }
+
+    // `profile = "name"` in a deploy config fills in that profile's settings;
+    // keys written in the script win
+    pub fn with_profile(&self, config: &HashMap<String, String>) -> std::result::Result<HashMap<String, String>, String> {
+        let Some(name) = config.get("profile") else {
+            return Ok(config.clone());
+        };
+        let profile = self.profiles.get(name).ok_or_else(|| format!("unknown profile '{}'", name))?;
+        Ok(profile.iter().chain(config).map(|(k, v)| (k.clone(), v.clone())).collect())
+    }
+}
+
+pub fn config_path() -> PathBuf {
+    std::env::var("RIFT_CONFIG")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(CONFIG_FILE))
+}
@@ -0,0 +1,94 @@
+// asciinema (asciicast v2) recordings for `rift demo export`: a transcript is
+// replayed and each statement appears typed at the prompt, followed by what
+// it printed after as long as it really took to run.
+use crate::error::Result;
+use crate::redact;
+use serde_json::json;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+const PROMPT: &str = "rift> ";
+const WIDTH: u32 = 100;
+const HEIGHT: u32 = 30;
+// Slow steps are cut short so a long build doesn't leave viewers waiting
+const MAX_WAIT: f64 = 3.0;
+
+#[derive(Debug, Default)]
+pub struct Cast {
+    clock: f64,
+    events: Vec<(f64, String)>,
+}
+
+impl Cast {
+    pub fn new() -> Self {
+        Cast::default()
+    }
+
+    // The prompt, then the statement a keystroke at a time, then Enter
+    pub fn type_line(&mut self, line: &str) {
+        self.emit(0.5, PROMPT);
+        for (i, ch) in redact::text(line).chars().enumerate() {
+            // Uneven but repeatable keystrokes, slower after a word
+            let delay = 0.04 + (i * 37 % 7) as f64 * 0.01 + if ch == ' ' { 0.06 } else { 0.0 };
+            self.emit(delay, &ch.to_string());
+        }
+        self.emit(0.3, "\r\n");
+    }
+
+    pub fn output(&mut self, text: &str, took: Duration) {
+        if !text.is_empty() {
+            self.emit(took.as_secs_f64().min(MAX_WAIT), &redact::text(text).replace("\r\n", "\n").replace('\n', "\r\n"));
+        }
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.clock
+    }
+
+    pub fn write(&self, path: &Path, title: &str) -> Result<()> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let header = json!({
+            "version": 2,
+            "width": WIDTH,
+            "height": HEIGHT,
+            "timestamp": chrono::Utc::now().timestamp(),
+            "title": title,
+            "env": { "SHELL": "rift", "TERM": "xterm-256color" },
+        });
+        writeln!(file, "{}", header)?;
+        for (time, data) in &self.events {
+            writeln!(file, "{}", json!([(time * 1000.0).round() / 1000.0, "o", data]))?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+
+    fn emit(&mut self, after: f64, data: &str) {
+        self.clock += after;
+        self.events.push((self.clock, data.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cast() {
+        let mut cast = Cast::new();
+        cast.type_line("print(1);");
+        cast.output("1\nOk\n", Duration::from_secs(60));
+        let path = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+        cast.write(&path, "demo").unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(lines.len(), 1 + 1 + "print(1);".len() + 1 + 1);
+        assert_eq!(lines[1], json!([0.5, "o", "rift> "]));
+        let last = lines.last().unwrap();
+        assert_eq!(last[2], "1\r\nOk\r\n");
+        assert!(last[0].as_f64().unwrap() - lines[lines.len() - 2][0].as_f64().unwrap() <= MAX_WAIT + 0.001);
+    }
+}
@@ -0,0 +1,1350 @@
+use crate::error::{self, RiftError};
+use crate::{i18n, package, state, upload};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use std::time::Duration;
+use tokio::time::sleep;
+#[cfg(feature = "deploy-web3")]
+use web3::{transports::Http, Web3};
+#[cfg(feature = "deploy-web3")]
+use web3::types::{Address, Bytes, CallRequest};
+#[cfg(feature = "deploy-web3")]
+use web3::ethabi::token::{LenientTokenizer, Tokenizer};
+#[cfg(feature = "deploy-solana")]
+use solana_client::rpc_client::RpcClient;
+#[cfg(feature = "deploy-solana")]
+use solana_sdk::{instruction::{AccountMeta, Instruction}, pubkey::Pubkey, transaction::Transaction};
+#[cfg(feature = "deploy-solana")]
+use solana_sdk::signature::{read_keypair_file, Signer};
+#[cfg(feature = "deploy-aws")]
+use rusoto_core::Region;
+#[cfg(feature = "deploy-aws")]
+use rusoto_s3::{S3Client, PutObjectRequest, S3};
+#[cfg(feature = "deploy-aws")]
+use rusoto_lambda::{LambdaClient, CreateFunctionRequest, Lambda};
+#[cfg(feature = "deploy-aws")]
+use rusoto_lambda::{AliasRoutingConfiguration, CreateAliasRequest, GetAliasRequest, InvocationRequest, UpdateAliasRequest, UpdateFunctionCodeRequest};
+#[cfg(feature = "deploy-aws")]
+use rusoto_cloudfront::{CloudFront, CloudFrontClient, CreateInvalidationRequest, InvalidationBatch, Paths};
+
+pub const DEPLOY_TARGETS: [&str; 9] = ["ethereum", "solana", "aws", "ipfs", "local", "terraform", "static", "systemd", "kubernetes"];
+
+// Targets that emit files for another tool, publish a separate directory,
+// modify a host/cluster or need a local IPFS node must be named explicitly
+// and are never part of "all"
+const EXPLICIT_ONLY_TARGETS: [&str; 5] = ["ipfs", "terraform", "static", "systemd", "kubernetes"];
+
+pub fn select_targets(target: &str) -> Vec<&'static str> {
+    DEPLOY_TARGETS.iter().copied()
+        .filter(|t| (target == "all" && !EXPLICIT_ONLY_TARGETS.contains(t)) || target.contains(t))
+        .collect()
+}
+
+// Config key the interpreter fills with a solidity fuse block's code for gas
+// estimates and Etherscan verification when no source_file is given
+pub const SOLIDITY_SOURCE: &str = "solidity_source";
+
+pub fn is_target(name: &str) -> bool {
+    DEPLOY_TARGETS.contains(&name)
+}
+
+// Keys each target cannot deploy without
+fn required_keys(target: &str) -> &'static [&'static str] {
+    match target {
+        "ethereum" => &["api_key", "contract"],
+        "solana" => &["rpc_url", "program_id"],
+        "aws" => &["region", "bucket", "function", "role"],
+        "static" => &["bucket", "path"],
+        "systemd" => &["unit_name"],
+        "kubernetes" => &["deployment", "image"],
+        _ => &[],
+    }
+}
+
+// Problems a deploy config would hit at deploy time, found without
+// contacting anything; used by `rift check`
+pub fn validate_config(target: &str, config: &HashMap<String, String>) -> Vec<String> {
+    let targets = select_targets(target);
+    if targets.is_empty() {
+        return vec![format!("unsupported deploy target '{}'", target)];
+    }
+    let mut problems = Vec::new();
+    for name in targets {
+        let missing: Vec<_> = required_keys(name).iter()
+            .filter(|key| !config.contains_key(**key) && !served_locally(name, key, config))
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            problems.push(format!("{} deploy is missing {}", name, missing.join(", ")));
+        }
+        match package::Compression::for_target(name, config) {
+            Err(e) if !problems.contains(&e) => problems.push(e),
+            _ => {}
+        }
+    }
+    if let Some(strategy) = config.get("strategy") {
+        if let Err(e) = Rollout::from_config(strategy, config) {
+            problems.push(e);
+        }
+    }
+    if let Some(network) = config.get("network").filter(|v| !NETWORKS.contains(&v.as_str())) {
+        problems.push(format!("network must be one of {}, got '{}'", NETWORKS.join(", "), network));
+    }
+    if let Some(force) = config.get("force").filter(|v| *v != "true" && *v != "false") {
+        problems.push(format!("force must be true or false, got '{}'", force));
+    }
+    if let Some(Err(e)) = config.get("timeout").map(String::as_str).map(parse_duration) {
+        problems.push(e);
+    }
+    if let Some(Err(e)) = config.get("max_size").map(String::as_str).map(parse_size) {
+        problems.push(e);
+    }
+    if let Some(action) = config.get("on_oversize").filter(|v| *v != "warn" && *v != "fail") {
+        problems.push(format!("on_oversize must be warn or fail, got '{}'", action));
+    }
+    for key in ["source_file", "bytecode_file"] {
+        if let Some(path) = config.get(key).filter(|path| !Path::new(path).is_file()) {
+            problems.push(format!("{} {} does not exist", key, path));
+        }
+    }
+    problems
+}
+
+const NETWORKS: [&str; 3] = ["live", "local", "auto"];
+
+// Dev stand-ins on their default ports: LocalStack, anvil and
+// solana-test-validator
+const LOCAL_ENDPOINTS: [(&str, &str); 3] = [
+    ("aws", "http://localhost:4566"),
+    ("ethereum", "http://localhost:8545"),
+    ("solana", "http://localhost:8899"),
+];
+
+// Keys a local node makes unnecessary: anvil needs no Infura key, and
+// `network = "local"` already says which Solana RPC to use
+fn served_locally(target: &str, key: &str, config: &HashMap<String, String>) -> bool {
+    let local = config.get("network").is_some_and(|n| n == "local");
+    match (target, key) {
+        ("ethereum", "api_key") => local || config.contains_key("rpc_url"),
+        ("solana", "rpc_url") => local,
+        _ => false,
+    }
+}
+
+// Where a backend sends its requests, when not to the live network. An
+// explicit `endpoint` (AWS, also AWS_ENDPOINT_URL) or `rpc_url` wins; then
+// `network = "local"` uses the local node, and "auto" uses it only when
+// something is listening there. Usually set in a rift.toml profile.
+pub(crate) async fn endpoint(target: &str, config: &HashMap<String, String>) -> Result<Option<String>, String> {
+    let backend = if target == "static" { "aws" } else { target };
+    let explicit = match backend {
+        "aws" => config.get("endpoint").cloned().or_else(|| std::env::var("AWS_ENDPOINT_URL").ok()),
+        _ => config.get("rpc_url").cloned(),
+    };
+    if explicit.is_some() {
+        return Ok(explicit);
+    }
+    let local = LOCAL_ENDPOINTS.iter().find(|(name, _)| *name == backend).map(|(_, url)| url.to_string());
+    match config.get("network").map(String::as_str) {
+        None | Some("live") => Ok(None),
+        Some("local") => Ok(local),
+        Some("auto") => match local {
+            Some(url) if is_listening(&url).await => {
+                println!("{}", i18n::message("deploy.local_node", &[&target, &url]));
+                Ok(Some(url))
+            }
+            _ => Ok(None),
+        },
+        Some(other) => Err(format!("network must be one of {}, got '{}'", NETWORKS.join(", "), other)),
+    }
+}
+
+async fn is_listening(url: &str) -> bool {
+    let Some(addr) = reqwest::Url::parse(url).ok().and_then(|u| u.socket_addrs(|| None).ok()).and_then(|a| a.into_iter().next()) else {
+        return false;
+    };
+    matches!(tokio::time::timeout(Duration::from_millis(500), tokio::net::TcpStream::connect(addr)).await, Ok(Ok(_)))
+}
+
+#[cfg(feature = "deploy-web3")]
+async fn ethereum_rpc(config: &HashMap<String, String>) -> Result<String, String> {
+    match endpoint("ethereum", config).await? {
+        Some(url) => Ok(url),
+        None => {
+            let api_key = config.get("api_key").ok_or("Missing Ethereum API key")?;
+            Ok(format!("https://mainnet.infura.io/v3/{}", api_key))
+        }
+    }
+}
+
+// LocalStack and other S3/Lambda stand-ins take the region name as given
+#[cfg(feature = "deploy-aws")]
+pub(crate) async fn aws_region(target: &str, config: &HashMap<String, String>) -> Result<Region, String> {
+    let name = config.get("region").ok_or("Missing AWS region")?;
+    match endpoint(target, config).await? {
+        Some(endpoint) => Ok(Region::Custom { name: name.clone(), endpoint }),
+        None => name.parse::<Region>().map_err(|e| format!("Invalid region: {}", e)),
+    }
+}
+
+// Backends compiled out by cargo features report which feature to enable
+fn missing_feature(target: &str) -> Option<&'static str> {
+    match target {
+        "ethereum" if !cfg!(feature = "deploy-web3") => Some("deploy-web3"),
+        "solana" if !cfg!(feature = "deploy-solana") => Some("deploy-solana"),
+        "aws" if !cfg!(feature = "deploy-aws") => Some("deploy-aws"),
+        _ => None,
+    }
+}
+
+fn check_feature(target: &str) -> error::Result<()> {
+    match missing_feature(target) {
+        Some(feature) => Err(RiftError::DeploymentError {
+            target: target.to_string(),
+            message: format!("rift was built without the '{}' feature", feature),
+        }),
+        None => Ok(()),
+    }
+}
+
+pub async fn deploy(target: &str, artifact: &str, config: &HashMap<String, String>) -> error::Result<String> {
+    check_feature(target)?;
+    let progress = Arc::new(Progress::default());
+    let work = deploy_to_target(target, artifact, config.clone(), progress.clone());
+    supervise(target, "deploying to", config, &progress, work).await.map_err(|message| RiftError::DeploymentError {
+        target: target.to_string(),
+        message,
+    })
+}
+
+// Calls a method on a contract previously deployed to `target`
+pub async fn invoke(target: &str, config: &HashMap<String, String>) -> error::Result<String> {
+    check_feature(target)?;
+    let progress = Arc::new(Progress::default());
+    supervise(target, "calling", config, &progress, invoke_contract(target, config)).await.map_err(|message| RiftError::DeploymentError {
+        target: target.to_string(),
+        message,
+    })
+}
+
+// Providers that throttle concurrent updates. Chains default to one deploy
+// at a time, which keeps this process's own deploys from racing; nonces are
+// left to the node, so other senders on the same account can still collide.
+// Targets not listed here, or in rift.toml [concurrency], are not limited.
+pub const DEFAULT_CONCURRENCY: &[(&str, usize)] = &[("aws", 2), ("ethereum", 1), ("solana", 1)];
+
+// Each target's current limit and the semaphore that enforces it
+static SLOTS: OnceLock<Mutex<HashMap<String, (usize, Arc<Semaphore>)>>> = OnceLock::new();
+
+// Waits for one of `target`'s deploy slots, shared by every deploy in the
+// process; holding the permit holds the slot. A call with a different limit
+// (say after the config was reloaded) resizes the slots for everyone.
+pub async fn acquire_slot(target: &str, configured: Option<usize>) -> Option<OwnedSemaphorePermit> {
+    let limit = configured.or_else(|| DEFAULT_CONCURRENCY.iter().find(|(name, _)| *name == target).map(|(_, n)| *n))?.max(1);
+    let (semaphore, shrink) = {
+        let mut slots = SLOTS.get_or_init(Default::default).lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (current, semaphore) = slots.entry(target.to_string())
+            .or_insert_with(|| (limit, Arc::new(Semaphore::new(limit))));
+        if limit > *current {
+            semaphore.add_permits(limit - *current);
+        }
+        let shrink = current.saturating_sub(limit);
+        *current = limit;
+        (semaphore.clone(), shrink)
+    };
+    if shrink > 0 {
+        // Retired as deploys in flight hand their slots back, in a task of
+        // its own so a cancelled deploy cannot leave the count half-changed
+        let retire = semaphore.clone();
+        tokio::spawn(async move {
+            if let Ok(permits) = retire.acquire_many_owned(shrink as u32).await {
+                permits.forget();
+            }
+        });
+    }
+    match semaphore.clone().try_acquire_owned() {
+        Ok(permit) => Some(permit),
+        Err(_) => {
+            println!("{}", i18n::message("deploy.waiting_for_slot", &[&target, &limit]));
+            semaphore.acquire_owned().await.ok()
+        }
+    }
+}
+
+const DEFAULT_DEPLOY_TIMEOUT: &str = "30m";
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+const UPLOAD_CHUNK: usize = 256 * 1024;
+
+// Bytes handed to the network so far, for heartbeats during uploads
+#[derive(Debug, Default)]
+struct Progress {
+    sent: AtomicU64,
+    total: AtomicU64,
+}
+
+// Runs a backend operation under the deploy config's `timeout` (default
+// 30m), printing a heartbeat while it is slow and giving up cleanly on
+// Ctrl-C. Dropping the operation's future cancels its pending requests.
+async fn supervise<F>(target: &str, doing: &str, config: &HashMap<String, String>, progress: &Progress, work: F) -> Result<String, String>
+where
+    F: std::future::Future<Output = Result<String, String>>,
+{
+    let limit = parse_duration(config.get("timeout").map(String::as_str).unwrap_or(DEFAULT_DEPLOY_TIMEOUT))?;
+    let started = std::time::Instant::now();
+    let deadline = sleep(limit);
+    let mut heartbeat = tokio::time::interval_at(tokio::time::Instant::now() + HEARTBEAT_INTERVAL, HEARTBEAT_INTERVAL);
+    tokio::pin!(work, deadline);
+    loop {
+        tokio::select! {
+            result = &mut work => break result,
+            _ = &mut deadline => {
+                break Err(format!("timed out after {}s (raise `timeout` in the deploy config)", limit.as_secs()));
+            }
+            _ = tokio::signal::ctrl_c() => break Err("cancelled".to_string()),
+            _ = heartbeat.tick() => {
+                let (sent, total) = (progress.sent.load(Ordering::Relaxed), progress.total.load(Ordering::Relaxed));
+                match total {
+                    0 => println!("{}", i18n::message("deploy.still_working", &[&doing, &target, &started.elapsed().as_secs()])),
+                    _ => println!("{}", i18n::message("deploy.still_uploading", &[&target, &format_size(sent), &format_size(total), &started.elapsed().as_secs()])),
+                }
+            }
+        }
+    }
+}
+
+// Hands `data` to an upload in chunks, counting what has been sent
+fn counted_chunks(data: Vec<u8>, progress: Arc<Progress>) -> impl futures::Stream<Item = std::io::Result<bytes::Bytes>> {
+    progress.total.store(data.len() as u64, Ordering::Relaxed);
+    let data = bytes::Bytes::from(data);
+    futures::stream::iter((0..data.len()).step_by(UPLOAD_CHUNK).map(move |start| {
+        let chunk = data.slice(start..(start + UPLOAD_CHUNK).min(data.len()));
+        progress.sent.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        Ok(chunk)
+    }))
+}
+
+async fn deploy_to_target(target: &str, artifact: &str, config: HashMap<String, String>, progress: Arc<Progress>) -> Result<String, String> {
+    let mut attempts = 0;
+    loop {
+        match target {
+            #[cfg(feature = "deploy-web3")]
+            "ethereum" => {
+                let contract = config.get("contract").ok_or("Missing contract address")?;
+                // Checked first, so a deploy that cannot be verified never starts
+                let source = if config.contains_key("etherscan_api_key") { Some(solidity_source(&config)?) } else { None };
+                let transport = Http::new(&ethereum_rpc(&config).await?).map_err(|e| format!("Ethereum connection failed: {}", e))?;
+                let web3 = Web3::new(transport);
+                let estimate = estimate_ethereum_cost(&web3, contract_bytecode(&config)?).await?;
+                confirm_deploy_cost("ethereum", &estimate, &config).await?;
+                println!("{}", i18n::message("deploy.ethereum", &[contract, &artifact]));
+                if let Some(source) = source {
+                    if let Err(e) = verify_contract(contract, &source, &config).await {
+                        eprintln!("{}", i18n::message("deploy.verify_failed", &[&e]));
+                    }
+                }
+                break Ok(contract.to_string());
+            }
+            #[cfg(feature = "deploy-solana")]
+            "solana" => {
+                let rpc_url = endpoint("solana", &config).await?.ok_or("Missing Solana RPC URL")?;
+                let program_id = config.get("program_id").ok_or("Missing Solana program ID")?;
+                let client = RpcClient::new(rpc_url);
+                let estimate = estimate_solana_cost(&client, artifact)?;
+                confirm_deploy_cost("solana", &estimate, &config).await?;
+                println!("{}", i18n::message("deploy.solana", &[program_id, &artifact]));
+                break Ok(program_id.to_string());
+            }
+            #[cfg(feature = "deploy-aws")]
+            "aws" => {
+                let region = aws_region("aws", &config).await?;
+                let bucket = config.get("bucket").ok_or("Missing S3 bucket")?;
+                let func_name = config.get("function").ok_or("Missing Lambda function name")?;
+                let role = config.get("role").ok_or("Missing IAM role ARN")?;
+                let s3_client = S3Client::new(region.clone());
+                let lambda_client = LambdaClient::new(region);
+                let package = package::ensure(artifact, "aws", &config)?.ok_or("aws deploys need a zip package")?;
+                let file = fs::read(&package).map_err(|e| format!("Cannot read {}: {}", package.display(), e))?;
+                let key = format!("{}.zip", func_name);
+                if file.len() >= upload::MULTIPART_THRESHOLD {
+                    let object = upload::S3Object { bucket, key: &key, content_type: None, cache_control: None };
+                    upload::s3_put(&s3_client, object, file).await?;
+                } else {
+                    let size = file.len();
+                    let put_req = PutObjectRequest {
+                        bucket: bucket.to_string(),
+                        key,
+                        body: Some(rusoto_core::ByteStream::new_with_size(counted_chunks(file, progress.clone()), size)),
+                        ..Default::default()
+                    };
+                    s3_client.put_object(put_req).await.map_err(|e| format!("S3 upload failed: {}", e))?;
+                }
+                if let Some(strategy) = config.get("strategy") {
+                    let rollout = Rollout::from_config(strategy, &config)?;
+                    rollout_lambda(&lambda_client, func_name, bucket, &rollout, &config).await?;
+                    println!("{}", i18n::message("deploy.lambda_rollout", &[func_name, strategy]));
+                    break Ok(func_name.to_string());
+                }
+                let lambda_req = CreateFunctionRequest {
+                    function_name: func_name.to_string(),
+                    runtime: Some("provided.al2".to_string()),
+                    role: role.to_string(),
+                    handler: Some("main".to_string()),
+                    code: Some(rusoto_lambda::FunctionCode {
+                        s3_bucket: Some(bucket.to_string()),
+                        s3_key: Some(format!("{}.zip", func_name)),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                };
+                lambda_client.create_function(lambda_req).await.map_err(|e| format!("Lambda creation failed: {}", e))?;
+                println!("{}", i18n::message("deploy.lambda", &[func_name]));
+                break Ok(func_name.to_string());
+            }
+            "ipfs" => {
+                let cid = pin_to_ipfs(artifact, &config, progress.clone()).await?;
+                println!("{}", i18n::message("deploy.ipfs", &[&cid]));
+                break Ok(cid);
+            }
+            "terraform" => {
+                let dir = emit_terraform(artifact, &config)?;
+                println!("{}", i18n::message("deploy.terraform", &[&dir]));
+                break Ok(dir);
+            }
+            "static" => {
+                let location = deploy_static_site(&config).await?;
+                println!("{}", i18n::message("deploy.static", &[&location]));
+                break Ok(location);
+            }
+            "systemd" => {
+                let unit = install_systemd_service(artifact, &config)?;
+                println!("{}", i18n::message("deploy.systemd", &[&unit]));
+                break Ok(unit);
+            }
+            "kubernetes" => {
+                let deployment = deploy_kubernetes(&config).await?;
+                println!("{}", i18n::message("deploy.kubernetes", &[&deployment]));
+                break Ok(deployment);
+            }
+            "local" => {
+                let path = format!("rift_power_{}", chrono::Utc::now().timestamp());
+                let path = match package::ensure(artifact, "local", &config)? {
+                    Some(package) => {
+                        let path = format!("{}{}", path, package::Compression::for_target("local", &config)?.extension());
+                        fs::copy(&package, &path).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+                        path
+                    }
+                    None => {
+                        fs::write(&path, artifact).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+                        path
+                    }
+                };
+                println!("{}", i18n::message("deploy.local", &[&path]));
+                break Ok(path);
+            }
+            _ => break Err(format!("Unsupported target: {}", target)),
+        }
+        attempts += 1;
+        if attempts > 3 { break Err(format!("Deploy to {} failed after retries", target)); }
+        sleep(Duration::from_millis(100 * 2u64.pow(attempts))).await; // Exponential backoff
+    }
+}
+
+// Traffic shifting plan for `strategy = "canary"` (step/interval) or
+// `strategy = "blue-green"` (a single switch after one health check).
+struct Rollout {
+    steps: Vec<u32>,
+    interval: Duration,
+    health_check: Option<String>,
+}
+
+impl Rollout {
+    fn from_config(strategy: &str, config: &HashMap<String, String>) -> Result<Rollout, String> {
+        let interval = parse_duration(config.get("interval").map(String::as_str).unwrap_or("1m"))?;
+        let steps = match strategy {
+            "canary" => {
+                let step = config.get("step").map(String::as_str).unwrap_or("10%");
+                let step: u32 = step.trim_end_matches('%').parse().map_err(|_| format!("Invalid canary step: {}", step))?;
+                if step == 0 || step > 100 {
+                    return Err(format!("Canary step must be between 1% and 100%, got {}%", step));
+                }
+                (1..).map(|i| (i * step).min(100)).take_while(|w| *w < 100).chain(std::iter::once(100)).collect()
+            }
+            "blue-green" | "bluegreen" => vec![100],
+            _ => return Err(format!("Unknown deploy strategy: {}", strategy)),
+        };
+        Ok(Rollout { steps, interval, health_check: config.get("health_check").cloned() })
+    }
+}
+
+pub(crate) fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let amount: u64 = value[..split].parse().map_err(|_| format!("Invalid duration: {}", value))?;
+    let seconds = match &value[split..] {
+        "ms" => return Ok(Duration::from_millis(amount)),
+        "" | "s" => amount,
+        "m" => amount * 60,
+        "h" => amount * 3600,
+        _ => return Err(format!("Invalid duration unit in {}", value)),
+    };
+    Ok(Duration::from_secs(seconds))
+}
+
+// "50MB", "512KB", "1.5GB" or plain bytes; units are powers of 1024, as
+// the limits platforms publish are (Lambda's 50 MB is 52428800 bytes)
+pub(crate) fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let amount: f64 = value[..split].parse().map_err(|_| format!("Invalid size: {}", value))?;
+    let unit: u64 = match value[split..].trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "KB" | "KIB" | "K" => 1 << 10,
+        "MB" | "MIB" | "M" => 1 << 20,
+        "GB" | "GIB" | "G" => 1 << 30,
+        _ => return Err(format!("Invalid size unit in {}", value)),
+    };
+    Ok((amount * unit as f64) as u64)
+}
+
+pub(crate) fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.1} GB", b as f64 / (1u64 << 30) as f64),
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1u64 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KB", b as f64 / (1u64 << 10) as f64),
+        b => format!("{} B", b),
+    }
+}
+
+// `max_size = "50MB"` caps the packaged artifact; `on_oversize = "warn"`
+// reports instead of failing. `parts` are (label, uncompressed bytes) of
+// what went into it, and the largest are listed so the fix is obvious.
+pub fn check_size(config: &HashMap<String, String>, size: u64, parts: &[(String, u64)]) -> Result<(), String> {
+    let Some(max) = config.get("max_size") else {
+        return Ok(());
+    };
+    let max = parse_size(max)?;
+    if size <= max {
+        return Ok(());
+    }
+    let mut largest: Vec<_> = parts.iter().collect();
+    largest.sort_by(|a, b| b.1.cmp(&a.1));
+    let total: u64 = parts.iter().map(|(_, bytes)| bytes).sum::<u64>().max(1);
+    let mut message = format!(
+        "Artifact is {}, over its max_size of {}. Largest contributors (before compression):",
+        format_size(size), format_size(max),
+    );
+    for (label, bytes) in largest.into_iter().take(5) {
+        message.push_str(&format!("\n  {:>9}  {:>3}%  {}", format_size(*bytes), bytes * 100 / total, label));
+    }
+    match config.get("on_oversize").map(String::as_str) {
+        Some("warn") => {
+            eprintln!("{}", i18n::message("warning", &[&message]));
+            Ok(())
+        }
+        _ => Err(message),
+    }
+}
+
+async fn url_is_healthy(url: &str) -> bool {
+    match reqwest::get(url).await {
+        Ok(response) => response.status().is_success(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(feature = "deploy-aws")]
+// Publishes a new version and moves the alias (default "live") onto it by
+// weighted routing, restoring the previous routing if a health check fails.
+async fn rollout_lambda(client: &LambdaClient, func_name: &str, bucket: &str, rollout: &Rollout, config: &HashMap<String, String>) -> Result<(), String> {
+    let alias = config.get("alias").cloned().unwrap_or_else(|| "live".to_string());
+    let updated = client.update_function_code(UpdateFunctionCodeRequest {
+        function_name: func_name.to_string(),
+        s3_bucket: Some(bucket.to_string()),
+        s3_key: Some(format!("{}.zip", func_name)),
+        publish: Some(true),
+        ..Default::default()
+    }).await.map_err(|e| format!("Lambda code update failed: {}", e))?;
+    let new_version = updated.version.ok_or("Lambda did not return a published version")?;
+
+    let current = client.get_alias(GetAliasRequest {
+        function_name: func_name.to_string(),
+        name: alias.clone(),
+    }).await.ok().and_then(|a| a.function_version);
+    let Some(stable_version) = current else {
+        client.create_alias(CreateAliasRequest {
+            function_name: func_name.to_string(),
+            name: alias.clone(),
+            function_version: new_version.clone(),
+            ..Default::default()
+        }).await.map_err(|e| format!("Alias creation failed: {}", e))?;
+        println!("{}", i18n::message("deploy.alias_created", &[&alias, &new_version]));
+        return Ok(());
+    };
+
+    for weight in &rollout.steps {
+        let routing = if *weight < 100 {
+            Some(AliasRoutingConfiguration {
+                additional_version_weights: Some([(new_version.clone(), *weight as f64 / 100.0)].into_iter().collect()),
+            })
+        } else {
+            Some(AliasRoutingConfiguration { additional_version_weights: Some(HashMap::new()) })
+        };
+        let version = if *weight < 100 { stable_version.clone() } else { new_version.clone() };
+        if *weight == 100 && !lambda_version_healthy(client, func_name, &new_version, rollout).await {
+            rollback_lambda(client, func_name, &alias, &stable_version).await?;
+            return Err(format!("Health check failed for version {}, rolled back to {}", new_version, stable_version));
+        }
+        client.update_alias(UpdateAliasRequest {
+            function_name: func_name.to_string(),
+            name: alias.clone(),
+            function_version: Some(version),
+            routing_config: routing,
+            ..Default::default()
+        }).await.map_err(|e| format!("Alias update failed: {}", e))?;
+        println!("{}", i18n::message("deploy.alias_weight", &[&func_name, &alias, &new_version, &weight]));
+
+        if *weight < 100 {
+            sleep(rollout.interval).await;
+            if !lambda_version_healthy(client, func_name, &new_version, rollout).await {
+                rollback_lambda(client, func_name, &alias, &stable_version).await?;
+                return Err(format!("Health check failed at {}%, rolled back to version {}", weight, stable_version));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "deploy-aws")]
+// Without a health_check URL, the new version must handle an empty invocation
+async fn lambda_version_healthy(client: &LambdaClient, func_name: &str, version: &str, rollout: &Rollout) -> bool {
+    if let Some(url) = &rollout.health_check {
+        return url_is_healthy(url).await;
+    }
+    match client.invoke(InvocationRequest {
+        function_name: func_name.to_string(),
+        qualifier: Some(version.to_string()),
+        payload: Some("{}".into()),
+        ..Default::default()
+    }).await {
+        Ok(response) => response.function_error.is_none(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(feature = "deploy-aws")]
+async fn rollback_lambda(client: &LambdaClient, func_name: &str, alias: &str, stable_version: &str) -> Result<(), String> {
+    client.update_alias(UpdateAliasRequest {
+        function_name: func_name.to_string(),
+        name: alias.to_string(),
+        function_version: Some(stable_version.to_string()),
+        routing_config: Some(AliasRoutingConfiguration { additional_version_weights: Some(HashMap::new()) }),
+        ..Default::default()
+    }).await.map_err(|e| format!("Rollback failed: {}", e))?;
+    Ok(())
+}
+
+// Updates a Deployment's image. With a strategy, a `<name>-canary` copy runs
+// the new image and traffic is shifted by moving replicas between the two,
+// since both carry the labels the Service selects on.
+async fn deploy_kubernetes(config: &HashMap<String, String>) -> Result<String, String> {
+    let name = config.get("deployment").ok_or("Missing deployment name for Kubernetes deploy")?;
+    let image = config.get("image").ok_or("Missing image for Kubernetes deploy")?;
+    let container = config.get("container").unwrap_or(name);
+    let namespace = config.get("namespace").map(String::as_str).unwrap_or("default");
+    let set_image = format!("{}={}", container, image);
+
+    let Some(strategy) = config.get("strategy") else {
+        kubectl(namespace, &["set", "image", &format!("deployment/{}", name), &set_image])?;
+        kubectl(namespace, &["rollout", "status", &format!("deployment/{}", name)])?;
+        return Ok(name.to_string());
+    };
+    let rollout = Rollout::from_config(strategy, config)?;
+
+    let stable: serde_json::Value = serde_json::from_str(&kubectl(namespace, &["get", "deployment", name, "-o", "json"])?)
+        .map_err(|e| format!("Invalid deployment JSON: {}", e))?;
+    let total = stable["spec"]["replicas"].as_u64().unwrap_or(1).max(1);
+    let canary_name = format!("{}-canary", name);
+    let mut canary = stable.clone();
+    canary["metadata"] = serde_json::json!({ "name": canary_name, "namespace": namespace });
+    canary["spec"]["replicas"] = serde_json::json!(0);
+    canary["spec"]["selector"]["matchLabels"]["track"] = serde_json::json!("canary");
+    canary["spec"]["template"]["metadata"]["labels"]["track"] = serde_json::json!("canary");
+    if let Some(containers) = canary["spec"]["template"]["spec"]["containers"].as_array_mut() {
+        for c in containers.iter_mut().filter(|c| c["name"] == container.as_str()) {
+            c["image"] = serde_json::json!(image);
+        }
+    }
+    if let Some(fields) = canary.as_object_mut() {
+        fields.remove("status");
+    }
+    kubectl_apply(namespace, &canary)?;
+
+    let health_url = rollout.health_check.clone();
+    for weight in &rollout.steps {
+        let canary_replicas = (total * *weight as u64).div_ceil(100);
+        kubectl(namespace, &["scale", &format!("deployment/{}", canary_name), &format!("--replicas={}", canary_replicas)])?;
+        kubectl(namespace, &["scale", &format!("deployment/{}", name), &format!("--replicas={}", total - canary_replicas.min(total))])?;
+        kubectl(namespace, &["rollout", "status", &format!("deployment/{}", canary_name)])?;
+        println!("{}", i18n::message("deploy.canary", &[&name, &image, &weight, &canary_replicas, &total]));
+
+        sleep(rollout.interval).await;
+        let healthy = match &health_url {
+            Some(url) => url_is_healthy(url).await,
+            None => kubectl(namespace, &["rollout", "status", &format!("deployment/{}", canary_name), "--timeout=30s"]).is_ok(),
+        };
+        if !healthy {
+            kubectl(namespace, &["scale", &format!("deployment/{}", name), &format!("--replicas={}", total)])?;
+            kubectl(namespace, &["delete", "deployment", &canary_name])?;
+            return Err(format!("Health check failed at {}%, rolled back {}", weight, name));
+        }
+    }
+
+    // Promote: the stable deployment takes the new image, the canary goes away
+    kubectl(namespace, &["set", "image", &format!("deployment/{}", name), &set_image])?;
+    kubectl(namespace, &["scale", &format!("deployment/{}", name), &format!("--replicas={}", total)])?;
+    kubectl(namespace, &["rollout", "status", &format!("deployment/{}", name)])?;
+    kubectl(namespace, &["delete", "deployment", &canary_name])?;
+    Ok(name.to_string())
+}
+
+fn kubectl(namespace: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("kubectl").arg("-n").arg(namespace).args(args).output()
+        .map_err(|e| format!("kubectl not found: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("kubectl {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn kubectl_apply(namespace: &str, manifest: &serde_json::Value) -> Result<(), String> {
+    use std::io::Write;
+    let mut child = Command::new("kubectl").args(["-n", namespace, "apply", "-f", "-"])
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("kubectl not found: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(manifest.to_string().as_bytes()).map_err(|e| e.to_string())?;
+    }
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err("kubectl apply failed".to_string());
+    }
+    Ok(())
+}
+
+// Installs the artifact to /usr/local/bin, writes a unit file and enables the
+// service. Privileged steps go through sudo, which prompts on the terminal.
+fn install_systemd_service(artifact: &str, config: &HashMap<String, String>) -> Result<String, String> {
+    let name = config.get("unit_name").ok_or("Missing unit_name for systemd deploy")?;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.@".contains(c)) {
+        return Err(format!("Invalid unit name: {}", name));
+    }
+    let binary = format!("/usr/local/bin/{}", name);
+    let exec = config.get("exec").cloned().unwrap_or_else(|| binary.clone());
+    let description = config.get("description").cloned().unwrap_or_else(|| format!("{} (deployed by rift)", name));
+    let restart = config.get("restart").map(String::as_str).unwrap_or("on-failure");
+    // A line break would add directives of its own to the unit
+    let user = config.get("user").map(String::as_str).unwrap_or_default();
+    let fields = [("description", description.as_str()), ("exec", exec.as_str()), ("restart", restart), ("user", user)];
+    if let Some((key, _)) = fields.iter().find(|(_, value)| value.contains(['\n', '\r'])) {
+        return Err(format!("Invalid {} for systemd deploy: line breaks are not allowed", key));
+    }
+
+    let mut unit = format!("[Unit]\nDescription={}\nAfter=network.target\n\n[Service]\nExecStart={}\nRestart={}\n", description, exec, restart);
+    if let Some(user) = config.get("user") {
+        unit.push_str(&format!("User={}\n", user));
+    }
+    unit.push_str("\n[Install]\nWantedBy=multi-user.target\n");
+
+    // Staged in a directory only we can open, since root installs from it
+    let staging = tempfile::tempdir().map_err(|e| format!("Cannot create staging directory: {}", e))?;
+    let staged_binary = staging.path().join(name);
+    let staged_unit = staging.path().join(format!("{}.service", name));
+    fs::write(&staged_binary, artifact).map_err(|e| format!("Cannot stage artifact: {}", e))?;
+    fs::write(&staged_unit, unit).map_err(|e| format!("Cannot stage unit file: {}", e))?;
+
+    let unit_path = format!("/etc/systemd/system/{}.service", name);
+    let service = format!("{}.service", name);
+    let steps: [&[&str]; 4] = [
+        &["install", "-m", "755", staged_binary.to_str().unwrap_or_default(), &binary],
+        &["install", "-m", "644", staged_unit.to_str().unwrap_or_default(), &unit_path],
+        &["systemctl", "daemon-reload"],
+        &["systemctl", "enable", "--now", &service],
+    ];
+    steps.iter().try_for_each(|args| run_privileged(args))?;
+    Ok(service)
+}
+
+fn run_privileged(args: &[&str]) -> Result<(), String> {
+    let status = Command::new("sudo").args(args).status()
+        .map_err(|e| format!("Failed to run sudo {}: {}", args.join(" "), e))?;
+    if !status.success() {
+        return Err(format!("sudo {} failed with {}", args.join(" "), status));
+    }
+    Ok(())
+}
+
+// Uploads every file under `path` with a content type derived from its
+// extension, then invalidates the CDN cache when a distribution is given.
+async fn deploy_static_site(config: &HashMap<String, String>) -> Result<String, String> {
+    let provider = config.get("provider").map(String::as_str).unwrap_or("s3");
+    let bucket = config.get("bucket").ok_or("Missing bucket for static deploy")?;
+    let root = PathBuf::from(config.get("path").ok_or("Missing path of directory to publish")?);
+    let prefix = config.get("prefix").map(|p| p.trim_matches('/').to_string()).unwrap_or_default();
+    let cache_control = config.get("cache_control").cloned();
+
+    let mut files = Vec::new();
+    collect_files(&root, &mut files).map_err(|e| format!("Cannot read {}: {}", root.display(), e))?;
+    if files.is_empty() {
+        return Err(format!("No files to publish in {}", root.display()));
+    }
+
+    #[cfg(feature = "deploy-aws")]
+    let s3_client = match provider {
+        "s3" => Some(S3Client::new(aws_region("static", config).await?)),
+        _ => None,
+    };
+    let http = reqwest::Client::new();
+
+    for file in &files {
+        let relative = file.strip_prefix(&root).unwrap_or(file).to_string_lossy().replace('\\', "/");
+        let key = if prefix.is_empty() { relative } else { format!("{}/{}", prefix, relative) };
+        let content_type = content_type_for(file);
+        let body = fs::read(file).map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+
+        match provider {
+            #[cfg(feature = "deploy-aws")]
+            "s3" => {
+                let object = upload::S3Object {
+                    bucket,
+                    key: &key,
+                    content_type: Some(content_type.to_string()),
+                    cache_control: cache_control.clone(),
+                };
+                upload::s3_put(s3_client.as_ref().unwrap(), object, body).await?;
+            }
+            "gcs" if body.len() >= upload::MULTIPART_THRESHOLD => {
+                let token = config.get("gcs_token").ok_or("Missing gcs_token for GCS upload")?;
+                let mut metadata = serde_json::json!({ "contentType": content_type });
+                if let Some(cache) = &cache_control {
+                    metadata["cacheControl"] = cache.as_str().into();
+                }
+                upload::gcs_put(&http, token, bucket, &key, body, metadata).await?;
+            }
+            "gcs" => {
+                let token = config.get("gcs_token").ok_or("Missing gcs_token for GCS upload")?;
+                let mut request = http.post(format!("https://storage.googleapis.com/upload/storage/v1/b/{}/o", bucket))
+                    .query(&[("uploadType", "media"), ("name", key.as_str())])
+                    .bearer_auth(token)
+                    .header("Content-Type", content_type)
+                    .body(body);
+                if let Some(cache) = &cache_control {
+                    request = request.header("Cache-Control", cache.as_str());
+                }
+                check_upload(request.send().await, &key)?;
+            }
+            "azure" => {
+                let account = config.get("account").ok_or("Missing storage account for Azure upload")?;
+                let sas = config.get("sas_token").ok_or("Missing sas_token for Azure upload")?;
+                let mut request = http.put(format!("https://{}.blob.core.windows.net/{}/{}?{}", account, bucket, key, sas.trim_start_matches('?')))
+                    .header("x-ms-blob-type", "BlockBlob")
+                    .header("x-ms-blob-content-type", content_type)
+                    .body(body);
+                if let Some(cache) = &cache_control {
+                    request = request.header("x-ms-blob-cache-control", cache.as_str());
+                }
+                check_upload(request.send().await, &key)?;
+            }
+            #[cfg(not(feature = "deploy-aws"))]
+            "s3" => return Err("S3 uploads require the 'deploy-aws' feature".to_string()),
+            _ => return Err(format!("Unsupported static provider: {}", provider)),
+        }
+        println!("  {} ({})", key, content_type);
+    }
+
+    if let Some(distribution) = config.get("distribution_id") {
+        invalidate_cdn(distribution, &prefix).await?;
+        println!("{}", i18n::message("deploy.invalidated", &[&distribution]));
+    }
+
+    Ok(format!("{}://{}/{}", provider, bucket, prefix))
+}
+
+#[cfg(feature = "deploy-aws")]
+async fn invalidate_cdn(distribution: &str, prefix: &str) -> Result<(), String> {
+    let cloudfront = CloudFrontClient::new(Region::UsEast1);
+    let invalidation = CreateInvalidationRequest {
+        distribution_id: distribution.to_string(),
+        invalidation_batch: InvalidationBatch {
+            caller_reference: format!("rift-{}", chrono::Utc::now().timestamp_millis()),
+            paths: Paths {
+                quantity: 1,
+                items: Some(vec![format!("/{}*", if prefix.is_empty() { String::new() } else { format!("{}/", prefix) })]),
+            },
+        },
+    };
+    cloudfront.create_invalidation(invalidation).await.map_err(|e| format!("CloudFront invalidation failed: {}", e))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "deploy-aws"))]
+async fn invalidate_cdn(_distribution: &str, _prefix: &str) -> Result<(), String> {
+    Err("CloudFront invalidation requires the 'deploy-aws' feature".to_string())
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn check_upload(response: reqwest::Result<reqwest::Response>, key: &str) -> Result<(), String> {
+    let response = response.map_err(|e| format!("Upload of {} failed: {}", key, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Upload of {} failed: HTTP {}", key, response.status()));
+    }
+    Ok(())
+}
+
+fn content_type_for(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") | Some("mjs") => "application/javascript",
+        Some("json") | Some("map") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("wasm") => "application/wasm",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+// Writes the artifact plus a main.tf describing the resources the aws (or
+// local) target would create, so they can go through an IaC pipeline instead.
+fn emit_terraform(artifact: &str, config: &HashMap<String, String>) -> Result<String, String> {
+    let dir = config.get("dir").cloned().unwrap_or_else(|| "terraform".to_string());
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir, e))?;
+    fs::write(format!("{}/artifact", dir), artifact).map_err(|e| format!("Cannot write artifact: {}", e))?;
+
+    let mut tf = String::new();
+    match (config.get("region"), config.get("bucket"), config.get("function"), config.get("role")) {
+        (Some(region), Some(bucket), Some(function), Some(role)) => {
+            tf.push_str("terraform {\n  required_providers {\n    aws = {\n      source = \"hashicorp/aws\"\n    }\n  }\n}\n\n");
+            tf.push_str(&format!("provider \"aws\" {{\n  region = {}\n}}\n\n", hcl_string(region)));
+            tf.push_str(&format!(
+                "resource \"aws_s3_object\" \"artifact\" {{\n  bucket = {}\n  key    = {}\n  source = \"${{path.module}}/artifact\"\n  etag   = filemd5(\"${{path.module}}/artifact\")\n}}\n\n",
+                hcl_string(bucket), hcl_string(&format!("{}.zip", function))
+            ));
+            tf.push_str(&format!(
+                "resource \"aws_lambda_function\" \"function\" {{\n  function_name = {}\n  role          = {}\n  runtime       = \"provided.al2\"\n  handler       = \"main\"\n  s3_bucket     = aws_s3_object.artifact.bucket\n  s3_key        = aws_s3_object.artifact.key\n}}\n",
+                hcl_string(function), hcl_string(role)
+            ));
+        }
+        _ => {
+            let path = config.get("path").cloned().unwrap_or_else(|| "rift_power".to_string());
+            tf.push_str(&format!(
+                "resource \"local_file\" \"artifact\" {{\n  filename = {}\n  source   = \"${{path.module}}/artifact\"\n}}\n",
+                hcl_string(&path)
+            ));
+        }
+    }
+    fs::write(format!("{}/main.tf", dir), tf).map_err(|e| format!("Cannot write main.tf: {}", e))?;
+    Ok(dir)
+}
+
+fn hcl_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace("${", "$${"))
+}
+
+const IPFS_API_URL: &str = "http://127.0.0.1:5001";
+const PINNING_SERVICE_URL: &str = "https://api.pinata.cloud/pinning/pinFileToIPFS";
+
+// Pins through a pinning service when a pinning_token is configured,
+// otherwise through the HTTP API of a local (or ipfs_api) Kubo node.
+async fn pin_to_ipfs(artifact: &str, config: &HashMap<String, String>, progress: Arc<Progress>) -> Result<String, String> {
+    let name = config.get("name").cloned().unwrap_or_else(|| "rift_artifact".to_string());
+    let bytes = match package::ensure(artifact, "ipfs", config)? {
+        Some(package) => fs::read(&package).map_err(|e| format!("Cannot read {}: {}", package.display(), e))?,
+        None => artifact.as_bytes().to_vec(),
+    };
+    let size = bytes.len() as u64;
+    let body = reqwest::Body::wrap_stream(counted_chunks(bytes, progress));
+    let part = reqwest::multipart::Part::stream_with_length(body, size).file_name(name);
+    let form = reqwest::multipart::Form::new().part("file", part);
+    let client = reqwest::Client::new();
+
+    let (request, cid_field) = match config.get("pinning_token") {
+        Some(token) => {
+            let url = config.get("pinning_url").map(String::as_str).unwrap_or(PINNING_SERVICE_URL);
+            (client.post(url).bearer_auth(token), "IpfsHash")
+        }
+        None => {
+            let api = config.get("ipfs_api").map(String::as_str).unwrap_or(IPFS_API_URL);
+            (client.post(format!("{}/api/v0/add?pin=true", api.trim_end_matches('/'))), "Hash")
+        }
+    };
+    let response = request.multipart(form).send().await.map_err(|e| format!("IPFS upload failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("IPFS upload failed: HTTP {}", response.status()));
+    }
+    let body: serde_json::Value = response.json().await.map_err(|e| format!("Invalid IPFS response: {}", e))?;
+    body[cid_field].as_str().map(str::to_string).ok_or_else(|| format!("IPFS response missing {}", cid_field))
+}
+
+#[cfg(any(feature = "deploy-web3", feature = "deploy-solana"))]
+// Calls a method on a deployed contract. Anything not given in the invoke
+// block (contract address, rpc_url, abi, ...) falls back to the most recent
+// recorded deployment for the same chain.
+async fn invoke_contract(target: &str, config: &HashMap<String, String>) -> Result<String, String> {
+    let deployment = state::latest_deployment(target).map_err(|e| e.to_string())?;
+    // The call's own settings, then those recorded with the deployment. The
+    // record holds no credentials, so api_key and the like come from the
+    // call itself, usually as "secret:NAME"
+    let settings: HashMap<String, String> = deployment.iter()
+        .flat_map(|d| d.config.clone())
+        .chain(config.clone())
+        .collect();
+    let lookup = |key: &str| settings.get(key).cloned();
+    let address = config.get("contract").or_else(|| config.get("program_id")).cloned()
+        .or_else(|| deployment.as_ref().map(|d| d.address.clone()))
+        .ok_or_else(|| format!("No address given and no recorded {} deployment", target))?;
+
+    match target {
+        #[cfg(feature = "deploy-web3")]
+        "ethereum" => {
+            let abi_path = lookup("abi").ok_or("Missing contract ABI path")?;
+            let method = config.get("method").ok_or("Missing method to invoke")?;
+            let abi_file = fs::File::open(&abi_path).map_err(|e| format!("Cannot open ABI {}: {}", abi_path, e))?;
+            let abi = web3::ethabi::Contract::load(abi_file).map_err(|e| format!("Invalid ABI {}: {}", abi_path, e))?;
+            let function = abi.function(method).map_err(|e| format!("Unknown method {}: {}", method, e))?;
+
+            let args = split_args(config.get("args"));
+            if args.len() != function.inputs.len() {
+                return Err(format!("{} expects {} arguments, got {}", method, function.inputs.len(), args.len()));
+            }
+            let tokens = function.inputs.iter().zip(&args)
+                .map(|(param, arg)| LenientTokenizer::tokenize(&param.kind, arg)
+                    .map_err(|e| format!("Invalid argument '{}' for {}: {}", arg, param.name, e)))
+                .collect::<Result<Vec<_>, String>>()?;
+            let data = function.encode_input(&tokens).map_err(|e| format!("Cannot encode call: {}", e))?;
+            let to = Address::from_str(&address).map_err(|e| format!("Invalid contract address {}: {}", address, e))?;
+
+            let transport = Http::new(&ethereum_rpc(&settings).await?).map_err(|e| format!("Ethereum connection failed: {}", e))?;
+            let web3 = Web3::new(transport);
+            let request = CallRequest {
+                to: Some(to),
+                data: Some(Bytes(data)),
+                ..Default::default()
+            };
+            let output = web3.eth().call(request, None).await.map_err(|e| format!("Contract call failed: {}", e))?;
+            let decoded = function.decode_output(&output.0).map_err(|e| format!("Cannot decode result: {}", e))?;
+            Ok(decoded.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", "))
+        }
+        #[cfg(feature = "deploy-solana")]
+        "solana" => {
+            let rpc_url = endpoint("solana", &settings).await?.ok_or("Missing Solana RPC URL")?;
+            let keypair_path = lookup("keypair").ok_or("Missing Solana keypair path")?;
+            let program_id = Pubkey::from_str(&address).map_err(|e| format!("Invalid program ID {}: {}", address, e))?;
+            let payer = read_keypair_file(&keypair_path).map_err(|e| format!("Cannot read keypair {}: {}", keypair_path, e))?;
+            let data = decode_hex(config.get("data").map(String::as_str).unwrap_or(""))?;
+            let accounts = split_args(config.get("accounts")).iter()
+                .map(|a| Pubkey::from_str(a).map(|key| AccountMeta::new_readonly(key, false))
+                    .map_err(|e| format!("Invalid account {}: {}", a, e)))
+                .collect::<Result<Vec<_>, String>>()?;
+
+            // Simulation runs the instruction without committing it, which is
+            // what we want for reading a program's return data.
+            let client = RpcClient::new(rpc_url);
+            let instruction = Instruction::new_with_bytes(program_id, &data, accounts);
+            let blockhash = client.get_latest_blockhash().map_err(|e| format!("Blockhash lookup failed: {}", e))?;
+            let tx = Transaction::new_signed_with_payer(&[instruction], Some(&payer.pubkey()), &[&payer], blockhash);
+            let simulation = client.simulate_transaction(&tx).map_err(|e| format!("Program invocation failed: {}", e))?;
+            if let Some(err) = simulation.value.err {
+                return Err(format!("Program invocation failed: {}", err));
+            }
+            Ok(simulation.value.return_data.map(|r| r.data.0).unwrap_or_default())
+        }
+        _ => Err(format!("Unsupported invoke target: {}", target)),
+    }
+}
+
+#[cfg(not(any(feature = "deploy-web3", feature = "deploy-solana")))]
+async fn invoke_contract(target: &str, _config: &HashMap<String, String>) -> Result<String, String> {
+    Err(format!("Unsupported invoke target: {}", target))
+}
+
+#[cfg(any(feature = "deploy-web3", feature = "deploy-solana"))]
+fn split_args(args: Option<&String>) -> Vec<String> {
+    args.map(|a| a.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(any(feature = "deploy-web3", feature = "deploy-solana"))]
+fn decode_hex(hex: &str) -> Result<Vec<u8>, String> {
+    let hex = hex.trim_start_matches("0x");
+    if hex.len() % 2 != 0 {
+        return Err(format!("Invalid hex data: {}", hex));
+    }
+    (0..hex.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("Invalid hex data: {}", hex)))
+        .collect()
+}
+
+// Solana program accounts carry a fixed header, and uploads are split into
+// write transactions of roughly this many bytes, each paying a signature fee.
+#[cfg(feature = "deploy-solana")]
+const SOLANA_PROGRAM_DATA_HEADER: usize = 45;
+#[cfg(feature = "deploy-solana")]
+const SOLANA_WRITE_CHUNK: usize = 1012;
+#[cfg(feature = "deploy-solana")]
+const SOLANA_SIGNATURE_FEE: u64 = 5000;
+
+#[cfg(any(feature = "deploy-web3", feature = "deploy-solana"))]
+struct CostEstimate {
+    units: u128,
+    unit_name: &'static str,
+    native: f64,
+    symbol: &'static str,
+}
+
+#[cfg(feature = "deploy-web3")]
+async fn estimate_ethereum_cost(web3: &Web3<Http>, bytecode: Vec<u8>) -> Result<CostEstimate, String> {
+    // No `to`: priced as the transaction that creates the contract
+    let request = CallRequest {
+        data: Some(Bytes(bytecode)),
+        ..Default::default()
+    };
+    let gas = web3.eth().estimate_gas(request, None).await.map_err(|e| format!("Gas estimation failed: {}", e))?;
+    let gas_price = web3.eth().gas_price().await.map_err(|e| format!("Gas price lookup failed: {}", e))?;
+    let wei = gas.saturating_mul(gas_price);
+    Ok(CostEstimate {
+        units: gas.as_u128(),
+        unit_name: "gas",
+        native: wei.as_u128() as f64 / 1e18,
+        symbol: "ETH",
+    })
+}
+
+#[cfg(feature = "deploy-solana")]
+fn estimate_solana_cost(client: &RpcClient, artifact: &str) -> Result<CostEstimate, String> {
+    let rent = client.get_minimum_balance_for_rent_exemption(artifact.len() + SOLANA_PROGRAM_DATA_HEADER)
+        .map_err(|e| format!("Rent estimation failed: {}", e))?;
+    let writes = artifact.len().div_ceil(SOLANA_WRITE_CHUNK).max(1) as u64;
+    let lamports = rent + writes * SOLANA_SIGNATURE_FEE;
+    Ok(CostEstimate {
+        units: lamports as u128,
+        unit_name: "lamports",
+        native: lamports as f64 / 1e9,
+        symbol: "SOL",
+    })
+}
+
+#[cfg(any(feature = "deploy-web3", feature = "deploy-solana"))]
+async fn confirm_deploy_cost(target: &str, estimate: &CostEstimate, config: &HashMap<String, String>) -> Result<(), String> {
+    let native = format!("{:.6}", estimate.native);
+    let mut preview = i18n::message("deploy.cost_estimate", &[&target, &estimate.units, &estimate.unit_name, &native, &estimate.symbol]);
+    if let Some(feed) = config.get("price_feed") {
+        match fetch_usd_price(feed).await {
+            Ok(price) => {
+                let about = if crate::plain::enabled() { "about" } else { "≈" };
+                preview.push_str(&format!(" {} ${:.2}", about, estimate.native * price));
+            }
+            Err(e) => eprintln!("{}", i18n::message("deploy.price_feed_unavailable", &[&e])),
+        }
+    }
+    println!("{}", preview);
+
+    if let Some(threshold) = config.get("confirm_above") {
+        let threshold: f64 = threshold.parse().map_err(|_| format!("Invalid confirm_above value: {}", threshold))?;
+        if estimate.native > threshold && !confirm(&i18n::message("deploy.confirm_cost", &[&threshold, &estimate.symbol]))? {
+            return Err(format!("Deploy to {} cancelled", target));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(any(feature = "deploy-web3", feature = "deploy-solana"))]
+// Price feeds differ in shape ({"usd": 1.0}, {"ethereum": {"usd": 1.0}}, ...),
+// so take the first "usd" field found anywhere in the response.
+async fn fetch_usd_price(url: &str) -> Result<f64, String> {
+    let body: serde_json::Value = reqwest::get(url).await.map_err(|e| e.to_string())?
+        .json().await.map_err(|e| e.to_string())?;
+    find_usd(&body).ok_or_else(|| "no 'usd' field in response".to_string())
+}
+
+#[cfg(any(feature = "deploy-web3", feature = "deploy-solana"))]
+fn find_usd(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Object(map) => map.get("usd").and_then(|v| v.as_f64())
+            .or_else(|| map.values().find_map(find_usd)),
+        serde_json::Value::Array(items) => items.iter().find_map(find_usd),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "deploy-web3")]
+const ETHERSCAN_API_URL: &str = "https://api.etherscan.io/api";
+#[cfg(feature = "deploy-web3")]
+const VERIFY_POLL_ATTEMPTS: u32 = 10;
+
+// Etherscan compiles the contract's Solidity again and compares bytecode, and
+// gas is estimated from the compiled contract, so both need that source
+// rather than the rift artifact: `source_file`, or the script's solidity fuse
+// block, which the interpreter passes as SOLIDITY_SOURCE
+#[cfg(feature = "deploy-web3")]
+fn solidity_source(config: &HashMap<String, String>) -> Result<String, String> {
+    match (config.get("source_file"), config.get(SOLIDITY_SOURCE)) {
+        (Some(path), _) => fs::read_to_string(path).map_err(|e| format!("Cannot read source_file {}: {}", path, e)),
+        (None, Some(source)) => Ok(source.clone()),
+        (None, None) => Err("No Solidity source for the contract: set source_file in the deploy config or add a solidity fuse block".to_string()),
+    }
+}
+
+// The contract's creation bytecode: `bytecode_file` (hex, as `solc --bin`
+// writes it), else the Solidity source compiled with solc using the same
+// optimizer settings as verification
+#[cfg(feature = "deploy-web3")]
+fn contract_bytecode(config: &HashMap<String, String>) -> Result<Vec<u8>, String> {
+    if let Some(path) = config.get("bytecode_file") {
+        let hex = fs::read_to_string(path).map_err(|e| format!("Cannot read bytecode_file {}: {}", path, e))?;
+        return decode_hex(hex.trim());
+    }
+    let source = solidity_source(config)?;
+    let file = tempfile::Builder::new().suffix(".sol").tempfile().map_err(|e| e.to_string())?;
+    fs::write(file.path(), source).map_err(|e| e.to_string())?;
+    let mut solc = Command::new("solc");
+    solc.arg("--bin");
+    if config.get("optimization_used").is_some_and(|v| v == "true" || v == "1") {
+        solc.args(["--optimize", "--optimize-runs", config.get("optimization_runs").map(String::as_str).unwrap_or("200")]);
+    }
+    let output = solc.arg(file.path()).output()
+        .map_err(|e| format!("Cannot run solc to estimate gas (or set bytecode_file): {}", e))?;
+    if !output.status.success() {
+        return Err(format!("solc failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let name = config.get("contract_name").map(String::as_str).unwrap_or("Main");
+    let binary = solc_binary(&String::from_utf8_lossy(&output.stdout), name)
+        .ok_or_else(|| format!("solc produced no bytecode for contract {}; set contract_name", name))?;
+    decode_hex(&binary)
+}
+
+// The hex under "Binary:" in the `======= file:Name =======` section
+#[cfg(feature = "deploy-web3")]
+fn solc_binary(output: &str, name: &str) -> Option<String> {
+    let header = format!(":{} =======", name);
+    let mut lines = output.lines().skip_while(|line| !(line.starts_with("=======") && line.ends_with(&header)));
+    lines.find(|line| line.starts_with("Binary"))?;
+    lines.next().map(str::trim).filter(|hex| !hex.is_empty()).map(str::to_string)
+}
+
+#[cfg(feature = "deploy-web3")]
+async fn verify_contract(contract: &str, source: &str, config: &HashMap<String, String>) -> Result<(), String> {
+    let api_key = config.get("etherscan_api_key").ok_or("Missing etherscan_api_key")?;
+    let compiler = config.get("compiler_version").ok_or("Missing compiler_version for verification")?;
+    let api_url = config.get("etherscan_api_url").map(String::as_str).unwrap_or(ETHERSCAN_API_URL);
+    let contract_name = config.get("contract_name").map(String::as_str).unwrap_or("Main");
+    let optimized = config.get("optimization_used").map(|v| v == "true" || v == "1").unwrap_or(false);
+    let runs = config.get("optimization_runs").map(String::as_str).unwrap_or("200");
+
+    let client = reqwest::Client::new();
+    let submission: serde_json::Value = client.post(api_url)
+        .form(&[
+            ("module", "contract"),
+            ("action", "verifysourcecode"),
+            ("apikey", api_key.as_str()),
+            ("contractaddress", contract),
+            ("sourceCode", source),
+            ("codeformat", "solidity-single-file"),
+            ("contractname", contract_name),
+            ("compilerversion", compiler.as_str()),
+            ("optimizationUsed", if optimized { "1" } else { "0" }),
+            ("runs", runs),
+        ])
+        .send().await.map_err(|e| format!("Verification request failed: {}", e))?
+        .json().await.map_err(|e| format!("Invalid verification response: {}", e))?;
+
+    let guid = submission["result"].as_str().unwrap_or_default().to_string();
+    if submission["status"] != "1" {
+        return Err(format!("Verification rejected: {}", guid));
+    }
+    println!("{}", i18n::message("deploy.verify_submitted", &[&contract, &guid]));
+
+    for _ in 0..VERIFY_POLL_ATTEMPTS {
+        sleep(Duration::from_secs(5)).await;
+        let status: serde_json::Value = client.get(api_url)
+            .query(&[
+                ("module", "contract"),
+                ("action", "checkverifystatus"),
+                ("guid", guid.as_str()),
+                ("apikey", api_key.as_str()),
+            ])
+            .send().await.map_err(|e| format!("Verification status check failed: {}", e))?
+            .json().await.map_err(|e| format!("Invalid verification status: {}", e))?;
+        let result = status["result"].as_str().unwrap_or_default();
+        if result.starts_with("Pending") {
+            continue;
+        }
+        if status["status"] == "1" {
+            println!("{}", i18n::message("deploy.verified", &[&contract, &result]));
+            return Ok(());
+        }
+        return Err(result.to_string());
+    }
+    Err(format!("Verification still pending after {} checks (guid {})", VERIFY_POLL_ATTEMPTS, guid))
+}
+
+#[cfg(any(feature = "deploy-web3", feature = "deploy-solana"))]
+fn confirm(prompt: &str) -> Result<bool, String> {
+    use std::io::Write;
+    print!("{} [y/N] ", prompt);
+    std::io::stdout().flush().map_err(|e| e.to_string())?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).map_err(|e| e.to_string())?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_acquire_slot() {
+        let wait = Duration::from_millis(50);
+        assert!(acquire_slot("slot-test-unlimited", None).await.is_none());
+
+        let first = acquire_slot("slot-test", Some(2)).await.unwrap();
+        let _second = acquire_slot("slot-test", Some(2)).await.unwrap();
+        assert!(tokio::time::timeout(wait, acquire_slot("slot-test", Some(2))).await.is_err());
+        drop(first);
+        let _third = tokio::time::timeout(wait, acquire_slot("slot-test", Some(2))).await.unwrap().unwrap();
+
+        // A raised limit applies at once
+        let fourth = tokio::time::timeout(wait, acquire_slot("slot-test", Some(3))).await.unwrap().unwrap();
+        drop((fourth, _second, _third));
+        // A lowered one takes over once the extra slots are retired
+        let _fifth = acquire_slot("slot-test", Some(1)).await.unwrap();
+        tokio::task::yield_now().await;
+        assert!(tokio::time::timeout(wait, acquire_slot("slot-test", Some(1))).await.is_err());
+    }
+}
@@ -1,56 +1,79 @@
+use crate::i18n;
+use std::fmt;
 use thiserror::Error;
 
+// Messages come from the `i18n` catalog, so Display is written out below
 #[derive(Error, Debug)]
 pub enum RiftError {
-    #[error("Parse error: {0}")]
     ParseError(String),
-    
-    #[error("Execution error in {language}: {message}")]
     ExecutionError { language: String, message: String },
-    
-    #[error("Unsupported language: {0}")]
     UnsupportedLanguage(String),
-    
-    #[error("Deployment error for {target}: {message}")]
     DeploymentError { target: String, message: String },
-    
-    #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
-    
-    #[error("Language transformation error: {from} -> {to}: {message}")]
     TransformationError {
         from: String,
         to: String,
         message: String,
     },
-    
-    #[error("Variable not found: {0}")]
     VariableNotFound(String),
-    
-    #[error("Function not found: {0}")]
     FunctionNotFound(String),
-    
-    #[error("Invalid configuration: {0}")]
     ConfigError(String),
-    
-    #[error("Dependency installation failed: {language}: {dependency}")]
     DependencyError { language: String, dependency: String },
-    
-    #[error("Cache error: {0}")]
     CacheError(String),
-    
-    #[error("Network error: {0}")]
+    SecretError(String),
     NetworkError(#[from] reqwest::Error),
-    
-    #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
-    
-    #[error("Tree-sitter parsing error: {0}")]
     TreeSitterError(String),
 }
 
 pub type Result<T> = std::result::Result<T, RiftError>;
 
+impl RiftError {
+    // Stable across releases and locales, for tools matching on errors
+    pub fn code(&self) -> &'static str {
+        match self {
+            RiftError::ParseError(_) => "E0001",
+            RiftError::ExecutionError { .. } => "E0002",
+            RiftError::UnsupportedLanguage(_) => "E0003",
+            RiftError::DeploymentError { .. } => "E0004",
+            RiftError::IoError(_) => "E0005",
+            RiftError::TransformationError { .. } => "E0006",
+            RiftError::VariableNotFound(_) => "E0007",
+            RiftError::FunctionNotFound(_) => "E0008",
+            RiftError::ConfigError(_) => "E0009",
+            RiftError::DependencyError { .. } => "E0010",
+            RiftError::CacheError(_) => "E0011",
+            RiftError::SecretError(_) => "E0012",
+            RiftError::NetworkError(_) => "E0013",
+            RiftError::SerializationError(_) => "E0014",
+            RiftError::TreeSitterError(_) => "E0015",
+        }
+    }
+}
+
+impl fmt::Display for RiftError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            RiftError::ParseError(message) => i18n::message("error.parse", &[message]),
+            RiftError::ExecutionError { language, message } => i18n::message("error.execution", &[language, message]),
+            RiftError::UnsupportedLanguage(lang) => i18n::message("error.unsupported_language", &[lang]),
+            RiftError::DeploymentError { target, message } => i18n::message("error.deployment", &[target, message]),
+            RiftError::IoError(e) => i18n::message("error.io", &[e]),
+            RiftError::TransformationError { from, to, message } => i18n::message("error.transformation", &[from, to, message]),
+            RiftError::VariableNotFound(name) => i18n::message("error.variable_not_found", &[name]),
+            RiftError::FunctionNotFound(name) => i18n::message("error.function_not_found", &[name]),
+            RiftError::ConfigError(message) => i18n::message("error.config", &[message]),
+            RiftError::DependencyError { language, dependency } => i18n::message("error.dependency", &[language, dependency]),
+            RiftError::CacheError(message) => i18n::message("error.cache", &[message]),
+            RiftError::SecretError(message) => i18n::message("error.secret", &[message]),
+            RiftError::NetworkError(e) => i18n::message("error.network", &[e]),
+            RiftError::SerializationError(e) => i18n::message("error.serialization", &[e]),
+            RiftError::TreeSitterError(message) => i18n::message("error.tree_sitter", &[message]),
+        };
+        f.write_str(&text)
+    }
+}
+
 impl From<String> for RiftError {
     fn from(s: String) -> Self {
         RiftError::ParseError(s)
@@ -0,0 +1,915 @@
+use crate::error::{Result, RiftError};
+use crate::i18n;
+use crate::provider::Provider;
+use crate::value::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tree_sitter::{Language, Parser};
+
+#[cfg(feature = "lang-python")]
+extern "C" { fn tree_sitter_python() -> Language; }
+#[cfg(feature = "lang-javascript")]
+extern "C" { fn tree_sitter_javascript() -> Language; }
+#[cfg(feature = "lang-go")]
+extern "C" { fn tree_sitter_go() -> Language; }
+#[cfg(feature = "lang-cpp")]
+extern "C" { fn tree_sitter_cpp() -> Language; }
+#[cfg(feature = "lang-java")]
+extern "C" { fn tree_sitter_java() -> Language; }
+#[cfg(feature = "lang-php")]
+extern "C" { fn tree_sitter_php() -> Language; }
+
+pub const DEFAULT_TIMEOUT_SECS: u64 = 300;
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 8 * 1024 * 1024;
+
+// What stays in memory of an output that spilled to disk
+const PREVIEW_BYTES: usize = 16 * 1024;
+const OUTPUTS_DIR: &str = "outputs";
+
+// Fuse code hands a structured value back to the script by printing this
+// prefix followed by JSON, or by writing JSON to the file named in $RIFT_RESULT
+pub const RESULT_MARKER: &str = "##RIFT:";
+const RESULT_FILE: &str = "rift_result.json";
+// pip --target directory inside the workspace, put on PYTHONPATH for the run
+const PYTHON_PACKAGES_DIR: &str = "site-packages";
+
+#[derive(Debug, Clone)]
+pub struct ExecOptions {
+    pub timeout: Duration,
+    pub stream: bool,
+    // Rift variables passed in as RIFT_<NAME> environment variables and, for
+    // interpreted languages, as globals of the same name
+    pub exports: Vec<(String, Value)>,
+    // Set with set_env(), on top of rift's own environment
+    pub env: Vec<(String, String)>,
+    // Output beyond this many bytes is written to a file under .rift/outputs
+    pub max_output: usize,
+    // Names the fuse block in diagnostics in place of the workspace source file
+    pub source_label: String,
+    pub provider: Provider,
+    // The rift, task or fn running the block, whose nix shell it uses
+    pub rift: Option<String>,
+}
+
+impl Default for ExecOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            stream: false,
+            exports: Vec::new(),
+            env: Vec::new(),
+            max_output: DEFAULT_MAX_OUTPUT_BYTES,
+            source_label: "@fuse".to_string(),
+            provider: Provider::default(),
+            rift: None,
+        }
+    }
+}
+
+// When a stream spills, `stdout`/`stderr` hold only its first PREVIEW_BYTES
+// and the full stream is in the matching file.
+#[derive(Debug, Clone)]
+pub struct ExecutionOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub exit_code: Option<i32>,
+    pub duration: Duration,
+    pub stdout_file: Option<PathBuf>,
+    pub stderr_file: Option<PathBuf>,
+    pub stdout_len: u64,
+    // Dependencies installed while building, for `stats`
+    pub deps_installed: usize,
+}
+
+impl ExecutionOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+// A scratch directory per execution, removed when dropped so compiled
+// binaries and dependency installs never leak into the working tree. The
+// name is unique, so identical blocks running at once never share one.
+pub struct Workspace {
+    dir: tempfile::TempDir,
+}
+
+impl Workspace {
+    pub fn new(lang: &str, code: &str) -> Result<Self> {
+        let hash = format!("{:x}", Sha256::digest(code.as_bytes()));
+        let dir = tempfile::Builder::new().prefix(&format!("rift_{}_{}_", lang, &hash[..12])).tempdir()?;
+        Ok(Self { dir })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn write(&self, name: &str, contents: &str) -> Result<PathBuf> {
+        let path = self.path().join(name);
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+}
+
+pub trait Executor: Send + Sync {
+    fn language(&self) -> &'static str;
+
+    // Command used to check the toolchain is installed
+    fn version_command(&self) -> Command;
+
+    fn install_command(&self, _dep: &str, _workspace: &Workspace) -> Option<Command> {
+        None
+    }
+
+    // Prepends definitions of the exported variables, plus a `rift_return`
+    // helper when the code uses one. Languages that cannot take extra top-level
+    // statements only get the RIFT_* environment variables and $RIFT_RESULT.
+    fn with_prelude(&self, code: &str, _exports: &[(String, Value)]) -> String {
+        code.to_string()
+    }
+
+    // Writes the source into the workspace and returns the commands to run:
+    // any build steps first, the program itself last.
+    fn prepare(&self, code: &str, workspace: &Workspace) -> Result<Vec<Command>>;
+}
+
+struct PythonExecutor;
+struct JavaScriptExecutor;
+struct GoExecutor;
+struct CppExecutor;
+struct JavaExecutor;
+struct PhpExecutor;
+struct RustExecutor;
+
+impl Executor for PythonExecutor {
+    fn language(&self) -> &'static str { "python" }
+
+    fn version_command(&self) -> Command {
+        command("python3", &["--version"])
+    }
+
+    // Into the workspace, like npm's --prefix, so runs never touch the system site-packages
+    fn install_command(&self, dep: &str, workspace: &Workspace) -> Option<Command> {
+        let target = workspace.path().join(PYTHON_PACKAGES_DIR).to_string_lossy().to_string();
+        Some(command("pip3", &["install", "--target", &target, dep]))
+    }
+
+    // A JSON document is also a valid Python string literal once JSON-quoted
+    fn with_prelude(&self, code: &str, exports: &[(String, Value)]) -> String {
+        let mut source = String::new();
+        for (name, value) in exports {
+            let json = serde_json::to_string(&value.to_json().to_string()).unwrap_or_default();
+            source.push_str(&format!("{} = __import__(\"json\").loads({})\n", name, json));
+        }
+        if code.contains("rift_return") {
+            source.push_str(&format!(
+                "def rift_return(value):\n    print(\"{}\" + __import__(\"json\").dumps(value), flush=True)\n",
+                RESULT_MARKER
+            ));
+        }
+        if code.contains("rift_store_") {
+            source.push_str(concat!(
+                "def _rift_store_path(key):\n",
+                "    return __import__(\"os\").path.join(__import__(\"os\").environ[\"RIFT_STORE\"], key + \".json\")\n",
+                "def rift_store_put(key, value):\n",
+                "    with open(_rift_store_path(key), \"w\") as f:\n",
+                "        __import__(\"json\").dump(value, f)\n",
+                "def rift_store_get(key, default=None):\n",
+                "    try:\n",
+                "        with open(_rift_store_path(key)) as f:\n",
+                "            return __import__(\"json\").load(f)\n",
+                "    except FileNotFoundError:\n",
+                "        return default\n",
+            ));
+        }
+        if code.contains("rift_table_") {
+            source.push_str(concat!(
+                "def _rift_table_path(name):\n",
+                "    return __import__(\"os\").path.join(__import__(\"os\").environ[\"RIFT_TABLES\"], name + \".parquet\")\n",
+                "def rift_table_write(name, table):\n",
+                "    if hasattr(table, \"to_parquet\"):\n",
+                "        table.to_parquet(_rift_table_path(name))\n",
+                "    else:\n",
+                "        __import__(\"pyarrow.parquet\").parquet.write_table(table, _rift_table_path(name))\n",
+                "def rift_table_read(name):\n",
+                "    return __import__(\"pandas\").read_parquet(_rift_table_path(name))\n",
+            ));
+        }
+        source + code
+    }
+
+    fn prepare(&self, code: &str, workspace: &Workspace) -> Result<Vec<Command>> {
+        workspace.write("main.py", code)?;
+        let mut run = command("python3", &["main.py"]);
+        let packages = workspace.path().join(PYTHON_PACKAGES_DIR);
+        if packages.exists() {
+            let inherited = std::env::var_os("PYTHONPATH").unwrap_or_default();
+            let path = std::env::join_paths(std::iter::once(packages).chain(std::env::split_paths(&inherited)))
+                .map_err(|e| RiftError::ExecutionError { language: "python".to_string(), message: e.to_string() })?;
+            run.env("PYTHONPATH", path);
+        }
+        Ok(vec![run])
+    }
+}
+
+impl Executor for JavaScriptExecutor {
+    fn language(&self) -> &'static str { "javascript" }
+
+    fn version_command(&self) -> Command {
+        command("node", &["--version"])
+    }
+
+    fn install_command(&self, dep: &str, workspace: &Workspace) -> Option<Command> {
+        let prefix = workspace.path().to_string_lossy().to_string();
+        Some(command("npm", &["install", "--prefix", &prefix, dep]))
+    }
+
+    fn with_prelude(&self, code: &str, exports: &[(String, Value)]) -> String {
+        let mut source = String::new();
+        for (name, value) in exports {
+            source.push_str(&format!("var {} = {};\n", name, value.to_json()));
+        }
+        if code.contains("rift_return") {
+            source.push_str(&format!(
+                "function rift_return(value) {{ console.log(\"{}\" + JSON.stringify(value)); }}\n",
+                RESULT_MARKER
+            ));
+        }
+        if code.contains("rift_store_") {
+            source.push_str(concat!(
+                "const _rift_store_path = (key) => require(\"path\").join(process.env.RIFT_STORE, key + \".json\");\n",
+                "function rift_store_put(key, value) { require(\"fs\").writeFileSync(_rift_store_path(key), JSON.stringify(value)); }\n",
+                "function rift_store_get(key, fallback = null) {\n",
+                "  try { return JSON.parse(require(\"fs\").readFileSync(_rift_store_path(key), \"utf8\")); }\n",
+                "  catch (e) { if (e.code === \"ENOENT\") return fallback; throw e; }\n",
+                "}\n",
+            ));
+        }
+        source + code
+    }
+
+    fn prepare(&self, code: &str, workspace: &Workspace) -> Result<Vec<Command>> {
+        workspace.write("main.js", code)?;
+        Ok(vec![command("node", &["main.js"])])
+    }
+}
+
+impl Executor for GoExecutor {
+    fn language(&self) -> &'static str { "go" }
+
+    fn version_command(&self) -> Command {
+        command("go", &["version"])
+    }
+
+    fn prepare(&self, code: &str, workspace: &Workspace) -> Result<Vec<Command>> {
+        workspace.write("main.go", code)?;
+        Ok(vec![command("go", &["run", "main.go"])])
+    }
+}
+
+impl Executor for CppExecutor {
+    fn language(&self) -> &'static str { "cpp" }
+
+    fn version_command(&self) -> Command {
+        command("g++", &["--version"])
+    }
+
+    fn prepare(&self, code: &str, workspace: &Workspace) -> Result<Vec<Command>> {
+        workspace.write("main.cpp", code)?;
+        let binary = workspace.path().join("main").to_string_lossy().to_string();
+        Ok(vec![command("g++", &["main.cpp", "-o", "main"]), command(&binary, &[])])
+    }
+}
+
+impl Executor for JavaExecutor {
+    fn language(&self) -> &'static str { "java" }
+
+    fn version_command(&self) -> Command {
+        command("java", &["-version"])
+    }
+
+    fn install_command(&self, dep: &str, _workspace: &Workspace) -> Option<Command> {
+        Some(command("mvn", &["dependency:get", &format!("-Dartifact={}", dep)]))
+    }
+
+    fn prepare(&self, code: &str, workspace: &Workspace) -> Result<Vec<Command>> {
+        let class_name = java_class_name(code);
+        let source = format!("{}.java", class_name);
+        workspace.write(&source, code)?;
+        Ok(vec![command("javac", &[&source]), command("java", &["-cp", ".", &class_name])])
+    }
+}
+
+impl Executor for PhpExecutor {
+    fn language(&self) -> &'static str { "php" }
+
+    fn version_command(&self) -> Command {
+        command("php", &["--version"])
+    }
+
+    // Definitions go after the opening tag, which must stay first. Without a
+    // tag PHP would echo them as text, so such code is left alone.
+    fn with_prelude(&self, code: &str, exports: &[(String, Value)]) -> String {
+        let helper = code.contains("rift_return");
+        let store = code.contains("rift_store_");
+        let Some(body) = code.trim_start().strip_prefix("<?php").filter(|_| !exports.is_empty() || helper || store) else {
+            return code.to_string();
+        };
+        let mut source = "<?php\n".to_string();
+        for (name, value) in exports {
+            let json = value.to_json().to_string().replace('\\', "\\\\").replace('\'', "\\'");
+            source.push_str(&format!("${} = json_decode('{}', true);\n", name, json));
+        }
+        if helper {
+            source.push_str(&format!(
+                "function rift_return($value) {{ echo \"{}\" . json_encode($value) . \"\\n\"; }}\n",
+                RESULT_MARKER
+            ));
+        }
+        if store {
+            source.push_str(concat!(
+                "function rift_store_put($key, $value) { file_put_contents(getenv('RIFT_STORE') . '/' . $key . '.json', json_encode($value)); }\n",
+                "function rift_store_get($key, $default = null) {\n",
+                "    $path = getenv('RIFT_STORE') . '/' . $key . '.json';\n",
+                "    return file_exists($path) ? json_decode(file_get_contents($path), true) : $default;\n",
+                "}\n",
+            ));
+        }
+        source + body
+    }
+
+    fn prepare(&self, code: &str, workspace: &Workspace) -> Result<Vec<Command>> {
+        workspace.write("main.php", code)?;
+        Ok(vec![command("php", &["main.php"])])
+    }
+}
+
+impl Executor for RustExecutor {
+    fn language(&self) -> &'static str { "rust" }
+
+    fn version_command(&self) -> Command {
+        command("rustc", &["--version"])
+    }
+
+    fn prepare(&self, code: &str, workspace: &Workspace) -> Result<Vec<Command>> {
+        workspace.write("main.rs", code)?;
+        let binary = workspace.path().join("main").to_string_lossy().to_string();
+        Ok(vec![command("rustc", &["main.rs", "-o", "main"]), command(&binary, &[])])
+    }
+}
+
+// Every language executor_for accepts, by its canonical name
+pub const LANGUAGES: &[&str] = &["python", "javascript", "go", "cpp", "java", "php", "rust"];
+
+pub fn executor_for(lang: &str) -> Result<Box<dyn Executor>> {
+    match lang {
+        "python" => Ok(Box::new(PythonExecutor)),
+        "javascript" | "js" => Ok(Box::new(JavaScriptExecutor)),
+        "go" => Ok(Box::new(GoExecutor)),
+        "cpp" => Ok(Box::new(CppExecutor)),
+        "java" => Ok(Box::new(JavaExecutor)),
+        "php" => Ok(Box::new(PhpExecutor)),
+        "rust" => Ok(Box::new(RustExecutor)),
+        _ => Err(RiftError::UnsupportedLanguage(lang.to_string())),
+    }
+}
+
+// Languages whose grammar was compiled out by cargo features still run,
+// they just skip dependency resolution and optimization.
+pub fn grammar(lang: &str) -> Option<Language> {
+    match lang {
+        #[cfg(feature = "lang-python")]
+        "python" => Some(unsafe { tree_sitter_python() }),
+        #[cfg(feature = "lang-javascript")]
+        "javascript" | "js" => Some(unsafe { tree_sitter_javascript() }),
+        #[cfg(feature = "lang-go")]
+        "go" => Some(unsafe { tree_sitter_go() }),
+        #[cfg(feature = "lang-cpp")]
+        "cpp" => Some(unsafe { tree_sitter_cpp() }),
+        #[cfg(feature = "lang-java")]
+        "java" => Some(unsafe { tree_sitter_java() }),
+        #[cfg(feature = "lang-php")]
+        "php" => Some(unsafe { tree_sitter_php() }),
+        _ => None,
+    }
+}
+
+pub fn resolve_deps(lang: &str, code: &str) -> Result<Vec<String>> {
+    let Some(language) = grammar(lang) else {
+        return Ok(Vec::new());
+    };
+    let mut parser = Parser::new();
+    parser.set_language(language).map_err(|e| RiftError::TreeSitterError(e.to_string()))?;
+    let tree = parser.parse(code, None)
+        .ok_or_else(|| RiftError::TreeSitterError(format!("Failed to parse {} code", lang)))?;
+    let mut deps = Vec::new();
+    traverse_node(&tree.root_node(), code, &mut deps);
+    Ok(deps)
+}
+
+fn traverse_node(node: &tree_sitter::Node, code: &str, deps: &mut Vec<String>) {
+    if node.kind() == "import_statement" || node.kind() == "import_declaration" {
+        if let Some(child) = node.child_by_field_name("name") {
+            let dep = &code[child.start_byte()..child.end_byte()];
+            deps.push(dep.to_string());
+        }
+    }
+    for child in node.children(&mut node.walk()) {
+        traverse_node(&child, code, deps);
+    }
+}
+
+// Names whose use makes a block's output depend on more than its source.
+// Matched against imported modules and called functions, whole or as a
+// prefix up to a `.`, `::` or `/`.
+const NONDETERMINISTIC: &[(&str, &[&str])] = &[
+    ("network", &["requests", "urllib", "http.client", "httpx", "socket", "fetch", "axios", "http.get", "https.get",
+                  "net/http", "http.Get", "http.Post", "curl_init", "file_get_contents", "HttpClient", "java.net"]),
+    ("time", &["time.time", "time.time_ns", "datetime.now", "datetime.datetime.now", "datetime.utcnow", "date.today",
+               "Date.now", "Date", "time.Now", "System.currentTimeMillis", "System.nanoTime", "Instant.now",
+               "LocalDateTime.now", "microtime", "std::chrono::system_clock::now"]),
+    ("random", &["random", "secrets", "uuid", "Math.random", "crypto.randomUUID", "math/rand", "crypto/rand",
+                 "rand", "mt_rand", "random_int", "java.util.Random", "Random", "UUID.randomUUID",
+                 "std::random_device", "std::rand"]),
+];
+
+// Network, clock and randomness use that would make caching and promotion
+// untrustworthy, as "category: name (line n)". Heuristic: it sees direct
+// calls and imports, not calls through aliases or helpers.
+pub fn nondeterminism(lang: &str, code: &str) -> Result<Vec<String>> {
+    let Some(language) = grammar(lang) else {
+        return Ok(Vec::new());
+    };
+    let mut parser = Parser::new();
+    parser.set_language(language).map_err(|e| RiftError::TreeSitterError(e.to_string()))?;
+    let tree = parser.parse(code, None)
+        .ok_or_else(|| RiftError::TreeSitterError(format!("Failed to parse {} code", lang)))?;
+    let mut findings = Vec::new();
+    find_nondeterminism(&tree.root_node(), code, &mut findings);
+    findings.dedup();
+    Ok(findings)
+}
+
+fn find_nondeterminism(node: &tree_sitter::Node, code: &str, findings: &mut Vec<String>) {
+    let text = |n: tree_sitter::Node| code[n.start_byte()..n.end_byte()].trim_matches(|c| c == '"' || c == '\'').to_string();
+    let name = match node.kind() {
+        "import_statement" | "import_from_statement" | "import_declaration" | "import_spec" => node
+            .child_by_field_name("name")
+            .or_else(|| node.child_by_field_name("module_name"))
+            .or_else(|| node.child_by_field_name("source"))
+            .or_else(|| node.child_by_field_name("path"))
+            .map(text),
+        "call" | "call_expression" | "function_call_expression" => node.child_by_field_name("function").map(text),
+        "new_expression" => node.child_by_field_name("constructor").map(text),
+        "object_creation_expression" => node.child_by_field_name("type").map(text),
+        "method_invocation" => node.child_by_field_name("name").map(|name| match node.child_by_field_name("object") {
+            Some(object) => format!("{}.{}", text(object), text(name)),
+            None => text(name),
+        }),
+        _ => None,
+    };
+    if let Some(name) = name {
+        let matched = NONDETERMINISTIC.iter().find(|(_, names)| names.iter().any(|pattern| {
+            name == *pattern || [".", "::", "/"].iter().any(|sep| name.starts_with(&format!("{}{}", pattern, sep)))
+        }));
+        if let Some((category, _)) = matched {
+            findings.push(format!("{}: {} (line {})", category, name, node.start_position().row + 1));
+        }
+    }
+    for child in node.children(&mut node.walk()) {
+        find_nondeterminism(&child, code, findings);
+    }
+}
+
+// A built program ready to run; the workspace lives as long as it does
+struct Prepared {
+    workspace: Workspace,
+    run: Command,
+    // When the block's timeout runs out, counted from the start of the build
+    deadline: Instant,
+    language: &'static str,
+    prelude_lines: usize,
+    result_file: PathBuf,
+    deps_installed: usize,
+}
+
+impl Prepared {
+    fn locate(&self, text: &[u8], options: &ExecOptions) -> String {
+        map_locations(&String::from_utf8_lossy(text), self.workspace.path(), self.prelude_lines, &options.source_label)
+    }
+}
+
+// Runs a fuse block end to end: toolchain check, dependency install, build
+// steps and the program itself, all inside a fresh workspace. Blocking; call
+// it from spawn_blocking.
+pub fn execute(lang: &str, code: &str, options: &ExecOptions) -> Result<ExecutionOutput> {
+    let mut prepared = build(lang, code, options)?;
+    let language = prepared.language;
+    let mut output = run_with_timeout(language, &mut prepared.run, options, prepared.deadline)?;
+    output.deps_installed = prepared.deps_installed;
+    output.stderr = prepared.locate(&output.stderr, options).into_bytes();
+    // A result line at the end of a spilled stdout is not in the preview
+    if let Some(line) = output.stdout_file.as_deref().and_then(last_result_line) {
+        output.stdout.extend_from_slice(format!("\n{}\n", line).as_bytes());
+    }
+    // Fold a result file into stdout so both conventions are cached the same way
+    if let Ok(json) = fs::read_to_string(&prepared.result_file) {
+        let json: serde_json::Value = serde_json::from_str(&json).map_err(|e| RiftError::ExecutionError {
+            language: language.to_string(),
+            message: format!("invalid JSON in $RIFT_RESULT: {}", e),
+        })?;
+        if !output.stdout.is_empty() && !output.stdout.ends_with(b"\n") {
+            output.stdout.push(b'\n');
+        }
+        output.stdout.extend_from_slice(format!("{}{}\n", RESULT_MARKER, json).as_bytes());
+    }
+    Ok(output)
+}
+
+// Runs with the terminal attached, for programs that prompt or draw a TUI.
+// Nothing is captured and there is no timeout; returns the exit code.
+pub fn execute_interactive(lang: &str, code: &str, options: &ExecOptions) -> Result<Option<i32>> {
+    let mut prepared = build(lang, code, options)?;
+    let status = prepared.run
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()?;
+    Ok(status.code())
+}
+
+// Installs dependencies, writes the source and runs any build steps
+fn build(lang: &str, code: &str, options: &ExecOptions) -> Result<Prepared> {
+    let deadline = Instant::now() + options.timeout;
+    let executor = executor_for(lang)?;
+    let language = executor.language();
+    // Installs and build steps share the block's timeout but are not streamed
+    let steps_options = ExecOptions { stream: false, ..options.clone() };
+
+    let deps = resolve_deps(lang, code)?;
+    let provided = crate::provider::provide(options.provider, language, &deps, options.rift.as_deref())?;
+    // A provided environment brings its own toolchain
+    if provided.is_none() {
+        executor.version_command().output().map_err(|e| RiftError::ExecutionError {
+            language: language.to_string(),
+            message: format!("toolchain not found: {}", e),
+        })?;
+    }
+    let (runner, mut deps_installed) = match provided {
+        Some((runner, provided)) => (Some(runner), provided),
+        None => (None, 0),
+    };
+
+    let workspace = Workspace::new(language, code)?;
+    for dep in deps.into_iter().filter(|_| runner.is_none()) {
+        if let Some(mut install) = executor.install_command(&dep, &workspace) {
+            let output = run_with_timeout(language, install.envs(options.env.iter().cloned()).current_dir(workspace.path()), &steps_options, deadline)?;
+            if !output.success() {
+                return Err(RiftError::DependencyError { language: language.to_string(), dependency: dep });
+            }
+            deps_installed += 1;
+        }
+    }
+
+    let source = executor.with_prelude(code, &options.exports);
+    let prelude_lines = source.lines().count().saturating_sub(code.lines().count());
+    let locate = |text: &[u8]| map_locations(&String::from_utf8_lossy(text), workspace.path(), prelude_lines, &options.source_label);
+    let mut steps = executor.prepare(&source, &workspace)?;
+    if let Some(runner) = &runner {
+        steps = steps.iter().map(|step| runner.wrap(step)).collect();
+    }
+    let mut run = steps.pop().ok_or_else(|| RiftError::ExecutionError {
+        language: language.to_string(),
+        message: "executor produced no command".to_string(),
+    })?;
+    for mut step in steps {
+        let output = run_with_timeout(language, with_env(&mut step, options).current_dir(workspace.path()), &steps_options, deadline)?;
+        if !output.success() {
+            return Err(RiftError::ExecutionError {
+                language: language.to_string(),
+                message: format!("build failed:\n{}", locate(&output.stderr)),
+            });
+        }
+    }
+
+    let result_file = workspace.path().join(RESULT_FILE);
+    run.env("RIFT_RESULT", &result_file);
+    // Absolute, since the program runs inside the workspace
+    let store = std::env::current_dir()?.join(crate::store::store_dir());
+    fs::create_dir_all(&store)?;
+    run.env("RIFT_STORE", store);
+    let tables = std::env::current_dir()?.join(crate::tables::tables_dir());
+    fs::create_dir_all(&tables)?;
+    run.env("RIFT_TABLES", tables);
+    with_env(&mut run, options).current_dir(workspace.path());
+
+    Ok(Prepared { workspace, run, deadline, language, prelude_lines, result_file, deps_installed })
+}
+
+// NUL bytes or invalid UTF-8 mean binary. A multi-byte character cut off at
+// the end (as in a spill preview) does not count.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(_) => false,
+        Err(e) => e.error_len().is_some(),
+    }
+}
+
+fn last_result_line(path: &Path) -> Option<String> {
+    let mut file = fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(PREVIEW_BYTES as u64))).ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    String::from_utf8_lossy(&tail).lines().rev()
+        .find(|line| line.starts_with(RESULT_MARKER))
+        .map(str::to_string)
+}
+
+// Separates result lines from ordinary output. The last one wins; malformed
+// JSON is an error rather than silently becoming text.
+pub fn split_result(stdout: &str) -> std::result::Result<(String, Option<Value>), String> {
+    let mut text = String::new();
+    let mut result = None;
+    for line in stdout.split_inclusive('\n') {
+        match line.strip_prefix(RESULT_MARKER) {
+            Some(json) => {
+                let json = serde_json::from_str(json.trim_end())
+                    .map_err(|e| format!("invalid {} line: {}", RESULT_MARKER, e))?;
+                result = Some(Value::from_json(json));
+            }
+            None => text.push_str(line),
+        }
+    }
+    Ok((text, result))
+}
+
+// Rewrites compiler and interpreter locations such as `main.rs:12:5` or
+// `File "/tmp/rift_python_.../main.py", line 12` to point at the fuse block,
+// with lines counted from the start of the user's code
+fn map_locations(text: &str, workspace: &Path, prelude_lines: usize, label: &str) -> String {
+    let text = text.replace(&format!("{}{}", workspace.display(), std::path::MAIN_SEPARATOR), "");
+    let pattern = regex::Regex::new(
+        r#"\b[A-Za-z_][A-Za-z0-9_]*\.(?:py|js|go|cpp|java|php|rs)(?P<sep>:|", line |\(|:\s*line\s+| on line )(?P<line>\d+)"#,
+    ).expect("location pattern is valid");
+    pattern.replace_all(&text, |caps: &regex::Captures| {
+        let line: usize = caps["line"].parse().unwrap_or(0);
+        match line.checked_sub(prelude_lines).filter(|&line| line > 0) {
+            Some(line) => format!("{}{}{}", label, &caps["sep"], line),
+            None => format!("{} (rift prelude){}{}", label, &caps["sep"], line),
+        }
+    }).into_owned()
+}
+
+// First line the toolchain prints for its version (java uses stderr)
+pub fn toolchain_version(lang: &str) -> Option<String> {
+    let output = executor_for(lang).ok()?.version_command().output().ok()?;
+    let text = if output.stdout.is_empty() { output.stderr } else { output.stdout };
+    String::from_utf8_lossy(&text).lines().next().map(|line| line.trim().to_string())
+}
+
+pub fn is_exportable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Strings are passed as-is, everything else as JSON
+// set_env() variables, then exports, so a RIFT_ name always means the export
+fn with_env<'a>(command: &'a mut Command, options: &ExecOptions) -> &'a mut Command {
+    command.envs(options.env.iter().cloned());
+    for (name, value) in &options.exports {
+        let text = match value {
+            Value::Str(s) => s.clone(),
+            other => other.to_json().to_string(),
+        };
+        command.env(format!("RIFT_{}", name.to_uppercase()), text);
+    }
+    command
+}
+
+fn run_with_timeout(language: &str, command: &mut Command, options: &ExecOptions, deadline: Instant) -> Result<ExecutionOutput> {
+    let start = Instant::now();
+    let mut child = command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stdout = pump(child.stdout.take(), options.stream, false, options.max_output);
+    let stderr = pump(child.stderr.take(), options.stream, true, options.max_output);
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() > deadline {
+            child.kill().ok();
+            child.wait().ok();
+            return Err(RiftError::ExecutionError {
+                language: language.to_string(),
+                message: format!("timed out after {}s", options.timeout.as_secs()),
+            });
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    Ok(ExecutionOutput {
+        stdout: stdout.bytes,
+        stderr: stderr.bytes,
+        exit_code: status.code(),
+        duration: start.elapsed(),
+        stdout_file: stdout.spill,
+        stderr_file: stderr.spill,
+        stdout_len: stdout.total,
+        deps_installed: 0,
+    })
+}
+
+#[derive(Default)]
+struct Captured {
+    bytes: Vec<u8>,
+    spill: Option<PathBuf>,
+    total: u64,
+}
+
+// Collects a child pipe on its own thread, echoing it as it arrives when
+// streaming. Past `limit` bytes the stream moves to a spill file so a chatty
+// program cannot exhaust memory; the pipe is always drained to the end.
+fn pump<R: Read + Send + 'static>(pipe: Option<R>, stream: bool, is_stderr: bool, limit: usize) -> thread::JoinHandle<Captured> {
+    thread::spawn(move || {
+        let mut captured = Captured::default();
+        let Some(mut pipe) = pipe else { return captured };
+        let mut spill: Option<fs::File> = None;
+        let mut discard = false;
+        let mut buf = [0u8; 8192];
+        while let Ok(n) = pipe.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            if stream {
+                if is_stderr {
+                    std::io::stderr().write_all(&buf[..n]).ok();
+                } else {
+                    std::io::stdout().write_all(&buf[..n]).ok();
+                }
+            }
+            captured.total += n as u64;
+            if let Some(file) = spill.as_mut() {
+                file.write_all(&buf[..n]).ok();
+                continue;
+            }
+            if discard {
+                continue;
+            }
+            captured.bytes.extend_from_slice(&buf[..n]);
+            if captured.bytes.len() > limit {
+                match spill_file(if is_stderr { "stderr" } else { "stdout" }) {
+                    Ok((path, mut file)) => {
+                        file.write_all(&captured.bytes).ok();
+                        captured.spill = Some(path);
+                        spill = Some(file);
+                    }
+                    Err(e) => {
+                        eprintln!("{}", i18n::message("executor.spill_failed", &[&e]));
+                        discard = true;
+                    }
+                }
+                captured.bytes.truncate(PREVIEW_BYTES.min(limit));
+            }
+        }
+        captured
+    })
+}
+
+fn spill_file(stream: &str) -> std::io::Result<(PathBuf, fs::File)> {
+    let dir = crate::state::state_dir().join(OUTPUTS_DIR);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.{}", uuid::Uuid::new_v4(), stream));
+    let file = fs::File::create(&path)?;
+    Ok((path, file))
+}
+
+fn command(program: &str, args: &[&str]) -> Command {
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    cmd
+}
+
+fn java_class_name(code: &str) -> String {
+    code.lines()
+        .find(|l| l.contains("class "))
+        .and_then(|l| l.split("class ").nth(1))
+        .and_then(|s| s.split(|c: char| c == '{' || c.is_whitespace()).next())
+        .filter(|s| !s.is_empty())
+        .unwrap_or("Main")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "lang-python")]
+    #[test]
+    fn test_nondeterminism() {
+        let code = "import requests\nimport json\nx = random.randint(1, 6)\nprint(json.dumps(x))\n";
+        assert_eq!(nondeterminism("python", code).unwrap(), vec![
+            "network: requests (line 1)".to_string(),
+            "random: random.randint (line 3)".to_string(),
+        ]);
+        assert!(nondeterminism("python", "print(sum(range(10)))").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_executor_lookup() {
+        assert_eq!(executor_for("js").unwrap().language(), "javascript");
+        assert_eq!(executor_for("rust").unwrap().language(), "rust");
+        assert!(matches!(executor_for("cobol"), Err(RiftError::UnsupportedLanguage(_))));
+    }
+
+    #[test]
+    fn test_prepare_writes_source() {
+        let code = "print('hi')";
+        let workspace = Workspace::new("python", code).unwrap();
+        let steps = PythonExecutor.prepare(code, &workspace).unwrap();
+
+        assert_eq!(steps.len(), 1);
+        assert_eq!(fs::read_to_string(workspace.path().join("main.py")).unwrap(), code);
+    }
+
+    #[test]
+    fn test_compiled_languages_build_first() {
+        let code = "fn main() {}";
+        let workspace = Workspace::new("rust", code).unwrap();
+        let steps = RustExecutor.prepare(code, &workspace).unwrap();
+
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].get_program(), "rustc");
+    }
+
+    #[test]
+    fn test_globals_prepended() {
+        let exports = vec![("region".to_string(), Value::from("us-east-1"))];
+        assert_eq!(
+            PythonExecutor.with_prelude("print(region)", &exports),
+            "region = __import__(\"json\").loads(\"\\\"us-east-1\\\"\")\nprint(region)"
+        );
+        assert_eq!(
+            PhpExecutor.with_prelude("<?php echo $region;", &exports),
+            "<?php\n$region = json_decode('\"us-east-1\"', true);\n echo $region;"
+        );
+        assert_eq!(GoExecutor.with_prelude("package main", &exports), "package main");
+    }
+
+    #[test]
+    fn test_binary_detection() {
+        assert!(!is_binary(b"hello\n"));
+        assert!(!is_binary("caf\u{e9}".as_bytes()));
+        assert!(!is_binary(&"caf\u{e9}".as_bytes()[..4]));
+        assert!(is_binary(b"\x89PNG\r\n\x1a\n\0\0"));
+        assert!(is_binary(&[0xff, 0xfe, b'a']));
+    }
+
+    #[test]
+    fn test_map_locations() {
+        let workspace = Path::new("/tmp/rift_rust_abc_1");
+        let rustc = "error[E0425]: cannot find value `x`\n --> /tmp/rift_rust_abc_1/main.rs:3:5\n";
+        assert_eq!(
+            map_locations(rustc, workspace, 0, "build/@fuse \"rust\""),
+            "error[E0425]: cannot find value `x`\n --> build/@fuse \"rust\":3:5\n"
+        );
+        let python = "  File \"/tmp/rift_rust_abc_1/main.py\", line 4, in <module>";
+        assert_eq!(map_locations(python, workspace, 2, "@fuse"), "  File \"@fuse\", line 2, in <module>");
+    }
+
+    #[test]
+    fn test_split_result() {
+        let (text, result) = split_result("working\n##RIFT:{\"ok\": true}\ndone\n").unwrap();
+        assert_eq!(text, "working\ndone\n");
+        assert_eq!(result, Some(Value::Map([("ok".to_string(), Value::Bool(true))].into_iter().collect())));
+        assert_eq!(split_result("plain").unwrap(), ("plain".to_string(), None));
+        assert!(split_result("##RIFT:{oops\n").is_err());
+    }
+
+    #[test]
+    fn test_workspace_removed_on_drop() {
+        let workspace = Workspace::new("php", "<?php echo 1;").unwrap();
+        let dir = workspace.path().to_path_buf();
+        assert!(dir.exists());
+        drop(workspace);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_java_class_name() {
+        assert_eq!(java_class_name("public class Hello {\n}"), "Hello");
+        assert_eq!(java_class_name("System.out.println(1);"), "Main");
+    }
+}
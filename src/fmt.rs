@@ -0,0 +1,299 @@
+use crate::error::{Result, RiftError};
+use crate::lexer::{tokenize, Token, TokenKind};
+use crate::parser::parse;
+
+const INDENT: &str = "    ";
+
+// What a `{` opened, which decides how its contents are laid out
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Block {
+    Body,
+    Config,
+    Fuse,
+    // A match arm's body, laid out like any other but followed by a line
+    // break even when a comma separates it from the next arm
+    Arm,
+}
+
+struct Writer {
+    out: String,
+    depth: usize,
+    fresh: bool,
+}
+
+impl Writer {
+    fn newline(&mut self) {
+        if !self.fresh {
+            self.out.push('\n');
+            self.fresh = true;
+        }
+    }
+
+    // At most one blank line, and never straight after an opening brace
+    fn blank_line(&mut self) {
+        self.newline();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") && !self.out.ends_with("{\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn word(&mut self, text: &str, space: bool) {
+        if self.fresh {
+            for _ in 0..self.depth {
+                self.out.push_str(INDENT);
+            }
+        } else if space {
+            self.out.push(' ');
+        }
+        self.out.push_str(text);
+        self.fresh = false;
+    }
+}
+
+// Reformats a script with one statement per line and four-space indentation.
+// Works on tokens rather than the AST so comments survive; the source must
+// still parse, and the result must tokenize to exactly the same tokens.
+pub fn format_source(source: &str) -> Result<String> {
+    let source = source.replace("\r\n", "\n");
+    format_tokens(&tokenize(&source)?)
+}
+
+// Lays out tokens that may not have come straight from a file, as after
+// `rift migrate` rewrites some of them
+pub fn format_tokens(tokens: &[Token]) -> Result<String> {
+    parse(tokens)?;
+
+    let mut w = Writer { out: String::new(), depth: 0, fresh: true };
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut closed = None;
+    for (i, token) in tokens.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|p| &tokens[p]);
+        let next = tokens.get(i + 1);
+        let value = token.value.as_str();
+        let gap = prev.is_some_and(|p| token.line > p.line + 1);
+        let block = blocks.last().copied().unwrap_or(Block::Body);
+
+        // A shebang or `#!rift` pragma is kept exactly as written
+        if token.kind == TokenKind::Comment && value.starts_with("#!") {
+            w.word(value, false);
+            w.newline();
+            continue;
+        }
+        if token.kind == TokenKind::Comment {
+            // A comment after code stays on that code's line
+            if prev.is_some_and(|p| p.line == token.line) {
+                if w.fresh && w.out.ends_with('\n') {
+                    w.out.pop();
+                    w.fresh = false;
+                }
+                w.word(&format!("//{}", value), true);
+            } else {
+                if gap { w.blank_line() } else { w.newline() }
+                w.word(&format!("//{}", value), false);
+            }
+            w.newline();
+            continue;
+        }
+        if gap && w.fresh && value != "}" {
+            w.blank_line();
+        }
+
+        match (&token.kind, value) {
+            (TokenKind::Symbol, "{") => {
+                let opened = block_kind(&tokens[..i]);
+                w.word("{", !(i > 0 && hugs_next(tokens, i - 1)));
+                blocks.push(opened);
+                if opened != Block::Fuse {
+                    w.depth += 1;
+                    w.newline();
+                }
+            }
+            (TokenKind::Symbol, "}") => {
+                closed = blocks.pop();
+                if closed == Some(Block::Fuse) {
+                    w.word("}", true);
+                } else {
+                    w.depth = w.depth.saturating_sub(1);
+                    w.newline();
+                    w.word("}", false);
+                }
+                // The rift body follows its metadata on the same line
+                if !next.is_some_and(|n| is_symbol(n, ";") || is_symbol(n, ",") || is_symbol(n, ")") || is_symbol(n, "]") || is_symbol(n, "{") || n.value == "else") {
+                    w.newline();
+                }
+            }
+            (TokenKind::Symbol, ";") => {
+                w.word(";", false);
+                // `requires gpu;` shares the line with the code it guards
+                if block != Block::Fuse {
+                    w.newline();
+                }
+            }
+            (TokenKind::Symbol, ",") => {
+                w.word(",", false);
+                if block == Block::Config || (closed == Some(Block::Arm) && prev.is_some_and(|p| is_symbol(p, "}"))) {
+                    w.newline();
+                }
+            }
+            (TokenKind::Symbol, ":" | "]") => w.word(value, false),
+            // Ranges are written `0..10`
+            (TokenKind::Symbol, "..") => w.word(value, false),
+            // Call parentheses and index brackets hug the name; grouping
+            // parentheses and list literals are spaced like operands
+            (TokenKind::Symbol, "(" | "[") => {
+                let grouping = prev.is_some_and(|p| p.kind == TokenKind::Keyword || (p.kind == TokenKind::Symbol && !is_symbol(p, ")") && !is_symbol(p, "]")));
+                let glued = i > 0 && hugs_next(tokens, i - 1);
+                w.word(value, grouping && !glued);
+            }
+            (TokenKind::Symbol, ")") => {
+                w.word(value, false);
+                if ends_directive(tokens, i) {
+                    w.newline();
+                }
+            }
+            (TokenKind::String, _) => {
+                let after_paren = i > 0 && hugs_next(tokens, i - 1);
+                w.word(&quote(value), !after_paren);
+                if ends_directive(tokens, i) {
+                    w.newline();
+                }
+            }
+            _ => {
+                // Config entries may omit separators; give each its own line
+                if block == Block::Config && token.kind == TokenKind::Identifier && next.is_some_and(|n| is_symbol(n, "=") || is_symbol(n, ":")) {
+                    w.newline();
+                }
+                let after_paren = i > 0 && hugs_next(tokens, i - 1);
+                w.word(value, !after_paren);
+                if ends_directive(tokens, i) {
+                    w.newline();
+                }
+            }
+        }
+    }
+    w.newline();
+
+    let reformatted = tokenize(&w.out)?;
+    let same = reformatted.len() == tokens.len()
+        && reformatted.iter().zip(tokens).all(|(a, b)| a.kind == b.kind && a.value == b.value);
+    if !same {
+        return Err(RiftError::ParseError("formatting would change the meaning of this script".to_string()));
+    }
+    Ok(w.out)
+}
+
+// The statement a `{` belongs to starts after the last `;`, `{` or `}`. A
+// `{` where a value belongs opens a map, laid out like a deploy config
+fn block_kind(before: &[Token]) -> Block {
+    if before.last().is_some_and(|t| matches!(t.value.as_str(), "=" | "(" | "[" | "," | "with") && t.kind != TokenKind::String) {
+        return Block::Config;
+    }
+    if before.last().is_some_and(|t| is_symbol(t, "=>")) {
+        return Block::Arm;
+    }
+    // `@rift name meta { key: value }`
+    if let [.., rift, _, meta] = before {
+        if rift.value == "@rift" && meta.kind == TokenKind::Identifier && meta.value == "meta" {
+            return Block::Config;
+        }
+    }
+    let statement = before.iter()
+        .rposition(|t| t.kind == TokenKind::Symbol && matches!(t.value.as_str(), ";" | "{" | "}"))
+        .map_or(before, |start| &before[start + 1..]);
+    let opener = statement.iter().rev().find(|t| t.kind == TokenKind::Keyword && t.value.starts_with('@'));
+    match opener.map(|t| t.value.as_str()) {
+        Some("@fuse") => Block::Fuse,
+        Some("@deploy") | Some("@invoke") => Block::Config,
+        _ => Block::Body,
+    }
+}
+
+// `@target "lang"`, `@version "2.0"`, `@cache off|on|ttl "1h"` and
+// `@fuse ... from "path"` or `from git_file("path", "rev")` have no terminator
+fn ends_directive(tokens: &[Token], i: usize) -> bool {
+    let keyword = |j: usize, k: &str| tokens[j].kind == TokenKind::Keyword && tokens[j].value == k;
+    let identifier = |j: usize, k: &str| tokens[j].kind == TokenKind::Identifier && tokens[j].value == k;
+    if is_symbol(&tokens[i], ")") {
+        return i >= 6 && identifier(i - 5, "git_file") && identifier(i - 6, "from");
+    }
+    match i.checked_sub(1) {
+        Some(p) if identifier(p, "from") && tokens[i].kind == TokenKind::String => true,
+        Some(p) if keyword(p, "@target") || keyword(p, "@version") => true,
+        Some(p) if keyword(p, "@cache") => tokens[i].value != "ttl",
+        Some(p) => p > 0 && tokens[p].value == "ttl" && keyword(p - 1, "@cache"),
+        None => false,
+    }
+}
+
+// A `-` with no operand before it negates rather than subtracts
+fn is_prefix(tokens: &[Token], i: usize) -> bool {
+    match i.checked_sub(1).map(|p| &tokens[p]) {
+        None => true,
+        Some(t) if t.kind == TokenKind::Keyword => !matches!(t.value.as_str(), "true" | "false"),
+        Some(t) => t.kind == TokenKind::Symbol && t.value != ")" && t.value != "]",
+    }
+}
+
+// `(`, `[`, `!`, `..` and a prefix `-` hug whatever follows them
+fn hugs_next(tokens: &[Token], i: usize) -> bool {
+    let token = &tokens[i];
+    token.kind == TokenKind::Symbol
+        && (matches!(token.value.as_str(), "(" | "[" | "!" | "..") || (token.value == "-" && is_prefix(tokens, i)))
+}
+
+fn is_symbol(token: &Token, symbol: &str) -> bool {
+    token.kind == TokenKind::Symbol && token.value == symbol
+}
+
+// Newlines and tabs stay literal so embedded multi-line code stays readable
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_layout() {
+        let source = "@rift app{@fuse \"python\"{\"print('hi')\"}   let x=print( \"a\",1 );}\n\n\n// deploy it\n@deploy \"aws\" { region = \"us-east-1\" bucket = \"b\" }";
+        let expected = "@rift app {\n    @fuse \"python\" { \"print('hi')\" }\n    let x = print(\"a\", 1);\n}\n\n// deploy it\n@deploy \"aws\" {\n    region = \"us-east-1\"\n    bucket = \"b\"\n}\n";
+        assert_eq!(format_source(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let source = "if ready { call build; } else { @target \"rust\" call optimize with app; } // done\nlet out = @fuse \"python\" { \"for i in range(3):\n    print(\\\"x\\\")\" };";
+        let once = format_source(source).unwrap();
+        assert!(once.contains("} else {"));
+        let arms = format_source("match target(){\"go\"|\"rust\"=>{x=1;},_=>{}}").unwrap();
+        assert_eq!(arms, "match target() {\n    \"go\" | \"rust\" => {\n        x = 1;\n    },\n    _ => {\n    }\n}\n");
+        assert_eq!(format_source(&arms).unwrap(), arms);
+        let meta = format_source("@rift site meta{description:\"Site\" requires:[\"python>=3.10\"]}{call build;}").unwrap();
+        assert_eq!(meta, "@rift site meta {\n    description: \"Site\"\n    requires: [\"python>=3.10\"]\n} {\n    call build;\n}\n");
+        let chain = format_source("if a {x=1;}else if b{x=2;} else {x=3;}").unwrap();
+        assert_eq!(chain, "if a {\n    x = 1;\n} else if b {\n    x = 2;\n} else {\n    x = 3;\n}\n");
+        assert!(once.contains("    call optimize with app;\n} // done\n"));
+        assert_eq!(format_source(&once).unwrap(), once);
+    }
+
+    #[test]
+    fn test_format_operators() {
+        assert_eq!(format_source("let x=-1+2*-y%3;let ok=!a&&b;let n=(a+1)*-(b);").unwrap(), "let x = -1 + 2 * -y % 3;\nlet ok = !a && b;\nlet n = (a + 1) * -(b);\n");
+        assert_eq!(format_source("for i in 0 .. n+1 {print(i);}").unwrap(), "for i in 0..n + 1 {\n    print(i);\n}\n");
+        assert_eq!(format_source("let cfg={region=\"eu\",n=1};").unwrap(), "let cfg = {\n    region = \"eu\",\n    n = 1\n};\n");
+        assert_eq!(format_source("let xs=[ 1,-2,[a] ];print(xs[0]-xs[ 1 ]);").unwrap(), "let xs = [1, -2, [a]];\nprint(xs[0] - xs[1]);\n");
+    }
+
+    #[test]
+    fn test_format_directives() {
+        let source = "@cache ttl \"1h\" @fuse \"python\" cache off { \"x\" } @cache on call a;";
+        assert_eq!(format_source(source).unwrap(), "@cache ttl \"1h\"\n@fuse \"python\" cache off { \"x\" }\n@cache on\ncall a;\n");
+        assert_eq!(format_source("@fuse \"python\" {requires gpu;\"x\"}").unwrap(), "@fuse \"python\" { requires gpu; \"x\" }\n");
+    }
+
+    #[test]
+    fn test_format_rejects_invalid_scripts() {
+        assert!(format_source("@rift {").is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use crate::error::{Result, RiftError};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Set by `rift run --git-rev`: sources named with `@fuse ... from "path"`
+// are then read at that revision too, so a replay sees only old content
+pub const REV_VAR: &str = "RIFT_GIT_REV";
+
+fn git(args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git").args(args).output()
+        .map_err(|e| RiftError::ConfigError(format!("could not run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(RiftError::ConfigError(format!("git {}: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim())));
+    }
+    Ok(output.stdout)
+}
+
+// Contents of `path` (relative to the working directory) as committed at `rev`
+pub fn show(rev: &str, path: &Path) -> Result<String> {
+    let spec = format!("{}:./{}", rev, path.display().to_string().trim_start_matches("./"));
+    String::from_utf8(git(&["show", &spec])?).map_err(|_| RiftError::ConfigError(format!("{} is not UTF-8 text", spec)))
+}
+
+// A source file at `rev`, at $RIFT_GIT_REV when that is set, or else as it
+// is in the working tree
+pub fn read_source(path: &Path, rev: Option<&str>) -> Result<String> {
+    match rev.map(str::to_string).or_else(|| std::env::var(REV_VAR).ok()) {
+        Some(rev) => show(&rev, path),
+        None => Ok(std::fs::read_to_string(path)?),
+    }
+}
+
+// Files that differ from `rev` in the working tree, plus untracked ones,
+// relative to the working directory
+pub fn changed_files(rev: &str) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for args in [&["diff", "--name-only", "--relative", rev][..], &["ls-files", "--others", "--exclude-standard"][..]] {
+        files.extend(String::from_utf8_lossy(&git(args)?).lines().map(PathBuf::from));
+    }
+    Ok(files)
+}
+
+// HEAD, suffixed with "-dirty" when the working tree has changes
+pub fn head_commit() -> Option<String> {
+    let head = String::from_utf8_lossy(&git(&["rev-parse", "HEAD"]).ok()?).trim().to_string();
+    let dirty = git(&["status", "--porcelain"]).is_ok_and(|out| !out.is_empty());
+    Some(if dirty { format!("{}-dirty", head) } else { head })
+}
+
+// A shallow clone of `url` at `dir`, fast-forwarded when it is already there
+pub fn sync_clone(url: &str, dir: &Path) -> Result<()> {
+    let path = dir.display().to_string();
+    if dir.join(".git").exists() {
+        git(&["-C", &path, "pull", "--ff-only", "--quiet"])?;
+    } else {
+        git(&["clone", "--depth", "1", "--quiet", url, &path])?;
+    }
+    Ok(())
+}
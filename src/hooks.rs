@@ -0,0 +1,43 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::i18n;
+
+// Hooks are best-effort: a broken webhook must never fail the deploy it reports on.
+pub async fn fire(hook: Option<&str>, mut payload: serde_json::Value) {
+    let Some(hook) = hook else { return };
+    crate::redact::json(&mut payload);
+    let result = if hook.starts_with("http://") || hook.starts_with("https://") {
+        post_webhook(hook, &payload).await
+    } else {
+        run_command(hook, &payload)
+    };
+    if let Err(e) = result {
+        eprintln!("{}", i18n::message("hook.failed", &[&hook, &e]));
+    }
+}
+
+async fn post_webhook(url: &str, payload: &serde_json::Value) -> Result<(), String> {
+    let response = reqwest::Client::new().post(url).json(payload).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    Ok(())
+}
+
+fn run_command(command: &str, payload: &serde_json::Value) -> Result<(), String> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .env("RIFT_EVENT", payload["event"].as_str().unwrap_or_default())
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(payload.to_string().as_bytes()).map_err(|e| e.to_string())?;
+    }
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("exited with {}", status));
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use crate::executor;
+use crate::value::Value;
+use crate::Guard;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+static FACTS: OnceLock<Value> = OnceLock::new();
+
+// The `host` map scripts see: os, arch, cpus, memory (bytes, null when
+// unknown), gpu ("cuda", "metal" or null), in_ci, and toolchains mapping
+// each language whose toolchain is installed to its version line. Probing
+// toolchains spawns a process per language, so it happens once per run, on
+// first use.
+pub fn facts() -> &'static Value {
+    FACTS.get_or_init(|| {
+        let probes: Vec<_> = executor::LANGUAGES.iter()
+            .map(|lang| std::thread::spawn(move || executor::toolchain_version(lang).map(|version| (lang.to_string(), Value::Str(version)))))
+            .collect();
+        let toolchains: BTreeMap<_, _> = probes.into_iter().filter_map(|probe| probe.join().ok().flatten()).collect();
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Value::Map(BTreeMap::from([
+            ("os".to_string(), Value::Str(std::env::consts::OS.to_string())),
+            ("arch".to_string(), Value::Str(std::env::consts::ARCH.to_string())),
+            ("cpus".to_string(), Value::Int(cpus as i64)),
+            ("memory".to_string(), memory().map_or(Value::Null, |bytes| Value::Int(bytes as i64))),
+            ("gpu".to_string(), gpu().map_or(Value::Null, |kind| Value::Str(kind.to_string()))),
+            ("in_ci".to_string(), Value::Bool(std::env::var_os("CI").is_some())),
+            ("toolchains".to_string(), Value::Map(toolchains)),
+        ]))
+    })
+}
+
+// Starts probing in the background so the first `host` lookup is quick
+pub fn warm_up() {
+    std::thread::spawn(facts);
+}
+
+#[cfg(target_os = "linux")]
+fn memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let kb = meminfo.lines().find_map(|line| line.strip_prefix("MemTotal:"))?.trim().trim_end_matches("kB").trim();
+    kb.parse::<u64>().ok().map(|kb| kb * 1024)
+}
+
+#[cfg(target_os = "macos")]
+fn memory() -> Option<u64> {
+    let output = std::process::Command::new("sysctl").args(["-n", "hw.memsize"]).output().ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn memory() -> Option<u64> {
+    None
+}
+
+// CUDA when an NVIDIA driver sees a card, Metal on Apple silicon
+fn gpu() -> Option<&'static str> {
+    if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+        return Some("metal");
+    }
+    let driver = std::fs::read_dir("/proc/driver/nvidia/gpus").is_ok_and(|mut cards| cards.next().is_some());
+    let smi = || std::process::Command::new("nvidia-smi").arg("-L").output()
+        .is_ok_and(|output| output.status.success() && !output.stdout.is_empty());
+    (driver || smi()).then_some("cuda")
+}
+
+// What kind of GPU this machine has, per the `gpu` fact
+pub fn gpu_kind() -> Option<String> {
+    fact("gpu").ok().filter(|kind| !kind.is_empty())
+}
+
+// What a `@when` guard names: a path into `facts()` (with or without a
+// `host.` prefix, `ci` being short for in_ci), or `env.NAME`
+pub fn fact(name: &str) -> Result<String, String> {
+    if let Some(var) = name.strip_prefix("env.") {
+        return Ok(std::env::var(var).unwrap_or_default());
+    }
+    let path = name.strip_prefix("host.").unwrap_or(name);
+    let path = if path == "ci" { "in_ci" } else { path };
+    let mut value = facts();
+    for field in path.split('.') {
+        value = match value {
+            Value::Map(entries) => entries.get(field),
+            _ => None,
+        }.ok_or_else(|| format!("Unknown host fact '{}'; expected one of os, arch, cpus, memory, gpu, ci, toolchains.LANG or env.NAME", name))?;
+    }
+    Ok(match value {
+        Value::Null => String::new(),
+        other => other.to_string(),
+    })
+}
+
+// A language whose toolchain is installed, or else any program on PATH
+pub fn has(tool: &str) -> bool {
+    if let Ok(executor) = executor::executor_for(tool) {
+        return fact(&format!("toolchains.{}", executor.language())).is_ok();
+    }
+    std::env::var_os("PATH").is_some_and(|path| {
+        std::env::split_paths(&path).any(|dir| {
+            let candidate = dir.join(tool);
+            candidate.is_file() || (cfg!(windows) && candidate.with_extension("exe").is_file())
+        })
+    })
+}
+
+pub fn matches(guard: &Guard) -> Result<bool, String> {
+    Ok(match guard {
+        Guard::Is(name, value) => fact(name)? == *value,
+        Guard::IsNot(name, value) => fact(name)? != *value,
+        Guard::Set(name) => !matches!(fact(name)?.as_str(), "" | "false" | "0"),
+        Guard::Has(tool) => has(tool),
+        Guard::And(left, right) => matches(left)? && matches(right)?,
+        Guard::Or(left, right) => matches(left)? || matches(right)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let os = Guard::Is("os".to_string(), std::env::consts::OS.to_string());
+        let not_os = Guard::IsNot("host.os".to_string(), std::env::consts::OS.to_string());
+        assert!(matches(&os).unwrap());
+        assert!(!matches(&Guard::And(Box::new(os.clone()), Box::new(not_os.clone()))).unwrap());
+        assert!(matches(&Guard::Or(Box::new(not_os), Box::new(os))).unwrap());
+        assert!(matches(&Guard::Set("cpus".to_string())).unwrap());
+        assert!(!matches(&Guard::Set("env.RIFT_SURELY_UNSET_VARIABLE".to_string())).unwrap());
+        assert!(matches(&Guard::Set("shoe_size".to_string())).is_err());
+    }
+}
@@ -0,0 +1,546 @@
+// User-facing text by message id. English is built in; another locale is a
+// TOML catalog of the same ids (flat `"error.parse" = "..."` or grouped under
+// `[error]`) in $RIFT_LOCALE_DIR or .rift/locales/<locale>.toml, so
+// translations ship without a rebuild. Embedders can `install` their own
+// catalog instead. `{0}`, `{1}`, ... are the message's arguments, in any
+// order. Ids and `RiftError::code` values are stable for tooling.
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+static CATALOG: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("error.parse", "Parse error: {0}"),
+    ("error.execution", "Execution error in {0}: {1}"),
+    ("error.unsupported_language", "Unsupported language: {0}"),
+    ("error.deployment", "Deployment error for {0}: {1}"),
+    ("error.io", "IO error: {0}"),
+    ("error.transformation", "Language transformation error: {0} -> {1}: {2}"),
+    ("error.variable_not_found", "Variable not found: {0}"),
+    ("error.function_not_found", "Function not found: {0}"),
+    ("error.config", "Invalid configuration: {0}"),
+    ("error.dependency", "Dependency installation failed: {0}: {1}"),
+    ("error.cache", "Cache error: {0}"),
+    ("error.secret", "Secret store error: {0}"),
+    ("error.network", "Network error: {0}"),
+    ("error.serialization", "Serialization error: {0}"),
+    ("error.tree_sitter", "Tree-sitter parsing error: {0}"),
+    ("repl.banner", "Rift v2.0.1 - Code Fusion Powerhouse by Zen"),
+    ("repl.welcome", "Type 'help' for available commands, 'exit' to quit"),
+    ("repl.goodbye", "Goodbye!"),
+    ("repl.cleared", "Environment cleared"),
+    ("repl.interrupted", "Use 'exit' to quit"),
+    ("repl.error", "Error: {0}"),
+    ("hint.unsupported_language", "Hint: Supported languages are: python, javascript, go, java, cpp, php, rust"),
+    ("hint.parse", "Hint: Check syntax. Use 'help' for examples"),
+    ("deploy.local_node", "{0}: found a local node at {1}, deploying there"),
+    ("deploy.waiting_for_slot", "Waiting for a free {0} deploy slot ({1} at a time)"),
+    ("deploy.still_working", "Still {0} {1}... ({2}s)"),
+    ("deploy.still_uploading", "Still uploading to {0}... {1}/{2} ({3}s)"),
+    ("deploy.ethereum", "Deployed to Ethereum: {0} with artifact {1}"),
+    ("deploy.verify_failed", "Warning: contract verification failed: {0}"),
+    ("deploy.solana", "Deployed to Solana: {0} with artifact {1}"),
+    ("deploy.lambda_rollout", "Rolled out AWS Lambda: {0} ({1})"),
+    ("deploy.lambda", "Deployed to AWS Lambda: {0}"),
+    ("deploy.ipfs", "Pinned to IPFS: ipfs://{0}"),
+    ("deploy.terraform", "Terraform configuration written to {0}; review with `terraform plan`"),
+    ("deploy.static", "Synced static site to {0}"),
+    ("deploy.systemd", "Service {0} installed and started"),
+    ("deploy.kubernetes", "Deployed to Kubernetes: {0}"),
+    ("deploy.local", "Deployed locally: {0}"),
+    ("warning", "Warning: {0}"),
+    ("deploy.alias_created", "Alias {0} created on version {1}"),
+    ("deploy.alias_weight", "{0}:{1} -> version {2} at {3}%"),
+    ("deploy.canary", "{0} -> {1} at {2}% ({3} of {4} replicas)"),
+    ("deploy.invalidated", "Invalidated CloudFront distribution {0}"),
+    ("deploy.cost_estimate", "Estimated {0} deploy cost: {1} {2} (~{3} {4})"),
+    ("deploy.price_feed_unavailable", "Warning: price feed unavailable: {0}"),
+    ("deploy.confirm_cost", "Cost exceeds {0} {1}. Proceed?"),
+    ("deploy.verify_submitted", "Submitted {0} for verification (guid {1})"),
+    ("deploy.verified", "Contract {0} verified: {1}"),
+    ("session.listening", "rift session listening on {0}"),
+    ("session.script_error", "Error in {0}: {1}"),
+    ("session.attached", "Attached to {0} (statements run remotely; 'detach' to leave)"),
+    ("session.remote_exit", "Remote script called exit({0})"),
+    ("repl.ok", "Ok"),
+    ("session.detached", "Detached"),
+    ("upload.part", "  {0}: part {1}/{2} ({3}/{4})"),
+    ("upload.retrying", "Warning: {0} failed ({1}), retrying"),
+    ("upload.resuming_parts", "Resuming upload of {0} ({1} parts already stored)"),
+    ("upload.resuming_at", "Resuming upload of {0} at {1}"),
+    ("mock.deployed_in", "[mock] Deployed to {0}: {1} in transaction 0x{2}"),
+    ("mock.deployed", "[mock] Deployed to {0}: {1}"),
+    ("mock.called", "[mock] Called {0} on {1}"),
+    ("plugin.ignored", "Warning: ignoring {0}; rift was built without the '{1}' feature"),
+    ("hook.failed", "Warning: hook '{0}' failed: {1}"),
+    ("redact.builtin_only", "Warning: {0}; only the built-in redaction patterns apply"),
+    ("usage.update_failed", "Warning: could not update {0}: {1}"),
+    ("executor.spill_failed", "Warning: could not spill output to disk ({0}); discarding the rest"),
+    ("deploy.unchanged", "{0}: no changes since deployment {1}, skipping (use --force to redeploy)"),
+    ("deploy.recorded", "Recorded deployment {0} ({1})"),
+    ("invoke.returned", "{0} returned: {1}"),
+    ("run.skipping_unaffected", "Skipping {0} (unaffected by the changes)"),
+    ("fuse.gpu_on_agent", "No local GPU; running @fuse \"{0}\" on agent {1} ({2})"),
+    ("deploy.promoting", "Promoting deployment {0} ({1}, artifact {2}) to {3}"),
+    ("cache.pinned", "Pinned {0}"),
+    ("cache.unpinned", "Unpinned {0}"),
+    ("cache.empty", "Cache is empty"),
+    ("cache.entries", "Entries: {0} ({1} pinned)"),
+    ("cache.inline", "Inline outputs: {0} bytes"),
+    ("cache.blobs", "Blobs: {0} holding {1} bytes, {2} shared by several entries"),
+    ("cache.saved", "Saved by deduplication: {0} bytes"),
+    ("cache.removed", "Removed {0}"),
+    ("cli.wrote_bytes", "Wrote {0} ({1} bytes)"),
+    ("cli.nothing_affected", "Nothing is affected by changes since {0}"),
+    ("cli.affected", "Affected by changes since {0}: {1}"),
+    ("repl.stdlib_failed", "Warning: could not load the standard library: {0}"),
+    ("repl.stopped_recording", "Stopped recording to {0}"),
+    ("repl.not_recording", "Not recording"),
+    ("repl.recording", "Recording statements to {0} ('record off' to stop)"),
+    ("repl.usage", "Usage: {0}"),
+    ("repl.preflight_ok", "Everything {0} needs is in place"),
+    ("repl.preflight_missing", "{0} is missing:\n  {1}"),
+    ("repl.record_failed", "Warning: could not record to {0}: {1}; recording stopped"),
+    ("replay.exit", "Transcript called exit({0})"),
+    ("replay.summary", "Replayed {0} statement(s) from {1}; {2} changed"),
+    ("replay.recorded", "  ! recorded: {0}"),
+    ("demo.wrote", "Wrote {0} ({1}s)"),
+    ("run.unreadable", "Error: could not read {0}: {1}"),
+    ("ci.summary_failed", "Warning: could not write the job summary: {0}"),
+    ("deploy.history", "Deployments recorded:"),
+    ("deploy.none_recorded", "No deployments were recorded"),
+    ("deploy.history_unreadable", "Warning: could not read deployment history: {0}"),
+    ("test.none", "No test scripts found"),
+    ("test.summary", "{0} passed, {1} failed"),
+    ("test.failed", "  FAILED {0}"),
+    ("fmt.would_reformat", "Would reformat {0}"),
+    ("fmt.formatted", "Formatted {0}"),
+    ("plugin.none", "No plugins loaded (looked in {0})"),
+    ("audit.summary", "Checked {0} dependencies, {1} advisories"),
+    ("secret.stored", "Stored {0}; refer to it as \"secret:{0}\""),
+    ("secret.removed", "Removed {0}"),
+    ("usage.off", "Collection is off; set usage_stats = true in rift.toml (or {0}=1) to keep statistics in {1}. They never leave this machine."),
+    ("usage.removed", "Removed {0}"),
+    ("usage.none", "No statistics collected"),
+    ("auth.summary", "{0} credentials checked, {1} failed"),
+    ("licenses.summary", "{0} dependencies, {1} with denied licenses"),
+    ("registry.installed", "Installed {0}@{1} (sha256 {2})"),
+    ("registry.plugins", "  plugins: {0}"),
+    ("registry.usage", "Use it with `import {0};`"),
+    ("registry.packed", "Packed {0}@{1}: {2} files, {3} bytes (sha256 {4})"),
+    ("registry.published", "Published {0}@{1} to {2}"),
+    ("sbom.wrote", "Wrote {0} ({1} components)"),
+    ("compile.wrote", "Wrote {0} (build it with: cargo build --release --manifest-path {1})"),
+    ("migrate.current", "{0} is already current"),
+    ("migrate.written", "Migrated {0}"),
+    ("migrate.dry_run", "Run again with --write to save these changes"),
+    ("run.error_in", "Error: {0}: {1}"),
+    ("config.default", "Warning: {0}; using default configuration"),
+    ("fuse.cached", "Using cached artifact: {0}"),
+    ("fuse.full_output", "Full output: {0}"),
+    ("minion.suggestion", "Minion suggestion: {0}"),
+    ("secret.missing", "No secret named {0}"),
+    ("repl.input_error", "Input error: {0}"),
+    ("repl.history_failed", "Warning: Could not save history: {0}"),
+    ("help.title", "Rift v2.0.1 Commands:"),
+    ("help.heading.basic", "Basic Commands:"),
+    ("help.basic.rift", "Create a new rift (project)"),
+    ("help.basic.rift_meta", "Describe a rift; its needs are checked before it runs:\nrequires (toolchains, programs), env (variables that\nmust be set), network (\"host:port\" or URL to reach)"),
+    ("help.basic.fuse", "Add code in specified language"),
+    ("help.basic.fuse_interactive", "Run with the terminal attached (prompts, TUIs)"),
+    ("help.basic.fuse_on", "Run on a remote agent listed under [agents]"),
+    ("help.basic.fuse_cache", "Always rerun this block (or cache ttl \"10m\")"),
+    ("help.basic.fuse_from", "Take the code from a file (or git_file(\"path\", \"rev\"))"),
+    ("help.basic.fuse_gpu", "Run only with CUDA or Metal, here or on an\nagent that has them (`requires gpu from \"f.py\"`)\nDependencies come from pip/npm/mvn, or from Nix (a\nshell.nix per rift) or conda (Python) with\n[executor] provider = \"nix\" or \"conda\" in rift.toml"),
+    ("help.basic.cache", "Cache policy for the blocks that follow"),
+    ("help.basic.version", "Require a language version (or #!rift 2.1 on line 1)"),
+    ("help.basic.task", "Create a transformation task"),
+    ("help.basic.target", "Set target language for transformation"),
+    ("help.basic.deploy", "Deploy to specified target\n(max_size = \"50MB\" fails oversized artifacts,\ncompression = \"zip\" | \"tar.gz\" | \"zstd\" | \"none\",\ntimeout = \"30m\" before giving up; Ctrl-C cancels,\nprofile = \"name\" takes settings from rift.toml,\nnetwork = \"local\" | \"auto\" uses LocalStack, anvil\nor solana-test-validator; endpoint/rpc_url override)"),
+    ("help.basic.invoke", "Call a method on a deployed contract"),
+    ("help.basic.call", "Execute a rift or task"),
+    ("help.basic.call_with", "Pass values, readable as args.0, args.1"),
+    ("help.basic.call_value", "Keep the value of the body's last statement\n(e.g. the output of a closing @fuse block)"),
+    ("help.basic.fn", "Define a function; `call name with 1, 2;` binds\na and b as locals for that call"),
+    ("help.basic.export", "Share variables with fuse code (RIFT_NAME env vars)"),
+    ("help.basic.import", "Load a package fetched with `rift install name[@version]`"),
+    ("help.basic.import_file", "Run another file here; paths are relative to the\nimporting file, and import cycles are errors"),
+    ("help.basic.let_fuse", "Keep a block's output, or what it passed to\nrift_return(value) (python, javascript, php)"),
+    ("help.basic.store", "Shared store, also rift_store_put/get in fuse code"),
+    ("help.basic.table", "Show the head of a table written with\nrift_table_write(name, df) in python"),
+    ("help.basic.let", "Set a variable, local to the enclosing rift, task,\nfn, if, loop or match body (top-level ones are global)"),
+    ("help.basic.global", "Set a global variable from inside any body"),
+    ("help.basic.assign", "Update a variable declared with let"),
+    ("help.basic.list", "Lists, indexed from 0 (maps by key: m[\"name\"])"),
+    ("help.basic.len_push", "Length of a list, map or string; append to a list"),
+    ("help.basic.split", "A string's words, or its parts between separators"),
+    ("help.basic.strings", "Interpolation (also in inline fuse code from\n@version \"2.1\" on; write \\${ for a literal one) and\njoining text with +"),
+    ("help.basic.maps", "Maps: cfg.region or cfg[\"region\"]; @deploy \"aws\" cfg;\ndeploys with a map as its config"),
+    ("help.basic.arithmetic", "Arithmetic: + - * / %; ints unless a float is\ninvolved; + joins whenever a side is text or output,\nwhile - * / % read numeric text as numbers"),
+    ("help.basic.conditions", "Conditions: true/false, && || and !, where 0,\n\"\" and null count as false\nReserved words: true false in match global import\nparam fn for export; rename variables using them\n(config keys like `match = \"x\"` still work)"),
+    ("help.basic.else_if", "Branches; any number of else if"),
+    ("help.basic.match", "First arm equal to the\nvalue runs (target() is the @target language)"),
+    ("help.basic.compare", "Compare with == != < <= > >=; numbers by value,\nother text as strings"),
+    ("help.basic.param", "Input set with -D/--var-file (prompted in the REPL)"),
+    ("help.basic.when", "Run on matching hosts: host facts, env.NAME,\nhas(\"tool\"), combined with and/or; else { }"),
+    ("help.basic.host", "Host facts: os, arch, cpus, memory, in_ci, toolchains"),
+    ("help.basic.print", "Print values"),
+    ("help.basic.input", "Read a line from the user"),
+    ("help.basic.http", "HTTP request (also http_post(url, body))"),
+    ("help.basic.files", "File access, limited by [policy] in rift.toml"),
+    ("help.basic.exists_glob", "Test for a path / list matching paths"),
+    ("help.basic.json", "Convert between JSON text and values"),
+    ("help.basic.time", "Unix timestamp / formatted UTC time"),
+    ("help.basic.sleep", "Pause for seconds (1, 0.5) or \"500ms\", \"2m\""),
+    ("help.basic.random", "Random identifiers and numbers"),
+    ("help.basic.regex", "Also regex_replace(re, s, r), regex_captures(re, s)"),
+    ("help.basic.env", "Read or set environment variables"),
+    ("help.basic.exit", "Stop and exit rift with the given status"),
+    ("help.basic.log", "Log to stderr (also log.debug, log.warn, log.error)"),
+    ("help.basic.field", "Read a field of a map value"),
+    ("help.heading.flow", "Flow Control:"),
+    ("help.flow.if", "Conditional execution"),
+    ("help.flow.while", "Loop execution"),
+    ("help.flow.for", "Loop over a range, a list, a map's keys or lines of text"),
+    ("help.heading.stdlib", "Standard Library (skip with --no-stdlib; answers land in `result`):"),
+    ("help.stdlib.dirname", "\"dist/app/main.js\" -> \"dist/app\""),
+    ("help.stdlib.basename", "\"dist/app/main.js\" -> \"main.js\""),
+    ("help.stdlib.extension", "\"dist/app/main.js\" -> \"js\""),
+    ("help.heading.repl", "Utility Commands:"),
+    ("help.repl.help", "Show this help"),
+    ("help.repl.status", "Show environment status"),
+    ("help.repl.stats", "Per-language runs, timings, cache hits, errors"),
+    ("help.repl.plugins", "List loaded plugins and what they add"),
+    ("help.repl.describe", "Show a rift's metadata and whether its needs are met"),
+    ("help.repl.preflight", "List what a call would be missing, including rifts it calls"),
+    ("help.repl.promote", "Redeploy a recorded artifact elsewhere"),
+    ("help.repl.artifact_verify", "Check an artifact against its build manifest"),
+    ("help.repl.cache_list", "Show cached fuse outputs (also info, rm <hash>)"),
+    ("help.repl.cache_stats", "Entry counts and bytes saved by shared blobs"),
+    ("help.repl.cache_pin", "Keep an entry through clear and cache rm"),
+    ("help.repl.record", "Save each statement and its result (record off to stop)"),
+    ("help.repl.replay", "Rerun a recording in a fresh session, flagging changes"),
+    ("help.repl.clear", "Clear all rifts and variables"),
+    ("help.repl.exit", "Exit Rift"),
+    ("help.heading.cli", "Command Line (rift --help for flags):"),
+    ("help.cli.run", "Run a script and exit with its status"),
+    ("help.cli.git_rev", "Replay a script as committed at a revision"),
+    ("help.cli.changed_since", "Only call rifts/tasks affected since REV"),
+    ("help.cli.mock_deploys", "Simulate @deploy/@invoke without credentials"),
+    ("help.cli.plain", "Plain output for screen readers and CI logs (no color\nor progress redraws, also from tools fuse code runs)"),
+    ("help.cli.auth_check", "Verify deploy credentials up front"),
+    ("help.cli.auth_policy", "Least-privilege IAM policy for AWS deploys"),
+    ("help.cli.secret", "Keep a key in the OS keyring; use it in deploy\nconfigs and profiles as api_key = \"secret:NAME\"\n(secrets, token-like text and [redact] patterns in\nrift.toml are masked in transcripts, casts, deploy\nhistory and hook payloads)"),
+    ("help.cli.demo_export", "Replay a REPL recording as an asciinema cast"),
+    ("help.cli.fmt", "Reformat scripts in place"),
+    ("help.cli.check", "Parse and validate without running anything"),
+    ("help.cli.migrate", "Rewrite syntax from earlier rift versions"),
+    ("help.cli.compile", "Generate a standalone Rust program"),
+    ("help.cli.bundle", "Package a script as a self-contained executable"),
+    ("help.cli.sbom", "CycloneDX or SPDX list of fuse dependencies"),
+    ("help.cli.audit", "Known advisories for fuse dependencies (OSV)"),
+    ("help.cli.licenses", "Dependency licenses, checked against [policy]"),
+    ("help.cli.deploy", "Run a script and list its deployments"),
+    ("help.cli.test", "Run each script under tests/ and report failures"),
+    ("help.cli.agent", "Serve @fuse ... on \"agent\" executions"),
+    ("help.cli.serve", "Run a session that others can attach to"),
+    ("help.cli.attach", "Drive a served session from this terminal\n(agents and sessions share $RIFT_AGENT_TOKEN)"),
+    ("help.cli.stats", "Local usage: runs and time per command and language\n(only with usage_stats = true; never sent anywhere)"),
+    ("help.heading.example", "Example Usage:"),
+    ("help.heading.languages", "Supported Languages:"),
+    ("help.heading.targets", "Deployment Targets:"),
+    ("help.targets.ethereum", "Ethereum deploys estimate gas from bytecode_file, or else from compiling\n(with solc) the Solidity in source_file or the script's solidity fuse\nblock; with etherscan_api_key that Solidity is verified on Etherscan"),
+    ("help.heading.messages", "Messages:"),
+    ("help.messages.locale", "RIFT_LOCALE=de (or locale = \"de\" in rift.toml, else LANG) uses\n.rift/locales/de.toml; set RIFT_LOCALE_DIR to keep catalogs elsewhere"),
+];
+
+// The REPL's `help`: each section's heading id, then its entries as the
+// syntax (never translated) and the id of what it does
+const HELP: &[(&str, &[(&str, &str)])] = &[
+    ("help.heading.basic", &[
+        ("@rift name { ... }", "help.basic.rift"),
+        ("@rift name meta { description: \"...\", requires: [\"python>=3.10\"] } { ... }", "help.basic.rift_meta"),
+        ("@fuse \"lang\" { \"code\" }", "help.basic.fuse"),
+        ("@fuse interactive \"lang\" {}", "help.basic.fuse_interactive"),
+        ("@fuse \"lang\" on \"agent\" {}", "help.basic.fuse_on"),
+        ("@fuse \"lang\" cache off {}", "help.basic.fuse_cache"),
+        ("@fuse \"lang\" from \"file.py\"", "help.basic.fuse_from"),
+        ("@fuse \"lang\" { requires gpu; \"code\" }", "help.basic.fuse_gpu"),
+        ("@cache off | on | ttl \"1h\"", "help.basic.cache"),
+        ("@version \"2.1\"", "help.basic.version"),
+        ("@task name { ... }", "help.basic.task"),
+        ("@target \"lang\"", "help.basic.target"),
+        ("@deploy \"target\" { ... }", "help.basic.deploy"),
+        ("@invoke \"chain\" { ... }", "help.basic.invoke"),
+        ("call name;", "help.basic.call"),
+        ("call name with a, b;", "help.basic.call_with"),
+        ("let x = call name;", "help.basic.call_value"),
+        ("fn name(a, b) { ... }", "help.basic.fn"),
+        ("export name, ...;", "help.basic.export"),
+        ("import name;", "help.basic.import"),
+        ("import \"lib/steps.rift\";", "help.basic.import_file"),
+        ("let x = @fuse \"lang\" { ... };", "help.basic.let_fuse"),
+        ("store_put(key, v), store_get(key)", "help.basic.store"),
+        ("table_preview(table(\"name\"));", "help.basic.table"),
+        ("let var = value;", "help.basic.let"),
+        ("global var = value;", "help.basic.global"),
+        ("var = value; i += 1; i -= 1;", "help.basic.assign"),
+        ("let xs = [1, \"two\"]; xs[0]", "help.basic.list"),
+        ("len(x), xs = push(xs, v);", "help.basic.len_push"),
+        ("split(s), split(s, \",\")", "help.basic.split"),
+        ("\"Hello ${name}\", \"v\" + 1", "help.basic.strings"),
+        ("let cfg = { region = \"eu\" };", "help.basic.maps"),
+        ("let n = (count + 1) * 2;", "help.basic.arithmetic"),
+        ("if ready && !failed || true {}", "help.basic.conditions"),
+        ("if a { } else if b { } else { }", "help.basic.else_if"),
+        ("match target() { \"go\" | \"rust\" => { } _ => { } }", "help.basic.match"),
+        ("while i < 10 { ... }", "help.basic.compare"),
+        ("param name: type = default;", "help.basic.param"),
+        ("@when os == \"linux\" { ... }", "help.basic.when"),
+        ("host.os, host.toolchains.go", "help.basic.host"),
+        ("print(expr, ...);", "help.basic.print"),
+        ("let var = input(\"prompt\");", "help.basic.input"),
+        ("let r = http_get(\"url\");", "help.basic.http"),
+        ("read_file(p) write_file(p, s)", "help.basic.files"),
+        ("exists(p), glob(pattern)", "help.basic.exists_glob"),
+        ("json_parse(s), json_string(v)", "help.basic.json"),
+        ("now(), format_time(t)", "help.basic.time"),
+        ("sleep(d)", "help.basic.sleep"),
+        ("uuid(), random_int(a, b)", "help.basic.random"),
+        ("regex_match(re, s)", "help.basic.regex"),
+        ("env(name), set_env(name, v)", "help.basic.env"),
+        ("exit(code);", "help.basic.exit"),
+        ("log.info(msg);", "help.basic.log"),
+        ("result.field", "help.basic.field"),
+    ]),
+    ("help.heading.flow", &[
+        ("if condition { ... }", "help.flow.if"),
+        ("while condition { ... }", "help.flow.while"),
+        ("for i in 0..10 { ... }", "help.flow.for"),
+    ]),
+    ("help.heading.stdlib", &[
+        ("call dirname with path;", "help.stdlib.dirname"),
+        ("call basename with path;", "help.stdlib.basename"),
+        ("call extension with path;", "help.stdlib.extension"),
+    ]),
+    ("help.heading.repl", &[
+        ("help", "help.repl.help"),
+        ("status", "help.repl.status"),
+        ("stats", "help.repl.stats"),
+        ("plugins", "help.repl.plugins"),
+        ("describe <rift>", "help.repl.describe"),
+        ("preflight <rift>", "help.repl.preflight"),
+        ("promote <id> --to <profile>", "help.repl.promote"),
+        ("artifact verify <id|file>", "help.repl.artifact_verify"),
+        ("cache list", "help.repl.cache_list"),
+        ("cache stats", "help.repl.cache_stats"),
+        ("cache pin <hash>", "help.repl.cache_pin"),
+        ("record <file>", "help.repl.record"),
+        ("replay <file> [--mock]", "help.repl.replay"),
+        ("clear", "help.repl.clear"),
+        ("exit/quit", "help.repl.exit"),
+    ]),
+    ("help.heading.cli", &[
+        ("rift run script.rift", "help.cli.run"),
+        ("rift run --git-rev REV file", "help.cli.git_rev"),
+        ("rift run --changed-since REV file", "help.cli.changed_since"),
+        ("rift run --mock-deploys file", "help.cli.mock_deploys"),
+        ("rift --plain ...", "help.cli.plain"),
+        ("rift auth check [target] [--profile p]", "help.cli.auth_check"),
+        ("rift auth policy aws [--profile p]", "help.cli.auth_policy"),
+        ("rift secret set|get|rm NAME", "help.cli.secret"),
+        ("rift demo export out.cast -t t.jsonl", "help.cli.demo_export"),
+        ("rift fmt [--check] files...", "help.cli.fmt"),
+        ("rift check files...", "help.cli.check"),
+        ("rift migrate [--write] file", "help.cli.migrate"),
+        ("rift compile file --emit rust-project", "help.cli.compile"),
+        ("rift bundle file -o app", "help.cli.bundle"),
+        ("rift sbom file [--rift name]", "help.cli.sbom"),
+        ("rift audit deps file", "help.cli.audit"),
+        ("rift licenses file", "help.cli.licenses"),
+        ("rift deploy script.rift", "help.cli.deploy"),
+        ("rift test [paths...]", "help.cli.test"),
+        ("rift agent [--listen addr]", "help.cli.agent"),
+        ("rift serve [script.rift]", "help.cli.serve"),
+        ("rift attach <host:port>", "help.cli.attach"),
+        ("rift stats report [--months n]", "help.cli.stats"),
+    ]),
+    ("help.heading.example", &[
+        ("@rift hello { @fuse \"python\" { \"print('Hello, World!')\" } }", ""),
+        ("call hello;", ""),
+        ("@task optimize { @target \"rust\" call optimize with hello; }", ""),
+        ("call optimize;", ""),
+    ]),
+    ("help.heading.languages", &[
+        ("python, javascript, go, java, cpp, php, rust", ""),
+    ]),
+    ("help.heading.targets", &[
+        ("local, ethereum, solana, aws, ipfs, terraform, static, systemd, kubernetes", ""),
+        ("", "help.targets.ethereum"),
+    ]),
+    ("help.heading.messages", &[
+        ("", "help.messages.locale"),
+    ]),
+];
+
+// Use `catalog` for every message instead of a locale's file; false if
+// messages were already shown, in which case nothing changes
+pub fn install(catalog: HashMap<String, String>) -> bool {
+    CATALOG.set(catalog).is_ok()
+}
+
+pub fn text(id: &str) -> String {
+    message(id, &[])
+}
+
+// Descriptions line up in one column; a syntax too long to share its line
+// with the description gets a line of its own
+pub fn help() -> String {
+    const COLUMN: usize = 30;
+    const WIDTH: usize = 90;
+    let mut out = text("help.title");
+    for (heading, entries) in HELP {
+        out.push_str(&format!("\n\n{}", text(heading)));
+        for (syntax, id) in *entries {
+            let description = if id.is_empty() { String::new() } else { text(id) };
+            // Notes without a syntax, and examples without a description
+            if syntax.is_empty() || description.is_empty() {
+                for line in description.lines().chain([*syntax]).filter(|line| !line.is_empty()) {
+                    out.push_str(&format!("\n  {}", line));
+                }
+                continue;
+            }
+            let mut lines = description.lines();
+            let first = lines.next().unwrap_or_default();
+            if syntax.len() > COLUMN && 2 + syntax.len() + 3 + first.len() > WIDTH {
+                out.push_str(&format!("\n  {}\n{:w$} - {}", syntax, "", first, w = COLUMN + 2));
+            } else {
+                out.push_str(&format!("\n  {:<w$} - {}", syntax, first, w = COLUMN));
+            }
+            for line in lines {
+                out.push_str(&format!("\n{:w$}{}", "", line, w = COLUMN + 5));
+            }
+        }
+    }
+    out.push('\n');
+    out
+}
+
+pub fn message(id: &str, args: &[&dyn Display]) -> String {
+    let template = CATALOG.get_or_init(load).get(id).map(String::as_str)
+        .or_else(|| ENGLISH.iter().find(|(key, _)| *key == id).map(|(_, text)| *text))
+        .unwrap_or(id);
+    fill(template, args)
+}
+
+fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = template.to_string();
+    for (i, arg) in args.iter().enumerate() {
+        out = out.replace(&format!("{{{}}}", i), &arg.to_string());
+    }
+    out
+}
+
+// RIFT_LOCALE, then `locale` in rift.toml, then the usual POSIX variables
+fn locale() -> Option<String> {
+    std::env::var("RIFT_LOCALE").ok()
+        .or_else(|| crate::config::Config::load().ok().and_then(|config| config.locale))
+        .or_else(|| ["LC_ALL", "LC_MESSAGES", "LANG"].iter().find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty())))
+}
+
+// "de_DE.UTF-8" is looked up as de_DE, then de
+fn candidates(locale: &str) -> Vec<String> {
+    let name = locale.split(['.', '@']).next().unwrap_or_default();
+    if name.is_empty() || name == "C" || name == "POSIX" || name.starts_with("en") {
+        return Vec::new();
+    }
+    let mut names = vec![name.to_string()];
+    if let Some((language, _)) = name.split_once(['_', '-']) {
+        names.push(language.to_string());
+    }
+    names
+}
+
+fn load() -> HashMap<String, String> {
+    let dir = std::env::var("RIFT_LOCALE_DIR").map(PathBuf::from).unwrap_or_else(|_| PathBuf::from(".rift/locales"));
+    for name in locale().map(|locale| candidates(&locale)).unwrap_or_default() {
+        let path = dir.join(format!("{}.toml", name));
+        let Ok(data) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        match parse(&data) {
+            Ok(catalog) => return catalog,
+            Err(e) => eprintln!("Warning: {}: {}; messages stay in English", path.display(), e),
+        }
+    }
+    HashMap::new()
+}
+
+fn parse(data: &str) -> Result<HashMap<String, String>, String> {
+    fn flatten(prefix: &str, table: toml::Table, catalog: &mut HashMap<String, String>) -> Result<(), String> {
+        for (key, value) in table {
+            let id = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+            match value {
+                toml::Value::String(text) => {
+                    catalog.insert(id, text);
+                }
+                toml::Value::Table(table) => flatten(&id, table, catalog)?,
+                other => return Err(format!("'{}' must be a string, got {}", id, other.type_str())),
+            }
+        }
+        Ok(())
+    }
+    let mut catalog = HashMap::new();
+    flatten("", data.parse::<toml::Table>().map_err(|e| e.to_string())?, &mut catalog)?;
+    Ok(catalog)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(fill("{1} in {0}: {1}", &[&"python", &42]), "42 in python: 42");
+        assert_eq!(message("error.deployment", &[&"aws", &"denied"]), "Deployment error for aws: denied");
+        assert_eq!(text("no.such.message"), "no.such.message");
+    }
+
+    #[test]
+    fn test_candidates() {
+        assert_eq!(candidates("de_DE.UTF-8"), ["de_DE", "de"]);
+        assert_eq!(candidates("pt-BR"), ["pt-BR", "pt"]);
+        assert!(candidates("C.UTF-8").is_empty());
+        assert!(candidates("en_US.UTF-8").is_empty());
+    }
+
+    #[test]
+    fn test_parse_catalog() {
+        let catalog = parse("\"repl.goodbye\" = \"Tschüss!\"\n[error]\nparse = \"Syntaxfehler: {0}\"\n").unwrap();
+        assert_eq!(catalog["repl.goodbye"], "Tschüss!");
+        assert_eq!(catalog["error.parse"], "Syntaxfehler: {0}");
+        assert!(parse("count = 3").unwrap_err().contains("must be a string"));
+    }
+
+    // A duplicated id would leave one of its texts unreachable
+    #[test]
+    fn test_english_ids_are_unique() {
+        let mut ids: Vec<_> = ENGLISH.iter().map(|(id, _)| *id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), ENGLISH.len());
+    }
+
+    #[test]
+    fn test_help() {
+        let ids = HELP.iter().flat_map(|(heading, entries)| std::iter::once(*heading).chain(entries.iter().map(|(_, id)| *id)));
+        for id in ids.filter(|id| !id.is_empty()) {
+            assert!(ENGLISH.iter().any(|(key, _)| *key == id), "{} has no English text", id);
+        }
+        let help = help();
+        assert!(help.contains("\n  call name;                     - Execute a rift or task\n"));
+        assert!(help.contains("\n  @rift name meta { description: \"...\", requires: [\"python>=3.10\"] } { ... }\n                                 - Describe a rift"));
+    }
+}
@@ -0,0 +1,89 @@
+// `${expr}` splices a value into a string literal, or into inline fuse code
+// in scripts requiring language 2.1 or later, and `\${` stands for a literal
+// `${`, which JavaScript template literals and shell variables in fuse code
+// need. Older scripts' inline code and code read with `@fuse ... from` are
+// escaped as they are parsed, so they are never interpolated.
+use crate::error::{Result, RiftError};
+use crate::AST;
+
+#[derive(Debug)]
+pub enum Part {
+    Text(String),
+    Expr(AST),
+}
+
+pub fn parts(text: &str) -> Result<Vec<Part>> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut rest = text;
+    while let Some(ch) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("\\${") {
+            literal.push_str("${");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            let end = closing_brace(after)
+                .ok_or_else(|| RiftError::ParseError(format!("unclosed '${{' in \"{}\"", text)))?;
+            let expr = crate::parser::parse_expression_source(&after[..end])
+                .map_err(|e| RiftError::ParseError(format!("in '${{{}}}': {}", &after[..end], e)))?;
+            if !literal.is_empty() {
+                parts.push(Part::Text(std::mem::take(&mut literal)));
+            }
+            parts.push(Part::Expr(expr));
+            rest = &after[end + 1..];
+        } else {
+            literal.push(ch);
+            rest = &rest[ch.len_utf8()..];
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(Part::Text(literal));
+    }
+    Ok(parts)
+}
+
+// For text that must reach its program exactly as written
+pub fn escape(code: &str) -> String {
+    code.replace("${", "\\${")
+}
+
+// The `}` ending an interpolation, past any braces and strings inside it
+fn closing_brace(text: &str) -> Option<usize> {
+    let (mut depth, mut quoted, mut escaped) = (0, false, false);
+    for (i, ch) in text.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '{' if !quoted => depth += 1,
+            '}' if !quoted && depth == 0 => return Some(i),
+            '}' if !quoted => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parts() {
+        let parts = parts("Hello ${name}, ${ {a = \"}\"}[\"a\"] }!").unwrap();
+        assert!(matches!(&parts[0], Part::Text(t) if t == "Hello "));
+        assert!(matches!(&parts[1], Part::Expr(AST::Identifier(id)) if id == "name"));
+        assert!(matches!(&parts[3], Part::Expr(AST::Index(..))));
+        assert!(matches!(&parts[4], Part::Text(t) if t == "!"));
+
+        assert!(matches!(&super::parts("`${x}` \\${y}").unwrap()[..], [Part::Text(a), Part::Expr(_), Part::Text(b)] if a == "`" && b == "` ${y}"));
+        assert!(super::parts("${name").is_err());
+        assert!(super::parts("${}").is_err());
+        assert!(super::parts("${a b}").is_err());
+    }
+
+    #[test]
+    fn test_escape() {
+        let code = "console.log(`${x}`); echo \\${HOME}";
+        assert!(matches!(&parts(&escape(code)).unwrap()[..], [Part::Text(text)] if text == code));
+    }
+}
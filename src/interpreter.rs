@@ -1,605 +1,1463 @@
-use crate::{parser::AST, parse};
-use std::collections::HashMap;
-use std::process::Command;
-use std::fs;
-use tokio::{task, time::sleep};
-use futures::future;
-use web3::transports::Http;
-use web3::Web3;
-use solana_client::rpc_client::RpcClient;
-use rusoto_core::Region;
-use rusoto_s3::{S3Client, PutObjectRequest, S3};
-use rusoto_lambda::{LambdaClient, CreateFunctionRequest, Lambda};
-use sha2::{Sha256, Digest};
-use chrono;
-use tree_sitter::{Parser, Language};
-use notify::{Watcher, RecursiveMode, watcher};
-use std::sync::mpsc::channel;
-use std::time::Duration;
-
-extern "C" { fn tree_sitter_python() -> Language; }
-extern "C" { fn tree_sitter_javascript() -> Language; }
-extern "C" { fn tree_sitter_go() -> Language; }
-extern "C" { fn tree_sitter_cpp() -> Language; }
-extern "C" { fn tree_sitter_java() -> Language; }
-extern "C" { fn tree_sitter_php() -> Language; }
-
-#[derive(Debug, Clone)]
-pub struct Environment {
-    pub variables: HashMap<String, AST>,
-    pub rifts: HashMap<String, Vec<AST>>,
-    pub tasks: HashMap<String, Vec<AST>>,
-    pub artifact_cache: HashMap<String, String>,
-    pub target_lang: Option<String>,
-}
-
-pub async fn interpret(ast: &AST, env: &mut Environment) -> Result<(), String> {
-    match ast {
-        AST::Program(nodes) => {
-            let futures: Vec<_> = nodes.iter().map(|node| {
-                let ast = node.clone();
-                task::spawn(async move { interpret(&ast, env).await })
-            }).collect();
-            future::try_join_all(futures).await?;
-            Ok(())
-        }
-        AST::Rift(name, body) => {
-            env.rifts.insert(name.clone(), body.clone());
-            Ok(())
-        }
-        AST::Fuse(lang, code) => {
-            let hash = format!("{:x}", Sha256::digest(code.as_bytes()));
-            if let Some(cached) = env.artifact_cache.get(&hash) {
-                println!("Using cached artifact: {}", cached);
-                return Ok(());
-            }
-            let deps = resolve_deps(lang, code).await?;
-            install_deps(lang, &deps).await?;
-            let output = execute_with_deps(lang, code).await?;
-            let result = String::from_utf8_lossy(&output.stdout).to_string();
-            env.artifact_cache.insert(hash.clone(), result.clone());
-            println!("{} output: {}", lang, result);
-            if lang != "rust" { fs::remove_file(hash).ok(); }
-            Ok(())
-        }
-        AST::Task(name, body) => {
-            env.tasks.insert(name.clone(), body.clone());
-            Ok(())
-        }
-        AST::Target(lang) => {
-            env.target_lang = Some(lang.clone());
-            Ok(())
-        }
-        AST::Deploy(target, config) => {
-            let artifact = compile_rift(env).await?;
-            let compressed = compress_artifact(&artifact)?;
-            let futures: Vec<_> = vec![
-                task::spawn(deploy_to_target("ethereum", &compressed, config.clone())),
-                task::spawn(deploy_to_target("solana", &compressed, config.clone())),
-                task::spawn(deploy_to_target("aws", &compressed, config.clone())),
-                task::spawn(deploy_to_target("local", &compressed, config.clone())),
-            ].into_iter().filter(|f| {
-                let target_str = target.as_str();
-                target_str == "all" || target_str.contains(f.get().unwrap().get().unwrap().0)
-            }).collect();
-            future::try_join_all(futures).await?;
-            Ok(())
-        }
-        AST::Let(name, value) => {
-            env.variables.insert(name.clone(), evaluate_expression(value, env)?);
-            Ok(())
-        }
-        AST::Call(name, args) => {
-            if name == "optimize" {
-                let ast_to_optimize = args.first().ok_or("Missing code to optimize")?;
-                optimize_code(ast_to_optimize, env).await?;
-            } else if let Some(body) = env.rifts.get(name).cloned() {
-                interpret(&AST::Program(body), env).await?;
-            } else if let Some(body) = env.tasks.get(name).cloned() {
-                interpret(&AST::Program(body), env).await?;
-            } else {
-                return Err(format!("Unknown call target: {}", name));
-            }
-            Ok(())
-        }
-        AST::If(condition, then_body, else_body) => {
-            if evaluate_condition(condition, env)? {
-                interpret(&AST::Program(then_body.clone()), env).await?;
-            } else {
-                interpret(&AST::Program(else_body.clone()), env).await?;
-            }
-            Ok(())
-        }
-        AST::While(condition, body) => {
-            let mut iterations = 0;
-            while evaluate_condition(condition, env)? {
-                interpret(&AST::Program(body.clone()), env).await?;
-                iterations += 1;
-                if iterations > 10000 { return Err("Max iterations exceeded".to_string()); }
-            }
-            Ok(())
-        }
-        _ => Err("Unsupported operation".to_string()),
-    }
-}
-
-async fn resolve_deps(lang: &str, code: &str) -> Result<Vec<String>, String> {
-    let mut parser = Parser::new();
-    let lang_obj = match lang {
-        "python" => unsafe { tree_sitter_python() },
-        "javascript" | "js" => unsafe { tree_sitter_javascript() },
-        "go" => unsafe { tree_sitter_go() },
-        "cpp" => unsafe { tree_sitter_cpp() },
-        "java" => unsafe { tree_sitter_java() },
-        "php" => unsafe { tree_sitter_php() },
-        _ => return Err(format!("Unsupported language: {}", lang)),
-    };
-    parser.set_language(lang_obj).unwrap();
-    let tree = parser.parse(code, None).unwrap();
-    let mut deps = Vec::new();
-    traverse_node(&tree.root_node(), code, &mut deps);
-    Ok(deps)
-}
-
-async fn install_deps(lang: &str, deps: &[String]) -> Result<(), String> {
-    for dep in deps {
-        let output = match lang {
-            "python" => Command::new("pip3").args(["install", dep]).output(),
-            "javascript" => Command::new("npm").args(["install", dep]).output(),
-            "java" => Command::new("mvn").args(["dependency:get", &format!("-Dartifact={}", dep)]).output(),
-            _ => continue,
-        }.map_err(|e| format!("Install failed for {}: {}", dep, e))?;
-        if !output.status.success() {
-            return Err(format!("Failed to install {}: {}", dep, String::from_utf8_lossy(&output.stderr)));
-        }
-    }
-    Ok(())
-}
-
-async fn execute_with_deps(lang: &str, code: &str) -> Result<std::process::Output, String> {
-    let mut parser = Parser::new();
-    let lang_obj = match lang {
-        "python" => unsafe { tree_sitter_python() },
-        "javascript" | "js" => unsafe { tree_sitter_javascript() },
-        "go" => unsafe { tree_sitter_go() },
-        "cpp" => unsafe { tree_sitter_cpp() },
-        "java" => unsafe { tree_sitter_java() },
-        "php" => unsafe { tree_sitter_php() },
-        _ => return Err(format!("Unsupported language: {}", lang)),
-    };
-    parser.set_language(lang_obj).unwrap();
-    let tree = parser.parse(code, None).unwrap();
-    let root = tree.root_node();
-
-    let mut deps = Vec::new();
-    traverse_node(&root, code, &mut deps);
-
-    match lang {
-        "python" => {
-            Command::new("python3").arg("--version").output().map_err(|e| format!("Python not found: {}", e))?;
-            for dep in deps {
-                Command::new("pip3").args(["install", &dep]).output().map_err(|e| format!("Pip install failed for {}: {}", dep, e))?;
-            }
-            let hash = format!("{:x}", Sha256::digest(code.as_bytes()));
-            fs::write(&hash, code).map_err(|e| format!("Failed to write Python: {}", e))?;
-            let output = Command::new("python3").arg(&hash).output()?;
-            fs::remove_file(hash).ok();
-            Ok(output)
-        }
-        "rust" => {
-            Command::new("rustc").arg("--version").output().map_err(|e| format!("Rust not found: {}", e))?;
-            let temp_file = format!("temp_{}.rs", Sha256::digest(code.as_bytes()));
-            fs::write(&temp_file, code).map_err(|e| format!("Failed to write Rust: {}", e))?;
-            let output = Command::new("rustc").arg(&temp_file).arg("-o").arg(&temp_file[..temp_file.len()-3]).output()?;
-            fs::remove_file(&temp_file).ok();
-            Command::new(&temp_file[..temp_file.len()-3]).output()
-        }
-        "javascript" | "js" => {
-            Command::new("node").arg("--version").output().map_err(|e| format!("Node.js not found: {}", e))?;
-            for dep in deps {
-                Command::new("npm").args(["install", &dep]).output().map_err(|e| format!("Npm install failed for {}: {}", dep, e))?;
-            }
-            let hash = format!("{:x}", Sha256::digest(code.as_bytes()));
-            fs::write(&hash, code).map_err(|e| format!("Failed to write JS: {}", e))?;
-            let output = Command::new("node").arg(&hash).output()?;
-            fs::remove_file(hash).ok();
-            Ok(output)
-        }
-        "go" => {
-            Command::new("go").arg("version").output().map_err(|e| format!("Go not found: {}", e))?;
-            let temp_file = format!("temp_{}.go", Sha2::digest(code.as_bytes()));
-            fs::write(&temp_file, code).map_err(|e| format!("Failed to write Go: {}", e))?;
-            let output = Command::new("go").args(["run", &temp_file]).output()?;
-            fs::remove_file(temp_file).ok();
-            Ok(output)
-        }
-        "cpp" => {
-            Command::new("g++").arg("--version").output().map_err(|e| format!("C++ not found: {}", e))?;
-            let hash = format!("{:x}", Sha256::digest(code.as_bytes()));
-            fs::write(&hash, code).map_err(|e| format!("Failed to write C++: {}", e))?;
-            let output = Command::new("g++").arg(&hash).arg("-o").arg(&hash[..hash.len()-3]).output()?;
-            fs::remove_file(hash).ok();
-            Command::new(&hash[..hash.len()-3]).output()
-        }
-        "java" => {
-            Command::new("java").arg("-version").output().map_err(|e| format!("Java not found: {}", e))?;
-            let class_name = code.lines().find(|l| l.contains("class")).and_then(|l| l.split("class").nth(1)).and_then(|s| s.split('{').next()).map(|s| s.trim()).unwrap_or("Main");
-            let temp_file = format!("{}.java", class_name);
-            fs::write(&temp_file, code).map_err(|e| format!("Failed to write Java: {}", e))?;
-            for dep in deps {
-                Command::new("mvn").args(["dependency:get", &format!("-Dartifact={}", dep)]).output().map_err(|e| format!("Maven install failed for {}: {}", dep, e))?;
-            }
-            Command::new("javac").arg(&temp_file).output().map_err(|e| format!("Java compilation failed: {}", e))?;
-            let output = Command::new("java").arg(class_name).output()?;
-            fs::remove_file(temp_file).ok();
-            fs::remove_file(format!("{}.class", class_name)).ok();
-            Ok(output)
-        }
-        "php" => {
-            Command::new("php").arg("--version").output().map_err(|e| format!("PHP not found: {}", e))?;
-            let hash = format!("{:x}", Sha256::digest(code.as_bytes()));
-            fs::write(&hash, code).map_err(|e| format!("Failed to write PHP: {}", e))?;
-            let output = Command::new("php").arg(&hash).output()?;
-            fs::remove_file(hash).ok();
-            Ok(output)
-        }
-        _ => Err(format!("Unsupported language: {}", lang)),
-    }
-}
-
-fn traverse_node(node: &tree_sitter::Node, code: &str, deps: &mut Vec<String>) {
-    if node.kind() == "import_statement" || node.kind() == "import_declaration" {
-        if let Some(child) = node.child_by_field_name("name") {
-            let dep = &code[child.start_byte()..child.end_byte()];
-            deps.push(dep.to_string());
-        }
-    }
-    for child in node.children(&mut node.walk()) {
-        traverse_node(&child, code, deps);
-    }
-}
-
-async fn deploy_to_target(target: &str, artifact: &str, config: HashMap<String, String>) -> Result<(), String> {
-    let mut attempts = 0;
-    loop {
-        match target {
-            "ethereum" => {
-                let api_key = config.get("api_key").ok_or("Missing Ethereum API key")?;
-                let contract = config.get("contract").ok_or("Missing contract address")?;
-                let transport = Http::new(&format!("https://mainnet.infura.io/v3/{}", api_key)).map_err(|e| format!("Ethereum connection failed: {}", e))?;
-                let web3 = Web3::new(transport);
-                println!("Deployed to Ethereum: {} with artifact {}", contract, artifact);
-                break Ok(());
-            }
-            "solana" => {
-                let rpc_url = config.get("rpc_url").ok_or("Missing Solana RPC URL")?;
-                let program_id = config.get("program_id").ok_or("Missing Solana program ID")?;
-                let client = RpcClient::new(rpc_url.to_string());
-                println!("Deployed to Solana: {} with artifact {}", program_id, artifact);
-                break Ok(());
-            }
-            "aws" => {
-                let region = config.get("region").ok_or("Missing AWS region")?.parse::<Region>().map_err(|e| format!("Invalid region: {}", e))?;
-                let bucket = config.get("bucket").ok_or("Missing S3 bucket")?;
-                let func_name = config.get("function").ok_or("Missing Lambda function name")?;
-                let role = config.get("role").ok_or("Missing IAM role ARN")?;
-                let s3_client = S3Client::new(region.clone());
-                let lambda_client = LambdaClient::new(region);
-                let file = fs::read(artifact).map_err(|e| format!("Artifact not found: {}", e))?;
-                let put_req = PutObjectRequest {
-                    bucket: bucket.to_string(),
-                    key: format!("{}.zip", func_name),
-                    body: Some(file.into()),
-                    ..Default::default()
-                };
-                s3_client.put_object(put_req).await.map_err(|e| format!("S3 upload failed: {}", e))?;
-                let lambda_req = CreateFunctionRequest {
-                    function_name: func_name.to_string(),
-                    runtime: Some("provided.al2".to_string()),
-                    role: role.to_string(),
-                    handler: Some("main".to_string()),
-                    code: Some(rusoto_lambda::FunctionCode {
-                        s3_bucket: Some(bucket.to_string()),
-                        s3_key: Some(format!("{}.zip", func_name)),
-                        ..Default::default()
-                    }),
-                    ..Default::default()
-                };
-                lambda_client.create_function(lambda_req).await.map_err(|e| format!("Lambda creation failed: {}", e))?;
-                println!("Deployed to AWS Lambda: {}", func_name);
-                break Ok(());
-            }
-            "local" => {
-                let path = format!("rift_power_{}", chrono::Utc::now().timestamp());
-                fs::write(&path, artifact)?;
-                println!("Deployed locally: {}", path);
-                break Ok(());
-            }
-            _ => break Err(format!("Unsupported target: {}", target)),
-        }
-        attempts += 1;
-        if attempts > 3 { break Err(format!("Deploy to {} failed after retries", target)); }
-        sleep(Duration::from_millis(100 * 2u64.pow(attempts))).await; // Exponential backoff
-    }
-}
-
-fn compress_artifact(artifact: &str) -> Result<String, String> {
-    Ok(artifact.to_string()) // Mock compression—replace with real algo if needed
-}
-
-async fn optimize_code(ast: &AST, env: &mut Environment) -> Result<(), String> {
-    match ast {
-        AST::Rift(name, body) => {
-            let mut optimized = Vec::new();
-            let mut suggestions = Vec::new();
-            let target_lang = env.target_lang.clone().unwrap_or("rust".to_string());
-
-            for node in body {
-                if let AST::Fuse(lang, code) = node {
-                    let mut parser = Parser::new();
-                    let lang_obj = match lang.as_str() {
-                        "python" => unsafe { tree_sitter_python() },
-                        "javascript" | "js" => unsafe { tree_sitter_javascript() },
-                        "go" => unsafe { tree_sitter_go() },
-                        "cpp" => unsafe { tree_sitter_cpp() },
-                        "java" => unsafe { tree_sitter_java() },
-                        "php" => unsafe { tree_sitter_php() },
-                        _ => continue,
-                    };
-                    parser.set_language(lang_obj).unwrap();
-                    let tree = parser.parse(code, None).unwrap();
-                    let root = tree.root_node();
-
-                    match (lang.as_str(), target_lang.as_str()) {
-                        ("php", "rust") => {
-                            suggestions.push("Rewriting PHP to Rust".to_string());
-                            let rust_code = transform_php_to_rust(&root, code)?;
-                            optimized.push(AST::Fuse("rust".to_string(), rust_code));
-                        }
-                        ("javascript", "rust") => {
-                            suggestions.push("Rewriting JavaScript to Rust".to_string());
-                            let rust_code = transform_js_to_rust(&root, code)?;
-                            optimized.push(AST::Fuse("rust".to_string(), rust_code));
-                        }
-                        ("python", "rust") => {
-                            suggestions.push("Rewriting Python to Rust".to_string());
-                            let rust_code = transform_python_to_rust(&root, code)?;
-                            optimized.push(AST::Fuse("rust".to_string(), rust_code));
-                        }
-                        ("go", "rust") => {
-                            suggestions.push("Rewriting Go to Rust".to_string());
-                            let rust_code = transform_go_to_rust(&root, code)?;
-                            optimized.push(AST::Fuse("rust".to_string(), rust_code));
-                        }
-                        ("cpp", "rust") => {
-                            suggestions.push("Rewriting C++ to Rust".to_string());
-                            let rust_code = transform_cpp_to_rust(&root, code)?;
-                            optimized.push(AST::Fuse("rust".to_string(), rust_code));
-                        }
-                        ("php", "python") => {
-                            suggestions.push("Rewriting PHP to Python".to_string());
-                            let py_code = transform_php_to_python(&root, code)?;
-                            optimized.push(AST::Fuse("python".to_string(), py_code));
-                        }
-                        ("javascript", "python") => {
-                            suggestions.push("Rewriting JavaScript to Python".to_string());
-                            let py_code = transform_js_to_python(&root, code)?;
-                            optimized.push(AST::Fuse("python".to_string(), py_code));
-                        }
-                        ("go", "python") => {
-                            suggestions.push("Rewriting Go to Python".to_string());
-                            let py_code = transform_go_to_python(&root, code)?;
-                            optimized.push(AST::Fuse("python".to_string(), py_code));
-                        }
-                        ("cpp", "python") => {
-                            suggestions.push("Rewriting C++ to Python".to_string());
-                            let py_code = transform_cpp_to_python(&root, code)?;
-                            optimized.push(AST::Fuse("python".to_string(), py_code));
-                        }
-                        ("php", "javascript") => {
-                            suggestions.push("Rewriting PHP to JavaScript".to_string());
-                            let js_code = transform_php_to_js(&root, code)?;
-                            optimized.push(AST::Fuse("javascript".to_string(), js_code));
-                        }
-                        ("python", "javascript") => {
-                            suggestions.push("Rewriting Python to JavaScript".to_string());
-                            let js_code = transform_python_to_js(&root, code)?;
-                            optimized.push(AST::Fuse("javascript".to_string(), js_code));
-                        }
-                        ("go", "javascript") => {
-                            suggestions.push("Rewriting Go to JavaScript".to_string());
-                            let js_code = transform_go_to_js(&root, code)?;
-                            optimized.push(AST::Fuse("javascript".to_string(), js_code));
-                        }
-                        ("cpp", "javascript") => {
-                            suggestions.push("Rewriting C++ to JavaScript".to_string());
-                            let js_code = transform_cpp_to_js(&root, code)?;
-                            optimized.push(AST::Fuse("javascript".to_string(), js_code));
-                        }
-                        ("php", "java") => {
-                            suggestions.push("Rewriting PHP to Java".to_string());
-                            let java_code = transform_php_to_java(&root, code)?;
-                            optimized.push(AST::Fuse("java".to_string(), java_code));
-                        }
-                        ("javascript", "java") => {
-                            suggestions.push("Rewriting JavaScript to Java".to_string());
-                            let java_code = transform_js_to_java(&root, code)?;
-                            optimized.push(AST::Fuse("java".to_string(), java_code));
-                        }
-                        ("python", "java") => {
-                            suggestions.push("Rewriting Python to Java".to_string());
-                            let java_code = transform_python_to_java(&root, code)?;
-                            optimized.push(AST::Fuse("java".to_string(), java_code));
-                        }
-                        ("go", "java") => {
-                            suggestions.push("Rewriting Go to Java".to_string());
-                            let java_code = transform_go_to_java(&root, code)?;
-                            optimized.push(AST::Fuse("java".to_string(), java_code));
-                        }
-                        ("cpp", "java") => {
-                            suggestions.push("Rewriting C++ to Java".to_string());
-                            let java_code = transform_cpp_to_java(&root, code)?;
-                            optimized.push(AST::Fuse("java".to_string(), java_code));
-                        }
-                        _ => optimized.push(node.clone()),
-                    }
-                } else {
-                    optimized.push(node.clone());
-                }
-            }
-
-            for suggestion in suggestions {
-                println!("Minion suggestion: {}", suggestion);
-            }
-            env.rifts.insert(format!("optimized_{}", name), optimized);
-            Ok(())
-        }
-        _ => Err("Optimization requires a rift".to_string()),
-    }
-}
-
-fn transform_php_to_rust(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut rust_code = String::new();
-    rust_code.push_str("use std::fs;\nfn main() {\n");
-    if code.contains("uploadFile") {
-        rust_code.push_str("    let source_path = \"input.txt\";\n    let target_path = \"uploads/input.txt\";\n    if fs::metadata(source_path).is_ok() {\n        if fs::copy(source_path, target_path).is_ok() {\n            println!(\"Uploaded {} to {}\", source_path, target_path);\n        } else {\n            println!(\"Upload failed\");\n        }\n    } else {\n        println!(\"File not found: {}\", source_path);\n    }\n");
-    }
-    rust_code.push_str("}\n");
-    Ok(rust_code)
-}
-
-fn transform_js_to_rust(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut rust_code = String::new();
-    rust_code.push_str("use tokio::time::{sleep, Duration};\n#[tokio::main]\nasync fn main() {\n");
-    if code.contains("setTimeout") {
-        rust_code.push_str("    tokio::spawn(async move {\n        sleep(Duration::from_millis(100)).await;\n        tokio::spawn(async move {\n            sleep(Duration::from_millis(100)).await;\n            println!(\"Deep\");\n        });\n    });\n    sleep(Duration::from_millis(300)).await;\n");
-    }
-    rust_code.push_str("}\n");
-    Ok(rust_code)
-}
-
-fn transform_python_to_rust(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut rust_code = String::new();
-    rust_code.push_str("use tch::{Tensor, nn};\nuse tokio::time::{sleep, Duration};\n#[tokio::main]\nasync fn main() {\n");
-    if code.contains("asyncio") {
-        rust_code.push_str("    tokio::spawn(async move {\n        sleep(Duration::from_millis(100)).await;\n        println!(\"Async\");\n    });\n    sleep(Duration::from_millis(200)).await;\n");
-    }
-    if code.contains("tf.matmul") {
-        rust_code.push_str("    let matrix1 = Tensor::of_slice(&[1.0, 2.0, 3.0, 4.0]).view([2, 2]);\n    let matrix2 = Tensor::of_slice(&[5.0, 6.0, 7.0, 8.0]).view([2, 2]);\n    let product = matrix1.matmul(&matrix2);\n    println!(\"{:?}\", product);\n");
-    }
-    rust_code.push_str("}\n");
-    Ok(rust_code)
-}
-
-fn transform_go_to_rust(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut rust_code = String::new();
-    rust_code.push_str("fn main() {\n");
-    if code.contains("log.Println") {
-        rust_code.push_str("    println!(\"Kubernetes node started\");\n");
-    }
-    rust_code.push_str("}\n");
-    Ok(rust_code)
-}
-
-fn transform_cpp_to_rust(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut rust_code = String::new();
-    rust_code.push_str("#[derive(Debug)]\nstruct Vector3D { x: f64, y: f64, z: f64 }\nfn add_vectors(v1: Vector3D, v2: Vector3D) -> Vector3D {\n    Vector3D { x: v1.x + v2.x, y: v1.y + v2.y, z: v1.z + v2.z }\n}\nfn main() {\n");
-    if code.contains("addVectors") {
-        rust_code.push_str("    let v1 = Vector3D { x: 1.0, y: 2.0, z: 3.0 };\n    let v2 = Vector3D { x: 4.0, y: 5.0, z: 6.0 };\n    let result = add_vectors(v1, v2);\n    println!(\"Result: {}, {}, {}\", result.x, result.y, result.z);\n");
-    }
-    rust_code.push_str("}\n");
-    Ok(rust_code)
-}
-
-fn transform_php_to_python(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut py_code = String::new();
-    py_code.push_str("import os\n\ndef upload_file(source_path, target_path):\n    if os.path.exists(source_path):\n        os.makedirs(os.path.dirname(target_path), exist_ok=True)\n        with open(source_path, 'rb') as src, open(target_path, 'wb') as dst:\n            dst.write(src.read())\n        print(f\"Uploaded {source_path} to {target_path}\")\n    else:\n        print(f\"File not found: {source_path}\")\n\nif __name__ == \"__main__\":\n    upload_file(\"input.txt\", \"uploads/input.txt\")\n");
-    Ok(py_code)
-}
-
-fn transform_js_to_python(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut py_code = String::new();
-    py_code.push_str("import watchdog.events\nimport watchdog.observers\nclass Handler(watchdog.events.FileSystemEventHandler):\n    def on_any_event(self, event):\n        print(f\"{event.src_path} changed: {event.event_type}\")\n\nif __name__ == \"__main__\":\n    from time import sleep\n    observer = watchdog.observers.Observer()\n    observer.schedule(Handler(), path=\"input.txt\")\n    observer.start()\n    print(\"Watching input.txt...\")\n    sleep(2)\n    observer.stop()\n    observer.join()\n");
-    Ok(py_code)
-}
-
-fn transform_python_to_js(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut js_code = String::new();
-    js_code.push_str("const tf = require('@tensorflow/tfjs');\nasync function main() {\n    const matrix1 = tf.tensor2d([[1, 2], [3, 4]]);\n    const matrix2 = tf.tensor2d([[5, 6], [7, 8]]);\n    const product = matrix1.matMul(matrix2);\n    console.log(await product.array());\n}\nmain();\n");
-    Ok(js_code)
-}
-
-fn transform_go_to_js(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut js_code = String::new();
-    js_code.push_str("console.log(\"Kubernetes node started\");\n");
-    Ok(js_code)
-}
-
-fn transform_cpp_to_js(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut js_code = String::new();
-    js_code.push_str("class Vector3D {\n    constructor(x, y, z) {\n        this.x = x;\n        this.y = y;\n        this.z = z;\n    }\n}\nfunction addVectors(v1, v2) {\n    return new Vector3D(v1.x + v2.x, v1.y + v2.y, v1.z + v2.z);\n}\nconst v1 = new Vector3D(1, 2, 3);\nconst v2 = new Vector3D(4, 5, 6);\nconst result = addVectors(v1, v2);\nconsole.log(`Result: ${result.x}, ${result.y}, ${result.z}`);\n");
-    Ok(js_code)
-}
-
-fn transform_php_to_java(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut java_code = String::new();
-    java_code.push_str("import java.io.*; import java.nio.file.*;\npublic class FileUploader {\n    public static void main(String[] args) {\n        String sourcePath = \"input.txt\";\n        String targetPath = \"uploads/input.txt\";\n        File source = new File(sourcePath);\n        if (source.exists()) {\n            try {\n                Files.copy(source.toPath(), new File(targetPath).toPath(), StandardCopyOption.REPLACE_EXISTING);\n                System.out.println(\"Uploaded \" + sourcePath + \" to \" + targetPath);\n            } catch (IOException e) {\n                System.out.println(\"Upload failed\");\n            }\n        } else {\n            System.out.println(\"File not found: \" + sourcePath);\n        }\n    }\n}\n");
-    Ok(java_code)
-}
-
-fn transform_js_to_java(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut java_code = String::new();
-    java_code.push_str("import java.nio.file.*;\nimport java.util.concurrent.*;\npublic class FileWatcher {\n    public static void main(String[] args) throws Exception {\n        WatchService watcher = FileSystems.getDefault().newWatchService();\n        Path dir = Paths.get(\".\");\n        dir.register(watcher, StandardWatchEventKinds.ENTRY_MODIFY);\n        System.out.println(\"Watching input.txt...\");\n        ScheduledExecutorService executor = Executors.newSingleThreadScheduledExecutor();\n        executor.schedule(() -> System.exit(0), 2, TimeUnit.SECONDS);\n        while (true) {\n            WatchKey key = watcher.take();\n            for (WatchEvent<?> event : key.pollEvents()) {\n                System.out.println(\"input.txt changed: \" + event.kind());\n            }\n            key.reset();\n        }\n    }\n}\n");
-    Ok(java_code)
-}
-
-fn transform_python_to_java(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut java_code = String::new();
-    java_code.push_str("import org.tensorflow.*;\npublic class MatrixMath {\n    public static void main(String[] args) {\n        try (Graph g = new Graph(); Session s = new Session(g)) {\n            float[][] m1 = {{1, 2}, {3, 4}};\n            float[][] m2 = {{5, 6}, {7, 8}};\n            Tensor<?> t1 = Tensor.create(m1);\n            Tensor<?> t2 = Tensor.create(m2);\n            g.opBuilder(\"MatMul\", \"MatMul\").addInput(t1).addInput(t2).build();\n            Tensor<?> output = s.runner().fetch(\"MatMul\").run().get(0);\n            float[][] result = output.copyTo(new float[2][2]);\n            System.out.println(\"[[\" + result[0][0] + \", \" + result[0][1] + \"], [\" + result[1][0] + \", \" + result[1][1] + \"]]\");\n        }\n    }\n}\n");
-    Ok(java_code)
-}
-
-fn transform_go_to_java(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut java_code = String::new();
-    java_code.push_str("public class Logger {\n    public static void main(String[] args) {\n        System.out.println(\"Kubernetes node started\");\n    }\n}\n");
-    Ok(java_code)
-}
-
-fn transform_cpp_to_java(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
-    let mut java_code = String::new();
-    java_code.push_str("public class Vector3D {\n    double x, y, z;\n    Vector3D(double x, double y, double z) {\n        this.x = x;\n        this.y = y;\n        this.z = z;\n    }\n    static Vector3D addVectors(Vector3D v1, Vector3D v2) {\n        return new Vector3D(v1.x + v2.x, v1.y + v2.y, v1.z + v2.z);\n    }\n    public static void main(String[] args) {\n        Vector3D v1 = new Vector3D(1, 2, 3);\n        Vector3D v2 = new Vector3D(4, 5, 6);\n        Vector3D result = addVectors(v1, v2);\n        System.out.println(\"Result: \" + result.x + \", \" + result.y + \", \" + result.z);\n    }\n}\n");
-    Ok(java_code)
-}
-
-fn evaluate_expression(ast: &AST, env: &Environment) -> Result<AST, String> {
-    match ast {
-        AST::Number(n) => Ok(AST::Number(*n)),
-        AST::String(s) => Ok(AST::String(s.clone())),
-        AST::Identifier(id) => env.variables.get(id).cloned().ok_or(format!("Variable '{}' not found", id)),
-        _ => Err("Invalid expression".to_string()),
-    }
-}
-
-fn evaluate_condition(ast: &AST, env: &Environment) -> Result<bool, String> {
-    match ast {
-        AST::Number(n) => Ok(*n != 0),
-        _ => Err("Invalid condition".to_string()),
-    }
-}
-
-async fn compile_rift(env: &Environment) -> Result<String, String> {
-    let mut artifact = Vec::new();
-    for (_, body) in &env.rifts {
-        for node in body {
-            if let AST::Fuse(lang, code) = node {
-                let hash = format!("{:x}", Sha256::digest(code.as_bytes()));
-                if let Some(cached) = env.artifact_cache.get(&hash) {
-                    artifact.push(cached.clone());
-                } else {
-                    artifact.push(format!("{}: {}", lang, code));
-                }
-            }
-        }
-    }
-    Ok(artifact.join("\n"))
-}
\ No newline at end of file
+use crate::{parser::AST, parse};
+use crate::agent;
+use crate::config::Config;
+use crate::{builtins, deployer, executor, hooks, i18n, state};
+use crate::executor::ExecOptions;
+use crate::ci::CiMode;
+use crate::plugin::{HostFn, Plugins};
+use crate::stats::ExecStats;
+use crate::value::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use tokio::task;
+use futures::future::{self, BoxFuture, FutureExt};
+use sha2::{Sha256, Digest};
+use chrono;
+use tree_sitter::Parser;
+use notify::{Watcher, RecursiveMode, watcher};
+use std::sync::mpsc::channel;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone)]
+pub struct Environment {
+    pub variables: HashMap<String, Value>,
+    pub rifts: HashMap<String, Vec<AST>>,
+    pub tasks: HashMap<String, Vec<AST>>,
+    // Fuse outputs by fuse_hash: Output for text (result lines still in it),
+    // Artifact for binary output kept in the on-disk cache
+    pub artifact_cache: HashMap<String, Value>,
+    pub target_lang: Option<String>,
+    pub config: Config,
+    pub force_deploy: bool,
+    // `--mock-deploys`: deploys and contract calls go to `mock` instead
+    pub mock_deploys: bool,
+    // Set by `exit(n);`, which then unwinds the rest of the program as an error
+    pub exit_code: Option<i32>,
+    // Names of the rifts/tasks currently being called, innermost last
+    pub call_stack: Vec<String>,
+    // `fn` definitions: parameter names and body
+    pub functions: HashMap<String, (Vec<String>, Vec<AST>)>,
+    // Locals of each running `fn`, rift or task call, innermost last. Code in
+    // a call sees its own locals, then globals; never its caller's locals.
+    pub frames: Vec<HashMap<String, Value>>,
+    // Block scopes of rift, task, if, while, for, match and @when bodies,
+    // innermost last, each with the number of frames when it opened. Code
+    // sees the scopes opened in its own function (or at the top level),
+    // innermost first, then that function's locals, then globals.
+    pub scopes: Vec<(usize, HashMap<String, Value>)>,
+    // Variables passed into every fuse execution, see `export`
+    pub exports: BTreeSet<String>,
+    // Set by set_env(): handed to fuse programs and read back by env(),
+    // without touching rift's own environment, which other threads read
+    pub env_vars: BTreeMap<String, String>,
+    // Per-language execution counters for `status` and `stats`
+    pub stats: ExecStats,
+    // Cache entries kept by `clear` and refused by `cache rm`, see `cache pin`
+    pub pinned: BTreeSet<String>,
+    // When each cache entry was stored, for `@cache ttl`
+    pub cached_at: HashMap<String, SystemTime>,
+    // Set by `@cache ...`; a fuse block's own `cache ...` overrides it
+    pub cache_policy: CachePolicy,
+    // Set by --deterministic: what to do with blocks that use the network,
+    // the clock or randomness
+    pub deterministic: Option<Determinism>,
+    // REPL commands, builtins and deploy targets added by plugins
+    pub plugins: Plugins,
+    // Set by --ci, see `ci::CiMode`
+    pub ci: Option<CiMode>,
+    // Set by --changed-since: top-level calls to rifts and tasks outside
+    // this set are skipped
+    pub selected: Option<BTreeSet<String>>,
+    // Set by the REPL: a `param` with no value is prompted for rather than
+    // reported missing
+    pub interactive: bool,
+    // Files being run, outermost first: the script, then each `import`ed
+    // file still running. Imports resolve against the last one's directory.
+    pub importing: Vec<PathBuf>,
+}
+
+const MAX_CALL_DEPTH: usize = 256;
+
+pub const PARAM_TYPES: &[&str] = &["string", "int", "float", "bool"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Determinism {
+    Warn,
+    Deny,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CachePolicy {
+    On,
+    Off,
+    Ttl(Duration),
+}
+
+impl CachePolicy {
+    // The parser has already checked the text, see `parse_cache_policy`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        match spec {
+            "on" => Ok(CachePolicy::On),
+            "off" => Ok(CachePolicy::Off),
+            ttl => deployer::parse_duration(ttl).map(CachePolicy::Ttl),
+        }
+    }
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        let config = Config::load().unwrap_or_else(|e| {
+            eprintln!("{}", i18n::message("config.default", &[&e]));
+            Config::default()
+        });
+        Self {
+            variables: HashMap::new(),
+            rifts: HashMap::new(),
+            tasks: HashMap::new(),
+            artifact_cache: HashMap::new(),
+            target_lang: None,
+            config,
+            force_deploy: false,
+            mock_deploys: false,
+            exit_code: None,
+            call_stack: Vec::new(),
+            functions: HashMap::new(),
+            frames: Vec::new(),
+            scopes: Vec::new(),
+            exports: BTreeSet::new(),
+            env_vars: BTreeMap::new(),
+            stats: ExecStats::default(),
+            pinned: BTreeSet::new(),
+            cached_at: HashMap::new(),
+            cache_policy: CachePolicy::On,
+            deterministic: None,
+            plugins: Plugins::default(),
+            ci: None,
+            selected: None,
+            interactive: false,
+            importing: Vec::new(),
+        }
+    }
+
+    // For applications embedding rift: makes `name(args)` callable from
+    // scripts. Builtins of the same name take precedence, and the first
+    // registration of a name wins.
+    pub fn register_fn<F>(&mut self, name: &str, function: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, String> + Send + Sync + 'static,
+    {
+        self.plugins.register(std::sync::Arc::new(HostFn::new(name, function)));
+    }
+
+    // A variable as code at the current point sees it
+    pub fn get_var(&self, name: &str) -> Option<&Value> {
+        let depth = self.frames.len();
+        self.scopes.iter().rev().take_while(|(opened, _)| *opened == depth).find_map(|(_, vars)| vars.get(name))
+            .or_else(|| self.frames.last().and_then(|locals| locals.get(name)))
+            .or_else(|| self.variables.get(name))
+    }
+
+    // `let` binds in the innermost block scope, else in the function's
+    // locals, else globally
+    pub fn set_var(&mut self, name: String, value: Value) {
+        match self.scopes.last_mut() {
+            Some((opened, vars)) if *opened == self.frames.len() => vars.insert(name, value),
+            _ => match self.frames.last_mut() {
+                Some(locals) => locals.insert(name, value),
+                None => self.variables.insert(name, value),
+            },
+        };
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push((self.frames.len(), HashMap::new()));
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    // `x = ...` updates whichever binding get_var would read; false when
+    // there is none
+    pub fn assign_var(&mut self, name: &str, value: Value) -> bool {
+        let depth = self.frames.len();
+        let scoped = self.scopes.iter_mut().rev().take_while(|(opened, _)| *opened == depth).find_map(|(_, vars)| vars.get_mut(name));
+        let slot = match (scoped, self.frames.last_mut()) {
+            (Some(slot), _) => Some(slot),
+            (None, Some(locals)) if locals.contains_key(name) => locals.get_mut(name),
+            (None, _) => self.variables.get_mut(name),
+        };
+        match slot {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.variables.clear();
+        self.scopes.clear();
+        self.rifts.clear();
+        self.tasks.clear();
+        self.functions.clear();
+        self.artifact_cache.retain(|hash, _| self.pinned.contains(hash));
+        self.cached_at.retain(|hash, _| self.pinned.contains(hash));
+        self.cache_policy = CachePolicy::On;
+        self.target_lang = None;
+        self.exports.clear();
+    }
+}
+
+// A statement's value: a fuse block's output, a call's result, or the value of
+// the last statement in a body. Everything else yields null
+pub async fn interpret(ast: &AST, env: &mut Environment) -> Result<Value, String> {
+    match ast {
+        AST::Program(nodes) => run_statements(nodes, env).await,
+        AST::Rift(name, body) => {
+            env.rifts.insert(name.clone(), body.clone());
+            Ok(Value::Null)
+        }
+        AST::Fuse(lang, code) => run_fuse(lang, code, None, None, env).await,
+        AST::RemoteFuse(agent, lang, code) => run_fuse(lang, code, Some(agent), None, env).await,
+        AST::CachedFuse(policy, fuse) => run_cached_fuse(policy, fuse, env).await,
+        AST::GpuFuse(fuse) => run_gpu_fuse(fuse, None, env).await,
+        AST::Cache(policy) => {
+            env.cache_policy = CachePolicy::parse(policy)?;
+            Ok(Value::Null)
+        }
+        AST::InteractiveFuse(lang, code) => run_interactive_fuse(lang, code, env).await,
+        AST::Task(name, body) => {
+            env.tasks.insert(name.clone(), body.clone());
+            Ok(Value::Null)
+        }
+        AST::Function(name, params, body) => {
+            env.functions.insert(name.clone(), (params.clone(), body.clone()));
+            Ok(Value::Null)
+        }
+        AST::Target(lang) => {
+            env.target_lang = Some(lang.clone());
+            Ok(Value::Null)
+        }
+        AST::DeployValue(target, config) => {
+            let config = match evaluate_expression(config, env).await? {
+                Value::Map(entries) => entries.into_iter()
+                    .map(|(key, value)| {
+                        let text = match value {
+                            Value::Output { stdout, .. } => stdout.trim_end().to_string(),
+                            other => other.to_string(),
+                        };
+                        (key, text)
+                    })
+                    .collect(),
+                other => return Err(format!("@deploy \"{}\" needs a map for its config, got {} '{}'", target, other.type_name(), other)),
+            };
+            interpret(&AST::Deploy(target.clone(), config), env).await
+        }
+        AST::Deploy(target, config) => {
+            let config = &env.config.with_profile(config)?;
+            let marked;
+            let config = if env.mock_deploys {
+                marked = crate::mock::mark(config);
+                &marked
+            } else {
+                config
+            };
+            let artifact = compile_rift(env).await?;
+            let targets: Vec<String> = match env.plugins.deploy_target(target) {
+                Some(_) => vec![target.clone()],
+                None => deployer::select_targets(target).into_iter().map(str::to_string).collect(),
+            };
+            if targets.is_empty() {
+                return Err(format!("Unsupported target: {}", target));
+            }
+            crate::audit::check_deploy(env).await?;
+            // Each target's own package counts against max_size
+            let parts: Vec<_> = artifact_parts(env)?.into_iter().map(|(label, text)| (label, text.len() as u64)).collect();
+            for name in &targets {
+                let size = crate::package::packaged_size(&artifact, name, config)?;
+                deployer::check_size(config, size, &parts).map_err(|e| format!("{}: {}", name, e))?;
+            }
+            let force = env.force_deploy || config.get("force").map(|v| v == "true").unwrap_or(false);
+            let hash = state::artifact_hash(&artifact);
+            state::write_manifest(&build_manifest(env, &artifact)).map_err(|e| e.to_string())?;
+            // Unchanged means the same artifact deployed with the same settings
+            let recorded = state::recorded_config(config);
+            let mut changed = Vec::new();
+            for name in targets {
+                match state::latest_deployment(&name).map_err(|e| e.to_string())? {
+                    Some(last) if !force && last.artifact_hash == hash && last.config == recorded && crate::mock::is_mock(&last) == env.mock_deploys => {
+                        println!("{}", i18n::message("deploy.unchanged", &[&name, &last.id]));
+                    }
+                    _ => changed.push(name),
+                }
+            }
+            let targets = changed;
+            let results = future::join_all(
+                targets.iter().map(|t| deploy_to(t, &artifact, config, &*env))
+            ).await;
+            let mut failures = Vec::new();
+            for (name, result) in targets.iter().zip(results) {
+                let address = match result {
+                    Ok(address) => address,
+                    Err(e) => {
+                        hooks::fire(env.config.hooks.on_deploy_failure.as_deref(), serde_json::json!({
+                            "event": "deploy_failure",
+                            "target": name,
+                            "error": e.to_string(),
+                            "timestamp": chrono::Utc::now().timestamp(),
+                        })).await;
+                        failures.push(e);
+                        continue;
+                    }
+                };
+                let record = state::record_deployment(name, &address, &artifact, config).map_err(|e| e.to_string())?;
+                println!("{}", i18n::message("deploy.recorded", &[&record.id, &name]));
+                hooks::fire(env.config.hooks.on_deploy_success.as_deref(), serde_json::json!({
+                    "event": "deploy_success",
+                    "target": name,
+                    "deployment_id": record.id,
+                    "address": record.address,
+                    "artifact_hash": record.artifact_hash,
+                    "timestamp": record.timestamp,
+                })).await;
+                let default_var = if name == "ipfs" { Some("ipfs_cid".to_string()) } else { None };
+                if let Some(var) = config.get("into").cloned().or(default_var) {
+                    env.set_var(var, Value::Str(record.address.clone()));
+                }
+            }
+            if !failures.is_empty() {
+                return Err(format!("Deploy failed: {}", failures.join("; ")));
+            }
+            Ok(Value::Null)
+        }
+        AST::Invoke(target, config) => {
+            let result = if env.mock_deploys {
+                crate::mock::invoke(target, config)?
+            } else {
+                deployer::invoke(target, &crate::secrets::resolve(config)?).await.map_err(|e| e.to_string())?
+            };
+            println!("{}", i18n::message("invoke.returned", &[&target, &result]));
+            if let Some(var) = config.get("into") {
+                env.set_var(var.clone(), Value::Str(result.clone()));
+            }
+            Ok(Value::Str(result))
+        }
+        AST::Export(names) => {
+            for name in names {
+                if !executor::is_exportable_name(name) {
+                    return Err(format!("Cannot export '{}': only plain identifiers can be exported", name));
+                }
+                env.exports.insert(name.clone());
+            }
+            Ok(Value::Null)
+        }
+        AST::Import(name) => {
+            for (path, source) in crate::registry::sources(name).map_err(|e| e.to_string())? {
+                let tokens = crate::lexer::tokenize(&source).map_err(|e| format!("{}: {}", path.display(), e))?;
+                let ast = parse(&tokens).map_err(|e| format!("{}: {}", path.display(), e))?;
+                interpret(&ast, env).await?;
+            }
+            Ok(Value::Null)
+        }
+        AST::ImportFile(file) => {
+            let path = import_path(&env.importing, file);
+            if let Some(at) = env.importing.iter().position(|running| same_file(running, &path)) {
+                let chain: Vec<String> = env.importing[at..].iter().chain([&path]).map(|p| p.display().to_string()).collect();
+                return Err(format!("Import cycle: {}", chain.join(" -> ")));
+            }
+            let source = crate::git::read_source(&path, None).map_err(|e| format!("import {}: {}", path.display(), e))?;
+            let tokens = crate::lexer::tokenize(&source).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let ast = parse(&tokens).map_err(|e| format!("{}: {}", path.display(), e))?;
+            env.importing.push(path);
+            let result = interpret(&ast, env).await;
+            env.importing.pop();
+            result.map(|_| Value::Null)
+        }
+        AST::Let(name, value) => {
+            let value = bound_value(value, env).await?;
+            env.set_var(name.clone(), value);
+            Ok(Value::Null)
+        }
+        AST::Global(name, value) => {
+            let value = bound_value(value, env).await?;
+            env.variables.insert(name.clone(), value);
+            Ok(Value::Null)
+        }
+        AST::Assign(name, value) => {
+            if env.get_var(name).is_none() {
+                return Err(format!("Cannot assign to '{}' before it is declared; use `let {} = ...;`", name, name));
+            }
+            let value = bound_value(value, env).await?;
+            env.assign_var(name, value);
+            Ok(Value::Null)
+        }
+        AST::Param(name, ty, default) => {
+            let value = param_value(name, ty, default.as_deref(), env).await?;
+            env.set_var(name.clone(), value);
+            Ok(Value::Null)
+        }
+        AST::Call(name, args) => {
+            if name == "optimize" {
+                let ast_to_optimize = args.first().ok_or("Missing code to optimize")?;
+                optimize_code(ast_to_optimize, env).await?;
+                Ok(Value::Null)
+            } else if name == "exit" {
+                let code = match args.first() {
+                    Some(arg) => evaluate_expression(arg, env).await?,
+                    None => Value::Int(0),
+                };
+                let code = code.as_int()
+                    .and_then(|n| i32::try_from(n).ok())
+                    .ok_or_else(|| format!("exit() expects an integer status, got {}", code))?;
+                env.exit_code = Some(code);
+                Err(format!("exit({}) called", code))
+            } else if builtins::is_builtin(name) || env.plugins.builtin(name).is_some() {
+                evaluate_expression(ast, env).await
+            } else if env.call_stack.is_empty()
+                && env.selected.as_ref().is_some_and(|selected| !selected.contains(name))
+                && (env.rifts.contains_key(name) || env.tasks.contains_key(name))
+            {
+                println!("{}", i18n::message("run.skipping_unaffected", &[&name]));
+                Ok(Value::Null)
+            } else if let Some(body) = env.rifts.get(name).or_else(|| env.tasks.get(name)).cloned() {
+                if env.call_stack.is_empty() {
+                    crate::meta::preflight(name, env)?;
+                }
+                // `call name with a, b;` exposes the values as `args` in the
+                // call's own frame, which hides the caller's locals
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(evaluate_expression(arg, env).await?);
+                }
+                env.frames.push(HashMap::new());
+                env.set_var("args".to_string(), Value::List(values));
+                env.call_stack.push(name.clone());
+                let result = run_block(&body, env).await;
+                env.call_stack.pop();
+                env.frames.pop();
+                result
+            } else if let Some((params, body)) = env.functions.get(name).cloned() {
+                if args.len() != params.len() {
+                    return Err(format!("fn {} takes {} argument(s), called with {}", name, params.len(), args.len()));
+                }
+                if env.frames.len() >= MAX_CALL_DEPTH {
+                    return Err(format!("fn {} recursed more than {} calls deep", name, MAX_CALL_DEPTH));
+                }
+                if env.call_stack.is_empty() {
+                    crate::meta::preflight(name, env)?;
+                }
+                let mut locals = HashMap::with_capacity(params.len());
+                for (param, arg) in params.into_iter().zip(args) {
+                    locals.insert(param, evaluate_expression(arg, env).await?);
+                }
+                env.frames.push(locals);
+                env.call_stack.push(name.clone());
+                let result = interpret(&AST::Program(body), env).await;
+                env.call_stack.pop();
+                env.frames.pop();
+                result
+            } else {
+                Err(format!("Unknown call target: {}", name))
+            }
+        }
+        AST::If(condition, then_body, else_body) => {
+            let body = if evaluate_condition(condition, env).await? { then_body } else { else_body };
+            run_block(body, env).await
+        }
+        // Its needs were checked by the preflight of the outermost call
+        AST::Meta(_) => Ok(Value::Null),
+        AST::Match(subject, arms) => {
+            let value = evaluate_expression(subject, env).await?;
+            for (patterns, body) in arms {
+                let mut matched = patterns.is_empty();
+                for pattern in patterns {
+                    let pattern = evaluate_expression(pattern, env).await?;
+                    if crate::value::compare("==", &value, &pattern)?.is_truthy() {
+                        matched = true;
+                        break;
+                    }
+                }
+                if matched {
+                    return run_block(body, env).await;
+                }
+            }
+            Ok(Value::Null)
+        }
+        AST::When(guard, then_body, else_body) => {
+            let body = if crate::host::matches(guard)? { then_body } else { else_body };
+            run_block(body, env).await
+        }
+        AST::While(condition, body) => {
+            let mut iterations = 0;
+            while evaluate_condition(condition, env).await? {
+                run_block(body, env).await?;
+                iterations += 1;
+                if iterations > 10000 { return Err("Max iterations exceeded".to_string()); }
+            }
+            Ok(Value::Null)
+        }
+        AST::For(name, iterable, body) => {
+            let items: Box<dyn Iterator<Item = Value> + Send> = match iterable.as_ref() {
+                AST::Range(start, end) => {
+                    let bound = |value: Value| value.as_int().ok_or_else(|| format!("for ranges need integer bounds, got {} '{}'", value.type_name(), value));
+                    let start = bound(evaluate_expression(start, env).await?)?;
+                    let end = bound(evaluate_expression(end, env).await?)?;
+                    Box::new((start..end).map(Value::Int))
+                }
+                other => match evaluate_expression(other, env).await? {
+                    Value::List(items) => Box::new(items.into_iter()),
+                    Value::Map(entries) => Box::new(entries.into_keys().map(Value::Str)),
+                    value => match value.as_str() {
+                        Some(text) => Box::new(text.lines().map(Value::from).collect::<Vec<_>>().into_iter()),
+                        None => return Err(format!("cannot loop over {} '{}'", value.type_name(), value)),
+                    },
+                },
+            };
+            for item in items {
+                env.push_scope();
+                env.set_var(name.clone(), item);
+                let result = run_statements(body, env).await;
+                env.pop_scope();
+                result?;
+            }
+            Ok(Value::Null)
+        }
+        _ => Err("Unsupported operation".to_string()),
+    }
+}
+
+// The right-hand side of `let` or an assignment. `let x = @fuse ...;` runs
+// the block, which needs the mutable cache
+async fn bound_value(value: &AST, env: &mut Environment) -> Result<Value, String> {
+    match value {
+        AST::Fuse(lang, code) => run_fuse(lang, code, None, None, env).await,
+        AST::RemoteFuse(agent, lang, code) => run_fuse(lang, code, Some(agent), None, env).await,
+        AST::CachedFuse(policy, fuse) => run_cached_fuse(policy, fuse, env).await,
+        AST::GpuFuse(fuse) => run_gpu_fuse(fuse, None, env).await,
+        AST::InteractiveFuse(lang, code) => run_interactive_fuse(lang, code, env).await,
+        other => evaluate_expression(other, env).await,
+    }
+}
+
+// Tasks from src/stdlib.rift, defined before any user code runs
+pub const STDLIB: &str = include_str!("stdlib.rift");
+
+// Runs a body in a block scope of its own, so its `let`s end with it
+async fn run_block(body: &[AST], env: &mut Environment) -> Result<Value, String> {
+    env.push_scope();
+    let result = run_statements(body, env).await;
+    env.pop_scope();
+    result
+}
+
+// The value of the last statement, as for a whole program
+async fn run_statements(body: &[AST], env: &mut Environment) -> Result<Value, String> {
+    let mut last = Value::Null;
+    for node in body {
+        last = interpret(node, env).await?;
+    }
+    Ok(last)
+}
+
+pub async fn load_stdlib(env: &mut Environment) -> Result<(), String> {
+    let tokens = crate::lexer::tokenize(STDLIB).map_err(|e| format!("stdlib: {}", e))?;
+    let ast = parse(&tokens).map_err(|e| format!("stdlib: {}", e))?;
+    interpret(&ast, env).await.map(|_| ())
+}
+
+// A fuse block's value is whatever it returned through the result convention
+// (see executor::RESULT_MARKER), otherwise its output. With an agent the
+// build and run happen on that machine; the cache is shared either way.
+async fn run_fuse(lang: &str, code: &str, agent: Option<&str>, cache: Option<&CachePolicy>, env: &mut Environment) -> Result<Value, String> {
+    let code = &interpolate(code, env).await?;
+    if let Some(mode) = env.deterministic {
+        let findings = executor::nondeterminism(lang, code).map_err(|e| e.to_string())?;
+        if !findings.is_empty() {
+            let message = format!("{} block is not deterministic: {}", lang, findings.join(", "));
+            match mode {
+                Determinism::Deny => return Err(format!("{} (rejected by --deterministic)", message)),
+                Determinism::Warn => eprintln!("{}", i18n::message("warning", &[&message])),
+            }
+        }
+    }
+    let hash = fuse_hash(code, env);
+    let policy = cache.unwrap_or(&env.cache_policy).clone();
+    let fresh = match &policy {
+        CachePolicy::On => true,
+        CachePolicy::Off => false,
+        CachePolicy::Ttl(ttl) => env.cached_at.get(&hash)
+            .and_then(|stored| stored.elapsed().ok())
+            .is_some_and(|age| age < *ttl),
+    };
+    if let Some(cached) = env.artifact_cache.get(&hash).filter(|_| fresh) {
+        let (text, value) = fuse_value(cached).map_err(|e| format!("{}: {}", lang, e))?;
+        println!("{}", i18n::message("fuse.cached", &[&text]));
+        env.stats.record_hit(lang);
+        return Ok(value);
+    }
+
+    let mut options = exec_options(env);
+    options.source_label = match env.call_stack.last() {
+        Some(name) => format!("{}/@fuse \"{}\"", name, lang),
+        None => format!("@fuse \"{}\"", lang),
+    };
+    let streamed = options.stream;
+    let started = std::time::Instant::now();
+    let output = match agent {
+        Some(agent) => agent::execute_remote(agent, &env.config, lang, code, &options)
+            .await
+            .map_err(|e| e.to_string()),
+        None => {
+            let (lang_owned, code_owned) = (lang.to_string(), code.to_string());
+            task::spawn_blocking(move || executor::execute(&lang_owned, &code_owned, &options))
+                .await
+                .map_err(|e| format!("Execution task failed: {}", e))
+                .and_then(|result| result.map_err(|e| e.to_string()))
+        }
+    };
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => return Err(env.stats.record_failure(lang, started.elapsed(), e)),
+    };
+    if streamed && agent.is_some() {
+        print!("{}", String::from_utf8_lossy(&output.stdout));
+    }
+    if !output.success() {
+        let full_log = output.stderr_file.as_ref()
+            .map(|path| format!("\n(stderr truncated, full log in {})", path.display()))
+            .unwrap_or_default();
+        let error = format!("{} exited with {:?}: {}{}", lang, output.exit_code, String::from_utf8_lossy(&output.stderr), full_log);
+        return Err(env.stats.record_failure(lang, started.elapsed(), error));
+    }
+    env.stats.record_run(lang, started.elapsed(), output.deps_installed);
+    // Only the preview of a spilled output is in memory, so it is not cached
+    if let Some(path) = &output.stdout_file {
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let (text, result) = executor::split_result(&stdout).map_err(|e| format!("{}: {}", lang, e))?;
+        if !streamed && !executor::is_binary(&output.stdout) {
+            println!("{} output (first {} of {} bytes):\n{}", lang, text.len(), output.stdout_len, text);
+        }
+        let mut hasher = Sha256::new();
+        std::fs::File::open(path)
+            .and_then(|mut file| std::io::copy(&mut file, &mut hasher))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let blob = format!("{:x}", hasher.finalize());
+        // Into the content-addressed cache, so rifts with the same output share one copy
+        let path = state::adopt_blob(path, &blob).map_err(|e| e.to_string())?;
+        println!("{}", i18n::message("fuse.full_output", &[&path.display()]));
+        if let Some(result) = result {
+            return Ok(result);
+        }
+        let cached = Value::Artifact { path, hash: blob, size: output.stdout_len };
+        env.cached_at.insert(hash.clone(), SystemTime::now());
+        env.artifact_cache.insert(hash, cached.clone());
+        return Ok(cached);
+    }
+
+    // Binary output is kept byte-for-byte on disk rather than forced into a string
+    let cached = if executor::is_binary(&output.stdout) {
+        let (path, hash) = state::cache_blob(&output.stdout).map_err(|e| e.to_string())?;
+        Value::Artifact { path, hash, size: output.stdout.len() as u64 }
+    } else {
+        Value::Output {
+            lang: lang.to_string(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            exit_code: output.exit_code,
+        }
+    };
+    let (text, value) = fuse_value(&cached).map_err(|e| format!("{}: {}", lang, e))?;
+    // Stored even with caching off, which only stops reuse, so deploys
+    // package the latest output
+    env.cached_at.insert(hash.clone(), SystemTime::now());
+    env.artifact_cache.insert(hash, cached);
+    if !streamed {
+        println!("{} output: {}", lang, text);
+    }
+    Ok(value)
+}
+
+async fn run_cached_fuse(policy: &str, fuse: &AST, env: &mut Environment) -> Result<Value, String> {
+    let policy = CachePolicy::parse(policy)?;
+    match fuse {
+        AST::Fuse(lang, code) => run_fuse(lang, code, None, Some(&policy), env).await,
+        AST::RemoteFuse(agent, lang, code) => run_fuse(lang, code, Some(agent), Some(&policy), env).await,
+        AST::GpuFuse(fuse) => run_gpu_fuse(fuse, Some(&policy), env).await,
+        _ => Err("cache policies apply only to @fuse blocks".to_string()),
+    }
+}
+
+// A `requires gpu;` block runs here when this machine has a GPU, on its own
+// agent only when that one has one, and otherwise on the first agent in
+// rift.toml that reports one. Without any it fails before the block starts
+// installing packages it can't use.
+async fn run_gpu_fuse(fuse: &AST, policy: Option<&CachePolicy>, env: &mut Environment) -> Result<Value, String> {
+    match fuse {
+        AST::RemoteFuse(agent, lang, code) => match gpu_agent(agent, &env.config).await {
+            Ok(_) => run_fuse(lang, code, Some(agent), policy, env).await,
+            Err(problem) => Err(format!("@fuse \"{}\" requires a GPU, but {}", lang, problem)),
+        },
+        AST::Fuse(lang, code) if crate::host::gpu_kind().is_some() => run_fuse(lang, code, None, policy, env).await,
+        AST::InteractiveFuse(lang, code) if crate::host::gpu_kind().is_some() => run_interactive_fuse(lang, code, env).await,
+        AST::Fuse(lang, code) => {
+            let mut agents: Vec<&String> = env.config.agents.keys().collect();
+            agents.sort();
+            let mut problems = vec!["this machine has neither CUDA nor Metal".to_string()];
+            for agent in agents {
+                match gpu_agent(agent, &env.config).await {
+                    Ok(kind) => {
+                        println!("{}", i18n::message("fuse.gpu_on_agent", &[&lang, &agent, &kind]));
+                        let agent = agent.clone();
+                        return run_fuse(lang, code, Some(&agent), policy, env).await;
+                    }
+                    Err(problem) => problems.push(problem),
+                }
+            }
+            Err(format!("@fuse \"{}\" requires a GPU, but {}", lang, problems.join("; ")))
+        }
+        AST::InteractiveFuse(lang, _) => Err(format!("@fuse interactive \"{}\" requires a GPU, but this machine has neither CUDA nor Metal", lang)),
+        _ => Err("requires gpu applies only to @fuse blocks".to_string()),
+    }
+}
+
+// The GPU kind of an agent, asked once per run
+async fn gpu_agent(agent: &str, config: &Config) -> Result<String, String> {
+    static KNOWN: std::sync::OnceLock<std::sync::Mutex<HashMap<String, Result<String, String>>>> = std::sync::OnceLock::new();
+    let known = KNOWN.get_or_init(Default::default);
+    if let Some(answer) = known.lock().unwrap().get(agent) {
+        return answer.clone();
+    }
+    let answer = match agent::facts(agent, config).await {
+        Ok(facts) => facts["gpu"].as_str().map(str::to_string).ok_or_else(|| format!("agent {} has no GPU", agent)),
+        Err(e) => Err(format!("agent {} did not say whether it has a GPU ({})", agent, e)),
+    };
+    known.lock().unwrap().insert(agent.to_string(), answer.clone());
+    answer
+}
+
+// Never cached: what the program does depends on what the user types. The
+// REPL is effectively suspended since this awaits the child's exit.
+async fn run_interactive_fuse(lang: &str, code: &str, env: &mut Environment) -> Result<Value, String> {
+    let code = &interpolate(code, env).await?;
+    let mut options = exec_options(env);
+    options.source_label = format!("@fuse interactive \"{}\"", lang);
+    let (lang_owned, code_owned) = (lang.to_string(), code.to_string());
+    let started = std::time::Instant::now();
+    let exit_code = task::spawn_blocking(move || executor::execute_interactive(&lang_owned, &code_owned, &options))
+        .await
+        .map_err(|e| format!("Execution task failed: {}", e))?
+        .map_err(|e| env.stats.record_failure(lang, started.elapsed(), e.to_string()))?;
+    if exit_code != Some(0) {
+        return Err(env.stats.record_failure(lang, started.elapsed(), format!("{} exited with {:?}", lang, exit_code)));
+    }
+    env.stats.record_run(lang, started.elapsed(), 0);
+    Ok(Value::Output { lang: lang.to_string(), stdout: String::new(), exit_code })
+}
+
+// `import "file"` names a file relative to the file importing it; outside
+// any file, as in the REPL, relative to the working directory
+pub fn import_path(importing: &[PathBuf], file: &str) -> PathBuf {
+    match importing.last().and_then(|running| running.parent()) {
+        Some(dir) if Path::new(file).is_relative() => dir.join(file),
+        _ => PathBuf::from(file),
+    }
+}
+
+// Also when the paths are spelled differently, like "lib/../a.rift" and "a.rift"
+pub(crate) fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// The text to show for a cached fuse output and the value the block yields
+fn fuse_value(cached: &Value) -> Result<(String, Value), String> {
+    match cached {
+        Value::Output { lang, stdout, exit_code } => {
+            let (text, result) = executor::split_result(stdout)?;
+            let value = result.unwrap_or_else(|| Value::Output {
+                lang: lang.clone(),
+                stdout: text.clone(),
+                exit_code: *exit_code,
+            });
+            Ok((text, value))
+        }
+        other => Ok((other.to_string(), other.clone())),
+    }
+}
+
+// Exported values are visible to the code, so they are part of its identity
+fn fuse_hash(code: &str, env: &Environment) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(code.as_bytes());
+    for (name, value) in exec_options(env).exports {
+        hasher.update(format!("\0{}={}", name, value.to_json()).as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+// Exports whose variable is not (or no longer) set are skipped
+fn exec_options(env: &Environment) -> ExecOptions {
+    ExecOptions {
+        timeout: Duration::from_secs(env.config.executor.timeout_secs),
+        stream: env.config.executor.stream_output,
+        max_output: env.config.executor.max_output_bytes,
+        exports: env.exports.iter()
+            .filter_map(|name| Some((name.clone(), env.get_var(name)?.clone())))
+            .collect(),
+        env: env.env_vars.clone().into_iter().collect(),
+        provider: env.config.executor.provider,
+        rift: env.call_stack.last().cloned(),
+        ..ExecOptions::default()
+    }
+}
+
+// Redeploys the exact artifact of a recorded deployment. `to` names either a
+// profile from rift.toml (whose settings overlay the recorded config and may
+// pick another target) or a deploy target.
+pub async fn promote(id: &str, to: &str, env: &mut Environment) -> Result<(), String> {
+    let record = state::find_deployment(id).map_err(|e| e.to_string())?;
+    let artifact = state::load_artifact(&record).map_err(|e| e.to_string())?;
+
+    let mut config = record.config.clone();
+    let target = match env.config.profiles.get(to) {
+        Some(profile) => {
+            config.extend(profile.clone());
+            profile.get("target").cloned().unwrap_or_else(|| record.target.clone())
+        }
+        None if deployer::is_target(to) || env.plugins.deploy_target(to).is_some() => to.to_string(),
+        None => return Err(format!("Unknown profile or target: {}", to)),
+    };
+    if env.mock_deploys {
+        config.insert(crate::mock::MARKER.to_string(), "true".to_string());
+    } else {
+        config.remove(crate::mock::MARKER);
+    }
+
+    println!("{}", i18n::message("deploy.promoting", &[&record.id, &record.target, &&record.artifact_hash[..12], &to]));
+    let address = deploy_to(&target, &artifact, &config, env).await?;
+    let promoted = state::record_deployment(&target, &address, &artifact, &config).map_err(|e| e.to_string())?;
+    println!("{}", i18n::message("deploy.recorded", &[&promoted.id, &target]));
+    Ok(())
+}
+
+// Plugin deploy targets first, then the built-in ones
+async fn deploy_to(target: &str, artifact: &str, config: &HashMap<String, String>, env: &Environment) -> Result<String, String> {
+    if env.mock_deploys {
+        return crate::mock::deploy(target, artifact, config);
+    }
+    let mut config = crate::secrets::resolve(config)?;
+    // For the gas estimate unless bytecode_file is given, and for verification
+    let needs_source = !config.contains_key("bytecode_file") || config.contains_key("etherscan_api_key");
+    if target == "ethereum" && needs_source && !config.contains_key("source_file") {
+        if let Some(source) = solidity_block(env)? {
+            config.insert(deployer::SOLIDITY_SOURCE.to_string(), source);
+        }
+    }
+    let config = &config;
+    let _slot = deployer::acquire_slot(target, env.config.concurrency.get(target).copied()).await;
+    match env.plugins.deploy_target(target) {
+        Some(plugin) => plugin.deploy(target, artifact, config),
+        None => deployer::deploy(target, artifact, config).await.map_err(|e| e.to_string()),
+    }
+}
+
+async fn optimize_code(ast: &AST, env: &mut Environment) -> Result<(), String> {
+    match ast {
+        AST::Rift(name, body) => {
+            let mut optimized = Vec::new();
+            let mut suggestions = Vec::new();
+            let target_lang = env.target_lang.clone().unwrap_or("rust".to_string());
+
+            // Rewritten code is escaped so it is never interpolated
+            for node in body {
+                if let AST::Fuse(lang, code) = node {
+                    if let Some(plugin) = env.plugins.transform(lang, &target_lang) {
+                        suggestions.push(format!("Rewriting {} to {} with plugin {}", lang, target_lang, plugin.name()));
+                        let rewritten = plugin.transform(lang, &target_lang, code, env)?;
+                        optimized.push(AST::Fuse(target_lang.clone(), crate::interpolate::escape(&rewritten)));
+                        continue;
+                    }
+                    let mut parser = Parser::new();
+                    let lang_obj = match executor::grammar(lang) {
+                        Some(grammar) => grammar,
+                        None => continue,
+                    };
+                    parser.set_language(lang_obj).unwrap();
+                    let tree = parser.parse(code, None).unwrap();
+                    let root = tree.root_node();
+
+                    match (lang.as_str(), target_lang.as_str()) {
+                        ("php", "rust") => {
+                            suggestions.push("Rewriting PHP to Rust".to_string());
+                            let rust_code = transform_php_to_rust(&root, code)?;
+                            optimized.push(AST::Fuse("rust".to_string(), crate::interpolate::escape(&rust_code)));
+                        }
+                        ("javascript", "rust") => {
+                            suggestions.push("Rewriting JavaScript to Rust".to_string());
+                            let rust_code = transform_js_to_rust(&root, code)?;
+                            optimized.push(AST::Fuse("rust".to_string(), crate::interpolate::escape(&rust_code)));
+                        }
+                        ("python", "rust") => {
+                            suggestions.push("Rewriting Python to Rust".to_string());
+                            let rust_code = transform_python_to_rust(&root, code)?;
+                            optimized.push(AST::Fuse("rust".to_string(), crate::interpolate::escape(&rust_code)));
+                        }
+                        ("go", "rust") => {
+                            suggestions.push("Rewriting Go to Rust".to_string());
+                            let rust_code = transform_go_to_rust(&root, code)?;
+                            optimized.push(AST::Fuse("rust".to_string(), crate::interpolate::escape(&rust_code)));
+                        }
+                        ("cpp", "rust") => {
+                            suggestions.push("Rewriting C++ to Rust".to_string());
+                            let rust_code = transform_cpp_to_rust(&root, code)?;
+                            optimized.push(AST::Fuse("rust".to_string(), crate::interpolate::escape(&rust_code)));
+                        }
+                        ("php", "python") => {
+                            suggestions.push("Rewriting PHP to Python".to_string());
+                            let py_code = transform_php_to_python(&root, code)?;
+                            optimized.push(AST::Fuse("python".to_string(), crate::interpolate::escape(&py_code)));
+                        }
+                        ("javascript", "python") => {
+                            suggestions.push("Rewriting JavaScript to Python".to_string());
+                            let py_code = transform_js_to_python(&root, code)?;
+                            optimized.push(AST::Fuse("python".to_string(), crate::interpolate::escape(&py_code)));
+                        }
+                        ("go", "python") => {
+                            suggestions.push("Rewriting Go to Python".to_string());
+                            let py_code = transform_go_to_python(&root, code)?;
+                            optimized.push(AST::Fuse("python".to_string(), crate::interpolate::escape(&py_code)));
+                        }
+                        ("cpp", "python") => {
+                            suggestions.push("Rewriting C++ to Python".to_string());
+                            let py_code = transform_cpp_to_python(&root, code)?;
+                            optimized.push(AST::Fuse("python".to_string(), crate::interpolate::escape(&py_code)));
+                        }
+                        ("php", "javascript") => {
+                            suggestions.push("Rewriting PHP to JavaScript".to_string());
+                            let js_code = transform_php_to_js(&root, code)?;
+                            optimized.push(AST::Fuse("javascript".to_string(), crate::interpolate::escape(&js_code)));
+                        }
+                        ("python", "javascript") => {
+                            suggestions.push("Rewriting Python to JavaScript".to_string());
+                            let js_code = transform_python_to_js(&root, code)?;
+                            optimized.push(AST::Fuse("javascript".to_string(), crate::interpolate::escape(&js_code)));
+                        }
+                        ("go", "javascript") => {
+                            suggestions.push("Rewriting Go to JavaScript".to_string());
+                            let js_code = transform_go_to_js(&root, code)?;
+                            optimized.push(AST::Fuse("javascript".to_string(), crate::interpolate::escape(&js_code)));
+                        }
+                        ("cpp", "javascript") => {
+                            suggestions.push("Rewriting C++ to JavaScript".to_string());
+                            let js_code = transform_cpp_to_js(&root, code)?;
+                            optimized.push(AST::Fuse("javascript".to_string(), crate::interpolate::escape(&js_code)));
+                        }
+                        ("php", "java") => {
+                            suggestions.push("Rewriting PHP to Java".to_string());
+                            let java_code = transform_php_to_java(&root, code)?;
+                            optimized.push(AST::Fuse("java".to_string(), crate::interpolate::escape(&java_code)));
+                        }
+                        ("javascript", "java") => {
+                            suggestions.push("Rewriting JavaScript to Java".to_string());
+                            let java_code = transform_js_to_java(&root, code)?;
+                            optimized.push(AST::Fuse("java".to_string(), crate::interpolate::escape(&java_code)));
+                        }
+                        ("python", "java") => {
+                            suggestions.push("Rewriting Python to Java".to_string());
+                            let java_code = transform_python_to_java(&root, code)?;
+                            optimized.push(AST::Fuse("java".to_string(), crate::interpolate::escape(&java_code)));
+                        }
+                        ("go", "java") => {
+                            suggestions.push("Rewriting Go to Java".to_string());
+                            let java_code = transform_go_to_java(&root, code)?;
+                            optimized.push(AST::Fuse("java".to_string(), crate::interpolate::escape(&java_code)));
+                        }
+                        ("cpp", "java") => {
+                            suggestions.push("Rewriting C++ to Java".to_string());
+                            let java_code = transform_cpp_to_java(&root, code)?;
+                            optimized.push(AST::Fuse("java".to_string(), crate::interpolate::escape(&java_code)));
+                        }
+                        _ => optimized.push(node.clone()),
+                    }
+                } else {
+                    optimized.push(node.clone());
+                }
+            }
+
+            for suggestion in suggestions {
+                println!("{}", i18n::message("minion.suggestion", &[&suggestion]));
+            }
+            env.rifts.insert(format!("optimized_{}", name), optimized);
+            Ok(())
+        }
+        _ => Err("Optimization requires a rift".to_string()),
+    }
+}
+
+fn transform_php_to_rust(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut rust_code = String::new();
+    rust_code.push_str("use std::fs;\nfn main() {\n");
+    if code.contains("uploadFile") {
+        rust_code.push_str("    let source_path = \"input.txt\";\n    let target_path = \"uploads/input.txt\";\n    if fs::metadata(source_path).is_ok() {\n        if fs::copy(source_path, target_path).is_ok() {\n            println!(\"Uploaded {} to {}\", source_path, target_path);\n        } else {\n            println!(\"Upload failed\");\n        }\n    } else {\n        println!(\"File not found: {}\", source_path);\n    }\n");
+    }
+    rust_code.push_str("}\n");
+    Ok(rust_code)
+}
+
+fn transform_js_to_rust(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut rust_code = String::new();
+    rust_code.push_str("use tokio::time::{sleep, Duration};\n#[tokio::main]\nasync fn main() {\n");
+    if code.contains("setTimeout") {
+        rust_code.push_str("    tokio::spawn(async move {\n        sleep(Duration::from_millis(100)).await;\n        tokio::spawn(async move {\n            sleep(Duration::from_millis(100)).await;\n            println!(\"Deep\");\n        });\n    });\n    sleep(Duration::from_millis(300)).await;\n");
+    }
+    rust_code.push_str("}\n");
+    Ok(rust_code)
+}
+
+fn transform_python_to_rust(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut rust_code = String::new();
+    rust_code.push_str("use tch::{Tensor, nn};\nuse tokio::time::{sleep, Duration};\n#[tokio::main]\nasync fn main() {\n");
+    if code.contains("asyncio") {
+        rust_code.push_str("    tokio::spawn(async move {\n        sleep(Duration::from_millis(100)).await;\n        println!(\"Async\");\n    });\n    sleep(Duration::from_millis(200)).await;\n");
+    }
+    if code.contains("tf.matmul") {
+        rust_code.push_str("    let matrix1 = Tensor::of_slice(&[1.0, 2.0, 3.0, 4.0]).view([2, 2]);\n    let matrix2 = Tensor::of_slice(&[5.0, 6.0, 7.0, 8.0]).view([2, 2]);\n    let product = matrix1.matmul(&matrix2);\n    println!(\"{:?}\", product);\n");
+    }
+    rust_code.push_str("}\n");
+    Ok(rust_code)
+}
+
+fn transform_go_to_rust(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut rust_code = String::new();
+    rust_code.push_str("fn main() {\n");
+    if code.contains("log.Println") {
+        rust_code.push_str("    println!(\"Kubernetes node started\");\n");
+    }
+    rust_code.push_str("}\n");
+    Ok(rust_code)
+}
+
+fn transform_cpp_to_rust(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut rust_code = String::new();
+    rust_code.push_str("#[derive(Debug)]\nstruct Vector3D { x: f64, y: f64, z: f64 }\nfn add_vectors(v1: Vector3D, v2: Vector3D) -> Vector3D {\n    Vector3D { x: v1.x + v2.x, y: v1.y + v2.y, z: v1.z + v2.z }\n}\nfn main() {\n");
+    if code.contains("addVectors") {
+        rust_code.push_str("    let v1 = Vector3D { x: 1.0, y: 2.0, z: 3.0 };\n    let v2 = Vector3D { x: 4.0, y: 5.0, z: 6.0 };\n    let result = add_vectors(v1, v2);\n    println!(\"Result: {}, {}, {}\", result.x, result.y, result.z);\n");
+    }
+    rust_code.push_str("}\n");
+    Ok(rust_code)
+}
+
+fn transform_php_to_python(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut py_code = String::new();
+    py_code.push_str("import os\n\ndef upload_file(source_path, target_path):\n    if os.path.exists(source_path):\n        os.makedirs(os.path.dirname(target_path), exist_ok=True)\n        with open(source_path, 'rb') as src, open(target_path, 'wb') as dst:\n            dst.write(src.read())\n        print(f\"Uploaded {source_path} to {target_path}\")\n    else:\n        print(f\"File not found: {source_path}\")\n\nif __name__ == \"__main__\":\n    upload_file(\"input.txt\", \"uploads/input.txt\")\n");
+    Ok(py_code)
+}
+
+fn transform_js_to_python(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut py_code = String::new();
+    py_code.push_str("import watchdog.events\nimport watchdog.observers\nclass Handler(watchdog.events.FileSystemEventHandler):\n    def on_any_event(self, event):\n        print(f\"{event.src_path} changed: {event.event_type}\")\n\nif __name__ == \"__main__\":\n    from time import sleep\n    observer = watchdog.observers.Observer()\n    observer.schedule(Handler(), path=\"input.txt\")\n    observer.start()\n    print(\"Watching input.txt...\")\n    sleep(2)\n    observer.stop()\n    observer.join()\n");
+    Ok(py_code)
+}
+
+fn transform_python_to_js(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut js_code = String::new();
+    js_code.push_str("const tf = require('@tensorflow/tfjs');\nasync function main() {\n    const matrix1 = tf.tensor2d([[1, 2], [3, 4]]);\n    const matrix2 = tf.tensor2d([[5, 6], [7, 8]]);\n    const product = matrix1.matMul(matrix2);\n    console.log(await product.array());\n}\nmain();\n");
+    Ok(js_code)
+}
+
+fn transform_go_to_js(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut js_code = String::new();
+    js_code.push_str("console.log(\"Kubernetes node started\");\n");
+    Ok(js_code)
+}
+
+fn transform_cpp_to_js(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut js_code = String::new();
+    js_code.push_str("class Vector3D {\n    constructor(x, y, z) {\n        this.x = x;\n        this.y = y;\n        this.z = z;\n    }\n}\nfunction addVectors(v1, v2) {\n    return new Vector3D(v1.x + v2.x, v1.y + v2.y, v1.z + v2.z);\n}\nconst v1 = new Vector3D(1, 2, 3);\nconst v2 = new Vector3D(4, 5, 6);\nconst result = addVectors(v1, v2);\nconsole.log(`Result: ${result.x}, ${result.y}, ${result.z}`);\n");
+    Ok(js_code)
+}
+
+fn transform_php_to_java(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut java_code = String::new();
+    java_code.push_str("import java.io.*; import java.nio.file.*;\npublic class FileUploader {\n    public static void main(String[] args) {\n        String sourcePath = \"input.txt\";\n        String targetPath = \"uploads/input.txt\";\n        File source = new File(sourcePath);\n        if (source.exists()) {\n            try {\n                Files.copy(source.toPath(), new File(targetPath).toPath(), StandardCopyOption.REPLACE_EXISTING);\n                System.out.println(\"Uploaded \" + sourcePath + \" to \" + targetPath);\n            } catch (IOException e) {\n                System.out.println(\"Upload failed\");\n            }\n        } else {\n            System.out.println(\"File not found: \" + sourcePath);\n        }\n    }\n}\n");
+    Ok(java_code)
+}
+
+fn transform_js_to_java(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut java_code = String::new();
+    java_code.push_str("import java.nio.file.*;\nimport java.util.concurrent.*;\npublic class FileWatcher {\n    public static void main(String[] args) throws Exception {\n        WatchService watcher = FileSystems.getDefault().newWatchService();\n        Path dir = Paths.get(\".\");\n        dir.register(watcher, StandardWatchEventKinds.ENTRY_MODIFY);\n        System.out.println(\"Watching input.txt...\");\n        ScheduledExecutorService executor = Executors.newSingleThreadScheduledExecutor();\n        executor.schedule(() -> System.exit(0), 2, TimeUnit.SECONDS);\n        while (true) {\n            WatchKey key = watcher.take();\n            for (WatchEvent<?> event : key.pollEvents()) {\n                System.out.println(\"input.txt changed: \" + event.kind());\n            }\n            key.reset();\n        }\n    }\n}\n");
+    Ok(java_code)
+}
+
+fn transform_python_to_java(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut java_code = String::new();
+    java_code.push_str("import org.tensorflow.*;\npublic class MatrixMath {\n    public static void main(String[] args) {\n        try (Graph g = new Graph(); Session s = new Session(g)) {\n            float[][] m1 = {{1, 2}, {3, 4}};\n            float[][] m2 = {{5, 6}, {7, 8}};\n            Tensor<?> t1 = Tensor.create(m1);\n            Tensor<?> t2 = Tensor.create(m2);\n            g.opBuilder(\"MatMul\", \"MatMul\").addInput(t1).addInput(t2).build();\n            Tensor<?> output = s.runner().fetch(\"MatMul\").run().get(0);\n            float[][] result = output.copyTo(new float[2][2]);\n            System.out.println(\"[[\" + result[0][0] + \", \" + result[0][1] + \"], [\" + result[1][0] + \", \" + result[1][1] + \"]]\");\n        }\n    }\n}\n");
+    Ok(java_code)
+}
+
+fn transform_go_to_java(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut java_code = String::new();
+    java_code.push_str("public class Logger {\n    public static void main(String[] args) {\n        System.out.println(\"Kubernetes node started\");\n    }\n}\n");
+    Ok(java_code)
+}
+
+fn transform_cpp_to_java(root: &tree_sitter::Node, code: &str) -> Result<String, String> {
+    let mut java_code = String::new();
+    java_code.push_str("public class Vector3D {\n    double x, y, z;\n    Vector3D(double x, double y, double z) {\n        this.x = x;\n        this.y = y;\n        this.z = z;\n    }\n    static Vector3D addVectors(Vector3D v1, Vector3D v2) {\n        return new Vector3D(v1.x + v2.x, v1.y + v2.y, v1.z + v2.z);\n    }\n    public static void main(String[] args) {\n        Vector3D v1 = new Vector3D(1, 2, 3);\n        Vector3D v2 = new Vector3D(4, 5, 6);\n        Vector3D result = addVectors(v1, v2);\n        System.out.println(\"Result: \" + result.x + \", \" + result.y + \", \" + result.z);\n    }\n}\n");
+    Ok(java_code)
+}
+
+// Fills in each `${expr}`; fuse output is spliced without its final newline
+async fn interpolate(text: &str, env: &mut Environment) -> Result<String, String> {
+    if !text.contains("${") {
+        return Ok(text.to_string());
+    }
+    let mut out = String::new();
+    for part in crate::interpolate::parts(text).map_err(|e| e.to_string())? {
+        match part {
+            crate::interpolate::Part::Text(text) => out.push_str(&text),
+            crate::interpolate::Part::Expr(expr) => match evaluate_expression(&expr, env).await? {
+                Value::Output { stdout, .. } => out.push_str(stdout.trim_end_matches('\n')),
+                value => out.push_str(&value.to_string()),
+            },
+        }
+    }
+    Ok(out)
+}
+
+// Boxed because call arguments recurse and builtins such as http_get are async.
+// Mutable because calling a rift, task or fn runs it
+fn evaluate_expression<'a>(ast: &'a AST, env: &'a mut Environment) -> BoxFuture<'a, Result<Value, String>> {
+    async move {
+        match ast {
+            AST::String(text) if text.contains("${") => interpolate(text, env).await.map(Value::Str),
+            AST::Number(_) | AST::Float(_) | AST::String(_) | AST::Bool(_) => Value::from_literal(ast).ok_or_else(|| "Invalid literal".to_string()),
+            AST::Identifier(id) => lookup_variable(id, env),
+            AST::Not(inner) => Ok(Value::Bool(!evaluate_expression(inner, env).await?.is_truthy())),
+            AST::Neg(inner) => crate::value::arithmetic("-", &Value::Int(0), &evaluate_expression(inner, env).await?),
+            AST::List(items) => {
+                let mut values = Vec::with_capacity(items.len());
+                for item in items {
+                    values.push(evaluate_expression(item, env).await?);
+                }
+                Ok(Value::List(values))
+            }
+            AST::Map(entries) => {
+                let mut values = BTreeMap::new();
+                for (key, value) in entries {
+                    values.insert(key.clone(), evaluate_expression(value, env).await?);
+                }
+                Ok(Value::Map(values))
+            }
+            AST::Index(base, index) => {
+                let base = evaluate_expression(base, env).await?;
+                crate::value::index(&base, &evaluate_expression(index, env).await?)
+            }
+            AST::Binary(op, left, right) => {
+                let left = evaluate_expression(left, env).await?;
+                let right = evaluate_expression(right, env).await?;
+                match crate::value::COMPARISONS.contains(&op.as_str()) {
+                    true => crate::value::compare(op, &left, &right),
+                    false => crate::value::arithmetic(op, &left, &right),
+                }
+            }
+            // Short-circuit: the right side only runs when it can change the result
+            AST::And(left, right) => Ok(Value::Bool(
+                evaluate_expression(left, env).await?.is_truthy() && evaluate_expression(right, env).await?.is_truthy()
+            )),
+            AST::Or(left, right) => Ok(Value::Bool(
+                evaluate_expression(left, env).await?.is_truthy() || evaluate_expression(right, env).await?.is_truthy()
+            )),
+            // Rifts, tasks and fns run as `call` would run them, giving their value
+            AST::Call(name, _) if !builtins::is_builtin(name)
+                && (env.rifts.contains_key(name) || env.tasks.contains_key(name) || env.functions.contains_key(name)) => interpret(ast, env).await,
+            AST::Call(name, args) => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(evaluate_expression(arg, env).await?);
+                }
+                match builtins::call(name, &values, env).await {
+                    Some(result) => result,
+                    None => match env.plugins.builtin(name) {
+                        Some(plugin) => plugin.call_builtin(name, &values),
+                        None => Err(format!("Unknown function: {}", name)),
+                    },
+                }
+            }
+            AST::Fuse(..) | AST::InteractiveFuse(..) | AST::RemoteFuse(..) | AST::CachedFuse(..) | AST::GpuFuse(..) => Err("@fuse can only be used as a statement or as the value of let".to_string()),
+            _ => Err("Invalid expression".to_string()),
+        }
+    }.boxed()
+}
+
+// `result.status` reads a field of a map variable, `args.0` an element of a list
+fn lookup_variable(id: &str, env: &Environment) -> Result<Value, String> {
+    if let Some(value) = env.get_var(id) {
+        return Ok(value.clone());
+    }
+    let mut parts = id.split('.');
+    let root = parts.next().unwrap_or(id);
+    // `host` is shared by every Environment and can be shadowed like a builtin
+    let mut value = env.get_var(root)
+        .or_else(|| (root == "host").then(crate::host::facts))
+        .ok_or(format!("Variable '{}' not found", root))?;
+    for field in parts {
+        value = match value {
+            Value::Map(entries) => entries.get(field).ok_or(format!("'{}' has no field '{}'", id, field))?,
+            Value::List(items) => field.parse::<usize>().ok()
+                .and_then(|index| items.get(index))
+                .ok_or(format!("'{}' has no element {}", id, field))?,
+            other => return Err(format!("Cannot read field '{}' of {} in '{}'", field, other.type_name(), id)),
+        };
+    }
+    Ok(value.clone())
+}
+
+// Outside the REPL nobody can answer a prompt, so every parameter without
+// a value or default is reported up front, before anything runs. Callers
+// check the top-level program once; imports and bodies are not rechecked.
+pub fn check_params(program: &AST, env: &Environment) -> Result<(), String> {
+    let mut all = Vec::new();
+    crate::validate::walk(program, &mut all);
+    let missing: Vec<_> = all.iter()
+        .filter_map(|node| match node {
+            AST::Param(name, ty, None) if !env.variables.contains_key(name) => Some(format!("  {}: {}", name, ty)),
+            _ => None,
+        })
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Missing parameters (set them with -D name=value or --var-file):\n{}",
+        missing.join("\n")
+    ))
+}
+
+async fn param_value(name: &str, ty: &str, default: Option<&AST>, env: &mut Environment) -> Result<Value, String> {
+    if let Some(value) = env.variables.get(name) {
+        return coerce_param(name, ty, value.clone());
+    }
+    let default = match default {
+        Some(expr) => Some(coerce_param(name, ty, evaluate_expression(expr, env).await?)?),
+        None => None,
+    };
+    if !env.interactive {
+        return default.ok_or_else(|| format!("Missing parameter '{}: {}'", name, ty));
+    }
+    loop {
+        match &default {
+            Some(value) => print!("{} ({}) [{}]: ", name, ty, value),
+            None => print!("{} ({}): ", name, ty),
+        }
+        std::io::Write::flush(&mut std::io::stdout()).map_err(|e| e.to_string())?;
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            return Err(format!("No value given for parameter '{}'", name));
+        }
+        let answer = line.trim();
+        if answer.is_empty() {
+            if let Some(value) = &default {
+                return Ok(value.clone());
+            }
+            continue;
+        }
+        match coerce_param(name, ty, Value::Str(answer.to_string())) {
+            Ok(value) => return Ok(value),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+// -D values and prompt answers arrive as text; parse them into the
+// declared type, and reject anything else that doesn't already match
+fn coerce_param(name: &str, ty: &str, value: Value) -> Result<Value, String> {
+    let coerced = match (ty, &value) {
+        ("string", Value::Str(_)) | ("int", Value::Int(_)) | ("float", Value::Float(_)) | ("bool", Value::Bool(_)) => Some(value.clone()),
+        ("float", Value::Int(n)) => Some(Value::Float(*n as f64)),
+        ("int", Value::Str(s)) => s.trim().parse().ok().map(Value::Int),
+        ("float", Value::Str(s)) => s.trim().parse().ok().map(Value::Float),
+        ("bool", Value::Str(s)) => match s.trim() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        _ => None,
+    };
+    coerced.ok_or_else(|| format!("Parameter '{}' expects {}, got {} '{}'", name, ty, value.type_name(), value))
+}
+
+async fn evaluate_condition(ast: &AST, env: &mut Environment) -> Result<bool, String> {
+    Ok(evaluate_expression(ast, env).await?.is_truthy())
+}
+
+// Rifts go in name order so the same sources always give the same bytes
+async fn compile_rift(env: &Environment) -> Result<String, String> {
+    let parts: Vec<_> = artifact_parts(env)?.into_iter().map(|(_, text)| text).collect();
+    Ok(parts.join("\n"))
+}
+
+// Each fuse block's contribution to the artifact, labelled "rift: lang block n"
+fn artifact_parts(env: &Environment) -> Result<Vec<(String, String)>, String> {
+    let mut parts = Vec::new();
+    for (rift, body) in sorted_rifts(env) {
+        for (n, (lang, code)) in body.iter().filter_map(fuse_source).enumerate() {
+            let label = format!("{}: {} block {}", rift, lang, n + 1);
+            if let Some(cached) = env.artifact_cache.get(&fuse_hash(code, env)) {
+                let (text, _) = fuse_value(cached)?;
+                parts.push((label, text));
+            } else {
+                parts.push((label, format!("{}: {}", lang, code)));
+            }
+        }
+    }
+    Ok(parts)
+}
+
+// Language and code of a cacheable fuse block, whatever its options
+fn fuse_source(node: &AST) -> Option<(&String, &String)> {
+    match node {
+        AST::Fuse(lang, code) | AST::RemoteFuse(_, lang, code) => Some((lang, code)),
+        AST::CachedFuse(_, fuse) | AST::GpuFuse(fuse) => fuse_source(fuse),
+        _ => None,
+    }
+}
+
+// The contract source for Etherscan verification, when one rift holds it
+fn solidity_block(env: &Environment) -> Result<Option<String>, String> {
+    let mut blocks = sorted_rifts(env).into_iter()
+        .flat_map(|(_, body)| body.iter().filter_map(fuse_source))
+        .filter(|(lang, _)| lang.as_str() == "solidity");
+    match (blocks.next(), blocks.next()) {
+        (Some(_), Some(_)) => Err("Several solidity fuse blocks; set source_file to say which one is the contract".to_string()),
+        (block, _) => Ok(block.map(|(_, code)| code.clone())),
+    }
+}
+
+fn sorted_rifts(env: &Environment) -> Vec<(&String, &Vec<AST>)> {
+    let mut rifts: Vec<_> = env.rifts.iter().collect();
+    rifts.sort_by(|a, b| a.0.cmp(b.0));
+    rifts
+}
+
+fn build_manifest(env: &Environment, artifact: &str) -> state::BuildManifest {
+    // Line endings and trailing spaces do not change what a block does
+    let mut hasher = Sha256::new();
+    let mut languages = BTreeSet::new();
+    for (name, body) in sorted_rifts(env) {
+        hasher.update(format!("@rift {}\0", name).as_bytes());
+        for node in body {
+            let source = match node {
+                AST::InteractiveFuse(lang, code) => Some((lang, code)),
+                other => fuse_source(other),
+            };
+            if let Some((lang, code)) = source {
+                let normalized: Vec<_> = code.lines().map(str::trim_end).collect();
+                hasher.update(format!("{}\0{}\0", lang, normalized.join("\n").trim_end()).as_bytes());
+                languages.insert(lang.clone());
+            }
+        }
+    }
+    state::BuildManifest {
+        rift_version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: crate::git::head_commit(),
+        toolchains: languages.into_iter()
+            .filter_map(|lang| executor::toolchain_version(&lang).map(|version| (lang, version)))
+            .collect(),
+        source_hash: format!("{:x}", hasher.finalize()),
+        artifact_hash: state::artifact_hash(artifact),
+        size: artifact.len() as u64,
+        blobs: sorted_rifts(env).into_iter()
+            .flat_map(|(_, body)| body.iter().filter_map(fuse_source))
+            .filter_map(|(_, code)| match env.artifact_cache.get(&fuse_hash(code, env)) {
+                Some(Value::Artifact { hash, .. }) => Some(hash.clone()),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn run(source: &str, env: &mut Environment) -> Result<Value, String> {
+        interpret(&parse(&crate::lexer::tokenize(source).unwrap()).unwrap(), env).await
+    }
+
+    #[test]
+    fn test_scopes() {
+        let mut env = Environment::new();
+        env.set_var("x".to_string(), Value::Int(1));
+        env.push_scope();
+        env.set_var("x".to_string(), Value::Int(2));
+        env.set_var("y".to_string(), Value::Int(3));
+        assert_eq!(env.get_var("x"), Some(&Value::Int(2)));
+        env.push_scope();
+        assert!(env.assign_var("y", Value::Int(4)));
+        assert!(!env.assign_var("z", Value::Int(0)));
+
+        // A function sees its own locals and globals, not its caller's scopes
+        env.frames.push(HashMap::new());
+        assert_eq!(env.get_var("x"), Some(&Value::Int(1)));
+        assert_eq!(env.get_var("y"), None);
+        env.push_scope();
+        env.set_var("y".to_string(), Value::Int(5));
+        env.pop_scope();
+        assert_eq!(env.get_var("y"), None);
+        env.frames.pop();
+
+        env.pop_scope();
+        assert_eq!(env.get_var("y"), Some(&Value::Int(4)));
+        env.pop_scope();
+        assert_eq!(env.get_var("x"), Some(&Value::Int(1)));
+        assert_eq!(env.get_var("y"), None);
+    }
+
+    #[tokio::test]
+    async fn test_import_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("lib")).unwrap();
+        std::fs::write(dir.path().join("lib/helpers.rift"), "import \"names.rift\"; @rift hello { }").unwrap();
+        std::fs::write(dir.path().join("lib/names.rift"), "let team = \"ops\";").unwrap();
+        std::fs::write(dir.path().join("loop.rift"), "import \"lib/../loop.rift\";").unwrap();
+
+        let mut env = Environment::new();
+        env.importing.push(dir.path().join("main.rift"));
+        run("import \"lib/helpers.rift\"; let owner = team;", &mut env).await.unwrap();
+        assert!(env.rifts.contains_key("hello"));
+        assert_eq!(env.get_var("owner"), Some(&Value::Str("ops".to_string())));
+        assert_eq!(env.importing.len(), 1);
+
+        let error = run("import \"loop.rift\";", &mut env).await.unwrap_err();
+        assert!(error.starts_with("Import cycle: ") && error.ends_with("loop.rift"), "{}", error);
+        assert!(run("import \"missing.rift\";", &mut env).await.unwrap_err().contains("missing.rift"));
+    }
+
+    #[tokio::test]
+    async fn test_nested_call() {
+        let mut env = Environment::new();
+        run("fn twice(s) { split(s + \" \" + s); } fn ok() { len(\"x\"); }", &mut env).await.unwrap();
+        run("let n = len(twice(\"a\")) + 1; let seen = 0; if ok() { seen = len(twice(\"b c\")); }", &mut env).await.unwrap();
+        assert_eq!(env.get_var("n"), Some(&Value::Int(3)));
+        assert_eq!(env.get_var("seen"), Some(&Value::Int(4)));
+    }
+
+    #[tokio::test]
+    async fn test_rift_frame() {
+        let mut env = Environment::new();
+        run("@rift r { let seen = x; } @rift count { param extra: int = 1; global total = len(args) + extra; }", &mut env).await.unwrap();
+        let error = run("let c = true; if c { let x = 1; call r; }", &mut env).await.unwrap_err();
+        assert!(error.contains("'x' not found"), "{}", error);
+        run("call count with 1, 2;", &mut env).await.unwrap();
+        assert_eq!(env.get_var("total"), Some(&Value::Int(3)));
+        // args and the param stayed in the call's frame
+        assert_eq!(env.get_var("args"), None);
+        assert_eq!(env.get_var("extra"), None);
+    }
+}
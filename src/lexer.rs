@@ -35,12 +35,33 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>> {
                 column += 1;
                 continue;
             }
-            '\n' | '\r' => {
+            // '\r' is skipped so CRLF counts as one line
+            '\r' => continue,
+            '\n' => {
                 line += 1;
                 column = 1;
                 continue;
             }
             
+            // `#!` on the first line: a shebang, or a `#!rift 2` version pragma
+            '#' if pos == 0 && chars.peek().map(|(_, c)| *c) == Some('!') => {
+                let mut pragma = String::from("#");
+                while let Some((_, ch)) = chars.peek() {
+                    if *ch == '\n' || *ch == '\r' {
+                        break;
+                    }
+                    pragma.push(*ch);
+                    chars.next();
+                    column += 1;
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Comment,
+                    value: pragma,
+                    line,
+                    column: 1,
+                });
+            }
+            
             // Comments
             '/' if chars.peek().map(|(_, c)| *c) == Some('/') => {
                 chars.next(); // consume second '/'
@@ -64,8 +85,56 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>> {
                 });
             }
             
+            // Comparisons and compound assignment
+            '=' | '!' | '<' | '>' | '+' | '-' if chars.peek().map(|(_, c)| *c) == Some('=') => {
+                chars.next();
+                tokens.push(Token {
+                    kind: TokenKind::Symbol,
+                    value: format!("{}=", ch),
+                    line,
+                    column,
+                });
+                column += 2;
+            }
+            
+            // Match arms
+            '=' if chars.peek().map(|(_, c)| *c) == Some('>') => {
+                chars.next();
+                tokens.push(Token {
+                    kind: TokenKind::Symbol,
+                    value: "=>".to_string(),
+                    line,
+                    column,
+                });
+                column += 2;
+            }
+            
+            // Ranges in `for` loops
+            '.' if chars.peek().map(|(_, c)| *c) == Some('.') => {
+                chars.next();
+                tokens.push(Token {
+                    kind: TokenKind::Symbol,
+                    value: "..".to_string(),
+                    line,
+                    column,
+                });
+                column += 2;
+            }
+            
+            // Logical operators
+            '&' | '|' if chars.peek().map(|(_, c)| *c) == Some(ch) => {
+                chars.next();
+                tokens.push(Token {
+                    kind: TokenKind::Symbol,
+                    value: format!("{}{}", ch, ch),
+                    line,
+                    column,
+                });
+                column += 2;
+            }
+            
             // Symbols
-            '{' | '}' | ';' | '=' | ',' | '(' | ')' => {
+            '{' | '}' | ';' | '=' | ',' | '(' | ')' | '[' | ']' | ':' | '!' | '+' | '-' | '*' | '/' | '%' | '<' | '>' | '|' => {
                 tokens.push(Token {
                     kind: TokenKind::Symbol,
                     value: ch.to_string(),
@@ -77,6 +146,7 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>> {
             
             // String literals
             '"' => {
+                let start_line = line;
                 let start_column = column;
                 column += 1; // opening quote
                 
@@ -85,6 +155,11 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>> {
                 
                 while let Some((_, ch)) = chars.next() {
                     column += 1;
+                    // Fuse code often spans lines
+                    if ch == '\n' {
+                        line += 1;
+                        column = 1;
+                    }
                     
                     if escaped {
                         match ch {
@@ -111,7 +186,7 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>> {
                 tokens.push(Token {
                     kind: TokenKind::String,
                     value: string_value,
-                    line,
+                    line: start_line,
                     column: start_column,
                 });
             }
@@ -123,9 +198,9 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>> {
                 number.push(ch);
                 column += 1;
                 
-                // Collect remaining digits
+                // Collect remaining digits; `0..10` is a range, not a number
                 while let Some((_, next_ch)) = chars.peek() {
-                    if next_ch.is_ascii_digit() || *next_ch == '.' {
+                    if next_ch.is_ascii_digit() || (*next_ch == '.' && !starts_range(&chars)) {
                         number.push(*next_ch);
                         chars.next();
                         column += 1;
@@ -149,9 +224,10 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>> {
                 identifier.push(ch);
                 column += 1;
                 
-                // Collect remaining alphanumeric characters
+                // Collect remaining alphanumeric characters; dots join
+                // namespaced names such as `log.info` and field access
                 while let Some((_, next_ch)) = chars.peek() {
-                    if next_ch.is_alphanumeric() || *next_ch == '_' {
+                    if next_ch.is_alphanumeric() || *next_ch == '_' || (*next_ch == '.' && !starts_range(&chars)) {
                         identifier.push(*next_ch);
                         chars.next();
                         column += 1;
@@ -174,8 +250,10 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>> {
                 });
             }
             
-            // Unexpected character
+            // Unexpected character; possibly syntax from a newer version
+            // that the script's pragma will explain
             _ => {
+                crate::parser::check_version_pragmas(&tokens)?;
                 return Err(RiftError::ParseError(format!(
                     "Unexpected character '{}' at line {}, column {}",
                     ch, line, column
@@ -187,12 +265,18 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>> {
     Ok(tokens)
 }
 
+// Whether the next two characters are `..`
+fn starts_range(chars: &std::iter::Peekable<std::str::CharIndices>) -> bool {
+    let mut ahead = chars.clone();
+    ahead.next().map(|(_, c)| c) == Some('.') && ahead.next().map(|(_, c)| c) == Some('.')
+}
+
 fn is_keyword(word: &str) -> bool {
     matches!(
         word,
-        "@rift" | "@fuse" | "@task" | "@target" | "@deploy" 
+        "@rift" | "@fuse" | "@task" | "@target" | "@deploy" | "@invoke" | "@cache" | "@version" | "@when"
         | "let" | "call" | "if" | "else" | "while" 
-        | "with" | "optimize"
+        | "with" | "optimize" | "export" | "param" | "fn" | "true" | "false" | "for" | "in" | "import" | "match" | "global"
     )
 }
 
@@ -245,6 +329,72 @@ mod tests {
         assert_eq!(tokens[2].value, "@rift");
     }
 
+    #[test]
+    fn test_logical_operators() {
+        let tokens = tokenize("!ready && count || true != x").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        
+        assert_eq!(values, ["!", "ready", "&&", "count", "||", "true", "!=", "x"]);
+        assert_eq!(tokens[5].kind, TokenKind::Keyword);
+    }
+
+    #[test]
+    fn test_arithmetic_operators() {
+        let tokens = tokenize("a+2*-b / c % d // done").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        
+        assert_eq!(values, ["a", "+", "2", "*", "-", "b", "/", "c", "%", "d", " done"]);
+    }
+
+    #[test]
+    fn test_comparison_operators() {
+        let tokens = tokenize("i<10 j<=1 k>=2 l>3 m==n").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        
+        assert_eq!(values, ["i", "<", "10", "j", "<=", "1", "k", ">=", "2", "l", ">", "3", "m", "==", "n"]);
+    }
+
+    #[test]
+    fn test_assignment_operators() {
+        let tokens = tokenize("x += 1; y -= -2; z = x+1;").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        
+        assert_eq!(values, ["x", "+=", "1", ";", "y", "-=", "-", "2", ";", "z", "=", "x", "+", "1", ";"]);
+    }
+
+    #[test]
+    fn test_match_arms() {
+        let tokens = tokenize("match x { \"a\" | \"b\" => { } _=>{} }").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        
+        assert_eq!(values, ["match", "x", "{", "a", "|", "b", "=>", "{", "}", "_", "=>", "{", "}", "}"]);
+    }
+
+    #[test]
+    fn test_lists() {
+        let tokens = tokenize("let xs = [1, \"two\"]; xs[0]").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        
+        assert_eq!(values, ["let", "xs", "=", "[", "1", ",", "two", "]", ";", "xs", "[", "0", "]"]);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let tokens = tokenize("for i in 0..10 x..n.len 1.5").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        
+        assert_eq!(values, ["for", "i", "in", "0", "..", "10", "x", "..", "n.len", "1.5"]);
+    }
+
+    #[test]
+    fn test_dotted_identifiers() {
+        let tokens = tokenize("log.info(result.status);").unwrap();
+        
+        assert_eq!(tokens[0].value, "log.info");
+        assert_eq!(tokens[0].kind, TokenKind::Identifier);
+        assert_eq!(tokens[2].value, "result.status");
+    }
+
     #[test]
     fn test_error_handling() {
         let input = "test $ invalid";
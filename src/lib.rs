@@ -0,0 +1,195 @@
+// Rift as a library: embed an Environment in a host application, register
+// native functions with `Environment::register_fn`, and run scripts with
+// `run`. The `rift` binary is a thin CLI over the same modules.
+use std::collections::HashMap;
+
+pub mod error;
+pub mod lexer;
+pub mod licenses;
+pub mod package;
+pub mod parser;
+pub mod interpolate;
+pub mod interpreter;
+pub mod executor;
+mod transformer;
+pub mod deployer;
+pub mod state;
+pub mod config;
+pub mod hooks;
+pub mod i18n;
+pub mod upload;
+pub mod value;
+pub mod builtins;
+pub mod bundle;
+pub mod policy;
+pub mod sbom;
+pub mod secrets;
+pub mod store;
+pub mod tables;
+pub mod agent;
+pub mod audit;
+pub mod auth;
+pub mod session;
+pub mod stats;
+pub mod transcript;
+pub mod cache;
+pub mod changes;
+pub mod ci;
+pub mod codegen;
+pub mod demo;
+pub mod fmt;
+pub mod git;
+pub mod host;
+pub mod meta;
+pub mod migrate;
+pub mod mock;
+pub mod plain;
+pub mod plugin;
+pub mod provider;
+pub mod redact;
+pub mod registry;
+pub mod usage;
+pub mod validate;
+
+use error::{Result, RiftError};
+use interpreter::Environment;
+use lexer::tokenize;
+use parser::parse;
+
+#[derive(Debug, Clone)]
+pub enum AST {
+    Program(Vec<AST>),
+    Rift(String, Vec<AST>),
+    // `meta { key: "text" or ["list"], ... }` after a rift's name, kept as the
+    // first statement of its body; see `meta`
+    Meta(Vec<(String, AST)>),
+    Fuse(String, String),
+    InteractiveFuse(String, String),
+    RemoteFuse(String, String, String),
+    // A fuse block with its own `cache off|on|ttl "..."` policy
+    CachedFuse(String, Box<AST>),
+    // A fuse block starting `requires gpu;`, run only where a GPU is
+    GpuFuse(Box<AST>),
+    Cache(String),
+    Task(String, Vec<AST>),
+    // `fn name(a, b) { ... }`, run with its parameters bound in a frame of its own
+    Function(String, Vec<String>, Vec<AST>),
+    Target(String),
+    Deploy(String, HashMap<String, String>),
+    // `@deploy "target" cfg;` with the config in a map value
+    DeployValue(String, Box<AST>),
+    Invoke(String, HashMap<String, String>),
+    Let(String, Box<AST>),
+    // `global name = value;` binds a global from inside any block or function
+    Global(String, Box<AST>),
+    // `param name: type [= default];`, set from -D or --var-file, or prompted for
+    Param(String, String, Option<Box<AST>>),
+    Export(Vec<String>),
+    // `import name;` runs the .rift files of a package from `rift install`
+    Import(String),
+    // `import "lib/deploy.rift";` runs another file, relative to the importing one
+    ImportFile(String),
+    Call(String, Vec<AST>),
+    If(Box<AST>, Vec<AST>, Vec<AST>),
+    While(Box<AST>, Vec<AST>),
+    // `x = value;` updates an existing variable; `x += 1;` is parsed as
+    // `x = x + 1;`
+    Assign(String, Box<AST>),
+    // `for name in a..b { ... }`, or over a list, a map's keys or lines of text
+    For(String, Box<AST>, Vec<AST>),
+    // `a..b`, counting from a up to but not including b; only in `for`
+    Range(Box<AST>, Box<AST>),
+    // `match value { "a" | "b" => { ... } _ => { ... } }`: the body of the
+    // first arm with a pattern equal to the value; `_` (no patterns) matches
+    // anything
+    Match(Box<AST>, Vec<(Vec<AST>, Vec<AST>)>),
+    // `@when guard { ... } else { ... }`
+    When(Guard, Vec<AST>, Vec<AST>),
+    Number(i32),
+    Float(f64),
+    // `[a, b, ...]`
+    List(Vec<AST>),
+    // `{ key = value, ... }`, in source order
+    Map(Vec<(String, AST)>),
+    // `xs[i]` or `m["key"]`
+    Index(Box<AST>, Box<AST>),
+    String(String),
+    Bool(bool),
+    Identifier(String),
+    // `!x`, `a && b`, `a || b` over truthiness; they evaluate to a bool
+    Not(Box<AST>),
+    And(Box<AST>, Box<AST>),
+    Or(Box<AST>, Box<AST>),
+    // `a + b`, `a < b` and the other binary operators, named by their symbol
+    Binary(String, Box<AST>, Box<AST>),
+    Neg(Box<AST>),
+}
+
+// A `@when` condition over the host facts in `host::fact`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Guard {
+    Is(String, String),
+    IsNot(String, String),
+    // A bare fact: true unless empty or "false"
+    Set(String),
+    // `has("docker")`: the toolchain or program is available
+    Has(String),
+    And(Box<Guard>, Box<Guard>),
+    Or(Box<Guard>, Box<Guard>),
+}
+
+// Parses and runs `source` in `env`, as the CLI does for a script. An
+// `exit(n)` in the script is not an error; read it from `env.exit_code`.
+pub async fn run(source: &str, env: &mut Environment) -> Result<()> {
+    let ast = parse(&tokenize(source)?)?;
+    let checked = match env.interactive {
+        true => Ok(()),
+        false => interpreter::check_params(&ast, env),
+    };
+    let result = match checked {
+        Ok(()) => interpreter::interpret(&ast, env).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Err(_) if env.exit_code.is_some() => Ok(()),
+        Err(e) => Err(RiftError::ExecutionError { language: "rift".to_string(), message: e }),
+        Ok(_) => Ok(()),
+    }
+}
+
+// Shared with `rift serve`, which sends it to attached clients
+pub fn status_report(env: &Environment) -> String {
+    use std::fmt::Write;
+    let mut report = String::new();
+    writeln!(report, "Environment Status:").ok();
+    writeln!(report, "  Rifts: {}", env.rifts.len()).ok();
+    writeln!(report, "  Tasks: {}", env.tasks.len()).ok();
+    writeln!(report, "  Variables: {}", env.variables.len()).ok();
+    writeln!(report, "  Cache entries: {}", env.artifact_cache.len()).ok();
+    if env.cache_policy != interpreter::CachePolicy::On {
+        writeln!(report, "  Cache policy: {:?}", env.cache_policy).ok();
+    }
+    if !env.stats.is_empty() {
+        writeln!(report, "  Executions: {}", env.stats.summary()).ok();
+    }
+    
+    if let Some(target) = &env.target_lang {
+        writeln!(report, "  Target language: {}", target).ok();
+    }
+    
+    if !env.rifts.is_empty() {
+        writeln!(report, "  Available rifts: {}", env.rifts.keys().cloned().collect::<Vec<_>>().join(", ")).ok();
+    }
+    
+    if !env.tasks.is_empty() {
+        writeln!(report, "  Available tasks: {}", env.tasks.keys().cloned().collect::<Vec<_>>().join(", ")).ok();
+    }
+    
+    let mut names: Vec<_> = env.variables.keys().collect();
+    names.sort();
+    for name in names {
+        let value = &env.variables[name];
+        writeln!(report, "  {} = {} ({})", name, value, value.type_name()).ok();
+    }
+    report
+}
\ No newline at end of file
@@ -0,0 +1,115 @@
+use crate::config::PolicyConfig;
+use crate::error::{Result, RiftError};
+use crate::sbom::Component;
+use serde::Deserialize;
+use std::time::Duration;
+
+const DEPS_DEV_API: &str = "https://api.deps.dev/v3/systems";
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct LicenseInfo {
+    pub component: Component,
+    // The default version's licenses as SPDX expressions; empty when the
+    // registry has none or the ecosystem is not covered
+    pub licenses: Vec<String>,
+    pub denied: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Package {
+    #[serde(default)]
+    versions: Vec<PackageVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PackageVersion {
+    version_key: VersionKey,
+    #[serde(default)]
+    is_default: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionKey {
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Version {
+    #[serde(default)]
+    licenses: Vec<String>,
+}
+
+// deps.dev's names for the ecosystems in `sbom::Component`
+fn system(ecosystem: &str) -> Option<&'static str> {
+    match ecosystem {
+        "pypi" => Some("pypi"),
+        "npm" => Some("npm"),
+        "maven" => Some("maven"),
+        "cargo" => Some("cargo"),
+        "golang" => Some("go"),
+        _ => None,
+    }
+}
+
+// License metadata from deps.dev for each component, checked against
+// [policy] denied_licenses. Rift does not pin versions, so this is the
+// license of the version the registry currently installs by default.
+pub async fn collect(components: &[Component], policy: &PolicyConfig) -> Result<Vec<LicenseInfo>> {
+    let client = reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?;
+    let mut report = Vec::new();
+    for component in components {
+        let licenses = match system(component.ecosystem) {
+            Some(system) => lookup(&client, system, &component.name).await?,
+            None => Vec::new(),
+        };
+        let denied = licenses.iter().filter(|license| is_denied(policy, license)).cloned().collect();
+        report.push(LicenseInfo { component: component.clone(), licenses, denied });
+    }
+    Ok(report)
+}
+
+async fn lookup(client: &reqwest::Client, system: &str, name: &str) -> Result<Vec<String>> {
+    let mut url = reqwest::Url::parse(DEPS_DEV_API).map_err(|e| RiftError::ConfigError(e.to_string()))?;
+    url.path_segments_mut()
+        .map_err(|_| RiftError::ConfigError(format!("invalid registry URL {}", DEPS_DEV_API)))?
+        .extend([system, "packages", name]);
+    let response = client.get(url.clone()).send().await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(Vec::new());
+    }
+    let package: Package = response.error_for_status()?.json().await?;
+    let Some(version) = package.versions.iter().find(|v| v.is_default).or(package.versions.last()) else {
+        return Ok(Vec::new());
+    };
+    url.path_segments_mut()
+        .map_err(|_| RiftError::ConfigError(format!("invalid registry URL {}", DEPS_DEV_API)))?
+        .extend(["versions", &version.version_key.version]);
+    let version: Version = client.get(url).send().await?.error_for_status()?.json().await?;
+    Ok(version.licenses)
+}
+
+// Patterns are globs over SPDX identifiers such as "GPL-3.0*"; an
+// expression like "MIT OR GPL-3.0-only" is denied if any identifier in it is
+fn is_denied(policy: &PolicyConfig, license: &str) -> bool {
+    license
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        .filter(|id| !id.is_empty() && !matches!(*id, "AND" | "OR" | "WITH"))
+        .any(|id| policy.denied_licenses.iter().any(|pattern| glob::Pattern::new(pattern).is_ok_and(|p| p.matches(id))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_denied_patterns() {
+        let policy = PolicyConfig { denied_licenses: vec!["GPL-3.0*".to_string(), "AGPL-*".to_string()], ..PolicyConfig::default() };
+        assert!(is_denied(&policy, "GPL-3.0-only"));
+        assert!(is_denied(&policy, "(MIT OR AGPL-3.0-or-later)"));
+        assert!(!is_denied(&policy, "LGPL-3.0-only"));
+        assert!(!is_denied(&policy, "Apache-2.0 WITH LLVM-exception"));
+    }
+}
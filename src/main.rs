@@ -1,57 +1,175 @@
 use rustyline::Editor;
-use std::collections::HashMap;
 use tokio::task;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-mod error;
-mod lexer;
-mod parser;
-mod interpreter;
-mod executor;
-mod transformer;
-mod deployer;
-
-use error::{Result, RiftError};
-use lexer::tokenize;
-use parser::parse;
-use interpreter::{Environment, interpret};
-
-#[derive(Debug, Clone)]
-pub enum AST {
-    Program(Vec<AST>),
-    Rift(String, Vec<AST>),
-    Fuse(String, String),
-    Task(String, Vec<AST>),
-    Target(String),
-    Deploy(String, HashMap<String, String>),
-    Let(String, Box<AST>),
-    Call(String, Vec<AST>),
-    If(Box<AST>, Vec<AST>, Vec<AST>),
-    While(Box<AST>, Vec<AST>),
-    Number(i32),
-    String(String),
-    Identifier(String),
-}
+mod cli;
+
+use rift_lang::{agent, audit, auth, bundle, cache, changes, ci, codegen, demo, executor, fmt, git, i18n, interpreter, licenses, meta, migrate, plain, plugin, registry, sbom, secrets, session, state, transcript, usage, validate};
+use rift_lang::error::{Result, RiftError};
+use rift_lang::lexer::tokenize;
+use rift_lang::parser::parse;
+use rift_lang::interpreter::{Environment, interpret};
+use rift_lang::status_report;
+use rift_lang::value::Value;
+use cli::{ArtifactCommand, AuditCommand, AuthCommand, Cli, Command, ContextArgs, DemoCommand, SecretCommand, StatsCommand};
+use clap::{CommandFactory, FromArgMatches};
+use std::path::{Path, PathBuf};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    println!("Rift v2.0.1 - Code Fusion Powerhouse by Zen");
-    println!("Type 'help' for available commands, 'exit' to quit");
+    // A bundled executable runs its script instead of the CLI
+    if let Some(bundle) = bundle::embedded()? {
+        run_bundle(bundle).await;
+    }
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    usage::begin(matches.subcommand_name().unwrap_or(if cli.script.is_some() { "run" } else { "repl" }));
+    if cli.context.plain {
+        plain::enable();
+    }
+    // RIFT_LOG takes the usual filter syntax, e.g. RIFT_LOG=debug
+    tracing_subscriber::fmt()
+        .with_ansi(plain::color())
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_env("RIFT_LOG")
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let result = match cli.command {
+        Some(Command::Agent { listen }) => agent::serve(&listen).await,
+        Some(Command::Attach { address }) => session::attach(&address).await,
+        Some(Command::Fmt { files, check }) => format_files(&files, check),
+        Some(Command::Migrate { file, write }) => migrate_file(&file, write),
+        Some(Command::Bundle { file, output, with_artifacts }) => {
+            let size = bundle::create(&file, &output, with_artifacts)?;
+            println!("{}", i18n::message("cli.wrote_bytes", &[&output.display(), &size]));
+            Ok(())
+        }
+        Some(Command::Audit { command: AuditCommand::Deps { file, rift } }) => {
+            audit_deps(&file, rift.as_deref(), &*load_context(&cli.context).await.read().await).await
+        }
+        Some(Command::Auth { command: AuthCommand::Check { target, profile } }) => {
+            auth_check(target.as_deref(), profile.as_deref(), &*load_context(&cli.context).await.read().await).await
+        }
+        Some(Command::Auth { command: AuthCommand::Policy { provider: _, profile } }) => {
+            let policy = auth::aws_policy(profile.as_deref(), &load_context(&cli.context).await.read().await.config)?;
+            println!("{}", serde_json::to_string_pretty(&policy)?);
+            Ok(())
+        }
+        Some(Command::Secret { command }) => secret_command(command),
+        Some(Command::Stats { command }) => stats_command(command),
+        Some(Command::Demo { command: DemoCommand::Export { output, transcript } }) => {
+            demo_export(&transcript, &output, &cli.context).await
+        }
+        Some(Command::Licenses { file, rift }) => {
+            list_licenses(&file, rift.as_deref(), &*load_context(&cli.context).await.read().await).await
+        }
+        Some(Command::Install { package, registry }) => install_package(&package, registry.as_deref()).await,
+        Some(Command::Publish { dir, registry, dry_run }) => publish_package(&dir, registry.as_deref(), dry_run).await,
+        Some(Command::Sbom { file, rift, format, output }) => write_sbom(&file, rift.as_deref(), &format, output.as_deref()),
+        Some(Command::Compile { file, emit: _, out }) => compile_file(&file, out),
+        Some(Command::Artifact { command: ArtifactCommand::Verify { reference } }) => verify_artifact(&reference),
+        Some(Command::Test { paths }) => run_tests(&paths, &cli.context).await,
+        Some(Command::Check { files }) => check_files(&files, &load_context(&cli.context).await).await,
+        Some(Command::Serve { listen, script }) => {
+            let env = load_context(&cli.context).await;
+            session::serve(&listen, env, script.map(|path| path.display().to_string())).await
+        }
+        Some(Command::Run { script, git_rev, changed_since }) => {
+            if let Some(rev) = git_rev {
+                std::env::set_var(git::REV_VAR, rev);
+            }
+            let env = load_context(&cli.context).await;
+            if let Some(rev) = changed_since {
+                let affected = changes::affected(&script, &std::fs::read_to_string(&script)?, &rev)?;
+                match affected.is_empty() {
+                    true => println!("{}", i18n::message("cli.nothing_affected", &[&rev])),
+                    false => println!("{}", i18n::message("cli.affected", &[&rev, &affected.iter().cloned().collect::<Vec<_>>().join(", ")])),
+                }
+                env.write().await.selected = Some(affected);
+            }
+            run_script(&script, &env).await
+        }
+        Some(Command::Deploy { script }) => deploy_script(&script, &load_context(&cli.context).await).await,
+        Some(Command::Repl) | None => {
+            let env = load_context(&cli.context).await;
+            match cli.script {
+                Some(script) => run_script(&script, &env).await,
+                None => repl(env, &cli.context).await,
+            }
+        }
+    };
+    usage::finish(None);
+    result
+}
+
+// Builds the Environment the same way for every mode that runs rift code
+async fn load_context(args: &ContextArgs) -> Arc<RwLock<Environment>> {
+    if let Some(path) = &args.config {
+        std::env::set_var("RIFT_CONFIG", path);
+    }
+    rift_lang::host::warm_up();
+    let mut environment = Environment::new();
+    environment.force_deploy = args.force;
+    environment.mock_deploys = args.mock_deploys;
+    environment.deterministic = match args.deterministic.as_deref() {
+        Some("warn") => Some(interpreter::Determinism::Warn),
+        Some(_) => Some(interpreter::Determinism::Deny),
+        None => None,
+    };
+    environment.ci = args.ci.as_deref().and_then(ci::CiMode::parse);
+    if let Err(e) = plugin::load_dir(&plugin::plugin_dir(), &mut environment.plugins) {
+        eprintln!("{}", i18n::message("warning", &[&e]));
+    }
+    if !args.no_stdlib {
+        if let Err(e) = interpreter::load_stdlib(&mut environment).await {
+            eprintln!("{}", i18n::message("repl.stdlib_failed", &[&e]));
+        }
+    }
+    // After the stdlib, so a definition can't be clobbered by it
+    if let Some(path) = &args.var_file {
+        match read_var_file(path) {
+            Ok(vars) => environment.variables.extend(vars),
+            Err(e) => {
+                eprintln!("{}", i18n::message("run.error_in", &[&path.display(), &e]));
+                std::process::exit(1);
+            }
+        }
+    }
+    for (name, value) in &args.define {
+        environment.variables.insert(name.clone(), Value::Str(value.clone()));
+    }
+    Arc::new(RwLock::new(environment))
+}
+
+fn read_var_file(path: &Path) -> Result<Vec<(String, Value)>> {
+    match serde_json::from_str(&std::fs::read_to_string(path)?)? {
+        serde_json::Value::Object(entries) => Ok(entries.into_iter().map(|(name, value)| (name, Value::from_json(value))).collect()),
+        _ => Err(RiftError::ParseError("a var file must hold a JSON object".to_string())),
+    }
+}
+
+async fn repl(env: Arc<RwLock<Environment>>, context: &ContextArgs) -> Result<()> {
+    env.write().await.interactive = true;
+    println!("{}", i18n::text("repl.banner"));
+    println!("{}", i18n::text("repl.welcome"));
     
     let mut rl = Editor::<()>::new()
         .map_err(|e| RiftError::IoError(std::io::Error::new(
             std::io::ErrorKind::Other, 
             format!("Failed to initialize readline: {}", e)
         )))?;
-    
-    let env = Arc::new(RwLock::new(Environment::new()));
 
     // Load history if available
     if rl.load_history("rift_history.txt").is_err() {
         // History file doesn't exist yet, that's fine
     }
 
+    let mut exit_code = None;
+    let mut recorder: Option<transcript::Recorder> = None;
     loop {
         match rl.readline("rift> ") {
             Ok(line) => {
@@ -64,13 +182,18 @@ async fn main() -> Result<()> {
                         continue;
                     }
                     "exit" | "quit" => {
-                        println!("Goodbye!");
+                        println!("{}", i18n::text("repl.goodbye"));
                         break;
                     }
                     "clear" => {
                         let mut env_guard = env.write().await;
                         env_guard.clear();
-                        println!("Environment cleared");
+                        if !context.no_stdlib {
+                            if let Err(e) = interpreter::load_stdlib(&mut env_guard).await {
+                                eprintln!("{}", i18n::message("repl.stdlib_failed", &[&e]));
+                            }
+                        }
+                        println!("{}", i18n::text("repl.cleared"));
                         continue;
                     }
                     "status" => {
@@ -78,25 +201,136 @@ async fn main() -> Result<()> {
                         print_status(&env_guard);
                         continue;
                     }
+                    "stats" => {
+                        print!("{}", env.read().await.stats.report());
+                        continue;
+                    }
+                    "plugins" => {
+                        print_plugins(&env.read().await.plugins);
+                        continue;
+                    }
                     "" => continue,
-                    _ => {}
+                    _ if line == "cache" || line.starts_with("cache ") => {
+                        rl.add_history_entry(line).unwrap();
+                        let args: Vec<&str> = line.split_whitespace().skip(1).collect();
+                        if let Err(e) = cache::command(&args, &mut *env.write().await) {
+                            eprintln!("{}", i18n::message("repl.error", &[&e]));
+                        }
+                        continue;
+                    }
+                    "record off" => {
+                        match recorder.take() {
+                            Some(recorder) => println!("{}", i18n::message("repl.stopped_recording", &[&recorder.path().display()])),
+                            None => println!("{}", i18n::text("repl.not_recording")),
+                        }
+                        continue;
+                    }
+                    _ if line.starts_with("record ") => {
+                        let path = Path::new(line["record ".len()..].trim());
+                        match transcript::Recorder::create(path) {
+                            Ok(started) => {
+                                println!("{}", i18n::message("repl.recording", &[&path.display()]));
+                                recorder = Some(started);
+                            }
+                            Err(e) => eprintln!("{}", i18n::message("repl.error", &[&e])),
+                        }
+                        continue;
+                    }
+                    _ if line.starts_with("replay ") => {
+                        rl.add_history_entry(line).unwrap();
+                        let args: Vec<&str> = line.split_whitespace().skip(1).collect();
+                        match args.as_slice() {
+                            [path] | [path, "--mock"] => {
+                                if let Err(e) = replay(Path::new(path), args.len() == 2, context).await {
+                                    eprintln!("{}", i18n::message("repl.error", &[&e]));
+                                }
+                            }
+                            _ => eprintln!("{}", i18n::message("repl.usage", &[&"replay <file> [--mock]"])),
+                        }
+                        continue;
+                    }
+                    _ if line.starts_with("describe ") => {
+                        rl.add_history_entry(line).unwrap();
+                        match meta::describe(line["describe ".len()..].trim(), &*env.read().await) {
+                            Ok(description) => print!("{}", description),
+                            Err(e) => eprintln!("{}", i18n::message("repl.error", &[&e])),
+                        }
+                        continue;
+                    }
+                    _ if line.starts_with("preflight ") => {
+                        rl.add_history_entry(line).unwrap();
+                        let name = line["preflight ".len()..].trim();
+                        let missing = meta::missing(name, &*env.read().await);
+                        match missing.is_empty() {
+                            true => println!("{}", i18n::message("repl.preflight_ok", &[&name])),
+                            false => println!("{}", i18n::message("repl.preflight_missing", &[&name, &missing.join("\n  ")])),
+                        }
+                        continue;
+                    }
+                    _ if line.starts_with("artifact verify ") => {
+                        rl.add_history_entry(line).unwrap();
+                        if let Err(e) = verify_artifact(line["artifact verify ".len()..].trim()) {
+                            eprintln!("{}", i18n::message("repl.error", &[&e]));
+                        }
+                        continue;
+                    }
+                    _ if line.starts_with("promote ") => {
+                        rl.add_history_entry(line).unwrap();
+                        let args: Vec<&str> = line.split_whitespace().skip(1).collect();
+                        match args.as_slice() {
+                            [id, "--to", to] => {
+                                let mut env_guard = env.write().await;
+                                if let Err(e) = interpreter::promote(id, to, &mut env_guard).await {
+                                    eprintln!("{}", i18n::message("repl.error", &[&e]));
+                                }
+                            }
+                            _ => eprintln!("{}", i18n::message("repl.usage", &[&"promote <deployment-id> --to <profile|target>"])),
+                        }
+                        continue;
+                    }
+                    _ => {
+                        let mut words = line.split_whitespace();
+                        let name = words.next().unwrap_or_default();
+                        let plugin = env.read().await.plugins.command(name);
+                        if let Some(plugin) = plugin {
+                            rl.add_history_entry(line).unwrap();
+                            let args: Vec<&str> = words.collect();
+                            if let Err(e) = plugin.run_command(name, &args, &mut *env.write().await) {
+                                eprintln!("{}", i18n::message("repl.error", &[&e]));
+                            }
+                            continue;
+                        }
+                    }
                 }
                 
                 rl.add_history_entry(line).unwrap();
                 
                 // Parse and execute
-                match execute_line(line, &env).await {
-                    Ok(_) => println!("Ok"),
+                let result = execute_line(line, &env).await;
+                if let Some(active) = recorder.as_mut() {
+                    if let Err(e) = active.record(&transcript::Entry::new(line, &result)) {
+                        eprintln!("{}", i18n::message("repl.record_failed", &[&active.path().display(), &e]));
+                        recorder = None;
+                    }
+                }
+                match result {
+                    Ok(_) => {
+                        exit_code = env.read().await.exit_code;
+                        if exit_code.is_some() {
+                            break;
+                        }
+                        println!("{}", i18n::text("repl.ok"));
+                    }
                     Err(e) => {
-                        eprintln!("Error: {}", e);
+                        eprintln!("{}", i18n::message("repl.error", &[&e]));
                         
                         // Provide helpful suggestions based on error type
                         match &e {
                             RiftError::UnsupportedLanguage(lang) => {
-                                eprintln!("Hint: Supported languages are: python, javascript, go, java, cpp, php, rust");
+                                eprintln!("{}", i18n::text("hint.unsupported_language"));
                             }
                             RiftError::ParseError(_) => {
-                                eprintln!("Hint: Check syntax. Use 'help' for examples");
+                                eprintln!("{}", i18n::text("hint.parse"));
                             }
                             _ => {}
                         }
@@ -104,15 +338,15 @@ async fn main() -> Result<()> {
                 }
             }
             Err(rustyline::error::ReadlineError::Interrupted) => {
-                println!("Use 'exit' to quit");
+                println!("{}", i18n::text("repl.interrupted"));
                 continue;
             }
             Err(rustyline::error::ReadlineError::Eof) => {
-                println!("Goodbye!");
+                println!("{}", i18n::text("repl.goodbye"));
                 break;
             }
             Err(e) => {
-                eprintln!("Input error: {}", e);
+                eprintln!("{}", i18n::message("repl.input_error", &[&e]));
                 break;
             }
         }
@@ -120,24 +354,504 @@ async fn main() -> Result<()> {
 
     // Save history
     if let Err(e) = rl.save_history("rift_history.txt") {
-        eprintln!("Warning: Could not save history: {}", e);
+        eprintln!("{}", i18n::message("repl.history_failed", &[&e]));
     }
 
+    usage::finish(Some(&env.read().await.stats));
+    if let Some(code) = exit_code {
+        std::process::exit(code);
+    }
     Ok(())
 }
 
-async fn execute_line(line: &str, env: &Arc<RwLock<Environment>>) -> Result<()> {
+// Runs a recorded transcript in a fresh Environment built from the same
+// flags, flagging each statement whose outcome differs from the recording.
+// With --mock, deploys and contract calls are simulated as with --mock-deploys
+async fn replay(path: &Path, mock: bool, context: &ContextArgs) -> Result<()> {
+    let entries = transcript::load(path)?;
+    let env = load_context(context).await;
+    {
+        let mut env_guard = env.write().await;
+        env_guard.interactive = true;
+        env_guard.mock_deploys |= mock;
+    }
+    let mut changed = 0;
+    for entry in &entries {
+        println!("rift> {}", entry.source);
+        let replayed = transcript::Entry::new(&entry.source, &execute_line(&entry.source, &env).await);
+        println!("{}", replayed.outcome());
+        if replayed != *entry {
+            changed += 1;
+            println!("{}", i18n::message("replay.recorded", &[&entry.outcome()]));
+        }
+        if let Some(code) = env.read().await.exit_code {
+            println!("{}", i18n::message("replay.exit", &[&code]));
+            break;
+        }
+    }
+    println!("{}", i18n::message("replay.summary", &[&entries.len(), &path.display(), &changed]));
+    Ok(())
+}
+
+// Replays a transcript like `replay`, capturing what each statement prints
+// (fuse programs included) into an asciinema recording. Nobody is there to
+// answer param prompts, so the session is not interactive
+async fn demo_export(transcript_path: &Path, output: &Path, context: &ContextArgs) -> Result<()> {
+    use std::io::{Read, Write};
+
+    let entries = transcript::load(transcript_path)?;
+    let env = load_context(context).await;
+    let mut cast = demo::Cast::new();
+    for entry in &entries {
+        cast.type_line(&entry.source);
+        let capture = gag::BufferRedirect::stdout()?;
+        let started = std::time::Instant::now();
+        let result = execute_line(&entry.source, &env).await;
+        let took = started.elapsed();
+        std::io::stdout().flush()?;
+        let mut printed = String::new();
+        capture.into_inner().read_to_string(&mut printed)?;
+
+        printed.push_str(&transcript::Entry::new(&entry.source, &result).outcome());
+        printed.push('\n');
+        print!("rift> {}\n{}", entry.source, printed);
+        cast.output(&printed, took);
+        if env.read().await.exit_code.is_some() {
+            break;
+        }
+    }
+    let title = transcript_path.file_stem().map_or_else(|| "rift".to_string(), |stem| stem.to_string_lossy().into_owned());
+    cast.write(output, &title)?;
+    println!("{}", i18n::message("demo.wrote", &[&output.display(), &format!("{:.0}", cast.duration())]));
+    Ok(())
+}
+
+// The process status is the script's exit(n) if it called one, otherwise 0
+// on success and 1 on error
+async fn run_script(path: &Path, env: &Arc<RwLock<Environment>>) -> ! {
+    let status = script_status(path, env).await;
+    usage::finish(Some(&env.read().await.stats));
+    std::process::exit(status)
+}
+
+async fn script_status(path: &Path, env: &Arc<RwLock<Environment>>) -> i32 {
+    let ci = env.read().await.ci;
+    env.write().await.importing.push(path.to_path_buf());
+    let before = state::load_deployments().map(|records| records.len()).unwrap_or(0);
+    let status = match git::read_source(path, None) {
+        Ok(source) => match execute_line(&source, env).await {
+            Ok(_) => 0,
+            Err(e) => {
+                eprintln!("{}", i18n::message("repl.error", &[&e]));
+                if ci == Some(ci::CiMode::Github) {
+                    println!("{}", ci::annotation("error", path, &e.to_string()));
+                }
+                1
+            }
+        },
+        Err(e) => {
+            eprintln!("{}", i18n::message("run.unreadable", &[&path.display(), &e]));
+            1
+        }
+    };
+    let env = env.read().await;
+    let status = env.exit_code.unwrap_or(status);
+    if ci == Some(ci::CiMode::Github) {
+        let recorded = state::load_deployments().map(|records| records.into_iter().skip(before).collect::<Vec<_>>()).unwrap_or_default();
+        if let Err(e) = ci::write_summary(&ci::job_summary(path, status, &env.stats, &recorded)) {
+            eprintln!("{}", i18n::message("ci.summary_failed", &[&e]));
+        }
+    }
+    status
+}
+
+async fn run_bundle(bundle: bundle::Bundle) -> ! {
+    if let Err(e) = bundle.unpack() {
+        eprintln!("{}", i18n::message("repl.error", &[&e]));
+        std::process::exit(1);
+    }
+    let env = load_context(&ContextArgs::default()).await;
+    let status = match execute_line(&bundle.source, &env).await {
+        Ok(_) => 0,
+        Err(e) => {
+            eprintln!("{}", i18n::message("run.error_in", &[&bundle.script_name, &e]));
+            1
+        }
+    };
+    std::process::exit(env.read().await.exit_code.unwrap_or(status))
+}
+
+async fn deploy_script(path: &Path, env: &Arc<RwLock<Environment>>) -> ! {
+    let before = state::load_deployments().map(|records| records.len()).unwrap_or(0);
+    let status = script_status(path, env).await;
+    match state::load_deployments() {
+        Ok(records) if records.len() > before => {
+            println!("{}", i18n::text("deploy.history"));
+            for record in &records[before..] {
+                let mock = if rift_lang::mock::is_mock(record) { " (mock)" } else { "" };
+                println!("  {}  {:<10} {}{}", record.id, record.target, record.address, mock);
+            }
+        }
+        Ok(_) => println!("{}", i18n::text("deploy.none_recorded")),
+        Err(e) => eprintln!("{}", i18n::message("deploy.history_unreadable", &[&e])),
+    }
+    usage::finish(Some(&env.read().await.stats));
+    std::process::exit(status)
+}
+
+// Each script gets a fresh Environment; one that errors or exits non-zero fails
+async fn run_tests(paths: &[PathBuf], context: &ContextArgs) -> Result<()> {
+    let roots = if paths.is_empty() { vec![PathBuf::from("tests")] } else { paths.to_vec() };
+    let mut scripts = Vec::new();
+    for root in roots {
+        if root.is_dir() {
+            let pattern = root.join("**").join("*.rift");
+            let found = glob::glob(&pattern.to_string_lossy())
+                .map_err(|e| RiftError::ConfigError(format!("{}: {}", root.display(), e)))?;
+            let mut found: Vec<_> = found.filter_map(|entry| entry.ok()).collect();
+            found.sort();
+            scripts.extend(found);
+        } else {
+            scripts.push(root);
+        }
+    }
+    if scripts.is_empty() {
+        println!("{}", i18n::text("test.none"));
+        return Ok(());
+    }
+
+    let mut failed = Vec::new();
+    for script in &scripts {
+        println!("--- {}", script.display());
+        let env = load_context(context).await;
+        if script_status(script, &env).await != 0 {
+            failed.push(script);
+        }
+    }
+    println!("\n{}", i18n::message("test.summary", &[&(scripts.len() - failed.len()), &failed.len()]));
+    if failed.is_empty() {
+        return Ok(());
+    }
+    for script in failed {
+        println!("{}", i18n::message("test.failed", &[&script.display()]));
+    }
+    std::process::exit(1)
+}
+
+// Differences from the current toolchains are reported but are not failures
+fn verify_artifact(reference: &str) -> Result<()> {
+    let (path, manifest) = state::verify_artifact(reference)?;
+    println!("{}: OK", path.display());
+    println!("  Built by rift {}{}", manifest.rift_version, match &manifest.git_commit {
+        Some(commit) => format!(" at commit {}", commit),
+        None => String::new(),
+    });
+    println!("  Source hash {}", manifest.source_hash);
+    for (lang, version) in &manifest.toolchains {
+        match executor::toolchain_version(lang) {
+            Some(current) if current != *version => println!("  {}: {} (now {})", lang, version, current),
+            _ => println!("  {}: {}", lang, version),
+        }
+    }
+    if manifest.rift_version != env!("CARGO_PKG_VERSION") {
+        println!("  Note: this is rift {}", env!("CARGO_PKG_VERSION"));
+    }
+    Ok(())
+}
+
+// Exits non-zero if any file fails to parse or validate; warnings alone pass
+async fn check_files(files: &[PathBuf], env: &Arc<RwLock<Environment>>) -> Result<()> {
+    let mut env = env.write().await;
+    let mut failed = 0;
+    for path in files {
+        // Imports are checked relative to the file, as they run
+        env.importing = vec![path.clone()];
+        let diagnostics = match std::fs::read_to_string(path).map_err(RiftError::from)
+            .and_then(|source| tokenize(&source))
+            .and_then(|tokens| parse(&tokens))
+        {
+            Ok(ast) => validate::check(&ast, &env),
+            Err(e) => vec![validate::Diagnostic { severity: validate::Severity::Error, lint: None, message: e.to_string() }],
+        };
+        if diagnostics.is_empty() {
+            println!("{}: ok", path.display());
+            continue;
+        }
+        if diagnostics.iter().any(|d| d.severity == validate::Severity::Error) {
+            failed += 1;
+        }
+        for diagnostic in diagnostics {
+            eprintln!("{}: {}", path.display(), diagnostic);
+            if env.ci == Some(ci::CiMode::Github) {
+                let level = if diagnostic.severity == validate::Severity::Error { "error" } else { "warning" };
+                println!("{}", ci::annotation(level, path, &diagnostic.message));
+            }
+        }
+    }
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn format_files(files: &[PathBuf], check: bool) -> Result<()> {
+    let mut unformatted = 0;
+    for path in files {
+        let source = std::fs::read_to_string(path)?;
+        let formatted = fmt::format_source(&source)
+            .map_err(|e| RiftError::ParseError(format!("{}: {}", path.display(), e)))?;
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", i18n::message("fmt.would_reformat", &[&path.display()]));
+            unformatted += 1;
+        } else {
+            std::fs::write(path, formatted)?;
+            println!("{}", i18n::message("fmt.formatted", &[&path.display()]));
+        }
+    }
+    if unformatted > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn print_plugins(plugins: &plugin::Plugins) {
+    if plugins.iter().next().is_none() {
+        println!("{}", i18n::message("plugin.none", &[&plugin::plugin_dir().display()]));
+        return;
+    }
+    for plugin in plugins.iter() {
+        println!("{}", plugin.name());
+        for (kind, names) in [("commands", plugin.commands()), ("builtins", plugin.builtins()), ("deploy targets", plugin.deploy_targets())] {
+            if !names.is_empty() {
+                println!("  {}: {}", kind, names.join(", "));
+            }
+        }
+    }
+}
+
+// Fails at [policy] block_severity, or at critical when that is unset
+async fn audit_deps(path: &Path, rift: Option<&str>, env: &Environment) -> Result<()> {
+    let program = parse(&tokenize(&std::fs::read_to_string(path)?)?)?;
+    let components = sbom::components(&program, rift)?;
+    let findings = audit::scan(&components).await?;
+    println!("{}", i18n::message("audit.summary", &[&components.len(), &findings.len()]));
+    for finding in &findings {
+        println!("  {:<9} {:<20} {} ({})", finding.severity, finding.id, finding.component.purl(), finding.summary);
+    }
+    let threshold = env.config.policy.block_severity.unwrap_or(audit::Severity::Critical);
+    if findings.iter().any(|finding| finding.severity >= threshold) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn secret_command(command: SecretCommand) -> Result<()> {
+    match command {
+        SecretCommand::Set { name } => {
+            secrets::set(&name, &read_secret(&name)?)?;
+            println!("{}", i18n::message("secret.stored", &[&name]));
+        }
+        SecretCommand::Get { name } => match secrets::get(&name)? {
+            Some(value) => println!("{}", value),
+            None => {
+                eprintln!("{}", i18n::message("secret.missing", &[&name]));
+                std::process::exit(1);
+            }
+        },
+        SecretCommand::Rm { name } => {
+            if !secrets::remove(&name)? {
+                eprintln!("{}", i18n::message("secret.missing", &[&name]));
+                std::process::exit(1);
+            }
+            println!("{}", i18n::message("secret.removed", &[&name]));
+        }
+    }
+    Ok(())
+}
+
+fn stats_command(command: StatsCommand) -> Result<()> {
+    let path = usage::path();
+    match command {
+        StatsCommand::Report { months } => {
+            print!("{}", usage::report(&usage::load(&path)?, months));
+            if !usage::enabled(&rift_lang::config::Config::load()?) {
+                println!("{}", i18n::message("usage.off", &[&usage::VAR, &path.display()]));
+            }
+        }
+        StatsCommand::Reset => match std::fs::remove_file(&path) {
+            Ok(()) => println!("{}", i18n::message("usage.removed", &[&path.display()])),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => println!("{}", i18n::text("usage.none")),
+            Err(e) => return Err(e.into()),
+        },
+    }
+    Ok(())
+}
+
+// Never from the command line, where it would land in shell history: a
+// prompt without echo, or the first line of piped input
+fn read_secret(name: &str) -> Result<String> {
+    use std::io::IsTerminal;
+    let value = if std::io::stdin().is_terminal() {
+        rpassword::prompt_password(format!("Value for {}: ", name))?
+    } else {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        line.trim_end_matches(['\r', '\n']).to_string()
+    };
+    if value.is_empty() {
+        return Err(RiftError::SecretError(format!("no value given for {}", name)));
+    }
+    Ok(value)
+}
+
+// Fails when any credential is missing, rejected or expired
+async fn auth_check(target: Option<&str>, profile: Option<&str>, env: &Environment) -> Result<()> {
+    let findings = auth::check(target, profile, &env.config).await?;
+    for finding in &findings {
+        let (status, detail) = match &finding.result {
+            Ok(detail) => ("ok", detail),
+            Err(problem) => ("FAILED", problem),
+        };
+        println!("  {:<7} {:<10} {:<20} {}", status, finding.target, finding.credential, detail);
+    }
+    let failed = findings.iter().filter(|finding| finding.result.is_err()).count();
+    println!("{}", i18n::message("auth.summary", &[&findings.len(), &failed]));
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// Fails when any license matches [policy] denied_licenses
+async fn list_licenses(path: &Path, rift: Option<&str>, env: &Environment) -> Result<()> {
+    let program = parse(&tokenize(&std::fs::read_to_string(path)?)?)?;
+    let report = licenses::collect(&sbom::components(&program, rift)?, &env.config.policy).await?;
+    for info in &report {
+        let licenses = if info.licenses.is_empty() { "unknown".to_string() } else { info.licenses.join(", ") };
+        let flag = if info.denied.is_empty() { String::new() } else { format!("  DENIED ({})", info.denied.join(", ")) };
+        println!("  {:<40} {}{}", info.component.purl(), licenses, flag);
+    }
+    let denied = report.iter().filter(|info| !info.denied.is_empty()).count();
+    println!("{}", i18n::message("licenses.summary", &[&report.len(), &denied]));
+    if denied > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn install_package(spec: &str, registry: Option<&str>) -> Result<()> {
+    let index = registry::index_location(registry, &rift_lang::config::Config::load()?)?;
+    let installed = registry::install(spec, &index).await?;
+    println!("{}", i18n::message("registry.installed", &[&installed.name, &installed.version, &&installed.sha256[..12]]));
+    if !installed.plugins.is_empty() {
+        println!("{}", i18n::message("registry.plugins", &[&installed.plugins.join(", ")]));
+    }
+    println!("{}", i18n::message("registry.usage", &[&installed.name]));
+    Ok(())
+}
+
+async fn publish_package(dir: &Path, registry: Option<&str>, dry_run: bool) -> Result<()> {
+    let package = registry::pack(dir)?;
+    let manifest = &package.manifest;
+    println!("{}", i18n::message("registry.packed", &[&manifest.name, &manifest.version, &package.files.len(), &package.archive.len(), &&package.sha256[..12]]));
+    if dry_run {
+        return Ok(());
+    }
+    let index = registry::index_location(registry, &rift_lang::config::Config::load()?)?;
+    let location = registry::publish(&package, &index).await?;
+    println!("{}", i18n::message("registry.published", &[&manifest.name, &manifest.version, &location]));
+    Ok(())
+}
+
+fn write_sbom(path: &Path, rift: Option<&str>, format: &str, output: Option<&Path>) -> Result<()> {
+    let program = parse(&tokenize(&std::fs::read_to_string(path)?)?)?;
+    let components = sbom::components(&program, rift)?;
+    let name = rift.map(str::to_string)
+        .unwrap_or_else(|| path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default());
+    let document = match format {
+        "spdx" => sbom::spdx(&name, &components),
+        _ => sbom::cyclonedx(&name, &components),
+    };
+    let text = serde_json::to_string_pretty(&document).map_err(|e| RiftError::ConfigError(e.to_string()))?;
+    match output {
+        Some(output) => {
+            std::fs::write(output, text + "\n")?;
+            println!("{}", i18n::message("sbom.wrote", &[&output.display(), &components.len()]));
+        }
+        None => println!("{}", text),
+    }
+    Ok(())
+}
+
+// The only --emit so far is rust-project
+fn compile_file(path: &Path, out: Option<PathBuf>) -> Result<()> {
+    let source = std::fs::read_to_string(path)?;
+    let program = parse(&tokenize(&source)?)?;
+    let name = path.file_stem().map(|s| s.to_string_lossy().replace(|c: char| !c.is_ascii_alphanumeric(), "-"))
+        .unwrap_or_else(|| "rift-app".to_string());
+    let files = codegen::rust_project(&program, &name, &path.display().to_string())
+        .map_err(|e| RiftError::ParseError(format!("{}: {}", path.display(), e)))?;
+    let out = out.unwrap_or_else(|| PathBuf::from(&name));
+    for (file, contents) in files {
+        let target = out.join(file);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&target, contents)?;
+    }
+    println!("{}", i18n::message("compile.wrote", &[&out.display(), &out.join("Cargo.toml").display()]));
+    Ok(())
+}
+
+fn migrate_file(path: &Path, write: bool) -> Result<()> {
+    let source = std::fs::read_to_string(path)?;
+    let (migrated, notes) = migrate::migrate(&source)
+        .map_err(|e| RiftError::ParseError(format!("{}: {}", path.display(), e)))?;
+    if migrated == source {
+        println!("{}", i18n::message("migrate.current", &[&path.display()]));
+        return Ok(());
+    }
+    for note in &notes {
+        println!("{}", note);
+    }
+    print!("--- {}\n+++ {} (migrated)\n{}", path.display(), path.display(), migrate::diff(&source, &migrated));
+    if write {
+        std::fs::write(path, migrated)?;
+        println!("{}", i18n::message("migrate.written", &[&path.display()]));
+    } else {
+        println!("{}", i18n::text("migrate.dry_run"));
+    }
+    Ok(())
+}
+
+async fn execute_line(line: &str, env: &Arc<RwLock<Environment>>) -> Result<Value> {
     let tokens = tokenize(line)?;
     let ast = parse(&tokens)?;
     
     let env_clone = Arc::clone(env);
     let result = task::spawn(async move {
         let mut env_guard = env_clone.write().await;
-        interpret(&ast, &mut env_guard).await
+        // The REPL prompts for a missing param instead
+        let checked = match env_guard.interactive {
+            true => Ok(()),
+            false => interpreter::check_params(&ast, &env_guard),
+        };
+        let result = match checked {
+            Ok(()) => interpret(&ast, &mut env_guard).await,
+            Err(e) => Err(e),
+        };
+        // exit(n) unwinds as an error, but it is not a failure
+        if env_guard.exit_code.is_some() {
+            return Ok(Value::Null);
+        }
+        result
     }).await;
     
     match result {
-        Ok(Ok(_)) => Ok(()),
+        Ok(Ok(value)) => Ok(value),
         Ok(Err(e)) => Err(e),
         Err(e) => Err(RiftError::ExecutionError {
             language: "runtime".to_string(),
@@ -147,59 +861,9 @@ async fn execute_line(line: &str, env: &Arc<RwLock<Environment>>) -> Result<()>
 }
 
 fn print_help() {
-    println!(r#"
-Rift v2.0.1 Commands:
-
-Basic Commands:
-  @rift name {{ ... }}           - Create a new rift (project)
-  @fuse "lang" {{ "code" }}      - Add code in specified language
-  @task name {{ ... }}           - Create a transformation task
-  @target "lang"                 - Set target language for transformation
-  @deploy "target" {{ ... }}     - Deploy to specified target
-  call name;                     - Execute a rift or task
-  let var = value;               - Set a variable
-
-Flow Control:
-  if condition {{ ... }}         - Conditional execution
-  while condition {{ ... }}      - Loop execution
-
-Utility Commands:
-  help                           - Show this help
-  status                         - Show environment status
-  clear                          - Clear all rifts and variables
-  exit/quit                      - Exit Rift
-
-Example Usage:
-  @rift hello {{ @fuse "python" {{ "print('Hello, World!')" }} }}
-  call hello;
-  
-  @task optimize {{ @target "rust" call optimize with hello; }}
-  call optimize;
-
-Supported Languages:
-  python, javascript, go, java, cpp, php, rust
-
-Deployment Targets:
-  local, ethereum, solana, aws
-"#);
+    println!("{}", i18n::help());
 }
 
 fn print_status(env: &Environment) {
-    println!("Environment Status:");
-    println!("  Rifts: {}", env.rifts.len());
-    println!("  Tasks: {}", env.tasks.len());
-    println!("  Variables: {}", env.variables.len());
-    println!("  Cache entries: {}", env.artifact_cache.len());
-    
-    if let Some(target) = &env.target_lang {
-        println!("  Target language: {}", target);
-    }
-    
-    if !env.rifts.is_empty() {
-        println!("  Available rifts: {}", env.rifts.keys().collect::<Vec<_>>().join(", "));
-    }
-    
-    if !env.tasks.is_empty() {
-        println!("  Available tasks: {}", env.tasks.keys().collect::<Vec<_>>().join(", "));
-    }
-}
\ No newline at end of file
+    print!("{}", status_report(env));
+}
@@ -0,0 +1,276 @@
+// Rift metadata, written `@rift name meta { description: "...", author: "...",
+// requires: ["python>=3.10", "docker"] } { ... }`. The parser keeps it as an
+// AST::Meta opening the rift's body, where `describe` finds it however the
+// rift was defined. Three keys are needs, checked by `preflight` before a
+// call starts: requires (toolchains and programs), env (variables that must
+// be set) and network (host, host:port or URL that must accept connections).
+use crate::interpreter::Environment;
+use crate::{executor, host, AST};
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+const NEEDS: [&str; 3] = ["requires", "env", "network"];
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Needs already found met, so a call in a loop doesn't probe again
+static MET: OnceLock<Mutex<BTreeSet<(String, String)>>> = OnceLock::new();
+
+// The metadata entries of a rift body, if it has any
+pub fn of(body: &[AST]) -> &[(String, AST)] {
+    match body.first() {
+        Some(AST::Meta(entries)) => entries,
+        _ => &[],
+    }
+}
+
+// Fails with everything missing for a call of `name`: its own needs and
+// those of every rift, task and fn it calls, in one report
+pub fn preflight(name: &str, env: &Environment) -> Result<(), String> {
+    let missing = missing(name, env);
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(format!("Cannot run '{}'; missing:\n  {}", name, missing.join("\n  ")))
+}
+
+// One line per unmet need, like "env: AWS_REGION is not set"
+pub fn missing(name: &str, env: &Environment) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    let mut needs = BTreeSet::new();
+    collect(name, env, &mut seen, &mut needs);
+    needs.into_iter().filter_map(|(kind, value)| check_need(&kind, &value).err().map(|problem| format!("{}: {}", kind, problem))).collect()
+}
+
+fn collect(name: &str, env: &Environment, seen: &mut BTreeSet<String>, needs: &mut BTreeSet<(String, String)>) {
+    if !seen.insert(name.to_string()) {
+        return;
+    }
+    let Some(body) = env.rifts.get(name).or_else(|| env.tasks.get(name)).or_else(|| env.functions.get(name).map(|(_, body)| body)) else {
+        return;
+    };
+    for (key, value) in of(body).iter().filter(|(key, _)| NEEDS.contains(&key.as_str())) {
+        needs.extend(strings(value).into_iter().map(|item| (key.clone(), item)));
+    }
+    let mut nodes = Vec::new();
+    for node in body {
+        crate::validate::walk(node, &mut nodes);
+    }
+    for node in nodes {
+        if let AST::Call(callee, _) = node {
+            collect(callee, env, seen, needs);
+        }
+    }
+}
+
+// What was found when the need is met, otherwise what is wrong
+fn check_need(kind: &str, value: &str) -> Result<String, String> {
+    let met = MET.get_or_init(Default::default);
+    let key = (kind.to_string(), value.to_string());
+    if met.lock().unwrap().contains(&key) {
+        return Ok("ok".to_string());
+    }
+    let found = match kind {
+        "requires" => check(value),
+        "env" => match std::env::var(value) {
+            Ok(set) if !set.is_empty() => Ok("set".to_string()),
+            _ => Err(format!("{} is not set", value)),
+        },
+        _ => reachable(value),
+    }?;
+    met.lock().unwrap().insert(key);
+    Ok(found)
+}
+
+// `host`, `host:port` or a URL; without a port, 80 for http:// and 443 otherwise
+fn reachable(target: &str) -> Result<String, String> {
+    let (default_port, rest) = match target.split_once("://") {
+        Some(("http", rest)) => (80, rest),
+        Some((_, rest)) => (443, rest),
+        None => (443, target),
+    };
+    let authority = rest.split('/').next().unwrap_or(rest);
+    let address = if authority.contains(':') { authority.to_string() } else { format!("{}:{}", authority, default_port) };
+    let addresses = address.to_socket_addrs().map_err(|e| format!("{} cannot be resolved ({})", address, e))?;
+    let mut last_error = format!("{} has no addresses", address);
+    for socket in addresses {
+        match TcpStream::connect_timeout(&socket, CONNECT_TIMEOUT) {
+            Ok(_) => return Ok(format!("reached {}", address)),
+            Err(e) => last_error = format!("{} is unreachable ({})", address, e),
+        }
+    }
+    Err(last_error)
+}
+
+// `tool`, or `tool OP version` with OP one of >= <= > < == =; the parser
+// rejects anything else
+pub fn parse_requirement(spec: &str) -> Result<(&str, Option<(&str, &str)>), String> {
+    let (tool, constraint) = match spec.find(['<', '>', '=']) {
+        Some(at) => {
+            let rest = &spec[at..];
+            let (op, version) = rest.split_at(if rest[1..].starts_with('=') { 2 } else { 1 });
+            (spec[..at].trim(), Some((op, version.trim())))
+        }
+        None => (spec.trim(), None),
+    };
+    let tool_ok = !tool.is_empty() && tool.chars().all(|c| c.is_alphanumeric() || "-_.+".contains(c));
+    let version_ok = constraint.map_or(true, |(_, version)| !version.is_empty() && !version.starts_with(['<', '>', '=']));
+    if !tool_ok || !version_ok {
+        return Err(format!("'{}' is not like \"python>=3.10\" or \"docker\"", spec));
+    }
+    Ok((tool, constraint))
+}
+
+// The version line of what was found when the requirement is met. A
+// language name checks its toolchain; anything else is looked for on PATH.
+// Versions compare on as many parts as the requirement gives, so 3.10.4
+// satisfies ==3.10.
+pub fn check(spec: &str) -> Result<String, String> {
+    let (tool, constraint) = parse_requirement(spec)?;
+    let line = installed(tool).ok_or_else(|| format!("{} is not installed", tool))?;
+    if let Some((op, wanted)) = constraint {
+        let found = Regex::new(r"\d+(\.\d+)*").unwrap().find(&line).map(|m| m.as_str().to_string())
+            .ok_or_else(|| format!("cannot tell the version of {} from '{}'", tool, line))?;
+        let ordering = compare(&found, wanted);
+        let ok = match op {
+            ">=" => ordering.is_ge(),
+            "<=" => ordering.is_le(),
+            ">" => ordering.is_gt(),
+            "<" => ordering.is_lt(),
+            _ => ordering.is_eq(),
+        };
+        if !ok {
+            return Err(format!("found {} {}", tool, found));
+        }
+    }
+    Ok(line)
+}
+
+// What `describe` prints: the description, the other metadata, whether each
+// of the rift's own needs is met and the languages its fuse blocks use
+pub fn describe(name: &str, env: &Environment) -> Result<String, String> {
+    let body = env.rifts.get(name).ok_or_else(|| format!("No rift named '{}'", name))?;
+    let entries = of(body);
+    let mut out = format!("rift {}\n", name);
+    for (key, value) in entries {
+        let _ = match key.as_str() {
+            "description" => writeln!(out, "  {}", text(value)),
+            need if NEEDS.contains(&need) => Ok(()),
+            _ => writeln!(out, "  {}: {}", key, text(value)),
+        };
+    }
+    for (key, value) in entries.iter().filter(|(key, _)| NEEDS.contains(&key.as_str())) {
+        for item in strings(value) {
+            let _ = match check_need(key, &item) {
+                Ok(found) => writeln!(out, "  {} {}: ok, {}", key, item, found),
+                Err(problem) => writeln!(out, "  {} {}: NOT MET, {}", key, item, problem),
+            };
+        }
+    }
+    let mut nodes = Vec::new();
+    for node in body {
+        crate::validate::walk(node, &mut nodes);
+    }
+    let mut languages: Vec<&str> = nodes.iter().filter_map(|node| match node {
+        AST::Fuse(lang, _) | AST::InteractiveFuse(lang, _) | AST::RemoteFuse(_, lang, _) => Some(lang.as_str()),
+        _ => None,
+    }).collect();
+    languages.sort();
+    languages.dedup();
+    if !languages.is_empty() {
+        writeln!(out, "  languages: {}", languages.join(", ")).ok();
+    }
+    Ok(out)
+}
+
+// The parser only lets strings and lists of strings through
+fn strings(value: &AST) -> Vec<String> {
+    match value {
+        AST::String(text) => vec![text.clone()],
+        AST::List(items) => items.iter().flat_map(strings).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn text(value: &AST) -> String {
+    strings(value).join(", ")
+}
+
+fn installed(tool: &str) -> Option<String> {
+    if let Ok(executor) = executor::executor_for(tool) {
+        return host::fact(&format!("toolchains.{}", executor.language())).ok();
+    }
+    if !host::has(tool) {
+        return None;
+    }
+    let output = std::process::Command::new(tool).arg("--version").output().ok()?;
+    let text = if output.stdout.is_empty() { output.stderr } else { output.stdout };
+    Some(String::from_utf8_lossy(&text).lines().next().unwrap_or(tool).trim().to_string())
+}
+
+fn compare(found: &str, wanted: &str) -> Ordering {
+    let parts = |version: &str| version.split('.').map(|part| part.parse::<u64>().unwrap_or(0)).collect::<Vec<_>>();
+    let wanted = parts(wanted);
+    let mut found = parts(found);
+    found.resize(wanted.len(), 0);
+    found.cmp(&wanted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_requirement() {
+        assert_eq!(parse_requirement("python>=3.10"), Ok(("python", Some((">=", "3.10")))));
+        assert_eq!(parse_requirement(" docker "), Ok(("docker", None)));
+        assert_eq!(parse_requirement("go = 1.21"), Ok(("go", Some(("=", "1.21")))));
+        assert!(parse_requirement(">=3").is_err());
+        assert!(parse_requirement("node>=").is_err());
+        assert!(parse_requirement("node=>18").is_err());
+        assert!(parse_requirement("node!=18").is_err());
+        assert!(parse_requirement("python 3").is_err());
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(compare("3.11.4", "3.10"), Ordering::Greater);
+        assert_eq!(compare("3.10.12", "3.10"), Ordering::Equal);
+        assert_eq!(compare("3.9", "3.10"), Ordering::Less);
+        assert_eq!(compare("20", "18.2.1"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_missing() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let open = listener.local_addr().unwrap().to_string();
+        let source = format!(
+            "@rift site meta {{ requires: \"surely-not-a-real-tool-rift\" env: [\"RIFT_TEST_UNSET_VAR\", \"PATH\"] }} {{ call deps; }}
+             @task deps {{ call helper; call site; }}
+             fn helper() {{ }}
+             @rift ok meta {{ network: \"http://{}/health\" }} {{ }}", open);
+        let mut env = Environment::new();
+        if let AST::Program(nodes) = crate::parser::parse(&crate::lexer::tokenize(&source).unwrap()).unwrap() {
+            for node in nodes {
+                match node {
+                    AST::Rift(name, body) => env.rifts.insert(name, body),
+                    AST::Task(name, body) => env.tasks.insert(name, body),
+                    AST::Function(name, params, body) => env.functions.insert(name, (params, body)).map(|(_, body)| body),
+                    _ => None,
+                };
+            }
+        }
+        assert_eq!(missing("site", &env), [
+            "env: RIFT_TEST_UNSET_VAR is not set",
+            "requires: surely-not-a-real-tool-rift is not installed",
+        ]);
+        assert!(preflight("site", &env).unwrap_err().contains("missing:\n  env: RIFT_TEST_UNSET_VAR"));
+        assert!(preflight("ok", &env).is_ok());
+        assert!(missing("deps", &env).len() == 2 && missing("helper", &env).is_empty());
+        drop(listener);
+    }
+}
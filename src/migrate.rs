@@ -0,0 +1,150 @@
+use crate::error::Result;
+use crate::fmt::format_tokens;
+use crate::lexer::{tokenize, Token, TokenKind};
+use crate::parser::{parse_version, FUSE_INTERPOLATION_VERSION, LANGUAGE_VERSION};
+
+// Rewrites older syntax into the current form and lays the script out with
+// the formatter. Returns the new source and a note for each rewrite.
+//
+// Covered so far: version pragmas older than the current version (`#!rift 1`,
+// `@version "2.0"`), the "js" language alias, and `${` in inline fuse code,
+// which is escaped as `\${` for scripts written before fuse code was
+// interpolated. Such a script without a pragma gets an `@version` so the
+// escapes mean what they say.
+pub fn migrate(source: &str) -> Result<(String, Vec<String>)> {
+    let mut tokens = tokenize_unchecked(&source.replace("\r\n", "\n"))?;
+    let current = format!("{}.{}", LANGUAGE_VERSION.0, LANGUAGE_VERSION.1);
+    let mut notes = Vec::new();
+    let declared = declared_version(&tokens);
+    let escape_fuse = !declared.is_some_and(|version| version >= FUSE_INTERPOLATION_VERSION);
+    let (mut in_fuse, mut escaped) = (false, false);
+
+    for i in 0..tokens.len() {
+        let prev = i.checked_sub(1).map(|p| tokens[p].clone());
+        let token = &mut tokens[i];
+        let after = |keyword: &str| prev.as_ref().is_some_and(|p| p.kind == TokenKind::Keyword && p.value == keyword);
+        let after_symbol = |symbol: &str| prev.as_ref().is_some_and(|p| p.kind == TokenKind::Symbol && p.value == symbol);
+        match token.kind {
+            TokenKind::Comment if i == 0 && is_old_version(token.value.strip_prefix("#!rift").map(str::trim)) => {
+                notes.push(format!("line {}: {} -> #!rift {}", token.line, token.value, current));
+                token.value = format!("#!rift {}", current);
+            }
+            TokenKind::String if after("@version") && is_old_version(Some(&token.value)) => {
+                notes.push(format!("line {}: @version \"{}\" -> @version \"{}\"", token.line, token.value, current));
+                token.value = current.clone();
+            }
+            TokenKind::String if token.value == "js" && (after("@fuse") || after("@target") || after("interactive")) => {
+                notes.push(format!("line {}: \"js\" -> \"javascript\"", token.line));
+                token.value = "javascript".to_string();
+            }
+            TokenKind::Keyword if token.value == "@fuse" => in_fuse = true,
+            // Code read from a file was never interpolated
+            TokenKind::Identifier if token.value == "from" => in_fuse = false,
+            // The code string, straight after `{` or `requires gpu;`
+            TokenKind::String if in_fuse && (after_symbol("{") || after_symbol(";")) => {
+                in_fuse = false;
+                if escape_fuse && token.value.contains("${") {
+                    notes.push(format!("line {}: ${{ -> \\${{ in fuse code", token.line));
+                    token.value = crate::interpolate::escape(&token.value);
+                    escaped = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    if escaped && declared.is_none() {
+        // After a shebang, which has to stay on the first line
+        let shebang = tokens.first().filter(|t| t.kind == TokenKind::Comment && t.value.starts_with("#!")).map(|t| t.line);
+        let (at, line) = match shebang {
+            Some(line) => (1, line),
+            None => (0, 0),
+        };
+        notes.push(format!("added @version \"{}\"", current));
+        tokens.insert(at, Token { kind: TokenKind::String, value: current.clone(), line, column: 10 });
+        tokens.insert(at, Token { kind: TokenKind::Keyword, value: "@version".to_string(), line, column: 1 });
+    }
+    Ok((format_tokens(&tokens)?, notes))
+}
+
+fn is_old_version(version: Option<&str>) -> bool {
+    version.and_then(parse_version).is_some_and(|version| version < LANGUAGE_VERSION)
+}
+
+// The version the script's pragmas ask for, if any
+fn declared_version(tokens: &[Token]) -> Option<(u32, u32)> {
+    tokens.iter().enumerate().find_map(|(i, token)| match token.kind {
+        TokenKind::Comment if i == 0 => token.value.strip_prefix("#!rift").and_then(|v| parse_version(v.trim())),
+        TokenKind::Keyword if token.value == "@version" => tokens.get(i + 1).and_then(|next| parse_version(&next.value)),
+        _ => None,
+    })
+}
+
+// A `#!rift 1` pragma makes tokenize bail out on the first character it
+// does not know; the pragma is exactly what is being migrated, so lex the
+// rest with it neutralised and put it back afterwards.
+fn tokenize_unchecked(source: &str) -> Result<Vec<Token>> {
+    let first_line = source.lines().next().unwrap_or_default();
+    if !(first_line.starts_with("#!rift") && is_old_version(first_line.strip_prefix("#!rift").map(str::trim))) {
+        return tokenize(source);
+    }
+    let mut tokens = tokenize(&source.replacen(first_line, "#!", 1))?;
+    tokens[0].value = first_line.to_string();
+    Ok(tokens)
+}
+
+// Changed lines as `-`/`+` pairs with line numbers, from a longest common
+// subsequence of lines; scripts are small enough for the quadratic table
+pub fn diff(old: &str, new: &str) -> String {
+    let (a, b): (Vec<_>, Vec<_>) = (old.lines().collect(), new.lines().collect());
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push_str(&format!("-{:>4} | {}\n", i + 1, a[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("+{:>4} | {}\n", j + 1, b[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate() {
+        let (migrated, notes) = migrate("#!rift 1\n@fuse \"js\" { \"console.log(1)\" }\n").unwrap();
+        assert_eq!(migrated, "#!rift 2.1\n@fuse \"javascript\" { \"console.log(1)\" }\n");
+        assert_eq!(notes.len(), 2);
+    }
+
+    #[test]
+    fn test_migrate_fuse_interpolation() {
+        // Only fuse code is escaped; rift strings were always interpolated
+        let source = "#!/usr/bin/env rift\n@fuse \"javascript\" { \"console.log(`${x}`)\" }\nprint(\"${y}\");\n@fuse \"python\" { requires gpu; \"print('\\${HOME}')\" }\n";
+        let (migrated, notes) = migrate(source).unwrap();
+        assert_eq!(migrated, "#!/usr/bin/env rift\n@version \"2.1\"\n@fuse \"javascript\" { \"console.log(`\\\\${x}`)\" }\nprint(\"${y}\");\n@fuse \"python\" { requires gpu; \"print('\\\\\\\\${HOME}')\" }\n");
+        assert_eq!(notes.len(), 3);
+        // Already at the version that interpolates: nothing to do
+        let current = "@version \"2.1\"\n@fuse \"javascript\" { \"console.log(`${x}`)\" }\n";
+        assert_eq!(migrate(current).unwrap(), (current.to_string(), Vec::new()));
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("a\nb\nc\n", "a\nx\nc\n"), "-   2 | b\n+   2 | x\n");
+        assert_eq!(diff("same\n", "same\n"), "");
+    }
+}
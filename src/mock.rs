@@ -0,0 +1,101 @@
+// Simulated deploy backends for `--mock-deploys`. Configs are checked as for a
+// real deploy, but nothing is contacted or written outside .rift/; addresses
+// that a real backend would get from the network are made up from the
+// artifact hash, so the same script always "deploys" to the same place.
+use crate::{deployer, i18n};
+use crate::state::{self, DeploymentRecord};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+// Config key recorded with simulated deployments, so a real deploy of the
+// same artifact is never skipped as "no changes"
+pub const MARKER: &str = "mock";
+
+pub fn mark(config: &HashMap<String, String>) -> HashMap<String, String> {
+    let mut config = config.clone();
+    config.insert(MARKER.to_string(), "true".to_string());
+    config
+}
+
+pub fn is_mock(record: &DeploymentRecord) -> bool {
+    record.config.get(MARKER).is_some_and(|v| v == "true")
+}
+
+pub fn deploy(target: &str, artifact: &str, config: &HashMap<String, String>) -> Result<String, String> {
+    if deployer::is_target(target) {
+        let problems = deployer::validate_config(target, config);
+        if !problems.is_empty() {
+            return Err(format!("{}: {}", target, problems.join("; ")));
+        }
+    }
+    let seed = Sha256::digest(format!("{}:{}", target, state::artifact_hash(artifact)).as_bytes());
+    let value = |key: &str, default: &str| config.get(key).cloned().unwrap_or_else(|| default.to_string());
+    let address = match target {
+        "ethereum" => value("contract", ""),
+        "solana" => value("program_id", ""),
+        "aws" => value("function", ""),
+        "systemd" => value("unit_name", ""),
+        "kubernetes" => value("deployment", ""),
+        "ipfs" => cid(&Sha256::digest(artifact.as_bytes())),
+        "terraform" => value("dir", "terraform"),
+        "static" => format!("{}://{}/{}", value("provider", "s3"), value("bucket", ""), value("prefix", "").trim_matches('/')),
+        "local" => format!("rift_power_{}", chrono::Utc::now().timestamp()),
+        _ => format!("mock-{}-{}", target, &hex(&seed)[..12]),
+    };
+    match target {
+        "ethereum" | "solana" => println!("{}", i18n::message("mock.deployed_in", &[&target, &address, &hex(&seed)])),
+        _ => println!("{}", i18n::message("mock.deployed", &[&target, &address])),
+    }
+    Ok(address)
+}
+
+// Contract calls return a made-up transaction hash
+pub fn invoke(target: &str, config: &HashMap<String, String>) -> Result<String, String> {
+    let method = config.get("method").ok_or_else(|| format!("{}: missing method", target))?;
+    let mut entries: Vec<_> = config.iter().collect();
+    entries.sort();
+    let seed = Sha256::digest(format!("{}:{:?}", target, entries).as_bytes());
+    println!("{}", i18n::message("mock.called", &[&method, &target]));
+    Ok(format!("0x{}", hex(&seed)))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// A CIDv1 for raw bytes with a sha2-256 multihash, in base32 ("bafkrei...")
+fn cid(digest: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+    let mut bytes = vec![0x01, 0x55, 0x12, 0x20];
+    bytes.extend_from_slice(digest);
+    let mut out = String::from("b");
+    let (mut buffer, mut bits) = (0u32, 0);
+    for byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deploy() {
+        let config = HashMap::from([("unit_name".to_string(), "api".to_string())]);
+        assert_eq!(deploy("systemd", "artifact", &config).unwrap(), "api");
+        assert!(deploy("systemd", "artifact", &HashMap::new()).unwrap_err().contains("unit_name"));
+
+        let cid = deploy("ipfs", "artifact", &HashMap::new()).unwrap();
+        assert!(cid.starts_with("bafkrei") && cid.len() == 59);
+        assert_eq!(deploy("ipfs", "artifact", &HashMap::new()).unwrap(), cid);
+    }
+}
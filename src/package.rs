@@ -0,0 +1,177 @@
+use crate::state;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+const PACKAGES_DIR: &str = "packages";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Zip,
+    TarGz,
+    Zstd,
+}
+
+impl Compression {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "none" => Ok(Compression::None),
+            "zip" => Ok(Compression::Zip),
+            "tar.gz" | "tgz" => Ok(Compression::TarGz),
+            "zstd" | "zst" => Ok(Compression::Zstd),
+            _ => Err(format!("Unknown compression '{}'; expected none, zip, tar.gz or zstd", name)),
+        }
+    }
+
+    // `compression = "..."` in the deploy config, else what the target
+    // needs. Lambda only takes zip packages, so aws can't choose another.
+    pub fn for_target(target: &str, config: &HashMap<String, String>) -> Result<Self, String> {
+        let chosen = config.get("compression").map(String::as_str).map(Compression::parse).transpose()?;
+        match (target, chosen) {
+            ("aws", Some(other)) if other != Compression::Zip => Err("aws deploys must use zip compression".to_string()),
+            ("aws", _) => Ok(Compression::Zip),
+            (_, chosen) => Ok(chosen.unwrap_or(Compression::None)),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Compression::None => "",
+            Compression::Zip => ".zip",
+            Compression::TarGz => ".tar.gz",
+            Compression::Zstd => ".zst",
+        }
+    }
+}
+
+// Name of the artifact inside zip and tar packages; Lambda's custom
+// runtimes start the file called bootstrap
+fn entry_name<'a>(target: &str, config: &'a HashMap<String, String>) -> &'a str {
+    match config.get("entry") {
+        Some(entry) => entry,
+        None if target == "aws" => "bootstrap",
+        None => "artifact",
+    }
+}
+
+// The artifact packaged the way `target` wants it, under .rift/packages.
+// Packages are keyed by content, so deploying the same artifact twice (or
+// measuring it for max_size first) compresses it once. None when the target
+// takes the artifact as it is.
+pub fn ensure(artifact: &str, target: &str, config: &HashMap<String, String>) -> Result<Option<PathBuf>, String> {
+    let compression = Compression::for_target(target, config)?;
+    if compression == Compression::None {
+        return Ok(None);
+    }
+    let entry = entry_name(target, config);
+    let mut hasher = Sha256::new();
+    hasher.update(entry.as_bytes());
+    hasher.update([0]);
+    hasher.update(artifact.as_bytes());
+    let dir = state::state_dir().join(PACKAGES_DIR);
+    let path = dir.join(format!("{:x}{}", hasher.finalize(), compression.extension()));
+    if path.exists() {
+        return Ok(Some(path));
+    }
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    // Written beside the final name first so a failed run never leaves a
+    // truncated package that later deploys would reuse
+    let partial = path.with_extension("partial");
+    let file = fs::File::create(&partial).map_err(|e| format!("Cannot create {}: {}", partial.display(), e))?;
+    write_package(artifact.as_bytes(), compression, entry, file)
+        .map_err(|e| format!("Packaging failed: {}", e))?;
+    fs::rename(&partial, &path).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    Ok(Some(path))
+}
+
+// Bytes the target will receive: the package size, or the artifact's own
+pub fn packaged_size(artifact: &str, target: &str, config: &HashMap<String, String>) -> Result<u64, String> {
+    match ensure(artifact, target, config)? {
+        Some(path) => fs::metadata(&path).map(|m| m.len()).map_err(|e| format!("Cannot read {}: {}", path.display(), e)),
+        None => Ok(artifact.len() as u64),
+    }
+}
+
+// Encoders write straight to the file through a fixed buffer, so memory
+// use does not grow with the compressed size
+fn write_package(mut data: &[u8], compression: Compression, entry: &str, file: fs::File) -> io::Result<()> {
+    match compression {
+        Compression::None => {
+            let mut out = BufWriter::new(file);
+            io::copy(&mut data, &mut out)?;
+            out.flush()
+        }
+        Compression::Zip => {
+            let mut zip = zip::ZipWriter::new(file);
+            let options = zip::write::FileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated)
+                .unix_permissions(0o755);
+            zip.start_file(entry, options)?;
+            io::copy(&mut data, &mut zip)?;
+            zip.finish()?;
+            Ok(())
+        }
+        Compression::TarGz => {
+            let gz = flate2::write::GzEncoder::new(BufWriter::new(file), flate2::Compression::default());
+            let mut tar = tar::Builder::new(gz);
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o755);
+            header.set_mtime(0);
+            header.set_cksum();
+            tar.append_data(&mut header, entry, data)?;
+            tar.into_inner()?.finish()?.flush()
+        }
+        Compression::Zstd => {
+            let mut zstd = zstd::stream::Encoder::new(BufWriter::new(file), 0)?;
+            io::copy(&mut data, &mut zstd)?;
+            zstd.finish()?.flush()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_round_trips() {
+        let dir = std::env::temp_dir().join(format!("rift_package_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let artifact = "python: print('hi')\n".repeat(100);
+        for compression in [Compression::Zip, Compression::TarGz, Compression::Zstd] {
+            let path = dir.join(format!("a{}", compression.extension()));
+            write_package(artifact.as_bytes(), compression, "artifact", fs::File::create(&path).unwrap()).unwrap();
+            let mut unpacked = String::new();
+            match compression {
+                Compression::Zip => {
+                    let mut zip = zip::ZipArchive::new(fs::File::open(&path).unwrap()).unwrap();
+                    zip.by_name("artifact").unwrap().read_to_string(&mut unpacked).unwrap();
+                }
+                Compression::TarGz => {
+                    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(fs::File::open(&path).unwrap()));
+                    tar.entries().unwrap().next().unwrap().unwrap().read_to_string(&mut unpacked).unwrap();
+                }
+                _ => {
+                    zstd::stream::Decoder::new(fs::File::open(&path).unwrap()).unwrap().read_to_string(&mut unpacked).unwrap();
+                }
+            }
+            assert_eq!(unpacked, artifact);
+            assert!(fs::metadata(&path).unwrap().len() < artifact.len() as u64);
+        }
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_for_target() {
+        let config = HashMap::from([("compression".to_string(), "zstd".to_string())]);
+        assert_eq!(Compression::for_target("local", &config), Ok(Compression::Zstd));
+        assert_eq!(Compression::for_target("local", &HashMap::new()), Ok(Compression::None));
+        assert_eq!(Compression::for_target("aws", &HashMap::new()), Ok(Compression::Zip));
+        assert!(Compression::for_target("aws", &config).is_err());
+    }
+}
@@ -1,14 +1,17 @@
-use crate::{lexer::{Token, TokenKind}, AST, error::{Result, RiftError}};
+use crate::{lexer::{Token, TokenKind}, AST, Guard, error::{Result, RiftError}};
 use std::collections::HashMap;
 
 pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
+    // Whether `${...}` in inline fuse code is interpolated, which the script
+    // opts into by requiring FUSE_INTERPOLATION_VERSION or later
+    fuse_interpolation: bool,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, pos: 0 }
+        Self { tokens, pos: 0, fuse_interpolation: false }
     }
     
     pub fn parse(&mut self) -> Result<AST> {
@@ -21,6 +24,13 @@ impl Parser {
                 continue;
             }
             
+            // Already checked by check_version_pragmas
+            if self.current_token_is(TokenKind::Keyword) && self.current().value == "@version" {
+                self.advance();
+                self.consume_string("Expected version string after @version")?;
+                continue;
+            }
+            
             match self.parse_statement() {
                 Ok(node) => nodes.push(node),
                 Err(e) => return Err(self.error_with_context(format!("Parse error: {}", e))),
@@ -39,12 +49,27 @@ impl Parser {
             "@rift" => self.parse_rift(),
             "@fuse" => self.parse_fuse(),
             "@task" => self.parse_task(),
+            "fn" => self.parse_function(),
             "@target" => self.parse_target(),
+            "@cache" => self.parse_cache(),
             "@deploy" => self.parse_deploy(),
-            "let" => self.parse_let(),
+            "@invoke" => self.parse_invoke(),
+            "let" | "global" => self.parse_let(),
+            "export" => self.parse_export(),
+            "import" => self.parse_import(),
+            "param" => self.parse_param(),
             "call" => self.parse_call(),
             "if" => self.parse_if(),
+            "@when" => self.parse_when(),
             "while" => self.parse_while(),
+            "for" => self.parse_for(),
+            "match" => self.parse_match(),
+            _ if self.current_token_is(TokenKind::Identifier) && ["=", "+=", "-="].iter().any(|op| self.peek_value_is(1, op)) => self.parse_assign(),
+            _ if self.current_token_is(TokenKind::Identifier) && self.peek_value_is(1, "(") => {
+                let call = self.parse_expression()?;
+                self.consume_symbol(";", "Expected ';' after function call")?;
+                Ok(call)
+            }
             _ => Err(RiftError::ParseError(format!(
                 "Unexpected token: '{}' at line {}, column {}",
                 self.current().value, self.current().line, self.current().column
@@ -56,24 +81,177 @@ impl Parser {
         self.consume_keyword("@rift")?;
         
         let name = self.consume_identifier("Expected rift name")?;
+        let meta = if self.current_token_is(TokenKind::Identifier) && self.current().value == "meta" {
+            Some(self.parse_meta()?)
+        } else {
+            None
+        };
         self.consume_symbol("{", "Expected '{' after rift name")?;
         
-        let body = self.parse_block()?;
+        let mut body = self.parse_block()?;
+        if let Some(meta) = meta {
+            body.insert(0, meta);
+        }
         
         Ok(AST::Rift(name, body))
     }
     
+    // Values are strings or lists of strings, separators optional
+    fn parse_meta(&mut self) -> Result<AST> {
+        self.advance(); // consume 'meta'
+        self.consume_symbol("{", "Expected '{' after 'meta'")?;
+        
+        let mut entries = Vec::new();
+        while !self.is_at_end() && !self.current_token_value_is("}") {
+            if self.current_token_is(TokenKind::Comment) || self.current_token_value_is(",") {
+                self.advance();
+                continue;
+            }
+            let key = self.consume_identifier("Expected metadata key")?;
+            self.consume_symbol(":", "Expected ':' after metadata key")?;
+            let value = self.parse_expression()?;
+            let strings = match &value {
+                AST::String(text) => vec![text],
+                AST::List(items) => items.iter().filter_map(|item| match item {
+                    AST::String(text) => Some(text),
+                    _ => None,
+                }).collect(),
+                _ => Vec::new(),
+            };
+            if !matches!(&value, AST::String(_)) && !matches!(&value, AST::List(items) if items.len() == strings.len()) {
+                return Err(self.error_with_context(format!("Metadata '{}' must be a string or a list of strings", key)));
+            }
+            if key == "requires" {
+                for spec in strings {
+                    crate::meta::parse_requirement(spec).map_err(|e| self.error_with_context(format!("In requires: {}", e)))?;
+                }
+            }
+            entries.push((key, value));
+        }
+        
+        self.consume_symbol("}", "Expected '}' after metadata")?;
+        
+        Ok(AST::Meta(entries))
+    }
+    
     fn parse_fuse(&mut self) -> Result<AST> {
         self.consume_keyword("@fuse")?;
         
+        let interactive = self.current_token_is(TokenKind::Identifier) && self.current().value == "interactive";
+        if interactive {
+            self.advance();
+        }
+        
         let lang = self.consume_string("Expected language string after @fuse")?;
-        self.consume_symbol("{", "Expected '{' after language")?;
         
-        let code = self.consume_string("Expected code string in fuse block")?;
+        let agent = if self.current_token_is(TokenKind::Identifier) && self.current().value == "on" {
+            self.advance();
+            Some(self.consume_string("Expected agent name after 'on'")?)
+        } else {
+            None
+        };
+        if interactive && agent.is_some() {
+            return Err(self.error_with_context("Interactive fuse blocks cannot run on a remote agent".to_string()));
+        }
+        
+        let cache = if self.current_token_is(TokenKind::Identifier) && self.current().value == "cache" {
+            self.advance();
+            Some(self.parse_cache_policy()?)
+        } else {
+            None
+        };
+        if interactive && cache.is_some() {
+            return Err(self.error_with_context("Interactive fuse blocks are never cached".to_string()));
+        }
+        
+        // `requires gpu` before `from`, as a file's block has no braces to hold it
+        let mut gpu = self.parse_requires_gpu()?;
+        let code = if self.current_token_is(TokenKind::Identifier) && self.current().value == "from" {
+            self.advance();
+            self.parse_fuse_source()?
+        } else {
+            self.consume_symbol("{", "Expected '{' after language")?;
+            if !gpu && self.parse_requires_gpu()? {
+                self.consume_symbol(";", "Expected ';' after requires gpu")?;
+                gpu = true;
+            }
+            let code = self.consume_string("Expected code string in fuse block")?;
+            // Older scripts pass `${` through, as for code read from a file
+            let code = if self.fuse_interpolation {
+                crate::interpolate::parts(&code).map_err(|e| self.error_with_context(e.to_string()))?;
+                code
+            } else {
+                crate::interpolate::escape(&code)
+            };
+            self.consume_symbol("}", "Expected '}' after code")?;
+            code
+        };
+        
+        let fuse = match agent {
+            Some(agent) => AST::RemoteFuse(agent, lang, code),
+            None if interactive => AST::InteractiveFuse(lang, code),
+            None => AST::Fuse(lang, code),
+        };
+        let fuse = if gpu { AST::GpuFuse(Box::new(fuse)) } else { fuse };
+        match cache {
+            Some(policy) => Ok(AST::CachedFuse(policy, Box::new(fuse))),
+            None => Ok(fuse),
+        }
+    }
+    
+    fn parse_requires_gpu(&mut self) -> Result<bool> {
+        if !(self.current_token_is(TokenKind::Identifier) && self.current().value == "requires") {
+            return Ok(false);
+        }
+        self.advance();
+        if !(self.current_token_is(TokenKind::Identifier) && self.current().value == "gpu") {
+            return Err(self.error_with_context("Expected 'gpu' after requires".to_string()));
+        }
+        self.advance();
+        Ok(true)
+    }
+    
+    // `from "path"` or `from git_file("path", "rev")`, read while parsing so
+    // the block behaves exactly as if the code had been written inline
+    fn parse_fuse_source(&mut self) -> Result<String> {
+        let (path, rev) = if self.current_token_is(TokenKind::Identifier) && self.current().value == "git_file" {
+            self.advance();
+            self.consume_symbol("(", "Expected '(' after git_file")?;
+            let path = self.consume_string("Expected path string in git_file")?;
+            self.consume_symbol(",", "Expected ',' between git_file path and revision")?;
+            let rev = self.consume_string("Expected revision string in git_file")?;
+            self.consume_symbol(")", "Expected ')' after git_file arguments")?;
+            (path, Some(rev))
+        } else {
+            (self.consume_string("Expected path or git_file(...) after 'from'")?, None)
+        };
+        crate::git::read_source(std::path::Path::new(&path), rev.as_deref())
+            .map(|code| crate::interpolate::escape(&code))
+            .map_err(|e| self.error_with_context(format!("@fuse from {}: {}", path, e)))
+    }
+    
+    fn parse_cache(&mut self) -> Result<AST> {
+        self.consume_keyword("@cache")?;
         
-        self.consume_symbol("}", "Expected '}' after code")?;
+        let policy = self.parse_cache_policy()?;
         
-        Ok(AST::Fuse(lang, code))
+        Ok(AST::Cache(policy))
+    }
+    
+    // `on`, `off` or `ttl "1h"`; a ttl is kept as its duration text
+    fn parse_cache_policy(&mut self) -> Result<String> {
+        match self.current_token_kind() {
+            Some(TokenKind::Identifier) if matches!(self.current().value.as_str(), "on" | "off") => {
+                Ok(self.advance().value.clone())
+            }
+            Some(TokenKind::Identifier) if self.current().value == "ttl" => {
+                self.advance();
+                let ttl = self.consume_string("Expected duration string after 'ttl'")?;
+                crate::deployer::parse_duration(&ttl).map_err(|e| self.error_with_context(e))?;
+                Ok(ttl)
+            }
+            _ => Err(self.error_with_context("Expected 'on', 'off' or 'ttl \"<duration>\"' after cache".to_string())),
+        }
     }
     
     fn parse_task(&mut self) -> Result<AST> {
@@ -87,6 +265,33 @@ impl Parser {
         Ok(AST::Task(name, body))
     }
     
+    fn parse_function(&mut self) -> Result<AST> {
+        self.consume_keyword("fn")?;
+        
+        let name = self.consume_identifier("Expected function name after 'fn'")?;
+        self.consume_symbol("(", "Expected '(' after function name")?;
+        
+        let mut params: Vec<String> = Vec::new();
+        while !self.is_at_end() && !self.current_token_value_is(")") {
+            let param = self.consume_identifier("Expected parameter name")?;
+            if params.contains(&param) {
+                return Err(self.error_with_context(format!("Duplicate parameter '{}' in fn {}", param, name)));
+            }
+            params.push(param);
+            if self.current_token_value_is(",") {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        self.consume_symbol(")", "Expected ')' after parameters")?;
+        self.consume_symbol("{", "Expected '{' after function parameters")?;
+        
+        let body = self.parse_block()?;
+        
+        Ok(AST::Function(name, params, body))
+    }
+    
     fn parse_target(&mut self) -> Result<AST> {
         self.consume_keyword("@target")?;
         
@@ -99,6 +304,11 @@ impl Parser {
         self.consume_keyword("@deploy")?;
         
         let target = self.consume_string("Expected target string after @deploy")?;
+        if !self.current_token_value_is("{") {
+            let config = self.parse_expression()?;
+            self.consume_symbol(";", "Expected '{' or a config value and ';' after deploy target")?;
+            return Ok(AST::DeployValue(target, Box::new(config)));
+        }
         self.consume_symbol("{", "Expected '{' after deploy target")?;
         
         let config = self.parse_config()?;
@@ -106,20 +316,115 @@ impl Parser {
         Ok(AST::Deploy(target, config))
     }
     
+    fn parse_invoke(&mut self) -> Result<AST> {
+        self.consume_keyword("@invoke")?;
+        
+        let target = self.consume_string("Expected chain string after @invoke")?;
+        self.consume_symbol("{", "Expected '{' after invoke target")?;
+        
+        let config = self.parse_config()?;
+        
+        Ok(AST::Invoke(target, config))
+    }
+    
     fn parse_let(&mut self) -> Result<AST> {
-        self.consume_keyword("let")?;
+        let keyword = self.current().value.clone();
+        self.advance(); // consume 'let' or 'global'
         
-        let name = self.consume_identifier("Expected variable name after 'let'")?;
+        let name = self.consume_identifier(&format!("Expected variable name after '{}'", keyword))?;
         self.consume_symbol("=", "Expected '=' after variable name")?;
         
         let value = self.parse_expression()?;
         
-        self.consume_symbol(";", "Expected ';' after let statement")?;
+        self.consume_symbol(";", &format!("Expected ';' after {} statement", keyword))?;
+        
+        match keyword.as_str() {
+            "global" => Ok(AST::Global(name, Box::new(value))),
+            _ => Ok(AST::Let(name, Box::new(value))),
+        }
+    }
+    
+    fn parse_assign(&mut self) -> Result<AST> {
+        let name = self.consume_identifier("Expected variable name")?;
+        let op = self.current().value.clone();
+        self.advance();
+        
+        let mut value = self.parse_expression()?;
+        if op != "=" {
+            value = AST::Binary(op[..1].to_string(), Box::new(AST::Identifier(name.clone())), Box::new(value));
+        }
+        
+        self.consume_symbol(";", "Expected ';' after assignment")?;
+        
+        Ok(AST::Assign(name, Box::new(value)))
+    }
+    
+    fn parse_param(&mut self) -> Result<AST> {
+        self.consume_keyword("param")?;
+        
+        let name = self.consume_identifier("Expected parameter name after 'param'")?;
+        self.consume_symbol(":", "Expected ':' after parameter name")?;
+        let ty = self.consume_identifier("Expected parameter type")?;
+        if !crate::interpreter::PARAM_TYPES.contains(&ty.as_str()) {
+            return Err(self.error_with_context(format!(
+                "Unknown parameter type '{}'; expected one of {}", ty, crate::interpreter::PARAM_TYPES.join(", ")
+            )));
+        }
+        
+        let default = if self.current_token_value_is("=") {
+            self.advance();
+            Some(Box::new(self.parse_expression()?))
+        } else {
+            None
+        };
+        
+        self.consume_symbol(";", "Expected ';' after param statement")?;
+        
+        Ok(AST::Param(name, ty, default))
+    }
+    
+    fn parse_export(&mut self) -> Result<AST> {
+        self.consume_keyword("export")?;
+        
+        let mut names = vec![self.consume_identifier("Expected variable name after 'export'")?];
+        while self.current_token_value_is(",") {
+            self.advance();
+            names.push(self.consume_identifier("Expected variable name after ','")?);
+        }
+        
+        self.consume_symbol(";", "Expected ';' after export statement")?;
         
-        Ok(AST::Let(name, Box::new(value)))
+        Ok(AST::Export(names))
+    }
+    
+    // Package names may have dashes, so `import "aws-helpers";` works too
+    fn parse_import(&mut self) -> Result<AST> {
+        self.consume_keyword("import")?;
+        
+        let name = if self.current_token_is(TokenKind::String) {
+            self.advance().value.clone()
+        } else {
+            self.consume_identifier("Expected package name or \"file.rift\" after 'import'")?
+        };
+        
+        self.consume_symbol(";", "Expected ';' after import statement")?;
+        
+        if name.ends_with(".rift") {
+            return Ok(AST::ImportFile(name));
+        }
+        Ok(AST::Import(name))
     }
     
     fn parse_call(&mut self) -> Result<AST> {
+        let call = self.parse_call_target()?;
+        
+        self.consume_symbol(";", "Expected ';' after call statement")?;
+        
+        Ok(call)
+    }
+    
+    // `call name [with a, b]`, shared by the statement and `let x = call name;`
+    fn parse_call_target(&mut self) -> Result<AST> {
         self.consume_keyword("call")?;
         
         let name = self.consume_identifier("Expected function name after 'call'")?;
@@ -140,8 +445,6 @@ impl Parser {
             }
         }
         
-        self.consume_symbol(";", "Expected ';' after call statement")?;
-        
         Ok(AST::Call(name, args))
     }
     
@@ -157,11 +460,69 @@ impl Parser {
         
         if !self.is_at_end() && self.current_token_value_is("else") {
             self.advance(); // consume 'else'
+            // `else if` is an else block holding just the next if
+            if self.current_token_value_is("if") {
+                else_body = vec![self.parse_if()?];
+            } else {
+                self.consume_symbol("{", "Expected '{' or 'if' after 'else'")?;
+                else_body = self.parse_block_content()?;
+            }
+        }
+        
+        Ok(AST::If(Box::new(condition), then_body, else_body))
+    }
+    
+    fn parse_when(&mut self) -> Result<AST> {
+        self.consume_keyword("@when")?;
+        
+        let guard = self.parse_guard()?;
+        
+        self.consume_symbol("{", "Expected '{' after @when guard")?;
+        let then_body = self.parse_block_content()?;
+        
+        let mut else_body = Vec::new();
+        if !self.is_at_end() && self.current_token_value_is("else") {
+            self.advance();
             self.consume_symbol("{", "Expected '{' after 'else'")?;
             else_body = self.parse_block_content()?;
         }
         
-        Ok(AST::If(Box::new(condition), then_body, else_body))
+        Ok(AST::When(guard, then_body, else_body))
+    }
+    
+    // `or` binds looser than `and`, as usual
+    fn parse_guard(&mut self) -> Result<Guard> {
+        let mut guard = self.parse_guard_all()?;
+        while self.current_token_is(TokenKind::Identifier) && self.current().value == "or" {
+            self.advance();
+            guard = Guard::Or(Box::new(guard), Box::new(self.parse_guard_all()?));
+        }
+        Ok(guard)
+    }
+    
+    fn parse_guard_all(&mut self) -> Result<Guard> {
+        let mut guard = self.parse_guard_test()?;
+        while self.current_token_is(TokenKind::Identifier) && self.current().value == "and" {
+            self.advance();
+            guard = Guard::And(Box::new(guard), Box::new(self.parse_guard_test()?));
+        }
+        Ok(guard)
+    }
+    
+    fn parse_guard_test(&mut self) -> Result<Guard> {
+        let name = self.consume_identifier("Expected a host fact or has(...) in @when guard")?;
+        if name == "has" && self.current_token_value_is("(") {
+            self.advance();
+            let tool = self.consume_string("Expected toolchain name string in has(...)")?;
+            self.consume_symbol(")", "Expected ')' after toolchain name")?;
+            return Ok(Guard::Has(tool));
+        }
+        if self.current_token_is(TokenKind::Symbol) && matches!(self.current().value.as_str(), "==" | "!=") {
+            let negated = self.advance().value == "!=";
+            let value = self.consume_string("Expected string to compare the host fact with")?;
+            return Ok(if negated { Guard::IsNot(name, value) } else { Guard::Is(name, value) });
+        }
+        Ok(Guard::Set(name))
     }
     
     fn parse_while(&mut self) -> Result<AST> {
@@ -175,6 +536,60 @@ impl Parser {
         Ok(AST::While(Box::new(condition), body))
     }
     
+    // Patterns are expressions compared with `==`; commas between arms are
+    // optional
+    fn parse_match(&mut self) -> Result<AST> {
+        self.consume_keyword("match")?;
+        
+        let subject = self.parse_expression()?;
+        self.consume_symbol("{", "Expected '{' after match value")?;
+        
+        let mut arms: Vec<(Vec<AST>, Vec<AST>)> = Vec::new();
+        while !self.is_at_end() && !self.current_token_value_is("}") {
+            if self.current_token_is(TokenKind::Comment) || self.current_token_value_is(",") {
+                self.advance();
+                continue;
+            }
+            if arms.last().is_some_and(|(patterns, _)| patterns.is_empty()) {
+                return Err(self.error_with_context("Match arms after '_' can never run".to_string()));
+            }
+            let mut patterns = Vec::new();
+            if self.current_token_is(TokenKind::Identifier) && self.current().value == "_" {
+                self.advance();
+            } else {
+                patterns.push(self.parse_expression()?);
+                while self.current_token_value_is("|") {
+                    self.advance();
+                    patterns.push(self.parse_expression()?);
+                }
+            }
+            self.consume_symbol("=>", "Expected '=>' after match pattern")?;
+            self.consume_symbol("{", "Expected '{' after '=>'")?;
+            arms.push((patterns, self.parse_block_content()?));
+        }
+        
+        self.consume_symbol("}", "Expected '}' after match arms")?;
+        
+        Ok(AST::Match(Box::new(subject), arms))
+    }
+    
+    fn parse_for(&mut self) -> Result<AST> {
+        self.consume_keyword("for")?;
+        
+        let name = self.consume_identifier("Expected loop variable after 'for'")?;
+        self.consume_keyword("in")?;
+        let mut iterable = self.parse_expression()?;
+        if self.current_token_value_is("..") {
+            self.advance();
+            iterable = AST::Range(Box::new(iterable), Box::new(self.parse_expression()?));
+        }
+        
+        self.consume_symbol("{", "Expected '{' after for loop header")?;
+        let body = self.parse_block_content()?;
+        
+        Ok(AST::For(name, Box::new(iterable), body))
+    }
+    
     fn parse_block(&mut self) -> Result<Vec<AST>> {
         let body = self.parse_block_content()?;
         Ok(body)
@@ -198,4 +613,637 @@ impl Parser {
         Ok(body)
     }
     
-    fn parse_expression(&mut self)
\ No newline at end of file
+    // `||` binds loosest, then `&&`, one comparison, `+ -`, `* / %` and the
+    // prefixes `!` and `-`
+    fn parse_expression(&mut self) -> Result<AST> {
+        let mut node = self.parse_and()?;
+        while self.current_token_value_is("||") {
+            self.advance();
+            node = AST::Or(Box::new(node), Box::new(self.parse_and()?));
+        }
+        Ok(node)
+    }
+    
+    fn parse_and(&mut self) -> Result<AST> {
+        let mut node = self.parse_comparison()?;
+        while self.current_token_value_is("&&") {
+            self.advance();
+            node = AST::And(Box::new(node), Box::new(self.parse_comparison()?));
+        }
+        Ok(node)
+    }
+    
+    // Comparisons don't chain: `a < b < c` is an error, not `(a < b) < c`
+    fn parse_comparison(&mut self) -> Result<AST> {
+        let node = self.parse_additive()?;
+        if crate::value::COMPARISONS.iter().any(|op| self.current_token_value_is(op)) {
+            let op = self.advance().value.clone();
+            return Ok(AST::Binary(op, Box::new(node), Box::new(self.parse_additive()?)));
+        }
+        Ok(node)
+    }
+    
+    fn parse_additive(&mut self) -> Result<AST> {
+        let mut node = self.parse_multiplicative()?;
+        while self.current_token_value_is("+") || self.current_token_value_is("-") {
+            let op = self.advance().value.clone();
+            node = AST::Binary(op, Box::new(node), Box::new(self.parse_multiplicative()?));
+        }
+        Ok(node)
+    }
+    
+    fn parse_multiplicative(&mut self) -> Result<AST> {
+        let mut node = self.parse_unary()?;
+        while ["*", "/", "%"].iter().any(|op| self.current_token_value_is(op)) {
+            let op = self.advance().value.clone();
+            node = AST::Binary(op, Box::new(node), Box::new(self.parse_unary()?));
+        }
+        Ok(node)
+    }
+    
+    fn parse_unary(&mut self) -> Result<AST> {
+        if self.current_token_value_is("!") {
+            self.advance();
+            return Ok(AST::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.current_token_value_is("-") {
+            self.advance();
+            return Ok(AST::Neg(Box::new(self.parse_unary()?)));
+        }
+        self.parse_postfix()
+    }
+    
+    // Any number of `[index]` after a primary: `xs[0]`, `rows[i]["name"]`
+    fn parse_postfix(&mut self) -> Result<AST> {
+        let mut node = self.parse_primary()?;
+        while self.current_token_value_is("[") {
+            self.advance();
+            let index = self.parse_expression()?;
+            self.consume_symbol("]", "Expected ']' after index")?;
+            node = AST::Index(Box::new(node), Box::new(index));
+        }
+        Ok(node)
+    }
+    
+    fn parse_primary(&mut self) -> Result<AST> {
+        if self.is_at_end() {
+            return Err(RiftError::ParseError("Expected expression, found end of input".to_string()));
+        }
+        
+        let token = self.current().clone();
+        let node = match token.kind {
+            TokenKind::Number if token.value.contains('.') => {
+                let value = token.value.parse::<f64>().map_err(|_| RiftError::ParseError(format!(
+                    "Invalid number '{}' at line {}, column {}",
+                    token.value, token.line, token.column
+                )))?;
+                AST::Float(value)
+            }
+            TokenKind::Number => {
+                let value = token.value.parse::<i32>().map_err(|_| RiftError::ParseError(format!(
+                    "Invalid number '{}' at line {}, column {}",
+                    token.value, token.line, token.column
+                )))?;
+                AST::Number(value)
+            }
+            TokenKind::String => {
+                crate::interpolate::parts(&token.value)?;
+                AST::String(token.value.clone())
+            }
+            TokenKind::Keyword if token.value == "true" || token.value == "false" => AST::Bool(token.value == "true"),
+            TokenKind::Symbol if token.value == "(" => {
+                self.advance();
+                let inner = self.parse_expression()?;
+                self.consume_symbol(")", "Expected ')' to close the parenthesized expression")?;
+                return Ok(inner);
+            }
+            TokenKind::Symbol if token.value == "[" => return self.parse_list(),
+            TokenKind::Symbol if token.value == "{" => return self.parse_map(),
+            TokenKind::Identifier if self.peek_value_is(1, "(") => return self.parse_call_expression(),
+            TokenKind::Keyword if token.value == "@fuse" => return self.parse_fuse(),
+            TokenKind::Keyword if token.value == "call" => return self.parse_call_target(),
+            TokenKind::Identifier => AST::Identifier(token.value.clone()),
+            _ => return Err(RiftError::ParseError(format!(
+                "Expected expression, found '{}' at line {}, column {}",
+                token.value, token.line, token.column
+            ))),
+        };
+        
+        self.advance();
+        Ok(node)
+    }
+    
+    // `[a, b, ...]`, with an optional trailing comma
+    fn parse_list(&mut self) -> Result<AST> {
+        self.consume_symbol("[", "Expected '['")?;
+        
+        let mut items = Vec::new();
+        while !self.is_at_end() && !self.current_token_value_is("]") {
+            items.push(self.parse_expression()?);
+            if self.current_token_value_is(",") {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        
+        self.consume_symbol("]", "Expected ']' to close the list")?;
+        Ok(AST::List(items))
+    }
+    
+    // `{ key = value, ... }`; like deploy configs, separators may be left out
+    fn parse_map(&mut self) -> Result<AST> {
+        self.consume_symbol("{", "Expected '{'")?;
+        
+        let mut entries = Vec::new();
+        while !self.is_at_end() && !self.current_token_value_is("}") {
+            if self.current_token_is(TokenKind::Comment) {
+                self.advance();
+                continue;
+            }
+            let key = match self.current_token_kind() {
+                Some(TokenKind::Identifier) | Some(TokenKind::String) => self.advance().value.clone(),
+                _ => return Err(self.error_with_context("Expected map key".to_string())),
+            };
+            self.consume_symbol("=", "Expected '=' after map key")?;
+            entries.push((key, self.parse_expression()?));
+            if self.current_token_value_is(",") {
+                self.advance();
+            }
+        }
+        
+        self.consume_symbol("}", "Expected '}' to close the map")?;
+        Ok(AST::Map(entries))
+    }
+    
+    // `name(arg, ...)` - builtins are resolved by the interpreter, not here
+    fn parse_call_expression(&mut self) -> Result<AST> {
+        let name = self.consume_identifier("Expected function name")?;
+        self.consume_symbol("(", "Expected '(' after function name")?;
+        
+        let mut args = Vec::new();
+        while !self.is_at_end() && !self.current_token_value_is(")") {
+            args.push(self.parse_expression()?);
+            if self.current_token_value_is(",") {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        
+        self.consume_symbol(")", "Expected ')' after function arguments")?;
+        Ok(AST::Call(name, args))
+    }
+    
+    fn parse_config(&mut self) -> Result<HashMap<String, String>> {
+        let mut config = HashMap::new();
+        
+        while !self.is_at_end() && !self.current_token_value_is("}") {
+            if self.current_token_is(TokenKind::Comment) {
+                self.advance();
+                continue;
+            }
+            
+            // Words that became keywords, like `match` or `import`, still name keys
+            let key = match self.current_token_kind() {
+                Some(TokenKind::Keyword) if !self.current().value.starts_with('@') => self.advance().value.clone(),
+                _ => self.consume_identifier("Expected config key")?,
+            };
+            self.consume_symbol("=", "Expected '=' after config key")?;
+            
+            let value = match self.current_token_kind() {
+                Some(TokenKind::String) | Some(TokenKind::Number) | Some(TokenKind::Identifier) => {
+                    self.advance().value.clone()
+                }
+                // Read back as "true"/"false", as before they were keywords
+                Some(TokenKind::Keyword) if matches!(self.current().value.as_str(), "true" | "false") => {
+                    self.advance().value.clone()
+                }
+                _ => return Err(self.error_with_context(format!("Expected value for config key '{}'", key))),
+            };
+            config.insert(key, value);
+            
+            // Entries may be separated by ',' or ';'
+            if self.current_token_value_is(",") || self.current_token_value_is(";") {
+                self.advance();
+            }
+        }
+        
+        self.consume_symbol("}", "Expected '}' to close config block")?;
+        
+        Ok(config)
+    }
+    
+    fn current(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+    
+    fn current_token_kind(&self) -> Option<TokenKind> {
+        self.tokens.get(self.pos).map(|t| t.kind.clone())
+    }
+    
+    fn advance(&mut self) -> &Token {
+        if !self.is_at_end() {
+            self.pos += 1;
+        }
+        &self.tokens[self.pos - 1]
+    }
+    
+    fn peek_value_is(&self, offset: usize, value: &str) -> bool {
+        self.tokens.get(self.pos + offset)
+            .is_some_and(|t| t.kind != TokenKind::String && t.value == value)
+    }
+    
+    fn is_at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+    
+    fn current_token_is(&self, kind: TokenKind) -> bool {
+        !self.is_at_end() && self.current().kind == kind
+    }
+    
+    // String literals never match, so `"}"` inside a fuse block is not a brace
+    fn current_token_value_is(&self, value: &str) -> bool {
+        !self.is_at_end() && self.current().kind != TokenKind::String && self.current().value == value
+    }
+    
+    fn consume_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.current_token_is(TokenKind::Keyword) && self.current().value == keyword {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.error_with_context(format!("Expected '{}'", keyword)))
+        }
+    }
+    
+    fn consume_symbol(&mut self, symbol: &str, message: &str) -> Result<()> {
+        if self.current_token_is(TokenKind::Symbol) && self.current().value == symbol {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.error_with_context(message.to_string()))
+        }
+    }
+    
+    fn consume_identifier(&mut self, message: &str) -> Result<String> {
+        match self.current_token_kind() {
+            Some(TokenKind::Identifier) => Ok(self.advance().value.clone()),
+            // `optimize` is a keyword but is invoked like any other name
+            Some(TokenKind::Keyword) if self.current().value == "optimize" => Ok(self.advance().value.clone()),
+            _ => Err(self.error_with_context(message.to_string())),
+        }
+    }
+    
+    fn consume_string(&mut self, message: &str) -> Result<String> {
+        if self.current_token_is(TokenKind::String) {
+            Ok(self.advance().value.clone())
+        } else {
+            Err(self.error_with_context(message.to_string()))
+        }
+    }
+    
+    fn error_with_context(&self, message: String) -> RiftError {
+        match self.tokens.get(self.pos) {
+            Some(token) => RiftError::ParseError(format!(
+                "{} (found '{}' at line {}, column {})",
+                message, token.value, token.line, token.column
+            )),
+            None => RiftError::ParseError(format!("{} (at end of input)", message)),
+        }
+    }
+}
+
+// The syntax version this parser implements. Scripts can require one with
+// `#!rift 2` on their first line or `@version "2.0"`. Those are checked
+// before anything else is parsed, so an older rift says to upgrade instead
+// of tripping over syntax it has never seen. Within a major version, newer
+// minors only add syntax.
+pub const LANGUAGE_VERSION: (u32, u32) = (2, 1);
+
+// Inline fuse code is interpolated from this version on. Before it `${` went
+// to the program untouched, as JavaScript template literals and shell
+// variables expect; `rift migrate` escapes those when upgrading a script.
+pub const FUSE_INTERPOLATION_VERSION: (u32, u32) = (2, 1);
+
+// A whole expression from `source`, e.g. the inside of `${...}`
+pub(crate) fn parse_expression_source(source: &str) -> Result<AST> {
+    let mut parser = Parser::new(crate::lexer::tokenize(source)?);
+    let expression = parser.parse_expression()?;
+    if !parser.is_at_end() {
+        return Err(parser.error_with_context("Unexpected token after expression".to_string()));
+    }
+    Ok(expression)
+}
+
+pub fn parse(tokens: &[Token]) -> Result<AST> {
+    let version = check_version_pragmas(tokens)?;
+    let mut parser = Parser::new(tokens.to_vec());
+    parser.fuse_interpolation = version.is_some_and(|version| version >= FUSE_INTERPOLATION_VERSION);
+    parser.parse()
+}
+
+// The version the script requires, if it names one
+pub(crate) fn check_version_pragmas(tokens: &[Token]) -> Result<Option<(u32, u32)>> {
+    let mut declared = None;
+    for (i, token) in tokens.iter().enumerate() {
+        let required = match token.kind {
+            TokenKind::Comment if i == 0 => token.value.strip_prefix("#!rift").map(str::trim),
+            TokenKind::Keyword if token.value == "@version" => tokens.get(i + 1)
+                .filter(|next| next.kind == TokenKind::String)
+                .map(|next| next.value.as_str()),
+            _ => None,
+        };
+        if let Some(required) = required {
+            let version = check_version(required, token.line)?;
+            declared.get_or_insert(version);
+        }
+    }
+    Ok(declared)
+}
+
+// "2" or "2.1"
+pub(crate) fn parse_version(text: &str) -> Option<(u32, u32)> {
+    match text.split_once('.') {
+        Some((major, minor)) => Some((major.parse().ok()?, minor.parse().ok()?)),
+        None => Some((text.parse().ok()?, 0)),
+    }
+}
+
+fn check_version(required: &str, line: usize) -> Result<(u32, u32)> {
+    let (major, minor) = parse_version(required).ok_or_else(|| RiftError::ParseError(format!(
+        "Invalid language version '{}' at line {} (expected e.g. \"2\" or \"2.1\")", required, line
+    )))?;
+    let (supported_major, supported_minor) = LANGUAGE_VERSION;
+    if major > supported_major || (major == supported_major && minor > supported_minor) {
+        return Err(RiftError::ParseError(format!(
+            "This script needs rift language {} (line {}), but rift {} supports up to {}.{}; upgrade rift to run it",
+            required, line, env!("CARGO_PKG_VERSION"), supported_major, supported_minor
+        )));
+    }
+    if major < supported_major {
+        return Err(RiftError::ParseError(format!(
+            "This script is written for rift language {} (line {}), which rift {} no longer supports",
+            required, line, env!("CARGO_PKG_VERSION")
+        )));
+    }
+    Ok((major, minor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+
+    fn parse_source(source: &str) -> Result<AST> {
+        parse(&tokenize(source)?)
+    }
+
+    #[test]
+    fn test_version_pragmas() {
+        assert!(parse_source("#!rift 2\nlet x = 1;").is_ok());
+        assert!(parse_source("@version \"2.0\"\nlet x = 1;").is_ok());
+        assert!(parse_source("#!/usr/bin/env rift\nlet x = 1;").is_ok());
+
+        // Reported instead of the syntax error later in the script
+        let error = parse_source("@version \"2.9\"\nlet x = 1 ?? 2;").unwrap_err().to_string();
+        assert!(error.contains("needs rift language 2.9"), "{}", error);
+        assert!(parse_source("#!rift 1").unwrap_err().to_string().contains("no longer supports"));
+        assert!(parse_source("@version \"two\"").unwrap_err().to_string().contains("Invalid language version"));
+    }
+
+    #[test]
+    fn test_fuse_from_file() {
+        let path = std::env::temp_dir().join(format!("rift_fuse_from_{}.py", std::process::id()));
+        std::fs::write(&path, "print('hi')\n").unwrap();
+        let ast = parse_source(&format!("@fuse \"python\" from \"{}\"", path.display())).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!(matches!(&ast, AST::Program(nodes) if matches!(&nodes[0], AST::Fuse(_, code) if code == "print('hi')\n")));
+        assert!(parse_source("@fuse \"python\" from \"/nonexistent/x.py\"").is_err());
+    }
+
+    #[test]
+    fn test_requires_gpu() {
+        let ast = parse_source("@fuse \"python\" on \"box\" cache off { requires gpu; \"import torch\" } let x = @fuse \"python\" { \"1\" };").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        assert!(matches!(&nodes[0], AST::CachedFuse(_, fuse) if matches!(fuse.as_ref(), AST::GpuFuse(inner) if matches!(inner.as_ref(), AST::RemoteFuse(..)))));
+        assert!(matches!(&nodes[1], AST::Let(_, value) if matches!(value.as_ref(), AST::Fuse(..))));
+        assert!(parse_source("@fuse \"python\" { requires cpu; \"x\" }").is_err());
+        assert!(parse_source("@fuse \"python\" { requires gpu \"x\" }").is_err());
+    }
+
+    #[test]
+    fn test_when() {
+        let ast = parse_source("@when os == \"linux\" and not_ci or has(\"docker\") { print(1); } else { print(2); }").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        assert!(matches!(&nodes[0], AST::When(Guard::Or(left, right), then_body, else_body)
+            if matches!(left.as_ref(), Guard::And(..)) && **right == Guard::Has("docker".to_string())
+            && then_body.len() == 1 && else_body.len() == 1));
+        assert!(parse_source("@when os = \"linux\" { }").is_err());
+    }
+
+    #[test]
+    fn test_function() {
+        let ast = parse_source("fn greet(name, greeting) { print(greeting, name); } call greet with \"a\", \"hi\";").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        assert!(matches!(&nodes[0], AST::Function(name, params, body) if name == "greet" && params == &["name", "greeting"] && body.len() == 1));
+        assert!(matches!(&nodes[1], AST::Call(name, args) if name == "greet" && args.len() == 2));
+        assert!(parse_source("fn f(a, a) { }").is_err());
+    }
+
+    #[test]
+    fn test_numbers() {
+        let ast = parse_source("let a = 3; let b = 45.67;").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        assert!(matches!(&nodes[0], AST::Let(_, value) if matches!(value.as_ref(), AST::Number(3))));
+        assert!(matches!(&nodes[1], AST::Let(_, value) if matches!(value.as_ref(), AST::Float(f) if *f == 45.67)));
+        assert!(parse_source("let c = 1.2.3;").unwrap_err().to_string().contains("Invalid number"));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let ast = parse_source("let x = 1 + 2 * -count;").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        let AST::Let(_, value) = &nodes[0] else { panic!() };
+        let AST::Binary(op, left, right) = value.as_ref() else { panic!("expected a binary expression") };
+        assert_eq!(op, "+");
+        assert!(matches!(left.as_ref(), AST::Number(1)));
+        assert!(matches!(right.as_ref(), AST::Binary(op, _, neg) if op == "*" && matches!(neg.as_ref(), AST::Neg(_))));
+
+        let ast = parse_source("let y = (1 + 2) * 3;").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        assert!(matches!(&nodes[0], AST::Let(_, value) if matches!(value.as_ref(), AST::Binary(op, _, _) if op == "*")));
+    }
+
+    #[test]
+    fn test_assignment() {
+        let ast = parse_source("i = 0; i += 2 * n; i -= 1; while i < 3 { i = i + 1; }").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        assert!(matches!(&nodes[0], AST::Assign(name, value) if name == "i" && matches!(value.as_ref(), AST::Number(_))));
+        assert!(matches!(&nodes[1], AST::Assign(_, value) if matches!(value.as_ref(), AST::Binary(op, left, right)
+            if op == "+" && matches!(left.as_ref(), AST::Identifier(id) if id == "i") && matches!(right.as_ref(), AST::Binary(..)))));
+        assert!(matches!(&nodes[2], AST::Assign(_, value) if matches!(value.as_ref(), AST::Binary(op, ..) if op == "-")));
+        assert!(matches!(&nodes[3], AST::While(_, body) if matches!(&body[0], AST::Assign(..))));
+        assert!(parse_source("i += ;").is_err());
+    }
+
+    #[test]
+    fn test_interpolation() {
+        assert!(parse_source("#!rift 2.1\nprint(\"Hello ${name}\" + \"!\"); @fuse \"javascript\" { \"console.log(`\\${x}`, ${n + 1})\" }").is_ok());
+        assert!(parse_source("print(\"Hello ${name\");").is_err());
+        assert!(parse_source("@version \"2.1\"\n@fuse \"python\" { \"print(${a b})\" }").is_err());
+
+        // Before 2.1 inline fuse code reaches the program as written
+        let ast = parse_source("#!rift 2\n@fuse \"javascript\" { \"console.log(`${x}`)\" }").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        assert!(matches!(&nodes[0], AST::Fuse(_, code) if code == "console.log(`\\${x}`)"));
+    }
+
+    #[test]
+    fn test_lists() {
+        let ast = parse_source("let xs = [1, \"two\", [x + 1],]; print(xs[2][0], [][0]);").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        assert!(matches!(&nodes[0], AST::Let(_, value) if matches!(value.as_ref(), AST::List(items)
+            if items.len() == 3 && matches!(&items[2], AST::List(inner) if matches!(inner[0], AST::Binary(..))))));
+        let AST::Call(_, args) = &nodes[1] else { panic!() };
+        assert!(matches!(&args[0], AST::Index(base, _) if matches!(base.as_ref(), AST::Index(..))));
+        assert!(matches!(&args[1], AST::Index(base, _) if matches!(base.as_ref(), AST::List(items) if items.is_empty())));
+        assert!(parse_source("let xs = [1, 2;").is_err());
+        assert!(parse_source("let x = xs[];").is_err());
+    }
+
+    #[test]
+    fn test_maps() {
+        let ast = parse_source("let cfg = { region = \"us-east-1\", \"max-size\" = 2 * n tags = {} }; @deploy \"aws\" cfg; call f with { a = 1 };").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        let AST::Let(_, value) = &nodes[0] else { panic!() };
+        let AST::Map(entries) = value.as_ref() else { panic!() };
+        let keys: Vec<_> = entries.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["region", "max-size", "tags"]);
+        assert!(matches!(&entries[1].1, AST::Binary(..)) && matches!(&entries[2].1, AST::Map(inner) if inner.is_empty()));
+        assert!(matches!(&nodes[1], AST::DeployValue(target, config) if target == "aws" && matches!(config.as_ref(), AST::Identifier(_))));
+        assert!(parse_source("let cfg = { region };").is_err());
+        assert!(parse_source("@deploy \"aws\" cfg").is_err());
+    }
+
+    #[test]
+    fn test_import() {
+        let ast = parse_source("import deploy_kit; import \"aws-helpers\"; import \"lib/steps.rift\";").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        assert!(matches!(&nodes[0], AST::Import(name) if name == "deploy_kit"));
+        assert!(matches!(&nodes[1], AST::Import(name) if name == "aws-helpers"));
+        assert!(matches!(&nodes[2], AST::ImportFile(path) if path == "lib/steps.rift"));
+        assert!(parse_source("import;").is_err());
+        assert!(parse_source("import kit").is_err());
+    }
+
+    #[test]
+    fn test_for() {
+        let ast = parse_source("for i in 0..n + 1 { print(i); } for item in items { }").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        assert!(matches!(&nodes[0], AST::For(name, range, body)
+            if name == "i" && body.len() == 1 && matches!(range.as_ref(), AST::Range(_, end) if matches!(end.as_ref(), AST::Binary(..)))));
+        assert!(matches!(&nodes[1], AST::For(_, items, _) if matches!(items.as_ref(), AST::Identifier(_))));
+        assert!(parse_source("for i 0..3 { }").is_err());
+    }
+
+    #[test]
+    fn test_comparison() {
+        let ast = parse_source("while i + 1 < 10 && done == false { }").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        let AST::While(condition, _) = &nodes[0] else { panic!() };
+        let AST::And(left, right) = condition.as_ref() else { panic!("&& should bind looser than comparisons") };
+        assert!(matches!(left.as_ref(), AST::Binary(op, sum, _) if op == "<" && matches!(sum.as_ref(), AST::Binary(op, _, _) if op == "+")));
+        assert!(matches!(right.as_ref(), AST::Binary(op, _, _) if op == "=="));
+        assert!(parse_source("if a < b < c { }").is_err());
+    }
+
+    #[test]
+    fn test_rift_meta() {
+        let ast = parse_source("@rift site meta { description: \"Static site\", requires: [\"python>=3.10\", \"docker\"] } { call build; } @rift plain { }").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        let AST::Rift(_, body) = &nodes[0] else { panic!() };
+        let AST::Meta(entries) = &body[0] else { panic!("meta should open the body") };
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(&entries[1].1, AST::List(items) if items.len() == 2));
+        assert!(matches!(&body[1], AST::Call(..)));
+        assert!(matches!(&nodes[1], AST::Rift(_, body) if body.is_empty()));
+        assert!(parse_source("@rift a meta { author: 1 } { }").is_err());
+        assert!(parse_source("@rift a meta { requires: [\"python>=\"] } { }").is_err());
+        assert!(parse_source("@rift a meta { author \"me\" } { }").is_err());
+    }
+
+    #[test]
+    fn test_global() {
+        let ast = parse_source("@task t { global result = 1; let local = 2; }").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        let AST::Task(_, body) = &nodes[0] else { panic!() };
+        assert!(matches!(&body[0], AST::Global(name, _) if name == "result"));
+        assert!(matches!(&body[1], AST::Let(..)));
+        assert!(parse_source("global = 1;").is_err());
+    }
+
+    #[test]
+    fn test_match() {
+        let ast = parse_source("match target() { \"python\" | \"go\" => { print(1); }, // compiled next\n \"rust\" => { } _ => { print(2); } }").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        let AST::Match(subject, arms) = &nodes[0] else { panic!() };
+        assert!(matches!(subject.as_ref(), AST::Call(name, _) if name == "target"));
+        let patterns: Vec<usize> = arms.iter().map(|(patterns, _)| patterns.len()).collect();
+        assert_eq!(patterns, [2, 1, 0]);
+        assert_eq!(arms[0].1.len(), 1);
+        assert!(parse_source("match x { _ => { } \"a\" => { } }").is_err());
+        assert!(parse_source("match x { \"a\" { } }").is_err());
+        assert!(parse_source("match x { \"a\" => print(1); }").is_err());
+    }
+
+    #[test]
+    fn test_else_if() {
+        let ast = parse_source("if n < 0 { print(\"neg\"); } else if n == 0 { print(\"zero\"); } else if n < 10 { } else { print(\"big\"); }").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        let AST::If(_, _, second) = &nodes[0] else { panic!() };
+        let [AST::If(_, zero, third)] = &second[..] else { panic!("else if should nest an If") };
+        assert_eq!(zero.len(), 1);
+        let [AST::If(_, empty, last)] = &third[..] else { panic!() };
+        assert!(empty.is_empty() && last.len() == 1);
+        assert!(parse_source("if a { } else if { }").is_err());
+        assert!(parse_source("if a { } else print(1);").is_err());
+    }
+
+    #[test]
+    fn test_logical_expression() {
+        let ast = parse_source("if !done && ready || false { }").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        let AST::If(condition, _, _) = &nodes[0] else { panic!() };
+        let AST::Or(left, right) = condition.as_ref() else { panic!("|| should bind loosest") };
+        assert!(matches!(left.as_ref(), AST::And(not, _) if matches!(not.as_ref(), AST::Not(_))));
+        assert!(matches!(right.as_ref(), AST::Bool(false)));
+    }
+
+    #[test]
+    fn test_config_keywords() {
+        let ast = parse_source("@deploy \"ethereum\" { force = true, optimization_used = false, match = \"x\" }").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        let AST::Deploy(_, config) = &nodes[0] else { panic!() };
+        assert_eq!(config["force"], "true");
+        assert_eq!(config["optimization_used"], "false");
+        assert_eq!(config["match"], "x");
+        assert!(parse_source("@deploy \"aws\" { region = let }").is_err());
+    }
+
+    #[test]
+    fn test_let_call() {
+        let ast = parse_source("let out = call build with \"x\"; let n = call count;").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        assert!(matches!(&nodes[0], AST::Let(name, value) if name == "out" && matches!(value.as_ref(), AST::Call(n, args) if n == "build" && args.len() == 1)));
+        assert!(matches!(&nodes[1], AST::Let(_, value) if matches!(value.as_ref(), AST::Call(_, args) if args.is_empty())));
+    }
+
+    #[test]
+    fn test_param() {
+        let ast = parse_source("param region: string = \"eu\"; param count: int;").unwrap();
+        let AST::Program(nodes) = ast else { panic!() };
+        assert!(matches!(&nodes[0], AST::Param(name, ty, Some(_)) if name == "region" && ty == "string"));
+        assert!(matches!(&nodes[1], AST::Param(_, _, None)));
+        assert!(parse_source("param x: date;").unwrap_err().to_string().contains("Unknown parameter type"));
+    }
+}
@@ -0,0 +1,38 @@
+// `--plain` output for screen readers, dumb terminals and CI logs: no color,
+// no symbols beyond ASCII and no redrawn progress, just one line per update.
+// It is recorded in the environment, so fuse programs and the package
+// managers and build tools they start inherit it too.
+pub const VAR: &str = "RIFT_PLAIN";
+
+// The usual switches for turning off color, spinners and progress bars in
+// the tools fuse code tends to run
+const CHILD_SETTINGS: &[(&str, &str)] = &[
+    ("NO_COLOR", "1"),
+    ("TERM", "dumb"),
+    ("FORCE_COLOR", "0"),
+    ("CLICOLOR", "0"),
+    ("PIP_PROGRESS_BAR", "off"),
+    ("NPM_CONFIG_PROGRESS", "false"),
+    ("NPM_CONFIG_COLOR", "false"),
+    ("CARGO_TERM_COLOR", "never"),
+    ("CARGO_TERM_PROGRESS_WHEN", "never"),
+    ("BUILDKIT_PROGRESS", "plain"),
+];
+
+pub fn enable() {
+    std::env::set_var(VAR, "1");
+    for (name, value) in CHILD_SETTINGS {
+        std::env::set_var(name, value);
+    }
+}
+
+// Also on for a terminal that says it can't do better
+pub fn enabled() -> bool {
+    std::env::var(VAR).is_ok_and(|v| !v.is_empty() && v != "0")
+        || std::env::var("TERM").is_ok_and(|term| term == "dumb")
+}
+
+// Color is also off under NO_COLOR (https://no-color.org)
+pub fn color() -> bool {
+    !enabled() && std::env::var_os("NO_COLOR").is_none()
+}
@@ -0,0 +1,407 @@
+use crate::error::Result;
+use crate::i18n;
+use crate::interpreter::Environment;
+use crate::value::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+// An extension adding REPL commands, builtins, deploy targets or
+// `optimize` transforms. Each method is only called with names the plugin
+// listed for that kind.
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn commands(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn builtins(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn deploy_targets(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    // (from, to) language pairs `transform` rewrites
+    fn transforms(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    fn run_command(&self, name: &str, _args: &[&str], _env: &mut Environment) -> std::result::Result<(), String> {
+        Err(format!("{} does not implement command '{}'", self.name(), name))
+    }
+
+    fn call_builtin(&self, name: &str, _args: &[Value]) -> std::result::Result<Value, String> {
+        Err(format!("{} does not implement builtin '{}'", self.name(), name))
+    }
+
+    // Returns the address of the deployment, like the built-in targets
+    fn deploy(&self, target: &str, _artifact: &str, _config: &HashMap<String, String>) -> std::result::Result<String, String> {
+        Err(format!("{} does not implement deploy target '{}'", self.name(), target))
+    }
+
+    fn transform(&self, from: &str, to: &str, _code: &str, _env: &Environment) -> std::result::Result<String, String> {
+        Err(format!("{} does not implement transforming {} to {}", self.name(), from, to))
+    }
+}
+
+// Loaded plugins, searched in load order; the first to claim a name wins
+#[derive(Clone, Default)]
+pub struct Plugins {
+    loaded: Vec<Arc<dyn Plugin>>,
+}
+
+impl fmt::Debug for Plugins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.loaded.iter().map(|p| p.name())).finish()
+    }
+}
+
+impl Plugins {
+    pub fn register(&mut self, plugin: Arc<dyn Plugin>) {
+        self.loaded.push(plugin);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Plugin>> {
+        self.loaded.iter()
+    }
+
+    pub fn command(&self, name: &str) -> Option<Arc<dyn Plugin>> {
+        self.find(|p| p.commands().iter().any(|c| c == name))
+    }
+
+    pub fn builtin(&self, name: &str) -> Option<Arc<dyn Plugin>> {
+        self.find(|p| p.builtins().iter().any(|b| b == name))
+    }
+
+    pub fn deploy_target(&self, name: &str) -> Option<Arc<dyn Plugin>> {
+        self.find(|p| p.deploy_targets().iter().any(|t| t == name))
+    }
+
+    pub fn transform(&self, from: &str, to: &str) -> Option<Arc<dyn Plugin>> {
+        self.find(|p| p.transforms().iter().any(|(f, t)| f == from && t == to))
+    }
+
+    fn find(&self, claims: impl Fn(&dyn Plugin) -> bool) -> Option<Arc<dyn Plugin>> {
+        self.loaded.iter().find(|p| claims(p.as_ref())).cloned()
+    }
+}
+
+type HostFunction = dyn Fn(&[Value]) -> std::result::Result<Value, String> + Send + Sync;
+
+// A native function registered by an application embedding rift, see
+// `Environment::register_fn`
+pub struct HostFn {
+    name: String,
+    function: Box<HostFunction>,
+}
+
+impl HostFn {
+    pub fn new<F>(name: &str, function: F) -> Self
+    where
+        F: Fn(&[Value]) -> std::result::Result<Value, String> + Send + Sync + 'static,
+    {
+        Self { name: name.to_string(), function: Box::new(function) }
+    }
+}
+
+impl Plugin for HostFn {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn builtins(&self) -> Vec<String> {
+        vec![self.name.clone()]
+    }
+
+    fn call_builtin(&self, _name: &str, args: &[Value]) -> std::result::Result<Value, String> {
+        (self.function)(args)
+    }
+}
+
+// Plugins live in `.rift/plugins`; each shared library or WASM component
+// there is loaded at startup
+pub fn plugin_dir() -> std::path::PathBuf {
+    crate::state::state_dir().join("plugins")
+}
+
+#[cfg(feature = "plugins")]
+pub fn load_dir(dir: &Path, plugins: &mut Plugins) -> Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let mut paths: Vec<_> = std::fs::read_dir(dir)?.filter_map(|entry| entry.ok().map(|e| e.path())).collect();
+    paths.sort();
+    for path in paths {
+        let plugin: Arc<dyn Plugin> = match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext == std::env::consts::DLL_EXTENSION => Arc::new(native::NativePlugin::load(&path)?),
+            #[cfg(feature = "wasm-plugins")]
+            Some("wasm") => Arc::new(wasm::WasmPlugin::load(&path)?),
+            #[cfg(not(feature = "wasm-plugins"))]
+            Some("wasm") => {
+                eprintln!("{}", i18n::message("plugin.ignored", &[&path.display(), &"wasm-plugins"]));
+                continue;
+            }
+            _ => continue,
+        };
+        tracing::info!("loaded plugin {} from {}", plugin.name(), path.display());
+        plugins.register(plugin);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "plugins"))]
+pub fn load_dir(dir: &Path, _plugins: &mut Plugins) -> Result<()> {
+    if dir.read_dir().is_ok_and(|mut entries| entries.next().is_some()) {
+        eprintln!("{}", i18n::message("plugin.ignored", &[&dir.display(), &"plugins"]));
+    }
+    Ok(())
+}
+
+// Shared libraries talk to rift over a C ABI with JSON payloads, so they
+// need not be built with the same compiler as rift. A plugin exports:
+//
+//   rift_plugin_manifest() -> *mut c_char
+//       {"name": "...", "commands": [...], "builtins": [...], "deploy_targets": [...]}
+//   rift_plugin_call(request: *const c_char) -> *mut c_char
+//       request  {"kind": "command" | "builtin" | "deploy", "name": "...", ...}
+//       response {"ok": <json>} or {"error": "..."}
+//   rift_plugin_free(ptr: *mut c_char)
+//
+// Commands get "args" (strings), builtins "args" (JSON values) and deploys
+// "artifact" and "config". Everything returned is freed by rift_plugin_free.
+#[cfg(feature = "plugins")]
+mod native {
+    use super::*;
+    use crate::error::RiftError;
+    use serde::Deserialize;
+    use std::ffi::{c_char, CStr, CString};
+
+    type ManifestFn = unsafe extern "C" fn() -> *mut c_char;
+    type CallFn = unsafe extern "C" fn(*const c_char) -> *mut c_char;
+    type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+    #[derive(Debug, Deserialize)]
+    struct Manifest {
+        name: String,
+        #[serde(default)]
+        commands: Vec<String>,
+        #[serde(default)]
+        builtins: Vec<String>,
+        #[serde(default)]
+        deploy_targets: Vec<String>,
+    }
+
+    pub struct NativePlugin {
+        manifest: Manifest,
+        call: CallFn,
+        free: FreeFn,
+        // Keeps the functions above mapped
+        _library: libloading::Library,
+    }
+
+    impl NativePlugin {
+        pub fn load(path: &Path) -> Result<Self> {
+            let error = |message: String| RiftError::ConfigError(format!("plugin {}: {}", path.display(), message));
+            // Loading runs the library's initialisers; the plugin directory
+            // is trusted like rift.toml hooks are
+            let library = unsafe { libloading::Library::new(path) }.map_err(|e| error(e.to_string()))?;
+            let (manifest, call, free) = unsafe {
+                let manifest: ManifestFn = *library.get(b"rift_plugin_manifest\0").map_err(|e| error(e.to_string()))?;
+                let call: CallFn = *library.get(b"rift_plugin_call\0").map_err(|e| error(e.to_string()))?;
+                let free: FreeFn = *library.get(b"rift_plugin_free\0").map_err(|e| error(e.to_string()))?;
+                (take_string(manifest(), free), call, free)
+            };
+            let manifest = serde_json::from_str(&manifest.ok_or_else(|| error("empty manifest".to_string()))?)
+                .map_err(|e| error(format!("invalid manifest: {}", e)))?;
+            Ok(Self { manifest, call, free, _library: library })
+        }
+
+        fn request(&self, request: serde_json::Value) -> std::result::Result<serde_json::Value, String> {
+            let request = CString::new(request.to_string()).map_err(|e| e.to_string())?;
+            let response = unsafe { take_string((self.call)(request.as_ptr()), self.free) }
+                .ok_or_else(|| format!("plugin {} returned nothing", self.manifest.name))?;
+            let mut response: serde_json::Value = serde_json::from_str(&response)
+                .map_err(|e| format!("plugin {} returned invalid JSON: {}", self.manifest.name, e))?;
+            if let Some(error) = response.get("error").and_then(|e| e.as_str()) {
+                return Err(error.to_string());
+            }
+            Ok(response.get_mut("ok").map(serde_json::Value::take).unwrap_or_default())
+        }
+    }
+
+    unsafe fn take_string(ptr: *mut c_char, free: FreeFn) -> Option<String> {
+        if ptr.is_null() {
+            return None;
+        }
+        let text = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+        free(ptr);
+        Some(text)
+    }
+
+    impl Plugin for NativePlugin {
+        fn name(&self) -> &str {
+            &self.manifest.name
+        }
+
+        fn commands(&self) -> Vec<String> {
+            self.manifest.commands.clone()
+        }
+
+        fn builtins(&self) -> Vec<String> {
+            self.manifest.builtins.clone()
+        }
+
+        fn deploy_targets(&self) -> Vec<String> {
+            self.manifest.deploy_targets.clone()
+        }
+
+        fn run_command(&self, name: &str, args: &[&str], _env: &mut Environment) -> std::result::Result<(), String> {
+            let output = self.request(serde_json::json!({ "kind": "command", "name": name, "args": args }))?;
+            if let Some(text) = output.as_str() {
+                println!("{}", text);
+            }
+            Ok(())
+        }
+
+        fn call_builtin(&self, name: &str, args: &[Value]) -> std::result::Result<Value, String> {
+            let args: Vec<_> = args.iter().map(Value::to_json).collect();
+            self.request(serde_json::json!({ "kind": "builtin", "name": name, "args": args })).map(Value::from_json)
+        }
+
+        fn deploy(&self, target: &str, artifact: &str, config: &HashMap<String, String>) -> std::result::Result<String, String> {
+            let address = self.request(serde_json::json!({ "kind": "deploy", "name": target, "artifact": artifact, "config": config }))?;
+            address.as_str().map(str::to_string).ok_or_else(|| format!("{} deploy returned no address", target))
+        }
+    }
+}
+
+// WASM components (see wit/plugin.wit) run under wasmtime with only the
+// host interface linked, so a community plugin can add commands and
+// transforms without being trusted with the process. Each call gets a fuel
+// budget so a runaway plugin traps instead of hanging the session.
+#[cfg(feature = "wasm-plugins")]
+mod wasm {
+    use super::*;
+    use crate::error::RiftError;
+    use std::sync::Mutex;
+    use wasmtime::component::{Component, Linker};
+    use wasmtime::{Config, Engine, Store};
+
+    wasmtime::component::bindgen!({ path: "wit/plugin.wit", world: "rift-plugin" });
+
+    const FUEL_PER_CALL: u64 = 1_000_000_000;
+
+    #[derive(Default)]
+    struct HostState {
+        variables: HashMap<String, String>,
+        transforms: Vec<(String, String)>,
+        initialising: bool,
+    }
+
+    impl rift::plugin::host::Host for HostState {
+        fn get_variable(&mut self, name: String) -> wasmtime::Result<Option<String>> {
+            Ok(self.variables.get(&name).cloned())
+        }
+
+        fn emit(&mut self, text: String) -> wasmtime::Result<()> {
+            println!("{}", text);
+            Ok(())
+        }
+
+        fn register_transform(&mut self, from: String, to: String) -> wasmtime::Result<()> {
+            if self.initialising {
+                self.transforms.push((from, to));
+            }
+            Ok(())
+        }
+    }
+
+    pub struct WasmPlugin {
+        name: String,
+        commands: Vec<String>,
+        transforms: Vec<(String, String)>,
+        instance: Mutex<(Store<HostState>, RiftPlugin)>,
+    }
+
+    impl WasmPlugin {
+        pub fn load(path: &Path) -> Result<Self> {
+            let error = |e: wasmtime::Error| RiftError::ConfigError(format!("plugin {}: {}", path.display(), e));
+            let mut config = Config::new();
+            config.wasm_component_model(true).consume_fuel(true);
+            let engine = Engine::new(&config).map_err(error)?;
+            let component = Component::from_file(&engine, path).map_err(error)?;
+            let mut linker = Linker::new(&engine);
+            RiftPlugin::add_to_linker(&mut linker, |state: &mut HostState| state).map_err(error)?;
+
+            let mut store = Store::new(&engine, HostState { initialising: true, ..Default::default() });
+            store.set_fuel(FUEL_PER_CALL).map_err(error)?;
+            let (bindings, _) = RiftPlugin::instantiate(&mut store, &component, &linker).map_err(error)?;
+            bindings.call_init(&mut store).map_err(error)?;
+            let name = bindings.call_name(&mut store).map_err(error)?;
+            let commands = bindings.call_commands(&mut store).map_err(error)?;
+            store.data_mut().initialising = false;
+            let transforms = std::mem::take(&mut store.data_mut().transforms);
+            Ok(Self { name, commands, transforms, instance: Mutex::new((store, bindings)) })
+        }
+
+        // Runs `call` with the session's variables visible to get-variable
+        fn with_instance<T>(
+            &self,
+            env: &Environment,
+            call: impl FnOnce(&RiftPlugin, &mut Store<HostState>) -> wasmtime::Result<std::result::Result<T, String>>,
+        ) -> std::result::Result<T, String> {
+            let mut guard = self.instance.lock().map_err(|_| format!("plugin {} panicked earlier", self.name))?;
+            let (store, bindings) = &mut *guard;
+            store.data_mut().variables = env.variables.iter().map(|(k, v)| (k.clone(), v.to_string())).collect();
+            store.set_fuel(FUEL_PER_CALL).map_err(|e| e.to_string())?;
+            let result = call(bindings, store).map_err(|e| format!("plugin {} trapped: {}", self.name, e));
+            store.data_mut().variables.clear();
+            result?
+        }
+    }
+
+    impl Plugin for WasmPlugin {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn commands(&self) -> Vec<String> {
+            self.commands.clone()
+        }
+
+        fn transforms(&self) -> Vec<(String, String)> {
+            self.transforms.clone()
+        }
+
+        fn run_command(&self, name: &str, args: &[&str], env: &mut Environment) -> std::result::Result<(), String> {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            self.with_instance(env, |bindings, store| bindings.call_run_command(store, name, &args))
+        }
+
+        fn transform(&self, from: &str, to: &str, code: &str, env: &Environment) -> std::result::Result<String, String> {
+            self.with_instance(env, |bindings, store| bindings.call_transform(store, from, to, code))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_fn() {
+        let mut env = Environment::new();
+        env.register_fn("double", |args| match args {
+            [Value::Int(n)] => Ok(Value::Int(n * 2)),
+            _ => Err("double() expects one integer".to_string()),
+        });
+        let plugin = env.plugins.builtin("double").unwrap();
+        assert_eq!(plugin.call_builtin("double", &[Value::Int(21)]).unwrap().to_string(), "42");
+        assert!(env.plugins.builtin("triple").is_none());
+        assert!(env.plugins.command("double").is_none());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::config::PolicyConfig;
+use std::path::{Component, Path, PathBuf};
+
+// Gate for anything a script can do to the host beyond running fuse blocks.
+// Paths are resolved before checking, so `../` and symlinks cannot escape an
+// allowed root.
+pub fn check_read(policy: &PolicyConfig, path: &str) -> Result<PathBuf, String> {
+    check_path(&policy.fs_read, path, "read")
+}
+
+pub fn check_write(policy: &PolicyConfig, path: &str) -> Result<PathBuf, String> {
+    check_path(&policy.fs_write, path, "write")
+}
+
+pub fn check_env_read(policy: &PolicyConfig, name: &str) -> Result<(), String> {
+    check_env(&policy.env_read, name, "read")
+}
+
+pub fn check_env_write(policy: &PolicyConfig, name: &str) -> Result<(), String> {
+    check_env(&policy.env_write, name, "write")
+}
+
+fn check_env(patterns: &[String], name: &str, access: &str) -> Result<(), String> {
+    let allowed = patterns.iter()
+        .any(|pattern| glob::Pattern::new(pattern).is_ok_and(|p| p.matches(name)));
+    if allowed {
+        Ok(())
+    } else {
+        Err(format!(
+            "Policy denies {} access to environment variable {} (allowed: {}); see [policy] in rift.toml",
+            access, name, patterns.join(", ")
+        ))
+    }
+}
+
+fn check_path(roots: &[String], path: &str, access: &str) -> Result<PathBuf, String> {
+    let resolved = resolve(Path::new(path))?;
+    for root in roots {
+        if resolved.starts_with(resolve(Path::new(root))?) {
+            return Ok(resolved);
+        }
+    }
+    Err(format!(
+        "Policy denies {} access to '{}' (allowed: {}); see [policy] in rift.toml",
+        access, path, roots.join(", ")
+    ))
+}
+
+// Canonicalizes the longest existing prefix so paths that do not exist yet
+// (write targets) still resolve through any symlinked parents
+fn resolve(path: &Path) -> Result<PathBuf, String> {
+    let cwd = std::env::current_dir().map_err(|e| e.to_string())?;
+    let absolute = normalize(&cwd.join(path));
+    let mut existing = absolute.as_path();
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_owned());
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+    let mut resolved = existing.canonicalize().unwrap_or_else(|_| existing.to_path_buf());
+    resolved.extend(rest.iter().rev());
+    Ok(resolved)
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::CurDir => {}
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parent_dir_cannot_escape_root() {
+        let policy = PolicyConfig::default();
+        assert!(check_read(&policy, "src/main.rs").is_ok());
+        assert!(check_write(&policy, "out/new_file.txt").is_ok());
+        assert!(check_read(&policy, "../outside.txt").is_err());
+        assert!(check_read(&policy, "src/../../outside.txt").is_err());
+        assert!(check_write(&policy, "/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_env_patterns() {
+        let policy = PolicyConfig {
+            env_read: vec!["CI_*".to_string(), "HOME".to_string()],
+            ..PolicyConfig::default()
+        };
+        assert!(check_env_read(&policy, "CI_COMMIT_SHA").is_ok());
+        assert!(check_env_read(&policy, "HOME").is_ok());
+        assert!(check_env_read(&policy, "AWS_SECRET_ACCESS_KEY").is_err());
+        assert!(check_env_write(&policy, "ANYTHING").is_ok());
+    }
+}
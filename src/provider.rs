@@ -0,0 +1,221 @@
+// Where fuse blocks get their toolchains and dependencies, chosen with
+// `[executor] provider = "..."` in rift.toml, for organizations that forbid
+// ad-hoc global pip and npm installs. "system" (the default) installs
+// dependencies with each language's own tool as blocks run. "nix" keeps a
+// shell.nix per rift under .rift/envs/nix, grown as its blocks need packages,
+// and builds and runs every block in `nix-shell`. "conda" creates a prefix
+// environment per set of Python dependencies under .rift/envs/conda and runs
+// Python blocks with `conda run`; other languages keep their own tools.
+use crate::error::{Result, RiftError};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const ENVS_DIR: &str = "envs";
+const PACKAGES_LINE: &str = "# rift-packages:";
+// The shell blocks outside any rift, task or fn share
+const TOP_LEVEL: &str = "main";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    #[default]
+    System,
+    Nix,
+    Conda,
+}
+
+// Runs a block's build steps and program inside a provided environment
+#[derive(Debug, Clone, PartialEq)]
+pub enum Runner {
+    Nix(PathBuf),
+    Conda(PathBuf),
+}
+
+impl Runner {
+    pub fn wrap(&self, command: &Command) -> Command {
+        let mut words = vec![command.get_program().to_string_lossy().to_string()];
+        words.extend(command.get_args().map(|arg| arg.to_string_lossy().to_string()));
+        match self {
+            Runner::Nix(shell) => {
+                let mut wrapped = Command::new("nix-shell");
+                wrapped.arg(shell).arg("--run").arg(words.iter().map(|word| shell_quote(word)).collect::<Vec<_>>().join(" "));
+                wrapped
+            }
+            Runner::Conda(prefix) => {
+                let mut wrapped = Command::new("conda");
+                wrapped.arg("run").arg("-p").arg(prefix).arg("--no-capture-output").args(words);
+                wrapped
+            }
+        }
+    }
+}
+
+// The runner for a block, and how many dependencies were newly provided for
+// it; None when the language's own tools install them. `rift` names the rift,
+// task or fn running the block.
+pub fn provide(provider: Provider, language: &str, deps: &[String], rift: Option<&str>) -> Result<Option<(Runner, usize)>> {
+    match provider {
+        Provider::System => Ok(None),
+        Provider::Conda if language != "python" => Ok(None),
+        Provider::Nix => {
+            require_tool("nix-shell", language)?;
+            let wanted = nix_entries(language, deps)?;
+            let path = envs_dir()?.join("nix").join(rift.unwrap_or(TOP_LEVEL)).join("shell.nix");
+            let mut entries = fs::read_to_string(&path).map(|text| entries_of(&text)).unwrap_or_default();
+            let new: Vec<String> = wanted.into_iter().filter(|entry| !entries.contains(entry)).collect();
+            if !new.is_empty() {
+                let added = new.iter().filter(|entry| entry.contains(':')).count();
+                entries.extend(new);
+                fs::create_dir_all(path.parent().expect("shell.nix has a directory"))?;
+                fs::write(&path, shell_nix(rift.unwrap_or(TOP_LEVEL), &entries))?;
+                return Ok(Some((Runner::Nix(path), added)));
+            }
+            Ok(Some((Runner::Nix(path), 0)))
+        }
+        Provider::Conda => {
+            require_tool("conda", language)?;
+            let packages: BTreeSet<&str> = deps.iter().map(|dep| top_module(dep)).collect();
+            let key = packages.iter().copied().collect::<Vec<_>>().join(" ");
+            let hash = format!("{:x}", Sha256::digest(key.as_bytes()));
+            let prefix = envs_dir()?.join("conda").join(&hash[..12]);
+            if prefix.exists() {
+                return Ok(Some((Runner::Conda(prefix), 0)));
+            }
+            // Created beside its final place, so an interrupted create is never reused
+            let partial = prefix.with_extension("partial");
+            fs::remove_dir_all(&partial).ok();
+            let output = Command::new("conda").args(["create", "--yes", "--quiet", "-p"]).arg(&partial).arg("python").args(&packages).output()?;
+            if !output.status.success() {
+                fs::remove_dir_all(&partial).ok();
+                return Err(RiftError::DependencyError { language: language.to_string(), dependency: format!("{} (conda create failed: {})", key, String::from_utf8_lossy(&output.stderr).trim()) });
+            }
+            fs::rename(&partial, &prefix)?;
+            Ok(Some((Runner::Conda(prefix), packages.len())))
+        }
+    }
+}
+
+// Absolute, since blocks run inside their workspace
+fn envs_dir() -> Result<PathBuf> {
+    Ok(std::env::current_dir()?.join(crate::state::state_dir()).join(ENVS_DIR))
+}
+
+fn require_tool(tool: &str, language: &str) -> Result<()> {
+    Command::new(tool).arg("--version").output().map(|_| ()).map_err(|e| RiftError::ExecutionError {
+        language: language.to_string(),
+        message: format!("{} not found ({}); it provides toolchains and dependencies under [executor] provider in rift.toml", tool, e),
+    })
+}
+
+// "numpy" for `import numpy.linalg`
+fn top_module(dep: &str) -> &str {
+    dep.split('.').next().unwrap_or(dep)
+}
+
+// The language itself, then "language:package" for each dependency
+fn nix_entries(language: &str, deps: &[String]) -> Result<BTreeSet<String>> {
+    let mut entries = BTreeSet::from([language.to_string()]);
+    let packages: Vec<&str> = match language {
+        "python" => deps.iter().map(|dep| top_module(dep)).collect(),
+        "javascript" => deps.iter().map(String::as_str).collect(),
+        // Go fetches its own modules; C++, PHP and Rust blocks install nothing
+        "go" | "cpp" | "php" | "rust" => Vec::new(),
+        _ => match deps.first() {
+            Some(dep) => return Err(RiftError::DependencyError { language: language.to_string(), dependency: format!("{} (the nix provider has no {} packages)", dep, language) }),
+            None => Vec::new(),
+        },
+    };
+    for package in packages {
+        if package.is_empty() || package.contains(|c: char| c.is_whitespace() || "\"\\$".contains(c)) {
+            return Err(RiftError::DependencyError { language: language.to_string(), dependency: package.to_string() });
+        }
+        entries.insert(format!("{}:{}", language, package));
+    }
+    Ok(entries)
+}
+
+fn entries_of(shell_nix: &str) -> BTreeSet<String> {
+    shell_nix.lines()
+        .find_map(|line| line.strip_prefix(PACKAGES_LINE))
+        .map(|entries| entries.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn shell_nix(rift: &str, entries: &BTreeSet<String>) -> String {
+    let packages_of = |language: &str| -> Vec<String> {
+        entries.iter().filter_map(|entry| entry.strip_prefix(language)?.strip_prefix(':')).map(attribute).collect()
+    };
+    let mut packages = Vec::new();
+    for language in entries.iter().filter(|entry| !entry.contains(':')) {
+        match language.as_str() {
+            "python" => packages.push(format!("(pkgs.python3.withPackages (ps: [ {} ]))", packages_of("python").iter().map(|p| format!("ps.{}", p)).collect::<Vec<_>>().join(" "))),
+            "javascript" => {
+                packages.push("pkgs.nodejs".to_string());
+                packages.extend(packages_of("javascript").iter().map(|p| format!("pkgs.nodePackages.{}", p)));
+            }
+            "cpp" => packages.push("pkgs.gcc".to_string()),
+            "java" => packages.push("pkgs.jdk".to_string()),
+            "rust" => packages.push("pkgs.rustc".to_string()),
+            other => packages.push(format!("pkgs.{}", other)),
+        }
+    }
+    let node_path: Vec<String> = packages_of("javascript").iter().map(|p| format!("${{pkgs.nodePackages.{}}}/lib/node_modules", p)).collect();
+    let mut text = format!("# Generated by rift for {}, which adds packages as its fuse blocks need them\n", rift);
+    text.push_str(&format!("{} {}\n", PACKAGES_LINE, entries.iter().cloned().collect::<Vec<_>>().join(" ")));
+    text.push_str("{ pkgs ? import <nixpkgs> {} }:\npkgs.mkShell {\n  packages = [\n");
+    for package in packages {
+        text.push_str(&format!("    {}\n", package));
+    }
+    text.push_str("  ];\n");
+    if !node_path.is_empty() {
+        text.push_str(&format!("  shellHook = ''\n    export NODE_PATH={}\n  '';\n", node_path.join(":")));
+    }
+    text.push_str("}\n");
+    text
+}
+
+// A nixpkgs attribute name, quoted unless it is a plain identifier
+fn attribute(name: &str) -> String {
+    let plain = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "_-'".contains(c));
+    if plain { name.to_string() } else { format!("\"{}\"", name) }
+}
+
+fn shell_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_nix() {
+        let mut entries = nix_entries("python", &["numpy.linalg".to_string(), "requests".to_string()]).unwrap();
+        entries.extend(nix_entries("javascript", &["lodash.merge".to_string()]).unwrap());
+        entries.extend(nix_entries("cpp", &[]).unwrap());
+        let text = shell_nix("train", &entries);
+        assert!(text.contains("    (pkgs.python3.withPackages (ps: [ ps.numpy ps.requests ]))\n"));
+        assert!(text.contains("    pkgs.gcc\n    pkgs.nodejs\n    pkgs.nodePackages.\"lodash.merge\"\n"));
+        assert!(text.contains("export NODE_PATH=${pkgs.nodePackages.\"lodash.merge\"}/lib/node_modules"));
+        assert_eq!(entries_of(&text), entries);
+
+        assert!(nix_entries("java", &["org.json:json:1.0".to_string()]).is_err());
+        assert!(nix_entries("python", &["bad\"name".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_wrap() {
+        let mut command = Command::new("python3");
+        command.arg("it's.py");
+        let nix = Runner::Nix(PathBuf::from("/p/shell.nix")).wrap(&command);
+        let args: Vec<_> = nix.get_args().map(|arg| arg.to_string_lossy().to_string()).collect();
+        assert_eq!(args, ["/p/shell.nix", "--run", "'python3' 'it'\\''s.py'"]);
+        let conda = Runner::Conda(PathBuf::from("/p/env")).wrap(&command);
+        assert_eq!(conda.get_args().count(), 6);
+    }
+}
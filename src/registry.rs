@@ -0,0 +1,514 @@
+// `rift install`: shared rift libraries (rifts, tasks and fns in .rift files,
+// optionally with transform plugins under plugins/) listed in a registry
+// index. The index is JSON mapping each package to its releases,
+//   { "name": { "1.2.0": { "url": "name-1.2.0.tar.gz", "sha256": "..." } } }
+// fetched over HTTP(S), from a git repository holding index.json
+// ("git+https://..."), or from a local path. Release urls may be relative
+// to the index. A package unpacks into .rift/lib/<name>, and `import name;`
+// loads its .rift files. `rift publish` checks and packs a package described
+// by rift-package.toml and adds it to a registry.
+use crate::error::{Result, RiftError};
+use crate::{executor, state, AST};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const INDEX_FILE: &str = "index.json";
+// Written into each installed package, recording what was installed
+const MANIFEST_FILE: &str = "rift-package.json";
+pub const PACKAGE_FILE: &str = "rift-package.toml";
+const TOKEN_VAR: &str = "RIFT_REGISTRY_TOKEN";
+const TOKEN_SECRET: &str = "registry-token";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Release {
+    pub url: String,
+    pub sha256: String,
+}
+
+type Index = BTreeMap<String, BTreeMap<String, Release>>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Installed {
+    pub name: String,
+    pub version: String,
+    pub sha256: String,
+    pub plugins: Vec<String>,
+}
+
+pub fn lib_dir() -> PathBuf {
+    state::state_dir().join("lib")
+}
+
+// `--registry`, then RIFT_REGISTRY, then `registry = "..."` in rift.toml
+pub fn index_location(flag: Option<&str>, config: &crate::config::Config) -> Result<String> {
+    flag.map(str::to_string)
+        .or_else(|| std::env::var("RIFT_REGISTRY").ok())
+        .or_else(|| config.registry.clone())
+        .ok_or_else(|| RiftError::ConfigError("no registry configured; pass --registry or set registry = \"...\" in rift.toml".to_string()))
+}
+
+// `name` or `name@version`; without a version the newest release is used
+pub async fn install(spec: &str, index: &str) -> Result<Installed> {
+    install_in(&lib_dir(), spec, index).await
+}
+
+async fn install_in(lib: &Path, spec: &str, index: &str) -> Result<Installed> {
+    let (name, version) = match spec.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (spec, None),
+    };
+    check_name(name)?;
+    let (entries, base) = fetch_index(index).await?;
+    let releases = entries.get(name).ok_or_else(|| RiftError::ConfigError(format!("{} has no package '{}'", index, name)))?;
+    let (version, release) = match version {
+        Some(version) => releases.get_key_value(version)
+            .ok_or_else(|| RiftError::ConfigError(format!("{} has no release {}; available: {}", name, version, releases.keys().cloned().collect::<Vec<_>>().join(", "))))?,
+        None => releases.iter().max_by(|a, b| version_key(a.0).cmp(&version_key(b.0)))
+            .ok_or_else(|| RiftError::ConfigError(format!("{} has no releases", name)))?,
+    };
+
+    let archive = fetch(&resolve(&base, &release.url)).await?;
+    let digest = format!("{:x}", Sha256::digest(&archive));
+    if !digest.eq_ignore_ascii_case(&release.sha256) {
+        return Err(RiftError::ConfigError(format!("{}@{}: checksum mismatch (index says {}, download is {})", name, version, release.sha256, digest)));
+    }
+
+    // Unpacked beside the final directory, so a bad archive leaves any
+    // installed version in place
+    let dir = lib.join(name);
+    let staging = lib.join(format!(".{}.partial", name));
+    let _ = fs::remove_dir_all(&staging);
+    fs::create_dir_all(&staging)?;
+    let unpacked = tar::Archive::new(flate2::read::GzDecoder::new(archive.as_slice())).unpack(&staging);
+    if let Err(e) = unpacked {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(RiftError::ConfigError(format!("{}@{}: {}", name, version, e)));
+    }
+    let plugin_dir = crate::plugin::plugin_dir();
+    let conflicts = plugin_conflicts(lib, &plugin_dir, name, &staging.join("plugins"));
+    if !conflicts.is_empty() {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(RiftError::ConfigError(format!(
+            "{}@{}: {} already has plugins named {}; remove them or install one of the packages",
+            name, version, plugin_dir.display(), conflicts.join(", ")
+        )));
+    }
+    let _ = fs::remove_dir_all(&dir);
+    fs::rename(&staging, &dir)?;
+
+    let plugins = install_plugins(&dir.join("plugins"), &plugin_dir)?;
+    let installed = Installed { name: name.to_string(), version: version.clone(), sha256: digest, plugins };
+    fs::write(dir.join(MANIFEST_FILE), serde_json::to_string_pretty(&installed)?)?;
+    Ok(installed)
+}
+
+// The .rift files `import name;` runs, in name order
+pub fn sources(name: &str) -> Result<Vec<(PathBuf, String)>> {
+    sources_in(&lib_dir(), name)
+}
+
+fn sources_in(lib: &Path, name: &str) -> Result<Vec<(PathBuf, String)>> {
+    check_name(name)?;
+    let dir = lib.join(name);
+    if !dir.join(MANIFEST_FILE).exists() {
+        return Err(RiftError::ConfigError(format!("package '{}' is not installed; run `rift install {}`", name, name)));
+    }
+    let mut paths: Vec<PathBuf> = fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rift"))
+        .collect();
+    paths.sort();
+    paths.into_iter().map(|path| Ok((path.clone(), fs::read_to_string(&path)?))).collect()
+}
+
+// Names become directory names, so nothing that could leave .rift/lib
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(RiftError::ConfigError(format!("invalid package name '{}'", name)));
+    }
+    Ok(())
+}
+
+// The index and where relative release urls start from
+async fn fetch_index(location: &str) -> Result<(Index, String)> {
+    let (text, base) = if let Some(url) = location.strip_prefix("git+") {
+        let checkout = state::state_dir().join("registry").join(format!("{:x}", Sha256::digest(url.as_bytes())));
+        crate::git::sync_clone(url, &checkout)?;
+        (fs::read_to_string(checkout.join(INDEX_FILE))?, checkout.display().to_string())
+    } else if location.starts_with("http://") || location.starts_with("https://") {
+        let url = match location.ends_with(".json") {
+            true => location.to_string(),
+            false => format!("{}/{}", location.trim_end_matches('/'), INDEX_FILE),
+        };
+        let text = String::from_utf8_lossy(&fetch(&url).await?).into_owned();
+        (text, url.rsplit_once('/').map_or(url.clone(), |(base, _)| base.to_string()))
+    } else {
+        let path = Path::new(location);
+        let file = if path.is_dir() { path.join(INDEX_FILE) } else { path.to_path_buf() };
+        let base = file.parent().map_or_else(|| ".".to_string(), |dir| dir.display().to_string());
+        (fs::read_to_string(&file)?, base)
+    };
+    let index = serde_json::from_str(&text).map_err(|e| RiftError::ConfigError(format!("{}: not a registry index: {}", location, e)))?;
+    Ok((index, base))
+}
+
+fn resolve(base: &str, url: &str) -> String {
+    if url.contains("://") || Path::new(url).is_absolute() {
+        url.to_string()
+    } else {
+        format!("{}/{}", base.trim_end_matches('/'), url)
+    }
+}
+
+async fn fetch(location: &str) -> Result<Vec<u8>> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        let response = reqwest::get(location).await?.error_for_status()?;
+        Ok(response.bytes().await?.to_vec())
+    } else {
+        Ok(fs::read(location)?)
+    }
+}
+
+// Plugins in `shipped` whose names are already taken in `target` by another
+// package or by hand, each with where it came from. A package may replace
+// its own plugins when it is reinstalled.
+fn plugin_conflicts(lib: &Path, target: &Path, name: &str, shipped: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(shipped) else {
+        return Vec::new();
+    };
+    let installed: Vec<Installed> = fs::read_dir(lib).into_iter().flatten()
+        .filter_map(|entry| fs::read_to_string(entry.ok()?.path().join(MANIFEST_FILE)).ok())
+        .filter_map(|text| serde_json::from_str(&text).ok())
+        .collect();
+    let mut conflicts: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|plugin| target.join(plugin).exists())
+        .filter_map(|plugin| match installed.iter().find(|package| package.plugins.contains(&plugin)) {
+            Some(package) if package.name == name => None,
+            Some(package) => Some(format!("{} (from {})", plugin, package.name)),
+            None => Some(format!("{} (not from a package)", plugin)),
+        })
+        .collect();
+    conflicts.sort();
+    conflicts
+}
+
+// Transform plugins shipped with a package join the others in .rift/plugins;
+// plugin_conflicts has checked that none is overwritten
+fn install_plugins(dir: &Path, target: &Path) -> Result<Vec<String>> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    fs::create_dir_all(target)?;
+    let mut copied = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if let (true, Some(name)) = (path.is_file(), path.file_name()) {
+            fs::copy(&path, target.join(name))?;
+            copied.push(name.to_string_lossy().into_owned());
+        }
+    }
+    copied.sort();
+    Ok(copied)
+}
+
+// "1.10.0" sorts after "1.9.2"; parts that aren't numbers sort first
+fn version_key(version: &str) -> Vec<u64> {
+    version.split(['.', '-']).map(|part| part.parse().unwrap_or(0)).collect()
+}
+
+// The author's description of a package, kept beside its .rift files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageManifest {
+    pub name: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    // Rifts, tasks and fns meant to be called by importers
+    #[serde(default)]
+    pub rifts: Vec<String>,
+    // Every language the package's fuse blocks use
+    #[serde(default)]
+    pub languages: Vec<String>,
+}
+
+pub struct Package {
+    pub manifest: PackageManifest,
+    pub files: Vec<String>,
+    pub archive: Vec<u8>,
+    pub sha256: String,
+}
+
+impl Package {
+    pub fn file_name(&self) -> String {
+        format!("{}-{}.tar.gz", self.manifest.name, self.manifest.version)
+    }
+}
+
+// Checks `dir`'s rift-package.toml against its sources, reporting every
+// problem at once, then packs the manifest, the .rift files and plugins/
+pub fn pack(dir: &Path) -> Result<Package> {
+    let manifest_path = dir.join(PACKAGE_FILE);
+    let text = fs::read_to_string(&manifest_path).map_err(|e| RiftError::ConfigError(format!("{}: {}", manifest_path.display(), e)))?;
+    let manifest: PackageManifest = toml::from_str(&text).map_err(|e| RiftError::ConfigError(format!("{}: {}", manifest_path.display(), e)))?;
+
+    let mut problems = Vec::new();
+    if check_name(&manifest.name).is_err() {
+        problems.push(format!("name '{}' may only use letters, digits, '-' and '_'", manifest.name));
+    }
+    if !valid_version(&manifest.version) {
+        problems.push(format!("version '{}' is not like 1.2.0", manifest.version));
+    }
+    if manifest.rifts.is_empty() {
+        problems.push("rifts lists nothing for importers to call".to_string());
+    }
+
+    let sources = files_in(dir, |path| path.extension().is_some_and(|ext| ext == "rift"))?;
+    if sources.is_empty() {
+        problems.push("there are no .rift files".to_string());
+    }
+    let (mut defined, mut used) = (BTreeSet::new(), BTreeSet::new());
+    for name in &sources {
+        let source = fs::read_to_string(dir.join(name))?;
+        let program = match crate::lexer::tokenize(&source).and_then(|tokens| crate::parser::parse(&tokens)) {
+            Ok(program) => program,
+            Err(e) => {
+                problems.push(format!("{}: {}", name, e));
+                continue;
+            }
+        };
+        let mut nodes = Vec::new();
+        crate::validate::walk(&program, &mut nodes);
+        for node in nodes {
+            match node {
+                AST::Rift(name, _) | AST::Task(name, _) | AST::Function(name, _, _) => {
+                    defined.insert(name.clone());
+                }
+                AST::Fuse(lang, _) | AST::InteractiveFuse(lang, _) | AST::RemoteFuse(_, lang, _) => {
+                    used.insert(if lang == "js" { "javascript".to_string() } else { lang.clone() });
+                }
+                _ => {}
+            }
+        }
+    }
+    for name in manifest.rifts.iter().filter(|name| !defined.contains(*name)) {
+        problems.push(format!("'{}' is listed in rifts but not defined", name));
+    }
+    for lang in manifest.languages.iter().filter(|lang| !executor::LANGUAGES.contains(&lang.as_str())) {
+        problems.push(format!("unknown language '{}' (one of {})", lang, executor::LANGUAGES.join(", ")));
+    }
+    for lang in used.iter().filter(|lang| !manifest.languages.contains(*lang)) {
+        problems.push(format!("fuse blocks use {} but languages does not list it", lang));
+    }
+    if !problems.is_empty() {
+        return Err(RiftError::ConfigError(format!("{} cannot be published:\n  {}", dir.display(), problems.join("\n  "))));
+    }
+
+    let mut files = vec![PACKAGE_FILE.to_string()];
+    files.extend(sources);
+    let plugins = dir.join("plugins");
+    if plugins.is_dir() {
+        files.extend(files_in(&plugins, |path| path.is_file())?.into_iter().map(|name| format!("plugins/{}", name)));
+    }
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()));
+    for name in &files {
+        let data = fs::read(dir.join(name))?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        // No timestamps, so packing the same files gives the same checksum
+        header.set_mtime(0);
+        builder.append_data(&mut header, name, data.as_slice())?;
+    }
+    let archive = builder.into_inner()?.finish()?;
+    let sha256 = format!("{:x}", Sha256::digest(&archive));
+    Ok(Package { manifest, files, archive, sha256 })
+}
+
+// An HTTP(S) registry takes the archive as a multipart POST to <registry>/publish,
+// authorized with RIFT_REGISTRY_TOKEN or the "registry-token" secret; a local
+// registry gets the archive beside its index.json. Returns where it went.
+pub async fn publish(package: &Package, registry: &str) -> Result<String> {
+    if registry.starts_with("git+") {
+        Err(RiftError::ConfigError("cannot publish to a git registry directly; publish to a local checkout of it, then commit and push".to_string()))
+    } else if registry.starts_with("http://") || registry.starts_with("https://") {
+        upload(package, registry).await
+    } else {
+        add_to_local(package, registry)
+    }
+}
+
+async fn upload(package: &Package, registry: &str) -> Result<String> {
+    let token = match std::env::var(TOKEN_VAR) {
+        Ok(token) => token,
+        Err(_) => crate::secrets::get(TOKEN_SECRET)?.ok_or_else(|| RiftError::SecretError(format!(
+            "publishing needs a registry token in {} or `rift secret set {}`", TOKEN_VAR, TOKEN_SECRET
+        )))?,
+    };
+    let url = format!("{}/publish", registry.trim_end_matches(INDEX_FILE).trim_end_matches('/'));
+    let form = reqwest::multipart::Form::new()
+        .text("manifest", serde_json::to_string(&package.manifest)?)
+        .text("sha256", package.sha256.clone())
+        .part("archive", reqwest::multipart::Part::bytes(package.archive.clone()).file_name(package.file_name()));
+    let response = reqwest::Client::new().post(&url).bearer_auth(token).multipart(form).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(RiftError::ConfigError(format!("{} refused {}: HTTP {} {}", url, package.file_name(), status, body.trim())));
+    }
+    Ok(url)
+}
+
+fn add_to_local(package: &Package, registry: &str) -> Result<String> {
+    let index_path = match registry.ends_with(".json") {
+        true => PathBuf::from(registry),
+        false => Path::new(registry).join(INDEX_FILE),
+    };
+    let dir = index_path.parent().unwrap_or(Path::new("."));
+    let mut index: Index = match fs::read_to_string(&index_path) {
+        Ok(text) => serde_json::from_str(&text).map_err(|e| RiftError::ConfigError(format!("{}: not a registry index: {}", index_path.display(), e)))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Index::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let PackageManifest { name, version, .. } = &package.manifest;
+    let releases = index.entry(name.clone()).or_default();
+    if releases.contains_key(version) {
+        return Err(RiftError::ConfigError(format!("{}@{} is already published; bump the version", name, version)));
+    }
+    releases.insert(version.clone(), Release { url: package.file_name(), sha256: package.sha256.clone() });
+    fs::create_dir_all(dir)?;
+    let archive_path = dir.join(package.file_name());
+    fs::write(&archive_path, &package.archive)?;
+    fs::write(&index_path, serde_json::to_string_pretty(&index)? + "\n")?;
+    Ok(archive_path.display().to_string())
+}
+
+// File names in `dir` that pass `keep`, sorted
+fn files_in(dir: &Path, keep: impl Fn(&Path) -> bool) -> Result<Vec<String>> {
+    let mut names: Vec<String> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| keep(path))
+        .filter_map(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+// Dot-separated numbers, optionally with a suffix: 1.2.0, 0.3, 2.0.0-beta
+fn valid_version(version: &str) -> bool {
+    let release = version.split_once('-').map_or(version, |(release, _)| release);
+    !release.is_empty() && release.split('.').all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_key() {
+        let mut versions = vec!["1.9.2", "1.10.0", "0.3", "1.10.0-beta"];
+        versions.sort_by_key(|v| version_key(v));
+        assert_eq!(versions, ["0.3", "1.9.2", "1.10.0", "1.10.0-beta"]);
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("https://r.example/pkgs", "a-1.0.tar.gz"), "https://r.example/pkgs/a-1.0.tar.gz");
+        assert_eq!(resolve("https://r.example", "https://cdn.example/a.tar.gz"), "https://cdn.example/a.tar.gz");
+        assert_eq!(resolve("/srv/index/", "a.tar.gz"), "/srv/index/a.tar.gz");
+    }
+
+    #[tokio::test]
+    async fn test_install_from_local_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let lib = dir.path().join("lib");
+
+        let mut archive = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()));
+        let source = b"@task greet { print(\"hi\"); }";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(source.len() as u64);
+        header.set_mode(0o644);
+        archive.append_data(&mut header, "greet.rift", &source[..]).unwrap();
+        let bytes = archive.into_inner().unwrap().finish().unwrap();
+        fs::write(dir.path().join("greet-1.0.0.tar.gz"), &bytes).unwrap();
+
+        let sha256 = format!("{:x}", Sha256::digest(&bytes));
+        let index = serde_json::json!({ "greet": {
+            "0.9.0": { "url": "missing.tar.gz", "sha256": "00" },
+            "1.0.0": { "url": "greet-1.0.0.tar.gz", "sha256": sha256 },
+        } });
+        fs::write(dir.path().join(INDEX_FILE), index.to_string()).unwrap();
+        let location = dir.path().display().to_string();
+
+        let installed = install_in(&lib, "greet", &location).await.unwrap();
+        assert_eq!(installed.version, "1.0.0");
+        let sources = sources_in(&lib, "greet").unwrap();
+        assert_eq!(sources.len(), 1);
+        assert!(sources[0].1.contains("@task greet"));
+        assert!(install_in(&lib, "greet@0.9.0", &location).await.is_err());
+        assert!(install_in(&lib, "greet@2.0", &location).await.unwrap_err().to_string().contains("no release 2.0"));
+        assert!(install_in(&lib, "../greet", &location).await.is_err());
+        assert!(sources_in(&lib, "other").is_err());
+    }
+
+    #[test]
+    fn test_plugin_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let (lib, target, shipped) = (dir.path().join("lib"), dir.path().join("plugins"), dir.path().join("shipped"));
+        for path in [lib.join("kit"), target.clone(), shipped.clone()] {
+            fs::create_dir_all(path).unwrap();
+        }
+        let kit = Installed { name: "kit".to_string(), version: "1.0.0".to_string(), sha256: String::new(), plugins: vec!["a.wasm".to_string()] };
+        fs::write(lib.join("kit").join(MANIFEST_FILE), serde_json::to_string(&kit).unwrap()).unwrap();
+        for plugin in ["a.wasm", "b.wasm", "c.wasm"] {
+            fs::write(shipped.join(plugin), "").unwrap();
+        }
+        fs::write(target.join("a.wasm"), "").unwrap();
+        fs::write(target.join("b.wasm"), "").unwrap();
+
+        assert_eq!(plugin_conflicts(&lib, &target, "other", &shipped), ["a.wasm (from kit)", "b.wasm (not from a package)"]);
+        // Reinstalling kit may replace its own plugin
+        assert_eq!(plugin_conflicts(&lib, &target, "kit", &shipped), ["b.wasm (not from a package)"]);
+    }
+
+    #[tokio::test]
+    async fn test_publish_to_local_registry() {
+        let dir = tempfile::tempdir().unwrap();
+        let package = dir.path().join("kit");
+        fs::create_dir_all(package.join("plugins")).unwrap();
+        fs::write(package.join("build.rift"), "@rift build { @fuse \"js\" { \"console.log(1)\" } }\nfn helper() { }").unwrap();
+        fs::write(package.join("plugins/rules.wasm"), b"\0asm").unwrap();
+        let manifest = "name = \"kit\"\nversion = \"1.0.0\"\nrifts = [\"build\", \"helper\", \"missing\"]\nlanguages = [\"python\", \"cobol\"]\n";
+        fs::write(package.join(PACKAGE_FILE), manifest).unwrap();
+
+        let problems = pack(&package).err().unwrap().to_string();
+        assert!(problems.contains("'missing' is listed in rifts but not defined"));
+        assert!(problems.contains("unknown language 'cobol'"));
+        assert!(problems.contains("fuse blocks use javascript"));
+        assert!(!problems.contains("'helper'"));
+
+        fs::write(package.join(PACKAGE_FILE), "name = \"kit\"\nversion = \"1.0.0\"\nrifts = [\"build\"]\nlanguages = [\"javascript\"]\n").unwrap();
+        assert_eq!(pack(&package).unwrap().files, [PACKAGE_FILE, "build.rift", "plugins/rules.wasm"]);
+        // Installing plugins would touch the real .rift/plugins
+        fs::remove_dir_all(package.join("plugins")).unwrap();
+        let packed = pack(&package).unwrap();
+        assert_eq!(pack(&package).unwrap().sha256, packed.sha256);
+
+        let registry = dir.path().join("registry").display().to_string();
+        publish(&packed, &registry).await.unwrap();
+        assert!(publish(&packed, &registry).await.unwrap_err().to_string().contains("already published"));
+        let lib = dir.path().join("lib");
+        let installed = install_in(&lib, "kit", &registry).await.unwrap();
+        assert_eq!((installed.version.as_str(), installed.sha256.as_str()), ("1.0.0", packed.sha256.as_str()));
+        assert_eq!(sources_in(&lib, "kit").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_valid_version() {
+        assert!(["1.2.0", "0.3", "2.0.0-beta"].iter().all(|v| valid_version(v)));
+        assert!(["", "1..2", "v1.0", "1.x", "-beta"].iter().all(|v| !valid_version(v)));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::error::{Result, RiftError};
+use crate::{executor, validate, AST};
+use serde_json::json;
+use std::collections::BTreeSet;
+
+// A dependency a fuse block imports, which rift installs before running it
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Component {
+    pub ecosystem: &'static str,
+    pub name: String,
+    pub language: String,
+}
+
+impl Component {
+    // Package URL, e.g. pkg:pypi/requests or pkg:maven/org.slf4j/slf4j-api
+    pub fn purl(&self) -> String {
+        format!("pkg:{}/{}", self.ecosystem, self.name.replacen(':', "/", 1))
+    }
+}
+
+// Package ecosystem each language's installs come from
+fn ecosystem(lang: &str) -> Option<&'static str> {
+    match lang {
+        "python" => Some("pypi"),
+        "javascript" | "js" => Some("npm"),
+        "java" => Some("maven"),
+        "rust" => Some("cargo"),
+        "go" => Some("golang"),
+        "php" => Some("composer"),
+        _ => None,
+    }
+}
+
+// Dependencies of every fuse block in `program`, or only in the rift
+// named `rift`, deduplicated and sorted. Names are as imported; rift does
+// not pin versions, so none are reported.
+pub fn components(program: &AST, rift: Option<&str>) -> Result<Vec<Component>> {
+    let mut nodes = Vec::new();
+    validate::walk(program, &mut nodes);
+    let scope: Vec<&AST> = match rift {
+        None => nodes,
+        Some(name) => {
+            let body = nodes.iter().find_map(|node| match node {
+                AST::Rift(n, body) if n == name => Some(body),
+                _ => None,
+            });
+            let body = body.ok_or_else(|| RiftError::FunctionNotFound(format!("rift '{}'", name)))?;
+            let mut nodes = Vec::new();
+            for node in body {
+                validate::walk(node, &mut nodes);
+            }
+            nodes
+        }
+    };
+
+    let mut found = BTreeSet::new();
+    for node in scope {
+        let (lang, code) = match node {
+            AST::Fuse(lang, code) | AST::InteractiveFuse(lang, code) | AST::RemoteFuse(_, lang, code) => (lang, code),
+            _ => continue,
+        };
+        let Some(ecosystem) = ecosystem(lang) else { continue };
+        for name in executor::resolve_deps(lang, code)? {
+            found.insert(Component { ecosystem, name, language: lang.clone() });
+        }
+    }
+    Ok(found.into_iter().collect())
+}
+
+pub fn cyclonedx(name: &str, components: &[Component]) -> serde_json::Value {
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+        "version": 1,
+        "metadata": {
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "tools": [{ "vendor": "rift", "name": "rift", "version": env!("CARGO_PKG_VERSION") }],
+            "component": { "type": "application", "name": name },
+        },
+        "components": components.iter().map(|c| json!({
+            "type": "library",
+            "bom-ref": c.purl(),
+            "name": c.name,
+            "purl": c.purl(),
+            "properties": [{ "name": "rift:language", "value": c.language }],
+        })).collect::<Vec<_>>(),
+    })
+}
+
+pub fn spdx(name: &str, components: &[Component]) -> serde_json::Value {
+    let packages: Vec<_> = components.iter().enumerate().map(|(i, c)| json!({
+        "name": c.name,
+        "SPDXID": format!("SPDXRef-Package-{}", i + 1),
+        "downloadLocation": "NOASSERTION",
+        "licenseConcluded": "NOASSERTION",
+        "filesAnalyzed": false,
+        "externalRefs": [{ "referenceCategory": "PACKAGE-MANAGER", "referenceType": "purl", "referenceLocator": c.purl() }],
+    })).collect();
+    let relationships: Vec<_> = (1..=components.len()).map(|i| json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": format!("SPDXRef-Package-{}", i),
+    })).collect();
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": name,
+        "documentNamespace": format!("https://spdx.org/spdxdocs/{}-{}", name, uuid::Uuid::new_v4()),
+        "creationInfo": {
+            "created": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            "creators": [format!("Tool: rift-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_purl() {
+        let component = Component { ecosystem: "maven", name: "org.slf4j:slf4j-api".to_string(), language: "java".to_string() };
+        assert_eq!(component.purl(), "pkg:maven/org.slf4j/slf4j-api");
+        let doc = cyclonedx("app", &[component]);
+        assert_eq!(doc["components"][0]["purl"], "pkg:maven/org.slf4j/slf4j-api");
+    }
+}
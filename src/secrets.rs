@@ -0,0 +1,59 @@
+// Secrets kept in the OS keyring (Keychain, Credential Manager or Secret
+// Service) by `rift secret set`. Deploy configs name them as "secret:NAME"
+// and get the value only when a backend is about to use it, so the recorded
+// deployment keeps the reference rather than the key.
+use crate::error::{Result, RiftError};
+use std::collections::HashMap;
+
+const SERVICE: &str = "rift";
+const PREFIX: &str = "secret:";
+
+fn entry(name: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, name).map_err(|e| RiftError::SecretError(format!("{}: {}", name, e)))
+}
+
+pub fn set(name: &str, value: &str) -> Result<()> {
+    entry(name)?.set_password(value).map_err(|e| RiftError::SecretError(format!("{}: {}", name, e)))
+}
+
+pub fn get(name: &str) -> Result<Option<String>> {
+    match entry(name)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(RiftError::SecretError(format!("{}: {}", name, e))),
+    }
+}
+
+// False when there was nothing to remove
+pub fn remove(name: &str) -> Result<bool> {
+    match entry(name)?.delete_password() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(RiftError::SecretError(format!("{}: {}", name, e))),
+    }
+}
+
+// The config with each "secret:NAME" value replaced by the stored secret
+pub fn resolve(config: &HashMap<String, String>) -> std::result::Result<HashMap<String, String>, String> {
+    let mut resolved = config.clone();
+    for (key, value) in resolved.iter_mut() {
+        let Some(name) = value.strip_prefix(PREFIX) else {
+            continue;
+        };
+        *value = get(name).map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("{} refers to secret '{}', which is not set; add it with `rift secret set {}`", key, name, name))?;
+        crate::redact::register(value);
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_leaves_plain_values() {
+        let config = HashMap::from([("region".to_string(), "eu-west-1".to_string())]);
+        assert_eq!(resolve(&config).unwrap(), config);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::agent::agent_token;
+use crate::error::{Result, RiftError};
+use crate::i18n;
+use crate::interpreter::Environment;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+// `rift serve` keeps an Environment alive behind HTTP so `rift attach` can
+// drive it from another machine, authenticated like agents are. Statements
+// run on the server, so their printed output appears there; the client sees
+// success, errors and status.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EvalRequest {
+    pub source: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EvalResponse {
+    pub error: Option<String>,
+    pub exit_code: Option<i32>,
+}
+
+#[cfg(feature = "server")]
+pub async fn serve(listen: &str, env: Arc<RwLock<Environment>>, script: Option<String>) -> Result<()> {
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+
+    let token = agent_token()?;
+    let authorized = move |headers: &HeaderMap| {
+        headers.get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value == format!("Bearer {}", token))
+    };
+    let check = authorized.clone();
+    let eval = move |State(env): State<Arc<RwLock<Environment>>>, headers: HeaderMap, Json(request): Json<EvalRequest>| async move {
+        if !check(&headers) {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Ok(Json(eval_source(&request.source, &env).await))
+    };
+    let status = move |State(env): State<Arc<RwLock<Environment>>>, headers: HeaderMap| async move {
+        if !authorized(&headers) {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Ok(crate::status_report(&*env.read().await))
+    };
+    let app = Router::new()
+        .route("/eval", post(eval))
+        .route("/status", get(status))
+        .with_state(Arc::clone(&env));
+
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    println!("{}", i18n::message("session.listening", &[&listener.local_addr()?]));
+
+    // The pipeline runs in the background; statements from attached clients
+    // run between its top-level statements
+    if let Some(path) = script {
+        let source = std::fs::read_to_string(&path)?;
+        tokio::spawn(async move {
+            if let Some(error) = eval_source(&source, &env).await.error {
+                eprintln!("{}", i18n::message("session.script_error", &[&path, &error]));
+            }
+        });
+    }
+
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+#[cfg(not(feature = "server"))]
+pub async fn serve(_listen: &str, _env: Arc<RwLock<Environment>>, _script: Option<String>) -> Result<()> {
+    Err(RiftError::ConfigError("rift was built without the 'server' feature".to_string()))
+}
+
+#[cfg(feature = "server")]
+async fn eval_source(source: &str, env: &Arc<RwLock<Environment>>) -> EvalResponse {
+    let ast = match crate::lexer::tokenize(source).and_then(|tokens| crate::parser::parse(&tokens)) {
+        Ok(ast) => ast,
+        Err(e) => return EvalResponse { error: Some(e.to_string()), ..EvalResponse::default() },
+    };
+    if let Err(e) = crate::interpreter::check_params(&ast, &*env.read().await) {
+        return EvalResponse { error: Some(e), ..EvalResponse::default() };
+    }
+    let crate::AST::Program(statements) = ast else {
+        unreachable!("parse returns a program")
+    };
+    // The lock is taken per statement so /status can answer between them
+    for statement in &statements {
+        let mut env = env.write().await;
+        if let Err(e) = crate::interpreter::interpret(statement, &mut env).await {
+            return EvalResponse {
+                error: Some(e).filter(|_| env.exit_code.is_none()),
+                exit_code: env.exit_code.take(),
+            };
+        }
+    }
+    EvalResponse::default()
+}
+
+// A REPL whose statements run in the remote session
+pub async fn attach(address: &str) -> Result<()> {
+    let token = agent_token()?;
+    let base = if address.starts_with("http://") || address.starts_with("https://") {
+        address.trim_end_matches('/').to_string()
+    } else {
+        format!("http://{}", address)
+    };
+    let client = reqwest::Client::new();
+    let status = client.get(format!("{}/status", base)).bearer_auth(&token).send().await?.error_for_status()?;
+    println!("{}", i18n::message("session.attached", &[&base]));
+    print!("{}", status.text().await?);
+
+    let mut rl = rustyline::Editor::<()>::new().map_err(|e| RiftError::IoError(std::io::Error::new(
+        std::io::ErrorKind::Other,
+        format!("Failed to initialize readline: {}", e),
+    )))?;
+    loop {
+        let line = match rl.readline(&format!("rift@{}> ", address)) {
+            Ok(line) => line,
+            Err(rustyline::error::ReadlineError::Interrupted) => continue,
+            Err(_) => break,
+        };
+        let line = line.trim();
+        match line {
+            "" => continue,
+            "detach" | "exit" | "quit" => break,
+            "status" => {
+                let response = client.get(format!("{}/status", base)).bearer_auth(&token).send().await;
+                match response.and_then(|r| r.error_for_status()) {
+                    Ok(response) => print!("{}", response.text().await?),
+                    Err(e) => eprintln!("{}", i18n::message("repl.error", &[&e])),
+                }
+                continue;
+            }
+            _ => {}
+        }
+        rl.add_history_entry(line);
+
+        let response = client.post(format!("{}/eval", base))
+            .bearer_auth(&token)
+            .json(&EvalRequest { source: line.to_string() })
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+        match response {
+            Ok(response) => match response.json::<EvalResponse>().await? {
+                EvalResponse { error: Some(error), .. } => eprintln!("{}", i18n::message("repl.error", &[&error])),
+                EvalResponse { exit_code: Some(code), .. } => println!("{}", i18n::message("session.remote_exit", &[&code])),
+                _ => println!("{}", i18n::text("repl.ok")),
+            },
+            Err(e) => eprintln!("{}", i18n::message("repl.error", &[&e])),
+        }
+    }
+    println!("{}", i18n::text("session.detached"));
+    Ok(())
+}
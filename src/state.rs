@@ -0,0 +1,204 @@
+use crate::error::{Result, RiftError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const STATE_DIR: &str = ".rift";
+const DEPLOYMENTS_FILE: &str = "deployments.json";
+const ARTIFACTS_DIR: &str = "artifacts";
+const CACHE_DIR: &str = "cache";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeploymentRecord {
+    pub id: String,
+    pub target: String,
+    pub address: String,
+    pub artifact_hash: String,
+    pub config: HashMap<String, String>,
+    pub timestamp: i64,
+}
+
+// Provenance stored next to an artifact as `<artifact>.json`. Nothing in it
+// depends on when the build ran, so rebuilding identical sources writes an
+// identical manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildManifest {
+    pub rift_version: String,
+    pub git_commit: Option<String>,
+    pub toolchains: BTreeMap<String, String>,
+    pub source_hash: String,
+    pub artifact_hash: String,
+    pub size: u64,
+    // Cache blobs (by hash) whose contents the artifact refers to; rifts
+    // with identical outputs list the same one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blobs: Vec<String>,
+}
+
+pub fn state_dir() -> PathBuf {
+    std::env::var("RIFT_STATE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(STATE_DIR))
+}
+
+pub fn load_deployments() -> Result<Vec<DeploymentRecord>> {
+    let path = state_dir().join(DEPLOYMENTS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&data)?)
+}
+
+pub fn record_deployment(
+    target: &str,
+    address: &str,
+    artifact: &str,
+    config: &HashMap<String, String>,
+) -> Result<DeploymentRecord> {
+    let timestamp = chrono::Utc::now().timestamp();
+    // The nonce keeps ids apart for deploys of one address within a second
+    let nonce = uuid::Uuid::new_v4();
+    let id = format!("{:x}", Sha256::digest(format!("{}:{}:{}:{}", target, address, timestamp, nonce).as_bytes()));
+    let record = DeploymentRecord {
+        id: id[..12].to_string(),
+        target: target.to_string(),
+        address: address.to_string(),
+        artifact_hash: artifact_hash(artifact),
+        config: recorded_config(config),
+        timestamp,
+    };
+
+    // Keep the exact bytes so the deployment can be promoted without a rebuild
+    let artifacts = state_dir().join(ARTIFACTS_DIR);
+    fs::create_dir_all(&artifacts)?;
+    let artifact_path = artifacts.join(&record.artifact_hash);
+    if !artifact_path.exists() {
+        fs::write(artifact_path, artifact)?;
+    }
+
+    let mut deployments = load_deployments()?;
+    deployments.push(record.clone());
+    fs::write(state_dir().join(DEPLOYMENTS_FILE), serde_json::to_string_pretty(&deployments)?)?;
+    Ok(record)
+}
+
+// Keys that steer a single deploy rather than describe what gets deployed
+const CONTROL_KEYS: &[&str] = &["force", "timeout"];
+
+// The deploy config as history keeps it. Credentials are looked up again at
+// use time and never written to disk, and control keys are left out so that
+// `force: true` or a longer timeout doesn't count as a change
+pub fn recorded_config(config: &HashMap<String, String>) -> HashMap<String, String> {
+    config.iter()
+        .filter(|(key, _)| !is_secret_key(key) && !CONTROL_KEYS.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), crate::redact::text(value)))
+        .collect()
+}
+
+pub fn artifact_hash(artifact: &str) -> String {
+    hash_bytes(artifact.as_bytes())
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+// Content-addressed, so identical outputs share one file
+pub fn cache_blob(bytes: &[u8]) -> Result<(PathBuf, String)> {
+    let hash = hash_bytes(bytes);
+    let dir = state_dir().join(CACHE_DIR);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(&hash);
+    if !path.exists() {
+        fs::write(&path, bytes)?;
+    }
+    Ok((path, hash))
+}
+
+// Moves a file whose contents hash to `hash` into the blob cache, or drops
+// it when an identical blob is already there
+pub fn adopt_blob(path: &Path, hash: &str) -> Result<PathBuf> {
+    let dir = state_dir().join(CACHE_DIR);
+    fs::create_dir_all(&dir)?;
+    let blob = dir.join(hash);
+    if blob.exists() {
+        fs::remove_file(path)?;
+    } else if fs::rename(path, &blob).is_err() {
+        // Across filesystems, when RIFT_STATE_DIR moved after the spill
+        fs::copy(path, &blob)?;
+        fs::remove_file(path)?;
+    }
+    Ok(blob)
+}
+
+// Written once per artifact; promotions reuse the original build's manifest
+pub fn write_manifest(manifest: &BuildManifest) -> Result<()> {
+    let artifacts = state_dir().join(ARTIFACTS_DIR);
+    fs::create_dir_all(&artifacts)?;
+    let path = manifest_path(&artifacts.join(&manifest.artifact_hash));
+    if !path.exists() {
+        fs::write(path, serde_json::to_string_pretty(manifest)?)?;
+    }
+    Ok(())
+}
+
+// `reference` is an artifact file or a deployment id. Fails unless the bytes
+// on disk match the hash and size recorded in the manifest.
+pub fn verify_artifact(reference: &str) -> Result<(PathBuf, BuildManifest)> {
+    let path = if Path::new(reference).is_file() {
+        PathBuf::from(reference)
+    } else {
+        state_dir().join(ARTIFACTS_DIR).join(find_deployment(reference)?.artifact_hash)
+    };
+    let manifest_file = manifest_path(&path);
+    let manifest: BuildManifest = serde_json::from_str(&fs::read_to_string(&manifest_file).map_err(|e| {
+        RiftError::CacheError(format!("No manifest for {} ({}): {}", path.display(), manifest_file.display(), e))
+    })?)?;
+    let bytes = fs::read(&path)?;
+    if bytes.len() as u64 != manifest.size || hash_bytes(&bytes) != manifest.artifact_hash {
+        return Err(RiftError::CacheError(format!(
+            "{} does not match its manifest (expected {} bytes with hash {})",
+            path.display(), manifest.size, manifest.artifact_hash
+        )));
+    }
+    Ok((path, manifest))
+}
+
+fn manifest_path(artifact: &Path) -> PathBuf {
+    let mut name = artifact.as_os_str().to_owned();
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+pub fn latest_deployment(target: &str) -> Result<Option<DeploymentRecord>> {
+    Ok(load_deployments()?.into_iter().rev().find(|d| d.target == target))
+}
+
+// Accepts any unambiguous prefix of a deployment id
+pub fn find_deployment(id: &str) -> Result<DeploymentRecord> {
+    let matches: Vec<_> = load_deployments()?.into_iter().filter(|d| d.id.starts_with(id)).collect();
+    match matches.len() {
+        0 => Err(RiftError::ConfigError(format!("No recorded deployment with id {}", id))),
+        1 => Ok(matches.into_iter().next().unwrap()),
+        _ => Err(RiftError::ConfigError(format!("Deployment id {} is ambiguous", id))),
+    }
+}
+
+pub fn load_artifact(record: &DeploymentRecord) -> Result<String> {
+    let path = state_dir().join(ARTIFACTS_DIR).join(&record.artifact_hash);
+    let artifact = fs::read_to_string(&path).map_err(|e| RiftError::CacheError(format!(
+        "Artifact for deployment {} is missing ({}): {}", record.id, path.display(), e
+    )))?;
+    if artifact_hash(&artifact) != record.artifact_hash {
+        return Err(RiftError::CacheError(format!("Artifact for deployment {} does not match its recorded hash", record.id)));
+    }
+    Ok(artifact)
+}
+
+pub(crate) fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    ["key", "secret", "token", "password"].iter().any(|s| key.contains(s))
+}
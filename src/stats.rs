@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+// Per-language counters for fuse executions in this session, shown by
+// `status` (one line each) and `stats` (the full table)
+#[derive(Debug, Clone, Default)]
+pub struct LangStats {
+    pub runs: u64,
+    pub cache_hits: u64,
+    pub failures: u64,
+    pub total_time: Duration,
+    pub deps_installed: u64,
+    pub last_error: Option<String>,
+}
+
+impl LangStats {
+    pub fn average_time(&self) -> Duration {
+        match self.runs {
+            0 => Duration::ZERO,
+            runs => self.total_time / runs as u32,
+        }
+    }
+
+    // Share of lookups answered from the cache instead of running the block
+    pub fn hit_rate(&self) -> f64 {
+        match self.runs + self.cache_hits {
+            0 => 0.0,
+            total => self.cache_hits as f64 / total as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExecStats {
+    languages: BTreeMap<String, LangStats>,
+}
+
+impl ExecStats {
+    fn entry(&mut self, lang: &str) -> &mut LangStats {
+        self.languages.entry(lang.to_string()).or_default()
+    }
+
+    pub fn record_hit(&mut self, lang: &str) {
+        self.entry(lang).cache_hits += 1;
+    }
+
+    pub fn record_run(&mut self, lang: &str, time: Duration, deps_installed: usize) {
+        let stats = self.entry(lang);
+        stats.runs += 1;
+        stats.total_time += time;
+        stats.deps_installed += deps_installed as u64;
+    }
+
+    // Returns the error so callers can `return Err(stats.record_failure(..))`
+    pub fn record_failure(&mut self, lang: &str, time: Duration, error: String) -> String {
+        let stats = self.entry(lang);
+        stats.runs += 1;
+        stats.failures += 1;
+        stats.total_time += time;
+        stats.last_error = Some(error.clone());
+        error
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.languages.is_empty()
+    }
+
+    pub fn languages(&self) -> impl Iterator<Item = (&String, &LangStats)> {
+        self.languages.iter()
+    }
+
+    pub fn summary(&self) -> String {
+        self.languages.iter()
+            .map(|(lang, stats)| format!("{} {} ({} cached, {} failed)", lang, stats.runs, stats.cache_hits, stats.failures))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        if self.languages.is_empty() {
+            writeln!(report, "No fuse blocks have run yet").ok();
+            return report;
+        }
+        writeln!(report, "{:<12} {:>5} {:>7} {:>10} {:>10} {:>6} {:>5}", "language", "runs", "failed", "total", "avg", "cache", "deps").ok();
+        for (lang, stats) in &self.languages {
+            writeln!(
+                report,
+                "{:<12} {:>5} {:>7} {:>10} {:>10} {:>5.0}% {:>5}",
+                lang,
+                stats.runs,
+                stats.failures,
+                format!("{:.2?}", stats.total_time),
+                format!("{:.2?}", stats.average_time()),
+                stats.hit_rate() * 100.0,
+                stats.deps_installed,
+            ).ok();
+        }
+        for (lang, stats) in &self.languages {
+            if let Some(error) = &stats.last_error {
+                // Only the first line; compiler errors can run to pages
+                writeln!(report, "Last {} error: {}", lang, error.lines().next().unwrap_or_default()).ok();
+            }
+        }
+        report
+    }
+
+    // The same table for Markdown, as in CI job summaries
+    pub fn markdown(&self) -> String {
+        let mut table = String::from("| Language | Runs | Failed | Total | Avg | Cache hits |\n|---|---:|---:|---:|---:|---:|\n");
+        for (lang, stats) in &self.languages {
+            writeln!(
+                table,
+                "| {} | {} | {} | {:.2?} | {:.2?} | {} |",
+                lang, stats.runs, stats.failures, stats.total_time, stats.average_time(), stats.cache_hits,
+            ).ok();
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_and_rates() {
+        let mut stats = ExecStats::default();
+        stats.record_run("python", Duration::from_millis(300), 2);
+        stats.record_hit("python");
+        let error = stats.record_failure("python", Duration::from_millis(100), "python exited with Some(1)".to_string());
+        assert_eq!(error, "python exited with Some(1)");
+
+        let python = &stats.languages["python"];
+        assert_eq!((python.runs, python.failures, python.deps_installed), (2, 1, 2));
+        assert_eq!(python.average_time(), Duration::from_millis(200));
+        assert!((python.hit_rate() - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.summary(), "python 2 (1 cached, 1 failed)");
+        assert!(stats.report().contains("Last python error: python exited with Some(1)"));
+    }
+}
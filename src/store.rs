@@ -0,0 +1,40 @@
+use crate::state::state_dir;
+use crate::value::Value;
+use std::fs;
+use std::path::PathBuf;
+
+const STORE_DIR: &str = "store";
+
+// Key-value store shared by the script and fuse code. Each key is one JSON
+// file, so fuse programs in any language can read and write entries directly
+// through $RIFT_STORE and large values never pass through environment variables.
+pub fn store_dir() -> PathBuf {
+    state_dir().join(STORE_DIR)
+}
+
+pub fn put(key: &str, value: &Value) -> Result<(), String> {
+    let path = entry_path(key)?;
+    fs::create_dir_all(store_dir()).map_err(|e| format!("store: {}", e))?;
+    fs::write(&path, value.to_json().to_string()).map_err(|e| format!("store: {}: {}", path.display(), e))
+}
+
+pub fn get(key: &str) -> Result<Option<Value>, String> {
+    let path = entry_path(key)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data = fs::read_to_string(&path).map_err(|e| format!("store: {}: {}", path.display(), e))?;
+    let json = serde_json::from_str(&data).map_err(|e| format!("store: entry '{}' is not valid JSON: {}", key, e))?;
+    Ok(Some(Value::from_json(json)))
+}
+
+// Keys become file names, so they are kept to a portable character set
+fn entry_path(key: &str) -> Result<PathBuf, String> {
+    let valid = !key.is_empty()
+        && !key.starts_with('.')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !valid {
+        return Err(format!("store: invalid key '{}' (use letters, digits, '_', '-' and '.')", key));
+    }
+    Ok(store_dir().join(format!("{}.json", key)))
+}
@@ -0,0 +1,64 @@
+use crate::state::{hash_bytes, state_dir};
+use crate::value::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const TABLES_DIR: &str = "tables";
+const EXTENSIONS: [&str; 3] = ["parquet", "arrow", "feather"];
+
+// Tabular data moves between fuse blocks as Parquet or Arrow IPC files in
+// this directory, exposed to fuse programs as $RIFT_TABLES
+pub fn tables_dir() -> PathBuf {
+    state_dir().join(TABLES_DIR)
+}
+
+pub fn table(name: &str) -> Result<Value, String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("invalid table name '{}'", name));
+    }
+    let path = EXTENSIONS.iter()
+        .map(|ext| tables_dir().join(format!("{}.{}", name, ext)))
+        .find(|path| path.exists())
+        .ok_or_else(|| format!("no table named '{}' in {}", name, tables_dir().display()))?;
+    let bytes = fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(Value::Artifact {
+        hash: hash_bytes(&bytes),
+        size: bytes.len() as u64,
+        path,
+    })
+}
+
+#[cfg(feature = "tables")]
+pub fn preview(path: &Path, rows: usize) -> Result<String, String> {
+    use arrow::record_batch::RecordBatch;
+
+    let file = fs::File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let is_parquet = path.extension().is_some_and(|ext| ext == "parquet");
+    let (batch, total): (Option<RecordBatch>, Option<i64>) = if is_parquet {
+        let builder = parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder::try_new(file)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let total = builder.metadata().file_metadata().num_rows();
+        let mut reader = builder.with_batch_size(rows.max(1)).build().map_err(|e| e.to_string())?;
+        (reader.next().transpose().map_err(|e| e.to_string())?, Some(total))
+    } else {
+        let mut reader = arrow::ipc::reader::FileReader::try_new(file, None)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        (reader.next().transpose().map_err(|e| e.to_string())?, None)
+    };
+
+    let Some(batch) = batch else {
+        return Ok(format!("{} (empty)", path.display()));
+    };
+    let head = batch.slice(0, rows.min(batch.num_rows()));
+    let table = arrow::util::pretty::pretty_format_batches(&[head.clone()]).map_err(|e| e.to_string())?;
+    let summary = match total {
+        Some(total) => format!("{} columns, showing {} of {} rows", batch.num_columns(), head.num_rows(), total),
+        None => format!("{} columns, first {} rows", batch.num_columns(), head.num_rows()),
+    };
+    Ok(format!("{}\n{}", table, summary))
+}
+
+#[cfg(not(feature = "tables"))]
+pub fn preview(_path: &Path, _rows: usize) -> Result<String, String> {
+    Err("rift was built without the 'tables' feature".to_string())
+}
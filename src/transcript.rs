@@ -0,0 +1,101 @@
+// REPL transcripts: `record <file>` writes each statement and its outcome as
+// a JSON line, and `replay <file>` runs them again in a fresh Environment,
+// pointing out every statement whose outcome changed. Handy to attach to a
+// bug report or to walk through in a tutorial.
+use crate::error::{Result, RiftError};
+use crate::redact;
+use crate::value::Value;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub source: String,
+    // Display text of the statement's value, unless it was null
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Entry {
+    pub fn new(source: &str, result: &Result<Value>) -> Self {
+        let (value, error) = match result {
+            Ok(Value::Null) => (None, None),
+            Ok(value) => (Some(value.to_string()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        Entry { source: source.to_string(), value, error }
+    }
+
+    pub fn outcome(&self) -> String {
+        match (&self.value, &self.error) {
+            (_, Some(error)) => format!("Error: {}", error),
+            (Some(value), None) => value.clone(),
+            (None, None) => "Ok".to_string(),
+        }
+    }
+}
+
+pub struct Recorder {
+    path: PathBuf,
+    file: std::fs::File,
+}
+
+impl Recorder {
+    // Starts a new transcript, replacing any file already at `path`
+    pub fn create(path: &Path) -> Result<Self> {
+        Ok(Recorder { path: path.to_path_buf(), file: std::fs::File::create(path)? })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // With secrets masked; see `redact`
+    pub fn record(&mut self, entry: &Entry) -> Result<()> {
+        let entry = Entry {
+            source: redact::text(&entry.source),
+            value: entry.value.as_deref().map(redact::text),
+            error: entry.error.as_deref().map(redact::text),
+        };
+        writeln!(self.file, "{}", serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+}
+
+pub fn load(path: &Path) -> Result<Vec<Entry>> {
+    std::fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line)
+            .map_err(|e| RiftError::ParseError(format!("{}:{}: not a transcript entry: {}", path.display(), i + 1, e))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_load() {
+        let path = std::env::temp_dir().join(format!("rift-transcript-{}.jsonl", std::process::id()));
+        let entries = [
+            Entry::new("let x = 1 + 2;", &Ok(Value::Null)),
+            Entry::new("x * 2", &Ok(Value::Int(6))),
+            Entry::new("call missing;", &Err(RiftError::FunctionNotFound("missing".to_string()))),
+        ];
+        let mut recorder = Recorder::create(&path).unwrap();
+        for entry in &entries {
+            recorder.record(entry).unwrap();
+        }
+        drop(recorder);
+
+        assert_eq!(load(&path).unwrap(), entries);
+        assert_eq!(entries[1].outcome(), "6");
+        assert_eq!(entries[2].outcome(), "Error: Function not found: missing");
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,289 @@
+use crate::deployer::format_size;
+use crate::{i18n, state};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+#[cfg(feature = "deploy-aws")]
+use rusoto_s3::{
+    CompleteMultipartUploadRequest, CompletedMultipartUpload, CompletedPart, CreateMultipartUploadRequest,
+    ListPartsRequest, PutObjectRequest, UploadPartRequest, S3, S3Client,
+};
+
+// Objects smaller than this go up in one request
+pub const MULTIPART_THRESHOLD: usize = 16 * 1024 * 1024;
+// A multiple of GCS's 256 KiB chunk granularity and over S3's 5 MiB minimum
+const PART_SIZE: usize = 8 * 1024 * 1024;
+const PART_ATTEMPTS: u32 = 4;
+const UPLOADS_DIR: &str = "uploads";
+
+// An upload in progress, kept in .rift/uploads so a retry (or the next
+// `rift deploy` after a dropped connection) continues where it stopped
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Resume {
+    // S3 upload id, or the GCS resumable session URI
+    session: String,
+    // S3 part number -> ETag of the parts already stored
+    #[serde(default)]
+    parts: BTreeMap<i64, String>,
+}
+
+// Keyed by destination and contents, so changed bytes start a new upload
+fn resume_path(bucket: &str, key: &str, body: &[u8]) -> PathBuf {
+    let id = state::hash_bytes(format!("{}\0{}\0{}", bucket, key, state::hash_bytes(body)).as_bytes());
+    state::state_dir().join(UPLOADS_DIR).join(format!("{}.json", &id[..32]))
+}
+
+fn load_resume(path: &PathBuf) -> Option<Resume> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn save_resume(path: &PathBuf, resume: &Resume) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    }
+    fs::write(path, serde_json::to_string(resume).map_err(|e| e.to_string())?)
+        .map_err(|e| format!("Cannot save upload state {}: {}", path.display(), e))
+}
+
+fn report_part(key: &str, part: usize, parts: usize, done: usize, total: usize) {
+    println!("{}", i18n::message("upload.part", &[&key, &part, &parts, &format_size(done as u64), &format_size(total as u64)]));
+}
+
+// Retries one part with backoff; the rest of the upload is unaffected
+async fn with_retries<T, F, Fut>(what: &str, mut attempt: F) -> Result<T, String>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, String>>,
+{
+    let mut tries = 0;
+    loop {
+        match attempt().await {
+            Ok(value) => return Ok(value),
+            Err(e) if tries + 1 < PART_ATTEMPTS => {
+                tries += 1;
+                eprintln!("{}", i18n::message("upload.retrying", &[&what, &e]));
+                tokio::time::sleep(std::time::Duration::from_millis(500 * 2u64.pow(tries))).await;
+            }
+            Err(e) => return Err(format!("{} failed after {} attempts: {}", what, PART_ATTEMPTS, e)),
+        }
+    }
+}
+
+#[cfg(feature = "deploy-aws")]
+pub struct S3Object<'a> {
+    pub bucket: &'a str,
+    pub key: &'a str,
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+}
+
+// Large bodies use a multipart upload whose finished parts survive a
+// failed attempt; S3 is asked which parts it has before resuming
+#[cfg(feature = "deploy-aws")]
+pub async fn s3_put(client: &S3Client, object: S3Object<'_>, body: Vec<u8>) -> Result<(), String> {
+    let S3Object { bucket, key, content_type, cache_control } = object;
+    if body.len() < MULTIPART_THRESHOLD {
+        let request = PutObjectRequest {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            body: Some(body.into()),
+            content_type,
+            cache_control,
+            ..Default::default()
+        };
+        return client.put_object(request).await.map(|_| ()).map_err(|e| format!("S3 upload of {} failed: {}", key, e));
+    }
+
+    let state_file = resume_path(bucket, key, &body);
+    let mut resume = match load_resume(&state_file) {
+        Some(mut resume) => {
+            let listed = client.list_parts(ListPartsRequest {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                upload_id: resume.session.clone(),
+                ..Default::default()
+            }).await;
+            match listed {
+                // Trust S3's list over the saved one, which may be missing a
+                // part that finished just before the failure
+                Ok(output) => {
+                    resume.parts = output.parts.unwrap_or_default().into_iter()
+                        .filter_map(|part| Some((part.part_number?, part.e_tag?)))
+                        .collect();
+                    println!("{}", i18n::message("upload.resuming_parts", &[&key, &resume.parts.len()]));
+                    resume
+                }
+                Err(_) => Resume::default(),
+            }
+        }
+        None => Resume::default(),
+    };
+    if resume.session.is_empty() {
+        let created = client.create_multipart_upload(CreateMultipartUploadRequest {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            content_type,
+            cache_control,
+            ..Default::default()
+        }).await.map_err(|e| format!("Starting multipart upload of {} failed: {}", key, e))?;
+        resume = Resume { session: created.upload_id.ok_or("S3 returned no upload id")?, parts: BTreeMap::new() };
+        save_resume(&state_file, &resume)?;
+    }
+
+    let chunks: Vec<&[u8]> = body.chunks(PART_SIZE).collect();
+    for (index, chunk) in chunks.iter().copied().enumerate() {
+        let number = index as i64 + 1;
+        if resume.parts.contains_key(&number) {
+            continue;
+        }
+        let upload_id = &resume.session;
+        let e_tag = with_retries(&format!("S3 part {} of {}", number, key), || async move {
+            let output = client.upload_part(UploadPartRequest {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                upload_id: upload_id.clone(),
+                part_number: number,
+                content_length: Some(chunk.len() as i64),
+                body: Some(chunk.to_vec().into()),
+                ..Default::default()
+            }).await.map_err(|e| e.to_string())?;
+            output.e_tag.ok_or_else(|| "no ETag in response".to_string())
+        }).await?;
+        resume.parts.insert(number, e_tag);
+        save_resume(&state_file, &resume)?;
+        report_part(key, index + 1, chunks.len(), (index * PART_SIZE + chunk.len()).min(body.len()), body.len());
+    }
+
+    client.complete_multipart_upload(CompleteMultipartUploadRequest {
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+        upload_id: resume.session.clone(),
+        multipart_upload: Some(CompletedMultipartUpload {
+            parts: Some(resume.parts.iter().map(|(number, e_tag)| CompletedPart {
+                part_number: Some(*number),
+                e_tag: Some(e_tag.clone()),
+            }).collect()),
+        }),
+        ..Default::default()
+    }).await.map_err(|e| format!("Completing upload of {} failed: {}", key, e))?;
+    fs::remove_file(&state_file).ok();
+    Ok(())
+}
+
+// GCS resumable upload: chunks go to a session URI, and after a failure the
+// session reports how many bytes it holds, which is where sending restarts
+pub async fn gcs_put(
+    http: &reqwest::Client,
+    token: &str,
+    bucket: &str,
+    key: &str,
+    body: Vec<u8>,
+    metadata: serde_json::Value,
+) -> Result<(), String> {
+    let total = body.len();
+    let state_file = resume_path(bucket, key, &body);
+    let resumed = load_resume(&state_file);
+    let mut offset = 0;
+    let session = match resumed {
+        Some(resume) => match gcs_committed(http, &resume.session, total).await {
+            Ok(Some(committed)) => {
+                println!("{}", i18n::message("upload.resuming_at", &[&key, &format_size(committed as u64)]));
+                offset = committed;
+                resume.session
+            }
+            // The previous attempt finished after all
+            Ok(None) => {
+                fs::remove_file(&state_file).ok();
+                return Ok(());
+            }
+            // Expired sessions start over
+            Err(_) => gcs_start(http, token, bucket, key, &metadata, &state_file).await?,
+        },
+        None => gcs_start(http, token, bucket, key, &metadata, &state_file).await?,
+    };
+
+    let parts = total.div_ceil(PART_SIZE).max(1);
+    let (session, body) = (&session, &body);
+    while offset < total {
+        let end = (offset + PART_SIZE).min(total);
+        let sent = with_retries(&format!("GCS chunk at {} of {}", offset, key), || async move {
+            let response = http.put(session)
+                .header("Content-Range", format!("bytes {}-{}/{}", offset, end - 1, total))
+                .body(body[offset..end].to_vec())
+                .send().await.map_err(|e| e.to_string())?;
+            match response.status().as_u16() {
+                200 | 201 => Ok(total),
+                // 308 Resume Incomplete: the Range header says what it kept
+                308 => Ok(committed_from(response.headers()).unwrap_or(end)),
+                status => {
+                    // Ask where the session stands before sending again
+                    match gcs_committed(http, session, total).await {
+                        Ok(Some(committed)) if committed > offset => Ok(committed),
+                        _ => Err(format!("HTTP {}", status)),
+                    }
+                }
+            }
+        }).await?;
+        offset = sent;
+        report_part(key, offset.div_ceil(PART_SIZE).min(parts), parts, offset, total);
+    }
+    fs::remove_file(&state_file).ok();
+    Ok(())
+}
+
+async fn gcs_start(
+    http: &reqwest::Client,
+    token: &str,
+    bucket: &str,
+    key: &str,
+    metadata: &serde_json::Value,
+    state_file: &PathBuf,
+) -> Result<String, String> {
+    let response = http.post(format!("https://storage.googleapis.com/upload/storage/v1/b/{}/o", bucket))
+        .query(&[("uploadType", "resumable"), ("name", key)])
+        .bearer_auth(token)
+        .json(metadata)
+        .send().await.map_err(|e| format!("Starting upload of {} failed: {}", key, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Starting upload of {} failed: HTTP {}", key, response.status()));
+    }
+    let session = response.headers().get("Location").and_then(|v| v.to_str().ok())
+        .ok_or_else(|| format!("GCS returned no upload session for {}", key))?
+        .to_string();
+    save_resume(state_file, &Resume { session: session.clone(), parts: BTreeMap::new() })?;
+    Ok(session)
+}
+
+// Bytes the session has stored, or None once the object is complete
+async fn gcs_committed(http: &reqwest::Client, session: &str, total: usize) -> Result<Option<usize>, String> {
+    let response = http.put(session)
+        .header("Content-Range", format!("bytes */{}", total))
+        .header("Content-Length", "0")
+        .send().await.map_err(|e| e.to_string())?;
+    match response.status().as_u16() {
+        200 | 201 => Ok(None),
+        308 => Ok(Some(committed_from(response.headers()).unwrap_or(0))),
+        status => Err(format!("HTTP {}", status)),
+    }
+}
+
+// "Range: bytes=0-8388607" means 8388608 bytes are stored
+fn committed_from(headers: &reqwest::header::HeaderMap) -> Option<usize> {
+    let range = headers.get("Range")?.to_str().ok()?;
+    let last: usize = range.rsplit('-').next()?.parse().ok()?;
+    Some(last + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_committed_from() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(committed_from(&headers), None);
+        headers.insert("Range", "bytes=0-8388607".parse().unwrap());
+        assert_eq!(committed_from(&headers), Some(8388608));
+    }
+}
@@ -0,0 +1,181 @@
+// Opt-in usage statistics, kept in .rift/usage.json and never sent anywhere:
+// per month, how often each command ran and for how long, and how many fuse
+// blocks each language ran and how long they took. Collected only with
+// `usage_stats = true` in rift.toml or RIFT_USAGE_STATS=1; `rift stats
+// report` shows them.
+use crate::config::Config;
+use crate::error::Result;
+use crate::i18n;
+use crate::state;
+use crate::stats::ExecStats;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const VAR: &str = "RIFT_USAGE_STATS";
+const FILE: &str = "usage.json";
+
+// The command `begin` started timing, until `finish` records it
+static PENDING: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tally {
+    pub count: u64,
+    pub millis: u64,
+    #[serde(default)]
+    pub failures: u64,
+}
+
+impl Tally {
+    fn add(&mut self, count: u64, time: Duration, failures: u64) {
+        self.count += count;
+        self.millis += time.as_millis() as u64;
+        self.failures += failures;
+    }
+
+    fn total(&self) -> Duration {
+        Duration::from_millis(self.millis)
+    }
+
+    fn average(&self) -> Duration {
+        Duration::from_millis(self.millis.checked_div(self.count).unwrap_or(0))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Month {
+    #[serde(default)]
+    pub commands: BTreeMap<String, Tally>,
+    #[serde(default)]
+    pub languages: BTreeMap<String, Tally>,
+}
+
+// Keyed by "2026-10"
+pub type Usage = BTreeMap<String, Month>;
+
+pub fn enabled(config: &Config) -> bool {
+    match std::env::var(VAR) {
+        Ok(value) => !value.is_empty() && value != "0",
+        Err(_) => config.usage_stats,
+    }
+}
+
+pub fn path() -> PathBuf {
+    state::state_dir().join(FILE)
+}
+
+pub fn begin(command: &str) {
+    *PENDING.lock().unwrap() = Some((command.to_string(), Instant::now()));
+}
+
+// Records the command `begin` started, with the fuse blocks it ran; only the
+// first call after `begin` counts, so every way out of a command can call it
+pub fn finish(stats: Option<&ExecStats>) {
+    let Some((command, started)) = PENDING.lock().unwrap().take() else {
+        return;
+    };
+    if !enabled(&Config::load().unwrap_or_default()) {
+        return;
+    }
+    let month = chrono::Local::now().format("%Y-%m").to_string();
+    let path = path();
+    let updated = load(&path).and_then(|mut usage| {
+        add(&mut usage, &month, &command, started.elapsed(), stats);
+        save(&path, &usage)
+    });
+    if let Err(e) = updated {
+        eprintln!("{}", i18n::message("usage.update_failed", &[&path.display(), &e]));
+    }
+}
+
+pub fn load(path: &Path) -> Result<Usage> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(serde_json::from_str(&text)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Usage::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Written beside the file first, so an interrupted write loses only this run
+fn save(path: &Path, usage: &Usage) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = path.with_extension("json.partial");
+    fs::write(&partial, serde_json::to_string_pretty(usage)? + "\n")?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+fn add(usage: &mut Usage, month: &str, command: &str, took: Duration, stats: Option<&ExecStats>) {
+    let month = usage.entry(month.to_string()).or_default();
+    month.commands.entry(command.to_string()).or_default().add(1, took, 0);
+    for (lang, lang_stats) in stats.into_iter().flat_map(ExecStats::languages) {
+        month.languages.entry(lang.clone()).or_default().add(lang_stats.runs, lang_stats.total_time, lang_stats.failures);
+    }
+}
+
+// The newest `months` months, newest first
+pub fn report(usage: &Usage, months: usize) -> String {
+    let mut report = String::new();
+    if usage.is_empty() {
+        writeln!(report, "No usage recorded yet").ok();
+        return report;
+    }
+    for (name, month) in usage.iter().rev().take(months) {
+        writeln!(report, "{}", name).ok();
+        writeln!(report, "  {:<14} {:>6} {:>7} {:>10} {:>10}", "command", "runs", "", "total", "avg").ok();
+        for (command, tally) in by_time(&month.commands) {
+            writeln!(report, "  {:<14} {:>6} {:>7} {:>10} {:>10}", command, tally.count, "", format!("{:.1?}", tally.total()), format!("{:.1?}", tally.average())).ok();
+        }
+        if !month.languages.is_empty() {
+            writeln!(report, "  {:<14} {:>6} {:>7} {:>10} {:>10}", "language", "blocks", "failed", "total", "avg").ok();
+            for (lang, tally) in by_time(&month.languages) {
+                writeln!(report, "  {:<14} {:>6} {:>7} {:>10} {:>10}", lang, tally.count, tally.failures, format!("{:.1?}", tally.total()), format!("{:.1?}", tally.average())).ok();
+            }
+        }
+    }
+    report
+}
+
+// Where the time went, most first
+fn by_time(tallies: &BTreeMap<String, Tally>) -> Vec<(&String, &Tally)> {
+    let mut sorted: Vec<_> = tallies.iter().collect();
+    sorted.sort_by(|a, b| b.1.millis.cmp(&a.1.millis));
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_and_report() {
+        let mut stats = ExecStats::default();
+        stats.record_run("python", Duration::from_millis(1500), 0);
+        stats.record_failure("go", Duration::from_millis(200), "build failed".to_string());
+
+        let mut usage = Usage::new();
+        add(&mut usage, "2026-09", "run", Duration::from_secs(4), Some(&stats));
+        add(&mut usage, "2026-10", "run", Duration::from_secs(2), Some(&stats));
+        add(&mut usage, "2026-10", "run", Duration::from_secs(4), None);
+        add(&mut usage, "2026-10", "fmt", Duration::from_millis(10), None);
+
+        let october = &usage["2026-10"];
+        assert_eq!(october.commands["run"], Tally { count: 2, millis: 6000, failures: 0 });
+        assert_eq!(october.languages["go"], Tally { count: 1, millis: 200, failures: 1 });
+
+        let path = tempfile::tempdir().unwrap().into_path().join("usage.json");
+        save(&path, &usage).unwrap();
+        assert_eq!(load(&path).unwrap(), usage);
+
+        let text = report(&usage, 1);
+        assert!(text.starts_with("2026-10\n") && !text.contains("2026-09"));
+        assert!(text.find("  run").unwrap() < text.find("  fmt").unwrap());
+        assert!(text.contains("3.0s"));
+    }
+}
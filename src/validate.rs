@@ -0,0 +1,290 @@
+use crate::config::LintLevel;
+use crate::interpreter::{self, Environment};
+use crate::{builtins, deployer, executor, registry, AST};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+
+// Lints `rift check` knows, with the level used when rift.toml's [lints]
+// does not set one
+pub const LINTS: &[(&str, LintLevel)] = &[
+    ("unused_variable", LintLevel::Warn),
+    ("shadowed_rift", LintLevel::Warn),
+    ("deprecated_syntax", LintLevel::Warn),
+];
+
+// Spellings that still work but have a preferred replacement
+const DEPRECATED_LANGUAGES: &[(&str, &str)] = &[("js", "javascript")];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub lint: Option<&'static str>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match self.lint {
+            Some(lint) => write!(f, "{}: {} [{}]", label, self.message, lint),
+            None => write!(f, "{}: {}", label, self.message),
+        }
+    }
+}
+
+// Static checks for `rift check`: every problem the program would run into
+// that can be found without executing anything, plus lints at the levels
+// configured in rift.toml. Rifts and tasks count as defined wherever they
+// appear, as well as those already in `env` (the standard library) and those
+// in imported packages.
+pub fn check(program: &AST, env: &Environment) -> Vec<Diagnostic> {
+    let mut nodes = Vec::new();
+    walk(program, &mut nodes);
+
+    let imported = imported_definitions(&nodes, &env.importing);
+    let mut defined: BTreeSet<&str> = env.rifts.keys().chain(env.tasks.keys()).chain(env.functions.keys()).chain(&imported).map(String::as_str).collect();
+    for node in &nodes {
+        if let AST::Rift(name, _) | AST::Task(name, _) | AST::Function(name, _, _) = node {
+            defined.insert(name);
+        }
+    }
+
+    let mut diagnostics: Vec<_> = nodes.iter()
+        .flat_map(|node| check_node(node, env, &defined))
+        .map(|message| Diagnostic { severity: Severity::Error, lint: None, message })
+        .collect();
+    for (lint, message) in lints(&nodes, env) {
+        let default = LINTS.iter().find(|(name, _)| *name == lint).map_or(LintLevel::Warn, |(_, level)| *level);
+        let severity = match env.config.lints.get(lint).copied().unwrap_or(default) {
+            LintLevel::Allow => continue,
+            LintLevel::Warn => Severity::Warning,
+            LintLevel::Deny => Severity::Error,
+        };
+        diagnostics.push(Diagnostic { severity, lint: Some(lint), message });
+    }
+    for name in env.config.lints.keys().filter(|name| !LINTS.iter().any(|(lint, _)| lint == name)) {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            lint: None,
+            message: format!("unknown lint '{}' in rift.toml [lints]", name),
+        });
+    }
+    diagnostics
+}
+
+// Every node in source order, parents before their children
+pub(crate) fn walk<'a>(node: &'a AST, nodes: &mut Vec<&'a AST>) {
+    nodes.push(node);
+    let children: Vec<&AST> = match node {
+        AST::Program(body) | AST::Rift(_, body) | AST::Task(_, body) | AST::Function(_, _, body) => body.iter().collect(),
+        AST::If(condition, then_body, else_body) => {
+            std::iter::once(condition.as_ref()).chain(then_body).chain(else_body).collect()
+        }
+        AST::While(condition, body) | AST::For(_, condition, body) => std::iter::once(condition.as_ref()).chain(body).collect(),
+        AST::Range(start, end) => vec![start.as_ref(), end.as_ref()],
+        AST::When(_, then_body, else_body) => then_body.iter().chain(else_body).collect(),
+        AST::Match(subject, arms) => {
+            std::iter::once(subject.as_ref()).chain(arms.iter().flat_map(|(patterns, body)| patterns.iter().chain(body))).collect()
+        }
+        AST::Let(_, value) | AST::Global(_, value) | AST::Assign(_, value) | AST::Param(_, _, Some(value)) => vec![value.as_ref()],
+        AST::CachedFuse(_, fuse) | AST::GpuFuse(fuse) => vec![fuse.as_ref()],
+        AST::Call(_, args) => args.iter().collect(),
+        AST::Not(inner) | AST::Neg(inner) => vec![inner.as_ref()],
+        AST::List(items) => items.iter().collect(),
+        AST::Map(entries) => entries.iter().map(|(_, value)| value).collect(),
+        AST::DeployValue(_, config) => vec![config.as_ref()],
+        AST::Index(base, index) => vec![base.as_ref(), index.as_ref()],
+        AST::And(left, right) | AST::Or(left, right) | AST::Binary(_, left, right) => vec![left.as_ref(), right.as_ref()],
+        _ => Vec::new(),
+    };
+    for child in children {
+        walk(child, nodes);
+    }
+}
+
+// Names defined at the top level of each installed package and file the
+// program imports, and of the files those import in turn; a missing package
+// or file is reported by `check_node`
+fn imported_definitions(nodes: &[&AST], importing: &[PathBuf]) -> Vec<String> {
+    let mut names = Vec::new();
+    for node in nodes {
+        let sources = match node {
+            AST::Import(package) => registry::sources(package).unwrap_or_default(),
+            // A file already being read is a cycle, which running reports
+            AST::ImportFile(file) => {
+                let path = interpreter::import_path(importing, file);
+                match importing.iter().any(|running| interpreter::same_file(running, &path)) {
+                    true => Vec::new(),
+                    false => crate::git::read_source(&path, None).map(|source| vec![(path, source)]).unwrap_or_default(),
+                }
+            }
+            _ => continue,
+        };
+        for (path, source) in sources {
+            let Ok(program) = crate::lexer::tokenize(&source).and_then(|tokens| crate::parser::parse(&tokens)) else { continue };
+            let AST::Program(items) = &program else { continue };
+            let mut inner = Vec::new();
+            walk(&program, &mut inner);
+            names.extend(imported_definitions(&inner, &[importing, &[path]].concat()));
+            names.extend(items.iter().filter_map(|item| match item {
+                AST::Rift(name, _) | AST::Task(name, _) | AST::Function(name, _, _) => Some(name.clone()),
+                _ => None,
+            }));
+        }
+    }
+    names
+}
+
+fn check_node(node: &AST, env: &Environment, defined: &BTreeSet<&str>) -> Vec<String> {
+    let mut problems = Vec::new();
+    match node {
+        AST::Fuse(lang, _) | AST::InteractiveFuse(lang, _) => check_language(lang, "@fuse", &mut problems),
+        AST::RemoteFuse(agent, lang, _) => {
+            check_language(lang, "@fuse", &mut problems);
+            if !env.config.agents.contains_key(agent) {
+                problems.push(format!("agent '{}' is not listed under [agents] in rift.toml", agent));
+            }
+        }
+        AST::Target(lang) => check_language(lang, "@target", &mut problems),
+        AST::Deploy(target, _) if env.plugins.deploy_target(target).is_some() => {}
+        AST::Deploy(target, config) => match env.config.with_profile(config) {
+            Ok(config) => {
+                problems.extend(deployer::validate_config(target, &config).into_iter().map(|p| format!("@deploy \"{}\": {}", target, p)));
+            }
+            Err(e) => problems.push(format!("@deploy \"{}\": {}", target, e)),
+        },
+        AST::Invoke(target, _) if !matches!(target.as_str(), "ethereum" | "solana") => {
+            problems.push(format!("@invoke supports ethereum and solana, not '{}'", target));
+        }
+        AST::Import(package) => {
+            if let Err(e) = registry::sources(package) {
+                problems.push(e.to_string());
+            }
+        }
+        AST::ImportFile(file) => {
+            let path = interpreter::import_path(&env.importing, file);
+            if let Err(e) = crate::git::read_source(&path, None) {
+                problems.push(format!("import {}: {}", path.display(), e));
+            }
+        }
+        AST::Call(name, _) => {
+            let known = builtins::is_builtin(name) || env.plugins.builtin(name).is_some() || matches!(name.as_str(), "exit" | "optimize") || defined.contains(name.as_str());
+            if !known {
+                problems.push(format!("call to undefined rift or task '{}'", name));
+            }
+        }
+        _ => {}
+    }
+    problems
+}
+
+fn check_language(lang: &str, directive: &str, problems: &mut Vec<String>) {
+    if executor::executor_for(lang).is_err() {
+        problems.push(format!("{} uses unsupported language '{}'", directive, lang));
+    }
+}
+
+fn lints(nodes: &[&AST], env: &Environment) -> Vec<(&'static str, String)> {
+    let mut found = Vec::new();
+
+    // A `let` nothing reads; `_name` opts out. Exported names count as read
+    // since fuse code sees them.
+    let mut used = BTreeSet::new();
+    for node in nodes {
+        match node {
+            AST::Identifier(name) => {
+                used.insert(name.split('.').next().unwrap_or(name));
+            }
+            AST::Export(names) => used.extend(names.iter().map(String::as_str)),
+            _ => {}
+        }
+    }
+    let mut reported = BTreeSet::new();
+    for node in nodes {
+        if let AST::Let(name, _) = node {
+            if !name.starts_with('_') && !used.contains(name.as_str()) && reported.insert(name) {
+                found.push(("unused_variable", format!("variable '{}' is never used", name)));
+            }
+        }
+    }
+
+    let mut seen: HashMap<&str, &str> = HashMap::new();
+    for node in nodes {
+        let (kind, name) = match node {
+            AST::Rift(name, _) => ("rift", name),
+            AST::Task(name, _) => ("task", name),
+            _ => continue,
+        };
+        if let Some(earlier) = seen.insert(name.as_str(), kind) {
+            found.push(("shadowed_rift", format!("{} '{}' replaces an earlier {} of the same name", kind, name, earlier)));
+        } else if env.rifts.contains_key(name) || env.tasks.contains_key(name) {
+            found.push(("shadowed_rift", format!("{} '{}' replaces the standard library task of the same name", kind, name)));
+        }
+        if builtins::is_builtin(name) {
+            found.push(("shadowed_rift", format!("{} '{}' can never be called; {}(...) is a builtin", kind, name, name)));
+        }
+    }
+
+    for node in nodes {
+        let lang = match node {
+            AST::Fuse(lang, _) | AST::InteractiveFuse(lang, _) | AST::RemoteFuse(_, lang, _) | AST::Target(lang) => lang,
+            _ => continue,
+        };
+        if let Some((_, preferred)) = DEPRECATED_LANGUAGES.iter().find(|(old, _)| old == lang) {
+            found.push(("deprecated_syntax", format!("language \"{}\" is deprecated; write \"{}\"", lang, preferred)));
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::tokenize, parser::parse};
+
+    fn diagnostics(source: &str, env: &Environment) -> Vec<String> {
+        check(&parse(&tokenize(source).unwrap()).unwrap(), env).iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn test_clean_program() {
+        let source = "@rift app { @fuse \"python\" { \"print(1)\" } } call app; let t = now(); print(t);";
+        assert!(diagnostics(source, &Environment::new()).is_empty());
+    }
+
+    #[test]
+    fn test_reports_problems() {
+        let found = diagnostics("call missing; @target \"cobol\" @deploy \"aws\" { region = \"us-east-1\" }", &Environment::new());
+        assert_eq!(found, vec![
+            "error: call to undefined rift or task 'missing'".to_string(),
+            "error: @target uses unsupported language 'cobol'".to_string(),
+            "error: @deploy \"aws\": aws deploy is missing bucket, function, role".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_lint_levels() {
+        let source = "let unused = 1; @rift a { } @task a { } @fuse \"js\" { \"1\" }";
+        let mut env = Environment::new();
+        assert_eq!(diagnostics(source, &env), vec![
+            "warning: variable 'unused' is never used [unused_variable]".to_string(),
+            "warning: task 'a' replaces an earlier rift of the same name [shadowed_rift]".to_string(),
+            "warning: language \"js\" is deprecated; write \"javascript\" [deprecated_syntax]".to_string(),
+        ]);
+
+        env.config.lints.insert("unused_variable".to_string(), LintLevel::Deny);
+        env.config.lints.insert("shadowed_rift".to_string(), LintLevel::Allow);
+        env.config.lints.insert("deprecated_syntax".to_string(), LintLevel::Allow);
+        assert_eq!(diagnostics(source, &env), vec!["error: variable 'unused' is never used [unused_variable]".to_string()]);
+    }
+}
@@ -0,0 +1,394 @@
+use crate::AST;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+// Runtime values held in the Environment. AST nodes describe source code;
+// these are what evaluating it produces.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+    Output {
+        lang: String,
+        stdout: String,
+        exit_code: Option<i32>,
+    },
+    Artifact {
+        path: PathBuf,
+        hash: String,
+        size: u64,
+    },
+}
+
+impl Value {
+    pub fn from_literal(ast: &AST) -> Option<Value> {
+        match ast {
+            AST::Number(n) => Some(Value::Int(*n as i64)),
+            AST::Float(x) => Some(Value::Float(*x)),
+            AST::String(s) => Some(Value::Str(s.clone())),
+            AST::Bool(b) => Some(Value::Bool(*b)),
+            _ => None,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Str(_) => "string",
+            Value::Bool(_) => "bool",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Output { .. } => "output",
+            Value::Artifact { .. } => "artifact",
+        }
+    }
+
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Int(n) => *n != 0,
+            Value::Float(f) => *f != 0.0,
+            Value::Str(s) => !s.is_empty(),
+            Value::Bool(b) => *b,
+            Value::List(items) => !items.is_empty(),
+            Value::Map(entries) => !entries.is_empty(),
+            Value::Output { exit_code, .. } => *exit_code == Some(0),
+            Value::Artifact { .. } => true,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            Value::Output { stdout, .. } => Some(stdout),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    // Output and Artifact have no JSON form and serialize as their display text
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Null => serde_json::Value::Null,
+            Value::Int(n) => (*n).into(),
+            Value::Float(x) => (*x).into(),
+            Value::Str(s) => s.clone().into(),
+            Value::Bool(b) => (*b).into(),
+            Value::List(items) => items.iter().map(Value::to_json).collect(),
+            Value::Map(entries) => entries.iter()
+                .map(|(key, value)| (key.clone(), value.to_json()))
+                .collect::<serde_json::Map<_, _>>()
+                .into(),
+            other => other.to_string().into(),
+        }
+    }
+
+    pub fn from_json(json: serde_json::Value) -> Value {
+        match json {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => n.as_i64().map(Value::Int)
+                .unwrap_or_else(|| Value::Float(n.as_f64().unwrap_or(f64::NAN))),
+            serde_json::Value::String(s) => Value::Str(s),
+            serde_json::Value::Array(items) => Value::List(items.into_iter().map(Value::from_json).collect()),
+            serde_json::Value::Object(entries) => Value::Map(
+                entries.into_iter().map(|(key, value)| (key, Value::from_json(value))).collect(),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_nested(f, item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{} = ", key)?;
+                    write_nested(f, value)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Output { stdout, .. } => write!(f, "{}", stdout.trim_end()),
+            Value::Artifact { path, size, .. } => write!(f, "<artifact {} ({} bytes)>", path.display(), size),
+        }
+    }
+}
+
+// Strings inside collections are quoted so `["a, b"]` and `["a", "b"]` differ
+fn write_nested(f: &mut fmt::Formatter<'_>, value: &Value) -> fmt::Result {
+    match value {
+        Value::Str(s) => write!(f, "{:?}", s),
+        other => write!(f, "{}", other),
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Int(n)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Self {
+        Value::Float(x)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Str(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Str(s.to_string())
+    }
+}
+
+// Operands of `+ - * / %`: numbers, and text that reads as one, such as a
+// fuse block's output or a -D value
+#[derive(Clone, Copy)]
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn of(value: &Value) -> Option<Number> {
+        match value {
+            Value::Int(n) => Some(Number::Int(*n)),
+            Value::Float(x) => Some(Number::Float(*x)),
+            _ => {
+                let text = value.as_str()?.trim();
+                text.parse().map(Number::Int).ok()
+                    .or_else(|| text.parse().ok().filter(|x: &f64| x.is_finite()).map(Number::Float))
+            }
+        }
+    }
+
+    fn float(self) -> f64 {
+        match self {
+            Number::Int(n) => n as f64,
+            Number::Float(x) => x,
+        }
+    }
+}
+
+// Ints stay ints, with `/` truncating, unless a float is on either side.
+// `+` joins text whenever either side is text or fuse output, even text that
+// reads as a number, so "1" + "2" is "12"; the other operators take such
+// text as the number it reads as
+pub fn arithmetic(op: &str, left: &Value, right: &Value) -> Result<Value, String> {
+    let is_text = |value: &Value| value.as_str().is_some();
+    if op == "+" && (is_text(left) || is_text(right)) {
+        let text = |value: &Value| match value {
+            Value::Output { stdout, .. } => stdout.trim_end_matches('\n').to_string(),
+            other => other.to_string(),
+        };
+        return Ok(Value::Str(text(left) + &text(right)));
+    }
+    let operand = |value: &Value| Number::of(value)
+        .ok_or_else(|| format!("'{}' expects numbers, got {} '{}'", op, value.type_name(), value));
+    let (a, b) = (operand(left)?, operand(right)?);
+    if matches!(op, "/" | "%") && b.float() == 0.0 {
+        return Err(format!("division by zero in {} {} {}", left, op, right));
+    }
+    if let (Number::Int(a), Number::Int(b)) = (a, b) {
+        let result = match op {
+            "+" => a.checked_add(b),
+            "-" => a.checked_sub(b),
+            "*" => a.checked_mul(b),
+            "/" => a.checked_div(b),
+            "%" => a.checked_rem(b),
+            _ => return Err(format!("unknown operator '{}'", op)),
+        };
+        return result.map(Value::Int).ok_or_else(|| format!("integer overflow in {} {} {}", a, op, b));
+    }
+    let (a, b) = (a.float(), b.float());
+    match op {
+        "+" => Ok(Value::Float(a + b)),
+        "-" => Ok(Value::Float(a - b)),
+        "*" => Ok(Value::Float(a * b)),
+        "/" => Ok(Value::Float(a / b)),
+        "%" => Ok(Value::Float(a % b)),
+        _ => Err(format!("unknown operator '{}'", op)),
+    }
+}
+
+pub const COMPARISONS: [&str; 6] = ["==", "!=", "<", "<=", ">", ">="];
+
+// Numbers compare by value, including text that reads as one; other text
+// compares as strings, ignoring the newline that ends most fuse output.
+// Anything else can only be tested for (in)equality.
+pub fn compare(op: &str, left: &Value, right: &Value) -> Result<Value, String> {
+    fn text(value: &Value) -> Option<&str> {
+        match value {
+            Value::Output { stdout, .. } => Some(stdout.trim_end_matches('\n')),
+            other => other.as_str(),
+        }
+    }
+    let numeric = matches!(left, Value::Int(_) | Value::Float(_)) || matches!(right, Value::Int(_) | Value::Float(_));
+    let ordering = match (Number::of(left), Number::of(right), text(left), text(right)) {
+        (Some(a), Some(b), _, _) if numeric => a.float().partial_cmp(&b.float()),
+        (_, _, Some(a), Some(b)) => Some(a.cmp(b)),
+        _ => match op {
+            "==" => return Ok(Value::Bool(left == right)),
+            "!=" => return Ok(Value::Bool(left != right)),
+            _ => return Err(format!("cannot compare {} '{}' with {} '{}' using {}", left.type_name(), left, right.type_name(), right, op)),
+        },
+    };
+    let Some(ordering) = ordering else {
+        return Ok(Value::Bool(op == "!="));
+    };
+    let result = match op {
+        "==" => ordering.is_eq(),
+        "!=" => ordering.is_ne(),
+        "<" => ordering.is_lt(),
+        "<=" => ordering.is_le(),
+        ">" => ordering.is_gt(),
+        ">=" => ordering.is_ge(),
+        _ => return Err(format!("unknown operator '{}'", op)),
+    };
+    Ok(Value::Bool(result))
+}
+
+// `xs[i]` counts from 0; `m["key"]` reads a map entry
+pub fn index(base: &Value, index: &Value) -> Result<Value, String> {
+    match (base, index) {
+        (Value::List(items), Value::Int(i)) => usize::try_from(*i).ok()
+            .and_then(|i| items.get(i))
+            .cloned()
+            .ok_or_else(|| format!("index {} is out of range for a list of {}", i, items.len())),
+        (Value::Map(entries), Value::Str(key)) => entries.get(key).cloned().ok_or_else(|| format!("map has no key '{}'", key)),
+        (Value::List(_), other) => Err(format!("list index must be an int, got {} '{}'", other.type_name(), other)),
+        (Value::Map(_), other) => Err(format!("map key must be a string, got {} '{}'", other.type_name(), other)),
+        (other, _) => Err(format!("cannot index {} '{}'", other.type_name(), other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Value::Int(5).to_string(), "5");
+        assert_eq!(Value::from("hi").to_string(), "hi");
+        let list = Value::List(vec![Value::Int(1), Value::from("two")]);
+        assert_eq!(list.to_string(), "[1, \"two\"]");
+        let map = Value::Map([("region".to_string(), Value::from("us-east-1"))].into_iter().collect());
+        assert_eq!(map.to_string(), "{region = \"us-east-1\"}");
+    }
+
+    #[test]
+    fn test_truthiness() {
+        assert!(Value::Int(1).is_truthy());
+        assert!(!Value::Int(0).is_truthy());
+        assert!(!Value::from("").is_truthy());
+        assert!(!Value::Null.is_truthy());
+        assert!(Value::List(vec![Value::Null]).is_truthy());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let json: serde_json::Value = serde_json::from_str(r#"{"ok": true, "ids": [1, 2.5], "name": null}"#).unwrap();
+        let value = Value::from_json(json.clone());
+        assert_eq!(value.to_string(), "{ids = [1, 2.5], name = null, ok = true}");
+        assert_eq!(value.to_json(), json);
+    }
+
+    #[test]
+    fn test_from_literal() {
+        assert_eq!(Value::from_literal(&AST::Number(7)), Some(Value::Int(7)));
+        assert_eq!(Value::from_literal(&AST::Float(0.5)), Some(Value::Float(0.5)));
+        assert_eq!(Value::from_literal(&AST::Identifier("x".to_string())), None);
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(compare("<", &Value::Int(3), &Value::Float(3.5)), Ok(Value::Bool(true)));
+        assert_eq!(compare("==", &Value::from("10\n"), &Value::Int(10)), Ok(Value::Bool(true)));
+        assert_eq!(compare(">", &Value::from("b"), &Value::from("a")), Ok(Value::Bool(true)));
+        // Text only compares numerically against a number
+        assert_eq!(compare("<", &Value::from("10"), &Value::from("9")), Ok(Value::Bool(true)));
+        assert_eq!(compare("!=", &Value::Null, &Value::Int(0)), Ok(Value::Bool(true)));
+        assert!(compare("<", &Value::Null, &Value::Int(0)).is_err());
+    }
+
+    #[test]
+    fn test_index() {
+        let xs = Value::List(vec![Value::Int(1), Value::from("two")]);
+        assert_eq!(index(&xs, &Value::Int(1)), Ok(Value::from("two")));
+        assert!(index(&xs, &Value::Int(2)).unwrap_err().contains("out of range"));
+        assert!(index(&xs, &Value::Int(-1)).is_err());
+        assert!(index(&xs, &Value::from("0")).unwrap_err().contains("must be an int"));
+        let map = Value::Map(BTreeMap::from([("a".to_string(), Value::Bool(true))]));
+        assert_eq!(index(&map, &Value::from("a")), Ok(Value::Bool(true)));
+        assert!(index(&Value::Int(3), &Value::Int(0)).is_err());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(arithmetic("/", &Value::Int(7), &Value::Int(2)), Ok(Value::Int(3)));
+        assert_eq!(arithmetic("*", &Value::Int(3), &Value::Float(0.5)), Ok(Value::Float(1.5)));
+        assert_eq!(arithmetic("-", &Value::from(" 44\n"), &Value::Int(2)), Ok(Value::Int(42)));
+        assert!(arithmetic("%", &Value::Int(1), &Value::Int(0)).unwrap_err().contains("division by zero"));
+        assert!(arithmetic("+", &Value::Int(i64::MAX), &Value::Int(1)).unwrap_err().contains("overflow"));
+        assert!(arithmetic("-", &Value::from("abc"), &Value::Int(1)).unwrap_err().contains("expects numbers"));
+        assert_eq!(arithmetic("+", &Value::from("v"), &Value::Int(1)), Ok(Value::from("v1")));
+        assert_eq!(arithmetic("+", &Value::from("Hello "), &Value::from("world")), Ok(Value::from("Hello world")));
+        assert!(arithmetic("+", &Value::Null, &Value::Int(1)).is_err());
+    }
+
+    #[test]
+    fn test_arithmetic_on_strings() {
+        assert_eq!(arithmetic("+", &Value::from("1"), &Value::from("2")), Ok(Value::from("12")));
+        assert_eq!(arithmetic("+", &Value::Int(40), &Value::from("2")), Ok(Value::from("402")));
+        // "2." would read as a float
+        let version = arithmetic("+", &arithmetic("+", &Value::from("2"), &Value::from(".")).unwrap(), &Value::from("1"));
+        assert_eq!(version, Ok(Value::from("2.1")));
+        let output = Value::Output { lang: "python".to_string(), stdout: "7\n".to_string(), exit_code: Some(0) };
+        assert_eq!(arithmetic("+", &output, &Value::Int(1)), Ok(Value::from("71")));
+        assert_eq!(arithmetic("*", &output, &Value::from("2")), Ok(Value::Int(14)));
+    }
+}